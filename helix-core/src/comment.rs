@@ -1,8 +1,10 @@
 //! This module contains the functionality toggle comments on lines over the selection
-//! using the comment character defined in the user's `languages.toml`
+//! using the comment character defined in the user's `languages.toml`, and to toggle
+//! block comments around the selections using the block comment tokens.
 
 use crate::{
-    find_first_non_whitespace_char, Change, Rope, RopeSlice, Selection, Tendril, Transaction,
+    find_first_non_whitespace_char, syntax::BlockCommentToken, Assoc, Change, Range, Rope,
+    RopeSlice, Selection, Tendril, Transaction,
 };
 use std::borrow::Cow;
 
@@ -94,6 +96,126 @@ pub fn toggle_line_comments(doc: &Rope, selection: &Selection, token: Option<&st
     Transaction::change(doc, changes.into_iter())
 }
 
+/// Shrinks the char range `from..to` to exclude leading and trailing whitespace.
+/// Returns `None` if the range is only whitespace.
+fn trim_whitespace(text: RopeSlice, from: usize, to: usize) -> Option<(usize, usize)> {
+    let from = from
+        + text
+            .slice(from..to)
+            .chars()
+            .position(|ch| !ch.is_whitespace())?;
+    let slice = text.slice(from..to);
+    let trailing = slice
+        .chars_at(slice.len_chars())
+        .reversed()
+        .take_while(|ch| ch.is_whitespace())
+        .count();
+    Some((from, to - trailing))
+}
+
+/// A block comment found around a range, as the char ranges to remove to uncomment it.
+#[derive(Debug, PartialEq, Eq)]
+struct BlockComment {
+    /// The start token and the space following it.
+    start: (usize, usize),
+    /// The end token and the space preceding it.
+    end: (usize, usize),
+}
+
+/// Given the trimmed range `from..to`, finds the first token that the range starts and
+/// ends with.
+fn find_block_comment(
+    text: RopeSlice,
+    from: usize,
+    to: usize,
+    tokens: &[BlockCommentToken],
+) -> Option<BlockComment> {
+    tokens.iter().find_map(|token| {
+        let start_len = token.start.chars().count();
+        let end_len = token.end.chars().count();
+        if to - from < start_len + end_len {
+            return None;
+        }
+
+        let inner_start = from + start_len;
+        let inner_end = to - end_len;
+        if text.slice(from..inner_start) != token.start.as_str()
+            || text.slice(inner_end..to) != token.end.as_str()
+        {
+            return None;
+        }
+
+        // remove a single space of margin next to each token
+        let start_margin = inner_start < inner_end && text.char(inner_start) == ' ';
+        let end_margin =
+            inner_start + (start_margin as usize) < inner_end && text.char(inner_end - 1) == ' ';
+        Some(BlockComment {
+            start: (from, inner_start + start_margin as usize),
+            end: (inner_end - end_margin as usize, to),
+        })
+    })
+}
+
+/// Toggles block comments around the selections. If every selection (ignoring
+/// surrounding whitespace) is wrapped in one of the `tokens`, the comments are removed.
+/// Otherwise each selection is wrapped with the first token. Falls back to `/* */`
+/// if no tokens are given.
+#[must_use]
+pub fn toggle_block_comments(
+    doc: &Rope,
+    selection: &Selection,
+    tokens: &[BlockCommentToken],
+) -> Transaction {
+    let text = doc.slice(..);
+    let default_token = [BlockCommentToken::default()];
+    let tokens = if tokens.is_empty() {
+        &default_token[..]
+    } else {
+        tokens
+    };
+
+    let ranges: Vec<_> = selection
+        .iter()
+        .filter_map(|range| trim_whitespace(text, range.from(), range.to()))
+        .collect();
+    let comments: Option<Vec<_>> = ranges
+        .iter()
+        .map(|&(from, to)| find_block_comment(text, from, to, tokens))
+        .collect();
+
+    let mut changes: Vec<Change> = Vec::with_capacity(ranges.len() * 2);
+    match comments {
+        Some(comments) if !comments.is_empty() => {
+            // uncomment
+            for comment in comments {
+                changes.push((comment.start.0, comment.start.1, None));
+                changes.push((comment.end.0, comment.end.1, None));
+            }
+        }
+        _ => {
+            let start = Tendril::from(format!("{} ", tokens[0].start));
+            let end = Tendril::from(format!(" {}", tokens[0].end));
+            for (from, to) in ranges {
+                changes.push((from, from, Some(start.clone())));
+                changes.push((to, to, Some(end.clone())));
+            }
+        }
+    }
+
+    // keep the comment tokens inside of the selections so that they can be toggled again
+    let transaction = Transaction::change(doc, changes.into_iter());
+    let selection = selection.clone().transform(|range| {
+        let from = transaction.changes().map_pos(range.from(), Assoc::Before);
+        let to = transaction.changes().map_pos(range.to(), Assoc::After);
+        if range.anchor <= range.head {
+            Range::new(from, to)
+        } else {
+            Range::new(to, from)
+        }
+    });
+    transaction.with_selection(selection)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -138,4 +260,43 @@ fn test_find_line_comment() {
 
         // TODO: account for uncommenting with uneven comment indentation
     }
+
+    #[test]
+    fn test_toggle_block_comments() {
+        let tokens = [BlockCommentToken {
+            start: "<!--".to_string(),
+            end: "-->".to_string(),
+        }];
+        let mut doc = Rope::from("  <p>1</p>  \n\n<p>2</p>");
+        // the first line with its surrounding whitespace and the third line
+        let selection = Selection::new(
+            smallvec::smallvec![Range::new(0, 12), Range::new(14, 22)],
+            0,
+        );
+
+        // comment, surrounding whitespace stays outside of the comment
+        let transaction = toggle_block_comments(&doc, &selection, &tokens);
+        transaction.apply(&mut doc);
+        let selection = transaction.selection().unwrap().clone();
+        assert_eq!(doc, "  <!-- <p>1</p> -->  \n\n<!-- <p>2</p> -->");
+
+        // uncomment
+        let transaction = toggle_block_comments(&doc, &selection, &tokens);
+        transaction.apply(&mut doc);
+        assert_eq!(doc, "  <p>1</p>  \n\n<p>2</p>");
+
+        // a selection that isn't commented comments all of them
+        let mut doc = Rope::from("/*1*/ 2");
+        let selection = Selection::new(smallvec::smallvec![Range::new(0, 5), Range::new(6, 7)], 0);
+        let transaction = toggle_block_comments(&doc, &selection, &[]);
+        transaction.apply(&mut doc);
+        assert_eq!(doc, "/* /*1*/ */ /* 2 */");
+
+        // comments without margin are removed
+        let mut doc = Rope::from("/*1*/");
+        let selection = Selection::single(0, 5);
+        let transaction = toggle_block_comments(&doc, &selection, &[]);
+        transaction.apply(&mut doc);
+        assert_eq!(doc, "1");
+    }
 }
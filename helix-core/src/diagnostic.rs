@@ -46,4 +46,6 @@ pub struct Diagnostic {
     pub tags: Vec<DiagnosticTag>,
     pub source: Option<String>,
     pub data: Option<serde_json::Value>,
+    /// Id of the language server that published the diagnostic.
+    pub language_server_id: usize,
 }
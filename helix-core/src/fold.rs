@@ -0,0 +1,299 @@
+//! Code folding. A closed fold hides the lines of a syntax node or language server
+//! folding range behind its first line.
+
+use std::ops::Range;
+
+use tree_sitter::{Query, QueryCursor};
+
+use crate::{
+    syntax::{RopeProvider, TREE_SITTER_MATCH_LIMIT},
+    Assoc, ChangeSet, RopeSlice, Syntax,
+};
+
+/// A foldable range of a document. When closed, the lines after the one containing
+/// `start` up to and including the one containing `end` are hidden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Fold {
+    /// The line the fold is displayed on when it is closed.
+    pub fn start_line(&self, text: RopeSlice) -> usize {
+        text.char_to_line(self.start.min(text.len_chars()))
+    }
+
+    /// The last line hidden by the fold.
+    pub fn end_line(&self, text: RopeSlice) -> usize {
+        text.char_to_line(self.end.min(text.len_chars()))
+    }
+
+    /// The lines of the fold, including the line it is displayed on.
+    pub fn lines(&self, text: RopeSlice) -> Range<usize> {
+        self.start_line(text)..self.end_line(text) + 1
+    }
+
+    /// Whether the fold hides any lines when closed.
+    pub fn is_multiline(&self, text: RopeSlice) -> bool {
+        self.end_line(text) > self.start_line(text)
+    }
+}
+
+/// Computes the foldable ranges of a document from the `@fold` captures of a
+/// tree-sitter query, sorted by their start.
+pub fn syntax_folds(syntax: &Syntax, query: &Query, text: RopeSlice) -> Vec<Fold> {
+    let capture_idx = match query.capture_index_for_name("fold") {
+        Some(capture_idx) => capture_idx,
+        None => return Vec::new(),
+    };
+
+    let mut cursor = QueryCursor::new();
+    cursor.set_match_limit(TREE_SITTER_MATCH_LIMIT);
+    let mut folds: Vec<_> = cursor
+        .captures(query, syntax.tree().root_node(), RopeProvider(text))
+        .flat_map(|(mat, _)| mat.captures)
+        .filter(|capture| capture.index == capture_idx)
+        .map(|capture| {
+            let start = text.byte_to_char(capture.node.start_byte());
+            // the end is exclusive, the fold ends on the line of the last char
+            let end = text.byte_to_char(capture.node.end_byte());
+            Fold {
+                start,
+                end: end.saturating_sub(1).max(start),
+            }
+        })
+        .filter(|fold| fold.is_multiline(text))
+        .collect();
+    folds.sort_unstable();
+    folds.dedup();
+    folds
+}
+
+/// The closed folds of a document in a view.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Folds {
+    /// Sorted by start. Folds may be nested.
+    folds: Vec<Fold>,
+}
+
+impl Folds {
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Fold> {
+        self.folds.iter()
+    }
+
+    /// Whether a fold spanning the same lines as `fold` is closed.
+    pub fn is_closed(&self, text: RopeSlice, fold: &Fold) -> bool {
+        let lines = fold.lines(text);
+        self.folds.iter().any(|closed| closed.lines(text) == lines)
+    }
+
+    /// Closes `fold`, unless it doesn't hide any lines or is already closed.
+    pub fn close(&mut self, text: RopeSlice, fold: Fold) {
+        if !fold.is_multiline(text) || self.is_closed(text, &fold) {
+            return;
+        }
+        let idx = self.folds.partition_point(|closed| *closed < fold);
+        self.folds.insert(idx, fold);
+    }
+
+    /// Opens the closed folds containing `line`. Returns whether any fold was opened.
+    pub fn open_at_line(&mut self, text: RopeSlice, line: usize) -> bool {
+        let len = self.folds.len();
+        self.folds.retain(|fold| !fold.lines(text).contains(&line));
+        self.folds.len() != len
+    }
+
+    pub fn clear(&mut self) {
+        self.folds.clear();
+    }
+
+    /// Maps the folds through `changes`, which were applied to get `text`. Folds that no
+    /// longer span multiple lines are opened.
+    pub fn map(&mut self, changes: &ChangeSet, text: RopeSlice) {
+        for fold in &mut self.folds {
+            fold.start = changes.map_pos(fold.start, Assoc::After);
+            fold.end = changes.map_pos(fold.end, Assoc::Before).max(fold.start);
+        }
+        self.folds.retain(|fold| fold.is_multiline(text));
+        self.folds.sort_unstable();
+    }
+
+    /// The lines hidden by the closed folds.
+    pub fn hidden_lines(&self, text: RopeSlice) -> HiddenLines {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for fold in &self.folds {
+            let Range { start, end } = fold.lines(text);
+            let hidden = start + 1..end;
+            match ranges.last_mut() {
+                // nested and overlapping folds are merged
+                Some(last) if hidden.start <= last.end => last.end = last.end.max(hidden.end),
+                _ => ranges.push(hidden),
+            }
+        }
+        HiddenLines { ranges }
+    }
+}
+
+/// The document lines hidden by closed folds, as sorted and non-overlapping ranges.
+/// A hidden range never includes the first line, so there is always a visible line
+/// that a closed fold is displayed on.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HiddenLines {
+    ranges: Vec<Range<usize>>,
+}
+
+impl HiddenLines {
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// The range of hidden lines containing `line`, if it is hidden.
+    fn range_containing(&self, line: usize) -> Option<&Range<usize>> {
+        let idx = self.ranges.partition_point(|range| range.end <= line);
+        self.ranges.get(idx).filter(|range| range.contains(&line))
+    }
+
+    pub fn is_hidden(&self, line: usize) -> bool {
+        self.range_containing(line).is_some()
+    }
+
+    /// The lines hidden after `line`, if a closed fold is displayed on it.
+    pub fn folded_after(&self, line: usize) -> Option<Range<usize>> {
+        let idx = self.ranges.partition_point(|range| range.start <= line);
+        self.ranges
+            .get(idx)
+            .filter(|range| range.start == line + 1)
+            .cloned()
+    }
+
+    /// Returns `line` if it is visible, otherwise the line the closed fold hiding it is
+    /// displayed on.
+    pub fn visible_line(&self, line: usize) -> usize {
+        self.range_containing(line)
+            .map_or(line, |range| range.start - 1)
+    }
+
+    /// The first visible line after `line`. May be past the end of the document.
+    pub fn next_visible_line(&self, line: usize) -> usize {
+        self.range_containing(line + 1)
+            .map_or(line + 1, |range| range.end)
+    }
+
+    /// The last visible line before `line`.
+    pub fn prev_visible_line(&self, line: usize) -> Option<usize> {
+        line.checked_sub(1).map(|line| self.visible_line(line))
+    }
+
+    /// The visible line `count` visible lines above `line`, or the first line.
+    pub fn line_above(&self, line: usize, count: usize) -> usize {
+        if self.ranges.is_empty() {
+            return line.saturating_sub(count);
+        }
+        let mut line = self.visible_line(line);
+        for _ in 0..count {
+            match self.prev_visible_line(line) {
+                Some(prev) => line = prev,
+                None => break,
+            }
+        }
+        line
+    }
+
+    /// The visible line `count` visible lines below `line`. May be past the end of the
+    /// document.
+    pub fn line_below(&self, line: usize, count: usize) -> usize {
+        if self.ranges.is_empty() {
+            return line + count;
+        }
+        (0..count).fold(line, |line, _| self.next_visible_line(line))
+    }
+
+    /// Counts the visible lines in `lines`.
+    pub fn count_visible(&self, lines: Range<usize>) -> usize {
+        let hidden: usize = self
+            .ranges
+            .iter()
+            .map(|range| {
+                range
+                    .end
+                    .min(lines.end)
+                    .saturating_sub(range.start.max(lines.start))
+            })
+            .sum();
+        lines.len() - hidden
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Rope, Transaction};
+
+    #[test]
+    fn hidden_lines() {
+        let text = Rope::from("fn a() {\n  b {\n    c\n  }\n}\nd\n");
+        let text = text.slice(..);
+        let outer = Fold { start: 0, end: 25 };
+        let inner = Fold { start: 11, end: 23 };
+
+        let mut folds = Folds::default();
+        folds.close(text, inner);
+        // already closed
+        folds.close(text, Fold { start: 13, end: 24 });
+        assert_eq!(folds.iter().count(), 1);
+        assert_eq!(folds.hidden_lines(text).folded_after(1), Some(2..4));
+
+        folds.close(text, outer);
+        let hidden = folds.hidden_lines(text);
+        assert_eq!(hidden.ranges().len(), 1);
+        assert!(hidden.is_hidden(4));
+        assert!(!hidden.is_hidden(5));
+        assert_eq!(hidden.visible_line(3), 0);
+        assert_eq!(hidden.visible_line(5), 5);
+        assert_eq!(hidden.next_visible_line(0), 5);
+        assert_eq!(hidden.prev_visible_line(5), Some(0));
+        assert_eq!(hidden.folded_after(0), Some(1..5));
+        assert_eq!(hidden.folded_after(1), None);
+        assert_eq!(hidden.count_visible(0..7), 3);
+        assert_eq!(hidden.line_above(6, 1), 5);
+        assert_eq!(hidden.line_above(6, 2), 0);
+        assert_eq!(hidden.line_above(6, 3), 0);
+        assert_eq!(hidden.line_below(0, 2), 6);
+
+        // opening the outer fold leaves the inner one closed
+        assert!(folds.open_at_line(text, 0));
+        assert_eq!(folds.hidden_lines(text).folded_after(1), Some(2..4));
+        assert!(!folds.open_at_line(text, 5));
+    }
+
+    #[test]
+    fn map_folds() {
+        let mut text = Rope::from("a {\n  b\n}\nc\n");
+        let mut folds = Folds::default();
+        folds.close(text.slice(..), Fold { start: 2, end: 8 });
+
+        // inserting lines in front moves the fold
+        let transaction = Transaction::change(&text, [(0, 0, Some("x\ny\n".into()))].into_iter());
+        transaction.apply(&mut text);
+        folds.map(transaction.changes(), text.slice(..));
+        assert_eq!(
+            folds.hidden_lines(text.slice(..)).folded_after(2),
+            Some(3..5)
+        );
+
+        // joining the lines of the fold opens it
+        let transaction = Transaction::change(&text, [(7, 14, None)].into_iter());
+        transaction.apply(&mut text);
+        folds.map(transaction.changes(), text.slice(..));
+        assert!(folds.is_empty());
+    }
+}
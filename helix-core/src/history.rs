@@ -1,8 +1,11 @@
+use crate::transaction::Operation;
 use crate::{Assoc, ChangeSet, Range, Rope, Selection, Transaction};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use smallvec::SmallVec;
+use std::io::{self, Read, Write};
 use std::num::NonZeroUsize;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone)]
 pub struct State {
@@ -46,6 +49,9 @@ pub struct State {
 ///  * Because delete transactions currently don't store the text that they
 ///    delete, we also store an inversion of the transaction.
 ///
+/// The history can be written to an undo file with [History::serialize] and read back
+/// with [History::deserialize] to keep it across editor sessions.
+///
 /// Using time to navigate the history: <https://github.com/helix-editor/helix/pull/194>
 #[derive(Debug)]
 pub struct History {
@@ -302,6 +308,288 @@ pub fn later(&mut self, uk: UndoKind) -> Vec<Transaction> {
     }
 }
 
+/// Identifies undo files, followed by the format version.
+const UNDO_FILE_MAGIC: &[u8] = b"HXUNDO";
+const UNDO_FILE_VERSION: u8 = 1;
+
+impl History {
+    /// Writes the revision tree to an undo file. `text` is the document content at the
+    /// current revision; a hash of it is stored so that the history can be discarded
+    /// if the file was modified by another program in the meantime.
+    pub fn serialize<W: Write>(&self, writer: &mut W, text: &Rope) -> io::Result<()> {
+        writer.write_all(UNDO_FILE_MAGIC)?;
+        writer.write_all(&[UNDO_FILE_VERSION])?;
+        writer.write_all(&content_hash(text))?;
+        write_usize(writer, self.current)?;
+        write_usize(writer, self.revisions.len())?;
+        for revision in &self.revisions {
+            write_usize(writer, revision.parent)?;
+            write_usize(writer, revision.last_child.map_or(0, NonZeroUsize::get))?;
+            write_u64(writer, instant_to_unix_millis(revision.timestamp))?;
+            write_transaction(writer, &revision.transaction)?;
+            write_transaction(writer, &revision.inversion)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a revision tree written by [History::serialize]. Fails with
+    /// [io::ErrorKind::InvalidData] if the undo file is malformed or was written for
+    /// content other than `text`.
+    pub fn deserialize<R: Read>(reader: &mut R, text: &Rope) -> io::Result<Self> {
+        let mut magic = [0; UNDO_FILE_MAGIC.len() + 1];
+        reader.read_exact(&mut magic)?;
+        if &magic[..UNDO_FILE_MAGIC.len()] != UNDO_FILE_MAGIC
+            || magic[UNDO_FILE_MAGIC.len()] != UNDO_FILE_VERSION
+        {
+            return Err(invalid_data("unsupported undo file format"));
+        }
+
+        let mut hash = [0; 20];
+        reader.read_exact(&mut hash)?;
+        if hash != content_hash(text) {
+            return Err(invalid_data("undo file is out of date"));
+        }
+
+        let current = read_usize(reader)?;
+        let len = read_usize(reader)?;
+        let mut revisions: Vec<Revision> = Vec::with_capacity(len.min(1024));
+        // the length of the text of the root revision, once known
+        let mut root_len = if current == 0 {
+            Some(text.len_chars())
+        } else {
+            None
+        };
+        for i in 0..len {
+            let parent = read_usize(reader)?;
+            let last_child = NonZeroUsize::new(read_usize(reader)?);
+            let timestamp = unix_millis_to_instant(read_u64(reader)?);
+            if (i > 0 && parent >= i) || matches!(last_child, Some(child) if child.get() >= len) {
+                return Err(invalid_data("malformed revision tree"));
+            }
+
+            let parent_len = match parent {
+                _ if i == 0 => Some(0),
+                0 => root_len,
+                parent => Some(revisions[parent].transaction.changes().len_after()),
+            };
+            let transaction = read_transaction(reader, parent_len)?;
+            let inversion = read_transaction(reader, Some(transaction.changes().len_after()))?;
+            if i > 0 && parent == 0 {
+                root_len.get_or_insert(transaction.changes().len());
+            }
+
+            revisions.push(Revision {
+                parent,
+                last_child,
+                transaction,
+                inversion,
+                timestamp,
+            });
+        }
+
+        if revisions.is_empty() || current >= revisions.len() {
+            return Err(invalid_data("malformed revision tree"));
+        }
+        for (i, revision) in revisions.iter().enumerate() {
+            if matches!(revision.last_child, Some(child) if revisions[child.get()].parent != i) {
+                return Err(invalid_data("malformed revision tree"));
+            }
+        }
+        check_lengths(&revisions, current, text.len_chars())?;
+
+        Ok(Self { revisions, current })
+    }
+}
+
+/// Checks that the changes of every revision apply to the text of its parent, and its
+/// inversion to the text after them, given the length `text_len` of the text at the
+/// `current` revision. Changes of the wrong length would fail to apply on undo or redo.
+fn check_lengths(revisions: &[Revision], current: usize, text_len: usize) -> io::Result<()> {
+    // the text of the root revision is only known from its children or if it's current
+    let mut root_len = if current == 0 { Some(text_len) } else { None };
+    if current > 0 && revisions[current].transaction.changes().len_after() != text_len {
+        return Err(invalid_data("undo history doesn't match the text"));
+    }
+
+    for revision in &revisions[1..] {
+        let changes = revision.transaction.changes();
+        let inversion = revision.inversion.changes();
+        let parent_len = match revision.parent {
+            0 => *root_len.get_or_insert(changes.len()),
+            parent => revisions[parent].transaction.changes().len_after(),
+        };
+        let len = changes.len_after();
+
+        let selection_fits = |transaction: &Transaction, len: usize| match transaction.selection() {
+            Some(selection) => selection
+                .iter()
+                .all(|range| range.anchor <= len && range.head <= len),
+            None => true,
+        };
+        if changes.len() != parent_len
+            || inversion.len() != len
+            || inversion.len_after() != parent_len
+            || !selection_fits(&revision.transaction, len)
+            || !selection_fits(&revision.inversion, parent_len)
+        {
+            return Err(invalid_data("undo history doesn't match the text"));
+        }
+    }
+    Ok(())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn too_long() -> io::Error {
+    invalid_data("changes are longer than the text")
+}
+
+fn content_hash(text: &Rope) -> [u8; 20] {
+    let mut hasher = sha1_smol::Sha1::new();
+    for chunk in text.chunks() {
+        hasher.update(chunk.as_bytes());
+    }
+    hasher.digest().bytes()
+}
+
+/// Instants can't be persisted, so timestamps are stored relative to the unix epoch.
+fn instant_to_unix_millis(instant: Instant) -> u64 {
+    let age = Instant::now().saturating_duration_since(instant);
+    SystemTime::now()
+        .checked_sub(age)
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_millis() as u64)
+}
+
+fn unix_millis_to_instant(millis: u64) -> Instant {
+    let time = UNIX_EPOCH + Duration::from_millis(millis);
+    let age = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or(Duration::ZERO);
+    let now = Instant::now();
+    now.checked_sub(age).unwrap_or(now)
+}
+
+fn write_u64<W: Write>(writer: &mut W, n: u64) -> io::Result<()> {
+    writer.write_all(&n.to_le_bytes())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn write_usize<W: Write>(writer: &mut W, n: usize) -> io::Result<()> {
+    write_u64(writer, n as u64)
+}
+
+fn read_usize<R: Read>(reader: &mut R) -> io::Result<usize> {
+    usize::try_from(read_u64(reader)?).map_err(|_| invalid_data("integer too large"))
+}
+
+fn write_transaction<W: Write>(writer: &mut W, transaction: &Transaction) -> io::Result<()> {
+    let changes = transaction.changes().changes();
+    write_usize(writer, changes.len())?;
+    for operation in changes {
+        match operation {
+            Operation::Retain(n) => {
+                writer.write_all(&[0])?;
+                write_usize(writer, *n)?;
+            }
+            Operation::Delete(n) => {
+                writer.write_all(&[1])?;
+                write_usize(writer, *n)?;
+            }
+            Operation::Insert(text) => {
+                writer.write_all(&[2])?;
+                write_usize(writer, text.len())?;
+                writer.write_all(text.as_bytes())?;
+            }
+        }
+    }
+
+    match transaction.selection() {
+        Some(selection) => {
+            writer.write_all(&[1])?;
+            write_usize(writer, selection.primary_index())?;
+            write_usize(writer, selection.len())?;
+            for range in selection.iter() {
+                write_usize(writer, range.anchor)?;
+                write_usize(writer, range.head)?;
+                // horiz is transient cursor state and isn't persisted
+            }
+        }
+        None => writer.write_all(&[0])?,
+    }
+    Ok(())
+}
+
+/// Reads a transaction written by `write_transaction`. Fails if its changes don't fit in
+/// `text_len`, the length of the text they apply to, when it's known.
+fn read_transaction<R: Read>(reader: &mut R, text_len: Option<usize>) -> io::Result<Transaction> {
+    let len = read_usize(reader)?;
+    let mut changes = ChangeSet::with_capacity(len.min(1024));
+    // the lengths before and after the changes, which must not overflow
+    let (mut before, mut after) = (0usize, 0usize);
+    for _ in 0..len {
+        let mut tag = [0];
+        reader.read_exact(&mut tag)?;
+        match tag[0] {
+            0 => {
+                let n = read_usize(reader)?;
+                before = before.checked_add(n).ok_or_else(too_long)?;
+                after = after.checked_add(n).ok_or_else(too_long)?;
+                changes.retain(n);
+            }
+            1 => {
+                let n = read_usize(reader)?;
+                before = before.checked_add(n).ok_or_else(too_long)?;
+                changes.delete(n);
+            }
+            2 => {
+                let len = read_usize(reader)?;
+                let mut bytes = Vec::new();
+                reader.take(len as u64).read_to_end(&mut bytes)?;
+                if bytes.len() != len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                let text = String::from_utf8(bytes).map_err(|_| invalid_data("invalid utf-8"))?;
+                after = after
+                    .checked_add(text.chars().count())
+                    .ok_or_else(too_long)?;
+                changes.insert(text.into());
+            }
+            _ => return Err(invalid_data("unknown change operation")),
+        }
+        if matches!(text_len, Some(text_len) if before > text_len) {
+            return Err(too_long());
+        }
+    }
+    let transaction = Transaction::from(changes);
+
+    let mut has_selection = [0];
+    reader.read_exact(&mut has_selection)?;
+    if has_selection[0] == 0 {
+        return Ok(transaction);
+    }
+
+    let primary_index = read_usize(reader)?;
+    let len = read_usize(reader)?;
+    if primary_index >= len {
+        return Err(invalid_data("invalid selection"));
+    }
+    let mut ranges = SmallVec::with_capacity(len.min(1024));
+    for _ in 0..len {
+        let anchor = read_usize(reader)?;
+        let head = read_usize(reader)?;
+        ranges.push(Range::new(anchor, head));
+    }
+    Ok(transaction.with_selection(Selection::new(ranges, primary_index)))
+}
+
 /// Whether to undo by a number of edits or a duration of time.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum UndoKind {
@@ -630,4 +918,108 @@ fn test_parse_undo_kind() {
             Err("duration too large".to_string())
         );
     }
+
+    #[test]
+    fn test_serialize_roundtrip() {
+        let mut history = History::default();
+        let mut state = State {
+            doc: Rope::from("hello"),
+            selection: Selection::point(0),
+        };
+
+        for (from, to, text) in [(5, 5, " world!"), (6, 11, "世界")] {
+            let transaction =
+                Transaction::change(&state.doc, vec![(from, to, Some(text.into()))].into_iter());
+            history.commit_revision(&transaction, &state);
+            transaction.apply(&mut state.doc);
+        }
+        assert_eq!("hello 世界!", state.doc);
+
+        let mut undo_file = Vec::new();
+        history.serialize(&mut undo_file, &state.doc).unwrap();
+
+        // stale undo files are rejected
+        let err =
+            History::deserialize(&mut undo_file.as_slice(), &Rope::from("hello")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // truncated undo files are rejected
+        assert!(History::deserialize(&mut &undo_file[..undo_file.len() - 1], &state.doc).is_err());
+
+        let mut history = History::deserialize(&mut undo_file.as_slice(), &state.doc).unwrap();
+        assert_eq!(history.current_revision(), 2);
+
+        let transaction = history.undo().unwrap();
+        assert_eq!(transaction.selection(), Some(&Selection::point(0)));
+        transaction.apply(&mut state.doc);
+        assert_eq!("hello world!", state.doc);
+        history.undo().unwrap().apply(&mut state.doc);
+        assert_eq!("hello", state.doc);
+        assert!(history.at_root());
+        history.redo().unwrap().apply(&mut state.doc);
+        assert_eq!("hello world!", state.doc);
+    }
+
+    #[test]
+    fn test_deserialize_mismatched_lengths() {
+        let mut history = History::default();
+        let state = State {
+            doc: Rope::from("hello"),
+            selection: Selection::point(0),
+        };
+        let mut doc = state.doc.clone();
+        let transaction = Transaction::change(&doc, vec![(5, 5, Some("!".into()))].into_iter());
+        history.commit_revision(&transaction, &state);
+        transaction.apply(&mut doc);
+
+        let deserialize = |history: &History| {
+            let mut undo_file = Vec::new();
+            history.serialize(&mut undo_file, &doc).unwrap();
+            History::deserialize(&mut undo_file.as_slice(), &doc)
+        };
+        assert!(deserialize(&history).is_ok());
+
+        // the changes of a revision have to apply to the text its inversion results in
+        let original = history.revisions[1].transaction.clone();
+        history.revisions[1].transaction = Transaction::change(
+            &Rope::from("hell"),
+            vec![(4, 4, Some("!!".into()))].into_iter(),
+        )
+        .with_selection(Selection::point(0));
+        let err = deserialize(&history).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // and the inversion to the text the changes result in
+        history.revisions[1].transaction = original;
+        history.revisions[1].inversion = Transaction::from(ChangeSet::new(&doc));
+        let err = deserialize(&history).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_transaction_lengths() {
+        let changes = |operations: &[(u8, usize)]| {
+            let mut bytes = Vec::new();
+            write_usize(&mut bytes, operations.len()).unwrap();
+            for &(tag, n) in operations {
+                bytes.push(tag);
+                write_usize(&mut bytes, n).unwrap();
+            }
+            bytes
+        };
+
+        // lengths that overflow
+        let bytes = changes(&[(0, usize::MAX), (1, 1)]);
+        let err = read_transaction(&mut bytes.as_slice(), None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // and lengths that don't fit in the text
+        let bytes = changes(&[(0, 3), (1, 3)]);
+        let err = read_transaction(&mut bytes.as_slice(), Some(5)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut bytes = changes(&[(0, 3), (1, 2)]);
+        bytes.push(0);
+        let transaction = read_transaction(&mut bytes.as_slice(), Some(5)).unwrap();
+        assert_eq!(transaction.changes().len(), 5);
+    }
 }
@@ -6,11 +6,13 @@
 pub mod config;
 pub mod diagnostic;
 pub mod diff;
+pub mod fold;
 pub mod graphemes;
 pub mod history;
 pub mod increment;
 pub mod indent;
 pub mod line_ending;
+pub mod link;
 pub mod macros;
 pub mod match_brackets;
 pub mod movement;
@@ -21,6 +23,7 @@
 pub mod search;
 pub mod selection;
 pub mod shellwords;
+pub mod snippets;
 pub mod surround;
 pub mod syntax;
 pub mod test;
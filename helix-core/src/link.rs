@@ -0,0 +1,171 @@
+//! Detection and parsing of links in text: URLs and file paths with an optional position.
+
+use crate::RopeSlice;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::ops::Range;
+use std::path::PathBuf;
+
+static URL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\b(?:https?|file)://[^\s<>"'`()\[\]{}]+"#).unwrap());
+
+/// Characters that commonly follow a URL in prose rather than being part of it.
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?'];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Link {
+    /// A URL that is handed to an external opener.
+    Url(String),
+    /// A file, optionally with a 1-based line and column to jump to.
+    Path {
+        path: PathBuf,
+        line: Option<usize>,
+        column: Option<usize>,
+    },
+}
+
+impl Link {
+    /// Parses a link target such as `https://helix-editor.com`, `file:///tmp/a.rs#L3` or
+    /// `src/main.rs:42:7`.
+    pub fn parse(target: &str) -> Self {
+        if let Some(rest) = target.strip_prefix("file://") {
+            let (path, fragment) = rest.split_once('#').unwrap_or((rest, ""));
+            let (line, column) = fragment_position(fragment);
+            return Self::Path {
+                path: PathBuf::from(path),
+                line,
+                column,
+            };
+        }
+        if matches!(URL_REGEX.find(target), Some(url) if url.start() == 0) {
+            return Self::Url(target.to_string());
+        }
+
+        let (path, line, column) = split_position(target);
+        Self::Path {
+            path: PathBuf::from(path),
+            line,
+            column,
+        }
+    }
+}
+
+/// Parses the position of a URL fragment like `L42` or `L42,7`.
+pub fn fragment_position(fragment: &str) -> (Option<usize>, Option<usize>) {
+    let position = match fragment.strip_prefix('L') {
+        Some(position) => position,
+        None => return (None, None),
+    };
+    let (line, column) = match position.split_once([',', ':', 'C']) {
+        Some((line, column)) => (line, Some(column)),
+        None => (position, None),
+    };
+    match line.parse() {
+        Ok(line) => (Some(line), column.and_then(|column| column.parse().ok())),
+        Err(_) => (None, None),
+    }
+}
+
+/// Splits a trailing `:line` or `:line:column` from a path, as printed by compilers and grep.
+fn split_position(target: &str) -> (&str, Option<usize>, Option<usize>) {
+    fn split_number(s: &str) -> Option<(&str, usize)> {
+        let (rest, number) = s.rsplit_once(':')?;
+        Some((rest, number.parse().ok()?))
+    }
+
+    let target = target.trim_end_matches(':');
+    match split_number(target) {
+        Some((rest, last)) => match split_number(rest) {
+            Some((path, line)) => (path, Some(line), Some(last)),
+            None => (rest, Some(last), None),
+        },
+        None => (target, None, None),
+    }
+}
+
+/// Returns the char ranges of the URLs in `text`.
+pub fn find_urls(text: RopeSlice) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut line_start = 0;
+    for line in text.lines() {
+        let line_str = String::from(line);
+        for url in URL_REGEX.find_iter(&line_str) {
+            let end = url.start() + url.as_str().trim_end_matches(TRAILING_PUNCTUATION).len();
+            urls.push(
+                line_start + line.byte_to_char(url.start())..line_start + line.byte_to_char(end),
+            );
+        }
+        line_start += line.len_chars();
+    }
+    urls
+}
+
+/// Returns the char range of the URL under `pos`, if any.
+pub fn url_at(text: RopeSlice, pos: usize) -> Option<Range<usize>> {
+    let line = text.char_to_line(pos);
+    let line_start = text.line_to_char(line);
+    find_urls(text.line(line))
+        .into_iter()
+        .map(|range| line_start + range.start..line_start + range.end)
+        .find(|range| range.contains(&pos))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Rope;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Link::parse("https://helix-editor.com/news"),
+            Link::Url("https://helix-editor.com/news".to_string())
+        );
+        assert_eq!(
+            Link::parse("src/main.rs:42:7"),
+            Link::Path {
+                path: PathBuf::from("src/main.rs"),
+                line: Some(42),
+                column: Some(7),
+            }
+        );
+        assert_eq!(
+            Link::parse("src/main.rs:42:"),
+            Link::Path {
+                path: PathBuf::from("src/main.rs"),
+                line: Some(42),
+                column: None,
+            }
+        );
+        assert_eq!(
+            Link::parse("Cargo.toml"),
+            Link::Path {
+                path: PathBuf::from("Cargo.toml"),
+                line: None,
+                column: None,
+            }
+        );
+        assert_eq!(
+            Link::parse("file:///tmp/a.rs#L3,5"),
+            Link::Path {
+                path: PathBuf::from("/tmp/a.rs"),
+                line: Some(3),
+                column: Some(5),
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_urls() {
+        let text = Rope::from("see https://example.com/a.\n(http://b.org/c?d=e) and x://y\n");
+        let urls: Vec<_> = find_urls(text.slice(..))
+            .into_iter()
+            .map(|range| text.slice(range).to_string())
+            .collect();
+        assert_eq!(urls, ["https://example.com/a", "http://b.org/c?d=e"]);
+
+        assert_eq!(url_at(text.slice(..), 10), Some(4..25));
+        assert_eq!(url_at(text.slice(..), 2), None);
+        assert_eq!(url_at(text.slice(..), 30), Some(28..46));
+    }
+}
@@ -5,6 +5,7 @@
 
 use crate::{
     chars::{categorize_char, char_is_line_ending, CharCategory},
+    fold::HiddenLines,
     graphemes::{
         next_grapheme_boundary, nth_next_grapheme_boundary, nth_prev_grapheme_boundary,
         prev_grapheme_boundary,
@@ -13,7 +14,12 @@
     pos_at_visual_coords,
     syntax::LanguageConfiguration,
     textobject::TextObject,
-    visual_coords_at_pos, Position, Range, RopeSlice,
+    visual_coords_at_pos,
+    wrap::{
+        pos_at_visual_coords_in_wrapped_line, soft_wrap_breaks, visual_coords_in_wrapped_line,
+        WrapFormat,
+    },
+    Position, Range, RopeSlice,
 };
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -80,6 +86,87 @@ pub fn move_vertically(
     new_range
 }
 
+/// Like [`move_vertically`], but moves between the visual rows of soft-wrapped lines
+/// instead of between document lines. `virtual_text` returns the virtual text displayed
+/// on the given document line. The lines in `hidden` are skipped, so that a closed fold
+/// counts as a single line.
+#[allow(clippy::too_many_arguments)]
+pub fn move_vertically_wrapped(
+    slice: RopeSlice,
+    range: Range,
+    dir: Direction,
+    count: usize,
+    behaviour: Movement,
+    format: &WrapFormat,
+    hidden: &HiddenLines,
+    virtual_text: impl Fn(usize) -> Vec<(usize, usize)>,
+) -> Range {
+    let pos = range.cursor(slice);
+
+    // A position hidden by a closed fold moves from the line the fold is displayed on.
+    let mut line_idx = slice.char_to_line(pos);
+    let pos = if hidden.is_hidden(line_idx) {
+        line_idx = hidden.visible_line(line_idx);
+        slice.line_to_char(line_idx)
+    } else {
+        pos
+    };
+
+    // Compute the current position's visual row and column within its line.
+    let mut line_start = slice.line_to_char(line_idx);
+    let mut line = slice.line(line_idx);
+    let mut line_virtual_text = virtual_text(line_idx);
+    let mut breaks = soft_wrap_breaks(line, format, &line_virtual_text);
+    let Position { mut row, col } =
+        visual_coords_in_wrapped_line(line, &breaks, pos - line_start, format, &line_virtual_text);
+    let horiz = range.horiz.unwrap_or(col as u32);
+
+    // Compute the new row, crossing into neighbouring lines as needed.
+    let last_line = slice.len_lines().saturating_sub(1);
+    for _ in 0..count {
+        match dir {
+            Direction::Forward if row < breaks.len() => row += 1,
+            Direction::Backward if row > 0 => row -= 1,
+            Direction::Forward if hidden.next_visible_line(line_idx) <= last_line => {
+                line_idx = hidden.next_visible_line(line_idx);
+                line_start = slice.line_to_char(line_idx);
+                line = slice.line(line_idx);
+                line_virtual_text = virtual_text(line_idx);
+                breaks = soft_wrap_breaks(line, format, &line_virtual_text);
+                row = 0;
+            }
+            Direction::Backward if line_idx > 0 => {
+                line_idx = hidden.visible_line(line_idx - 1);
+                line_start = slice.line_to_char(line_idx);
+                line = slice.line(line_idx);
+                line_virtual_text = virtual_text(line_idx);
+                breaks = soft_wrap_breaks(line, format, &line_virtual_text);
+                row = breaks.len();
+            }
+            _ => break,
+        }
+    }
+
+    // Special-case to avoid moving to the end of the last non-empty line.
+    if behaviour == Movement::Extend && line.len_chars() == 0 {
+        return range;
+    }
+
+    let new_col = col.max(horiz as usize);
+    let new_pos = line_start
+        + pos_at_visual_coords_in_wrapped_line(
+            line,
+            &breaks,
+            Position::new(row, new_col),
+            format,
+            &line_virtual_text,
+        );
+
+    let mut new_range = range.put_cursor(slice, new_pos, behaviour == Movement::Extend);
+    new_range.horiz = Some(horiz);
+    new_range
+}
+
 pub fn move_next_word_start(slice: RopeSlice, range: Range, count: usize) -> Range {
     word_move(slice, range, count, WordMotionTarget::NextWordStart)
 }
@@ -445,7 +532,11 @@ pub fn goto_treesitter_object(
 mod test {
     use ropey::Rope;
 
-    use crate::{coords_at_pos, pos_at_coords};
+    use crate::{
+        coords_at_pos,
+        fold::{Fold, Folds},
+        pos_at_coords,
+    };
 
     use super::*;
 
@@ -479,6 +570,87 @@ fn test_vertical_move() {
         );
     }
 
+    #[test]
+    fn test_vertical_move_wrapped() {
+        let text = Rope::from("hello world foo\nabc");
+        let slice = text.slice(..);
+        let format = WrapFormat {
+            width: 10,
+            tab_width: 4,
+            indicator_width: 2,
+        };
+        let move_down = |range, count| {
+            move_vertically_wrapped(
+                slice,
+                range,
+                Direction::Forward,
+                count,
+                Movement::Move,
+                &format,
+                &HiddenLines::default(),
+                |_| Vec::new(),
+            )
+        };
+
+        // "hello " / "world " / "foo" are the visual rows of the first line
+        let range = Range::point(1);
+        let range = move_down(range, 1);
+        assert_eq!(coords_at_pos(slice, range.head), (0, 6).into());
+        let range = move_down(range, 1);
+        assert_eq!(coords_at_pos(slice, range.head), (0, 12).into());
+        // the indicator column carries over to the next line
+        let range = move_down(range, 1);
+        assert_eq!(coords_at_pos(slice, range.head), (1, 2).into());
+
+        let range = move_vertically_wrapped(
+            slice,
+            range,
+            Direction::Backward,
+            2,
+            Movement::Move,
+            &format,
+            &HiddenLines::default(),
+            |_| Vec::new(),
+        );
+        assert_eq!(coords_at_pos(slice, range.head), (0, 6).into());
+    }
+
+    #[test]
+    fn test_vertical_move_folded() {
+        let text = Rope::from("a {\n  b\n}\nc\n");
+        let slice = text.slice(..);
+        let format = WrapFormat {
+            width: usize::MAX,
+            tab_width: 4,
+            indicator_width: 0,
+        };
+        let mut folds = Folds::default();
+        folds.close(slice, Fold { start: 2, end: 8 });
+        let hidden = folds.hidden_lines(slice);
+        let move_vertically = |range, dir| {
+            move_vertically_wrapped(
+                slice,
+                range,
+                dir,
+                1,
+                Movement::Move,
+                &format,
+                &hidden,
+                |_| Vec::new(),
+            )
+        };
+
+        // the closed fold is skipped in both directions
+        let range = move_vertically(Range::point(0), Direction::Forward);
+        assert_eq!(coords_at_pos(slice, range.head), (3, 0).into());
+        let range = move_vertically(range, Direction::Backward);
+        assert_eq!(coords_at_pos(slice, range.head), (0, 0).into());
+
+        // from inside the fold
+        let range = move_vertically(Range::point(6), Direction::Forward);
+        assert_eq!(coords_at_pos(slice, range.head), (3, 0).into());
+    }
+
     #[test]
     fn horizontal_moves_through_single_line_text() {
         let text = Rope::from(SINGLE_LINE_SAMPLE);
@@ -16,6 +16,59 @@ pub fn shrink_selection(syntax: &Syntax, text: RopeSlice, selection: Selection)
     })
 }
 
+/// Expands each range of the selection to the smallest of its candidate ranges enclosing it.
+/// The candidates of each range, like the selection ranges of a language server, are
+/// ordered from the innermost to the outermost range.
+pub fn expand_selection_to_ranges(selection: Selection, candidates: &[Vec<Range>]) -> Selection {
+    select_range_impl(selection, candidates, |range, mut candidates| {
+        candidates.find(|candidate| {
+            candidate.from() <= range.from()
+                && candidate.to() >= range.to()
+                && candidate.len() > range.len()
+        })
+    })
+}
+
+/// Shrinks each range of the selection to the largest of its candidate ranges inside it, see
+/// [`expand_selection_to_ranges`].
+pub fn shrink_selection_to_ranges(selection: Selection, candidates: &[Vec<Range>]) -> Selection {
+    select_range_impl(selection, candidates, |range, candidates| {
+        candidates.rev().find(|candidate| {
+            candidate.from() >= range.from()
+                && candidate.to() <= range.to()
+                && candidate.len() < range.len()
+        })
+    })
+}
+
+fn select_range_impl<'a, F>(
+    selection: Selection,
+    candidates: &'a [Vec<Range>],
+    select_fn: F,
+) -> Selection
+where
+    F: Fn(Range, std::slice::Iter<'a, Range>) -> Option<&'a Range>,
+{
+    let ranges = selection
+        .ranges()
+        .iter()
+        .enumerate()
+        .map(|(i, &range)| {
+            let candidate = match candidates.get(i).and_then(|c| select_fn(range, c.iter())) {
+                Some(candidate) => candidate,
+                None => return range,
+            };
+            if range.head < range.anchor {
+                Range::new(candidate.to(), candidate.from())
+            } else {
+                Range::new(candidate.from(), candidate.to())
+            }
+        })
+        .collect();
+
+    Selection::new(ranges, selection.primary_index())
+}
+
 pub fn select_sibling<F>(
     syntax: &Syntax,
     text: RopeSlice,
@@ -74,3 +127,34 @@ fn select_node_impl<F>(
         }
     })
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_select_ranges() {
+        // e.g. `foo` in `(foo bar)` in `[(foo bar)]`
+        let candidates = vec![vec![Range::new(2, 5), Range::new(1, 10), Range::new(0, 11)]];
+
+        let cursor = Selection::single(3, 4);
+        let expanded = expand_selection_to_ranges(cursor, &candidates);
+        assert_eq!(expanded, Selection::single(2, 5));
+        let expanded = expand_selection_to_ranges(expanded, &candidates);
+        assert_eq!(expanded, Selection::single(1, 10));
+
+        // the direction of the range is kept
+        let backward = expand_selection_to_ranges(Selection::single(10, 1), &candidates);
+        assert_eq!(backward, Selection::single(11, 0));
+
+        let outermost = Selection::single(0, 11);
+        assert_eq!(
+            expand_selection_to_ranges(outermost.clone(), &candidates),
+            outermost
+        );
+        let shrunk = shrink_selection_to_ranges(outermost, &candidates);
+        assert_eq!(shrunk, Selection::single(1, 10));
+        let shrunk = shrink_selection_to_ranges(shrunk, &candidates);
+        assert_eq!(shrunk, Selection::single(2, 5));
+    }
+}
@@ -0,0 +1,849 @@
+//! Parsing and expansion of snippets in the LSP snippet syntax, see
+//! <https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#snippet_syntax>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    regex::Regex, Assoc, ChangeSet, Range, Rope, RopeSlice, Selection, Tendril, Transaction,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnippetElement {
+    /// `$1` or `${1}`
+    Tabstop {
+        tabstop: usize,
+    },
+    /// `${1:default}`
+    Placeholder {
+        tabstop: usize,
+        value: Vec<SnippetElement>,
+    },
+    /// `${1|one,two,three|}`
+    Choice {
+        tabstop: usize,
+        choices: Vec<String>,
+    },
+    /// `$name`, `${name:default}` or `${name/regex/format/options}`
+    Variable {
+        name: String,
+        default: Option<Vec<SnippetElement>>,
+        transform: Option<Transform>,
+    },
+    Text(String),
+}
+
+/// A regex replacement applied to the value of a variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transform {
+    pub regex: String,
+    pub format: String,
+    pub options: String,
+}
+
+impl Transform {
+    fn apply(&self, value: &str) -> String {
+        let regex = match Regex::new(&self.regex) {
+            Ok(regex) => regex,
+            Err(err) => {
+                log::error!("invalid regex in snippet transform: {}", err);
+                return value.to_string();
+            }
+        };
+        if self.options.contains('g') {
+            regex.replace_all(value, self.format.as_str()).into_owned()
+        } else {
+            regex.replace(value, self.format.as_str()).into_owned()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Snippet {
+    pub elements: Vec<SnippetElement>,
+}
+
+impl Snippet {
+    /// Parses a snippet. Malformed syntax is kept as literal text, like VS Code does.
+    pub fn parse(snippet: &str) -> Self {
+        let mut parser = Parser {
+            input: snippet,
+            pos: 0,
+        };
+        Self {
+            elements: parser.parse_any(&[]),
+        }
+    }
+
+    /// Renders the snippet to text. Lines after the first are prefixed with `indent`
+    /// and tabs are replaced with `indent_unit`. `resolve_variable` returns the value
+    /// of a variable, or `None` if it's unknown.
+    pub fn render(
+        &self,
+        indent: &str,
+        indent_unit: &str,
+        resolve_variable: &dyn Fn(&str) -> Option<String>,
+    ) -> RenderedSnippet {
+        let mut renderer = Renderer {
+            indent,
+            indent_unit,
+            resolve_variable,
+            text: String::new(),
+            len_chars: 0,
+            tabstops: Vec::new(),
+            choices: Vec::new(),
+        };
+        renderer.render(&self.elements);
+
+        let mut tabstops = renderer.tabstops;
+        // the cursor ends up at the end of the snippet if it doesn't specify a final tabstop
+        if !tabstops.iter().any(|(tabstop, _)| *tabstop == 0) {
+            tabstops.push((0, renderer.len_chars..renderer.len_chars));
+        }
+        RenderedSnippet {
+            text: renderer.text.into(),
+            tabstops,
+            choices: renderer.choices,
+        }
+    }
+}
+
+/// The text of a snippet along with the char ranges (relative to the start of the
+/// text) of its tabstops.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedSnippet {
+    pub text: Tendril,
+    pub tabstops: Vec<(usize, std::ops::Range<usize>)>,
+    /// The options of the choice tabstops by tabstop number.
+    pub choices: Vec<(usize, Vec<String>)>,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        if self.peek() == Some(ch) {
+            self.pos += ch.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, ch: char) -> Option<()> {
+        if self.eat(ch) {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn int(&mut self) -> Option<usize> {
+        let len = self.input[self.pos..]
+            .find(|ch: char| !ch.is_ascii_digit())
+            .unwrap_or(self.input.len() - self.pos);
+        let int = self.input[self.pos..self.pos + len].parse().ok()?;
+        self.pos += len;
+        Some(int)
+    }
+
+    fn var_name(&mut self) -> Option<String> {
+        let rest = &self.input[self.pos..];
+        if !rest.starts_with(|ch: char| ch == '_' || ch.is_ascii_alphabetic()) {
+            return None;
+        }
+        let len = rest
+            .find(|ch: char| ch != '_' && !ch.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        self.pos += len;
+        Some(rest[..len].to_string())
+    }
+
+    /// Parses text up to one of the unescaped `terminators`, which is not consumed.
+    fn text(&mut self, terminators: &[char]) -> String {
+        let mut text = String::new();
+        while let Some(ch) = self.peek() {
+            if terminators.contains(&ch) {
+                break;
+            }
+            self.next();
+            match ch {
+                '\\' => match self.peek() {
+                    Some(escaped) if escaped == '\\' || terminators.contains(&escaped) => {
+                        self.next();
+                        text.push(escaped);
+                    }
+                    _ => text.push('\\'),
+                },
+                ch => text.push(ch),
+            }
+        }
+        text
+    }
+
+    /// Parses snippet elements up to one of the unescaped `terminators`, which is not
+    /// consumed.
+    fn parse_any(&mut self, terminators: &[char]) -> Vec<SnippetElement> {
+        let mut elements = Vec::new();
+        let mut text = String::new();
+        while let Some(ch) = self.peek() {
+            if terminators.contains(&ch) {
+                break;
+            }
+            match ch {
+                '\\' => {
+                    self.next();
+                    match self.peek() {
+                        Some(escaped @ ('$' | '}' | '\\')) => {
+                            self.next();
+                            text.push(escaped);
+                        }
+                        _ => text.push('\\'),
+                    }
+                }
+                '$' => {
+                    let start = self.pos;
+                    match self.parse_dollar() {
+                        Some(element) => {
+                            if !text.is_empty() {
+                                elements.push(SnippetElement::Text(std::mem::take(&mut text)));
+                            }
+                            elements.push(element);
+                        }
+                        None => {
+                            // not a valid construct, keep the `$` as text
+                            self.pos = start + 1;
+                            text.push('$');
+                        }
+                    }
+                }
+                ch => {
+                    self.next();
+                    text.push(ch);
+                }
+            }
+        }
+        if !text.is_empty() {
+            elements.push(SnippetElement::Text(text));
+        }
+        elements
+    }
+
+    fn parse_dollar(&mut self) -> Option<SnippetElement> {
+        self.next(); // `$`
+        if let Some(tabstop) = self.int() {
+            return Some(SnippetElement::Tabstop { tabstop });
+        }
+        if let Some(name) = self.var_name() {
+            return Some(SnippetElement::Variable {
+                name,
+                default: None,
+                transform: None,
+            });
+        }
+        if !self.eat('{') {
+            return None;
+        }
+
+        if let Some(tabstop) = self.int() {
+            return match self.next()? {
+                '}' => Some(SnippetElement::Tabstop { tabstop }),
+                ':' => {
+                    let value = self.parse_any(&['}']);
+                    self.expect('}')?;
+                    Some(SnippetElement::Placeholder { tabstop, value })
+                }
+                '|' => {
+                    let mut choices = vec![self.text(&[',', '|'])];
+                    while self.eat(',') {
+                        choices.push(self.text(&[',', '|']));
+                    }
+                    self.expect('|')?;
+                    self.expect('}')?;
+                    Some(SnippetElement::Choice { tabstop, choices })
+                }
+                _ => None,
+            };
+        }
+
+        let name = self.var_name()?;
+        match self.next()? {
+            '}' => Some(SnippetElement::Variable {
+                name,
+                default: None,
+                transform: None,
+            }),
+            ':' => {
+                let default = self.parse_any(&['}']);
+                self.expect('}')?;
+                Some(SnippetElement::Variable {
+                    name,
+                    default: Some(default),
+                    transform: None,
+                })
+            }
+            '/' => {
+                let regex = self.text(&['/']);
+                self.expect('/')?;
+                let format = self.text(&['/']);
+                self.expect('/')?;
+                let options = self.text(&['}']);
+                self.expect('}')?;
+                Some(SnippetElement::Variable {
+                    name,
+                    default: None,
+                    transform: Some(Transform {
+                        regex,
+                        format,
+                        options,
+                    }),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Renderer<'a> {
+    indent: &'a str,
+    indent_unit: &'a str,
+    resolve_variable: &'a dyn Fn(&str) -> Option<String>,
+    text: String,
+    len_chars: usize,
+    tabstops: Vec<(usize, std::ops::Range<usize>)>,
+    choices: Vec<(usize, Vec<String>)>,
+}
+
+impl Renderer<'_> {
+    fn push_str(&mut self, text: &str) {
+        for ch in text.chars() {
+            match ch {
+                '\n' => {
+                    self.text.push('\n');
+                    self.text.push_str(self.indent);
+                    self.len_chars += 1 + self.indent.chars().count();
+                }
+                '\t' => {
+                    self.text.push_str(self.indent_unit);
+                    self.len_chars += self.indent_unit.chars().count();
+                }
+                ch => {
+                    self.text.push(ch);
+                    self.len_chars += 1;
+                }
+            }
+        }
+    }
+
+    fn render(&mut self, elements: &[SnippetElement]) {
+        for element in elements {
+            let start = self.len_chars;
+            match element {
+                SnippetElement::Tabstop { tabstop } => self.tabstops.push((*tabstop, start..start)),
+                SnippetElement::Placeholder { tabstop, value } => {
+                    self.render(value);
+                    self.tabstops.push((*tabstop, start..self.len_chars));
+                }
+                SnippetElement::Choice { tabstop, choices } => {
+                    // the first choice is the placeholder, all of them are offered once
+                    // the tabstop is selected
+                    self.push_str(choices.first().map_or("", String::as_str));
+                    self.tabstops.push((*tabstop, start..self.len_chars));
+                    self.choices.push((*tabstop, choices.clone()));
+                }
+                SnippetElement::Variable {
+                    name,
+                    default,
+                    transform,
+                } => match ((self.resolve_variable)(name), default) {
+                    (Some(value), _) => {
+                        let value = match transform {
+                            Some(transform) => transform.apply(&value),
+                            None => value,
+                        };
+                        self.push_str(&value);
+                    }
+                    (None, Some(default)) => self.render(default),
+                    // unknown variables are inserted as their name
+                    (None, None) => self.push_str(name),
+                },
+                SnippetElement::Text(text) => self.push_str(text),
+            }
+        }
+    }
+}
+
+/// Resolves the variables defined by the LSP specification that are available
+/// without editor state, for the cursor of `range` in a document at `path`.
+pub fn resolve_variable(
+    name: &str,
+    text: RopeSlice,
+    range: Range,
+    path: Option<&Path>,
+) -> Option<String> {
+    let cursor = range.cursor(text);
+    let line = text.char_to_line(cursor);
+    let value = match name {
+        "TM_SELECTED_TEXT" => range.fragment(text).into_owned(),
+        "TM_CURRENT_LINE" => {
+            let line = text.line(line);
+            let end = crate::line_ending::rope_end_without_line_ending(&line);
+            line.slice(..end).to_string()
+        }
+        "TM_CURRENT_WORD" => {
+            let word = crate::textobject::textobject_word(
+                text,
+                Range::point(cursor),
+                crate::textobject::TextObject::Inside,
+                1,
+                false,
+            );
+            word.fragment(text).into_owned()
+        }
+        "TM_LINE_INDEX" => line.to_string(),
+        "TM_LINE_NUMBER" => (line + 1).to_string(),
+        "TM_FILENAME" => path?.file_name()?.to_string_lossy().into_owned(),
+        "TM_FILENAME_BASE" => path?.file_stem()?.to_string_lossy().into_owned(),
+        "TM_DIRECTORY" => path?.parent()?.to_string_lossy().into_owned(),
+        "TM_FILEPATH" => path?.to_string_lossy().into_owned(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Expands `snippet` at every range of `selection`, replacing the text from
+/// `start_offset` to `end_offset` relative to each cursor. Continuation lines of the
+/// snippet are indented like the line of the cursor.
+///
+/// Returns the transaction along with the [`ActiveSnippet`] used to jump between the
+/// tabstops, which is `None` if the snippet has no tabstops besides the final one.
+/// The transaction selects the first tabstop.
+pub fn expand(
+    doc: &Rope,
+    selection: &Selection,
+    snippet: &Snippet,
+    start_offset: i128,
+    end_offset: i128,
+    indent_unit: &str,
+    path: Option<&Path>,
+) -> (Transaction, Option<ActiveSnippet>) {
+    let text = doc.slice(..);
+    let mut tabstops: BTreeMap<usize, Vec<Range>> = BTreeMap::new();
+    let mut choices: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    // difference in length between the document before and after the changes so far
+    let mut offset = 0i128;
+
+    let transaction = Transaction::change_by_selection(doc, selection, |range| {
+        let cursor = range.cursor(text) as i128;
+        let from = (cursor + start_offset) as usize;
+        let to = (cursor + end_offset) as usize;
+
+        let line = text.line(text.char_to_line(from));
+        let indent: String = line
+            .chars()
+            .take_while(|ch| *ch == ' ' || *ch == '\t')
+            .collect();
+        let rendered = snippet.render(&indent, indent_unit, &|name| {
+            resolve_variable(name, text, *range, path)
+        });
+
+        let start = from as i128 + offset;
+        for (tabstop, tabstop_range) in rendered.tabstops {
+            let anchor = (start + tabstop_range.start as i128) as usize;
+            let head = (start + tabstop_range.end as i128) as usize;
+            tabstops
+                .entry(tabstop)
+                .or_default()
+                .push(Range::new(anchor, head));
+        }
+        choices.extend(rendered.choices);
+        offset += rendered.text.chars().count() as i128 - (to - from) as i128;
+
+        let replacement = if rendered.text.is_empty() {
+            None
+        } else {
+            Some(rendered.text)
+        };
+        (from, to, replacement)
+    });
+
+    // tabstops are visited in ascending order, with the final tabstop `$0` last
+    let final_tabstop = tabstops.remove(&0).unwrap_or_default();
+    let mut tabstop_choices: Vec<_> = tabstops
+        .keys()
+        .map(|tabstop| choices.remove(tabstop).unwrap_or_default())
+        .collect();
+    tabstop_choices.push(choices.remove(&0).unwrap_or_default());
+    let mut tabstops: Vec<_> = tabstops.into_values().collect();
+    tabstops.push(final_tabstop);
+
+    let selection = Selection::new(tabstops[0].iter().copied().collect(), 0);
+    let transaction = transaction.with_selection(selection);
+    let active_snippet = if tabstops.len() > 1 {
+        Some(ActiveSnippet {
+            tabstops,
+            choices: tabstop_choices,
+            current: 0,
+            choices_offered: false,
+        })
+    } else {
+        None
+    };
+    (transaction, active_snippet)
+}
+
+/// A snippet that was expanded into a document, tracking the positions of its
+/// tabstops to move between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveSnippet {
+    /// The ranges of every tabstop in the order they are visited. Tabstops with the
+    /// same number, or snippets expanded at multiple cursors, have multiple ranges.
+    tabstops: Vec<Vec<Range>>,
+    /// The options of every tabstop, empty if it isn't a choice.
+    choices: Vec<Vec<String>>,
+    current: usize,
+    /// Whether the choices of the current tabstop were taken since it was selected.
+    choices_offered: bool,
+}
+
+impl ActiveSnippet {
+    /// Maps the tabstops through `changes`. Text inserted at the edges of a tabstop
+    /// becomes part of it.
+    pub fn map(&mut self, changes: &ChangeSet) {
+        for ranges in &mut self.tabstops {
+            for range in ranges.iter_mut() {
+                let from = changes.map_pos(range.from(), Assoc::Before);
+                let to = changes.map_pos(range.to(), Assoc::After);
+                *range = Range::new(from, to);
+            }
+        }
+    }
+
+    fn selection(&self, text: RopeSlice) -> Selection {
+        Selection::new(self.tabstops[self.current].iter().copied().collect(), 0)
+            .ensure_invariants(text)
+    }
+
+    /// Whether the current tabstop is a selected placeholder, which is replaced when
+    /// typing.
+    pub fn is_placeholder_selected(&self, selection: &Selection) -> bool {
+        let ranges = &self.tabstops[self.current];
+        ranges.iter().any(|range| !range.is_empty())
+            && selection.len() == ranges.len()
+            && selection
+                .iter()
+                .zip(ranges)
+                .all(|(a, b)| a.from() == b.from() && a.to() == b.to())
+    }
+
+    /// Whether the final tabstop is selected, after which the snippet is done.
+    pub fn is_done(&self) -> bool {
+        self.current + 1 == self.tabstops.len()
+    }
+
+    /// Moves to the next tabstop and returns the selection of its ranges.
+    pub fn next_tabstop(&mut self, text: RopeSlice) -> Selection {
+        self.select((self.current + 1).min(self.tabstops.len() - 1));
+        self.selection(text)
+    }
+
+    /// Moves to the previous tabstop and returns the selection of its ranges.
+    pub fn prev_tabstop(&mut self, text: RopeSlice) -> Selection {
+        self.select(self.current.saturating_sub(1));
+        self.selection(text)
+    }
+
+    fn select(&mut self, tabstop: usize) {
+        if tabstop != self.current {
+            self.current = tabstop;
+            self.choices_offered = false;
+        }
+    }
+
+    /// The options of the current tabstop if it's a choice, returned only once after
+    /// the tabstop was selected so they're offered a single time.
+    pub fn take_choices(&mut self) -> Option<&[String]> {
+        let choices = &self.choices[self.current];
+        if self.choices_offered || choices.is_empty() {
+            return None;
+        }
+        self.choices_offered = true;
+        Some(choices)
+    }
+
+    /// Replaces every range of the current tabstop with `choice`, selecting the
+    /// inserted text.
+    pub fn choose(&self, doc: &Rope, choice: &str) -> Transaction {
+        let mut ranges = self.tabstops[self.current].clone();
+        ranges.sort_by_key(|range| range.from());
+        let len = choice.chars().count() as i128;
+        // difference in length between the document before and after the changes so far
+        let mut offset = 0i128;
+        let selection = ranges
+            .iter()
+            .map(|range| {
+                let from = (range.from() as i128 + offset) as usize;
+                offset += len - (range.to() - range.from()) as i128;
+                Range::new(from, from + len as usize)
+            })
+            .collect();
+        let changes = ranges
+            .iter()
+            .map(|range| (range.from(), range.to(), Some(choice.into())));
+        Transaction::change(doc, changes).with_selection(Selection::new(selection, 0))
+    }
+}
+
+/// A snippet defined by the user, in the format of VS Code snippet files.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct UserSnippet {
+    #[serde(deserialize_with = "deserialize_one_or_many")]
+    pub prefix: Vec<String>,
+    #[serde(deserialize_with = "deserialize_one_or_many")]
+    pub body: Vec<String>,
+    pub description: Option<String>,
+}
+
+impl UserSnippet {
+    /// The body of the snippet with its lines joined.
+    pub fn body(&self) -> String {
+        self.body.join("\n")
+    }
+}
+
+fn deserialize_one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// The snippets defined by the user, by language name.
+#[derive(Debug, Clone, Default)]
+pub struct UserSnippets {
+    languages: HashMap<String, Vec<(String, UserSnippet)>>,
+}
+
+impl UserSnippets {
+    /// Loads the `<language name>.json` snippet files of the user's snippet directory.
+    pub fn load() -> Self {
+        Self::load_dir(&helix_loader::snippet_dir())
+    }
+
+    fn load_dir(dir: &Path) -> Self {
+        let mut languages = HashMap::new();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Self::default(),
+        };
+        for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            match Self::load_file(&path) {
+                Ok(snippets) => {
+                    let language = path.file_stem().unwrap().to_string_lossy().into_owned();
+                    languages.insert(language, snippets);
+                }
+                Err(err) => log::error!("failed to load snippets {}: {}", path.display(), err),
+            }
+        }
+        Self { languages }
+    }
+
+    fn load_file(path: &Path) -> std::io::Result<Vec<(String, UserSnippet)>> {
+        let content = std::fs::read_to_string(path)?;
+        let snippets: BTreeMap<String, UserSnippet> = serde_json::from_str(&content)?;
+        Ok(snippets.into_iter().collect())
+    }
+
+    /// The snippets of a language as pairs of their names and definitions.
+    pub fn language_snippets(&self, language: &str) -> &[(String, UserSnippet)] {
+        self.languages
+            .get(language)
+            .map_or(&[], |snippets| snippets.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use SnippetElement::*;
+
+    fn text(text: &str) -> SnippetElement {
+        Text(text.to_string())
+    }
+
+    #[test]
+    fn parse_tabstops_and_placeholders() {
+        assert_eq!(
+            Snippet::parse("fn ${1:name}($2) {\n\t$0\n}").elements,
+            vec![
+                text("fn "),
+                Placeholder {
+                    tabstop: 1,
+                    value: vec![text("name")],
+                },
+                text("("),
+                Tabstop { tabstop: 2 },
+                text(") {\n\t"),
+                Tabstop { tabstop: 0 },
+                text("\n}"),
+            ]
+        );
+        // nested placeholders
+        assert_eq!(
+            Snippet::parse("${1:a ${2:b}}").elements,
+            vec![Placeholder {
+                tabstop: 1,
+                value: vec![
+                    text("a "),
+                    Placeholder {
+                        tabstop: 2,
+                        value: vec![text("b")],
+                    }
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_choices_and_variables() {
+        assert_eq!(
+            Snippet::parse("${1|one,t\\,wo|}$TM_FILENAME${NAME:x}${A/(.*)/$1/g}").elements,
+            vec![
+                Choice {
+                    tabstop: 1,
+                    choices: vec!["one".to_string(), "t,wo".to_string()],
+                },
+                Variable {
+                    name: "TM_FILENAME".to_string(),
+                    default: None,
+                    transform: None,
+                },
+                Variable {
+                    name: "NAME".to_string(),
+                    default: Some(vec![text("x")]),
+                    transform: None,
+                },
+                Variable {
+                    name: "A".to_string(),
+                    default: None,
+                    transform: Some(Transform {
+                        regex: "(.*)".to_string(),
+                        format: "$1".to_string(),
+                        options: "g".to_string(),
+                    }),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_malformed_as_text() {
+        assert_eq!(Snippet::parse("$ ${1").elements, vec![text("$ ${1")]);
+        assert_eq!(Snippet::parse("\\$1 \\}").elements, vec![text("$1 }")]);
+        assert_eq!(Snippet::parse("a}").elements, vec![text("a}")]);
+    }
+
+    #[test]
+    fn render() {
+        let snippet = Snippet::parse("fn ${1:name}($2) {\n\t$0\n}");
+        let rendered = snippet.render("  ", "    ", &|_| None);
+        assert_eq!(rendered.text, "fn name() {\n      \n  }");
+        assert_eq!(rendered.tabstops, vec![(1, 3..7), (2, 8..8), (0, 18..18)]);
+
+        // a final tabstop is added at the end, variables resolve or fall back
+        let snippet = Snippet::parse("${A:x}${B:y}$C${D/o/0/g}");
+        let rendered = snippet.render("", "", &|name| match name {
+            "A" => Some("a".to_string()),
+            "D" => Some("foo".to_string()),
+            _ => None,
+        });
+        assert_eq!(rendered.text, "ayCf00");
+        assert_eq!(rendered.tabstops, vec![(0, 6..6)]);
+    }
+
+    #[test]
+    fn expand_at_multiple_cursors() {
+        let doc = Rope::from("ab\n  cd\n");
+        let selection = Selection::new(smallvec::smallvec![Range::point(1), Range::point(6)], 0);
+        let snippet = Snippet::parse("(${1:x}, $2)$0");
+        let (transaction, active_snippet) = expand(&doc, &selection, &snippet, -1, 0, "\t", None);
+
+        let mut doc = doc;
+        assert!(transaction.apply(&mut doc));
+        assert_eq!(doc, "(x, )b\n  (x, )d\n");
+        assert_eq!(
+            transaction.selection().unwrap().ranges(),
+            &[Range::new(1, 2), Range::new(10, 11)]
+        );
+
+        let mut active_snippet = active_snippet.unwrap();
+        let selection = active_snippet.next_tabstop(doc.slice(..));
+        assert_eq!(selection.ranges(), &[Range::new(4, 5), Range::new(13, 14)]);
+        assert!(!active_snippet.is_done());
+        let selection = active_snippet.next_tabstop(doc.slice(..));
+        assert_eq!(selection.ranges(), &[Range::new(5, 6), Range::new(14, 15)]);
+        assert!(active_snippet.is_done());
+    }
+
+    #[test]
+    fn choose_choice() {
+        let doc = Rope::from("a\nb\n");
+        let selection = Selection::new(smallvec::smallvec![Range::point(1), Range::point(3)], 0);
+        let snippet = Snippet::parse("${1|one,three|}: $2");
+        let (transaction, active_snippet) = expand(&doc, &selection, &snippet, 0, 0, "\t", None);
+
+        let mut doc = doc;
+        assert!(transaction.apply(&mut doc));
+        assert_eq!(doc, "aone: \nbone: \n");
+
+        let mut active_snippet = active_snippet.unwrap();
+        assert_eq!(
+            active_snippet.take_choices(),
+            Some(&["one".to_string(), "three".to_string()][..])
+        );
+        assert_eq!(active_snippet.take_choices(), None);
+
+        let transaction = active_snippet.choose(&doc, "three");
+        assert!(transaction.apply(&mut doc));
+        assert_eq!(doc, "athree: \nbthree: \n");
+        assert_eq!(
+            transaction.selection().unwrap().ranges(),
+            &[Range::new(1, 6), Range::new(10, 15)]
+        );
+        active_snippet.map(transaction.changes());
+
+        let selection = active_snippet.next_tabstop(doc.slice(..));
+        assert_eq!(selection.ranges(), &[Range::new(8, 9), Range::new(17, 18)]);
+        assert_eq!(active_snippet.take_choices(), None);
+        active_snippet.prev_tabstop(doc.slice(..));
+        assert!(active_snippet.take_choices().is_some());
+    }
+}
@@ -16,7 +16,7 @@
 use std::{
     borrow::Cow,
     cell::RefCell,
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     fmt,
     hash::{Hash, Hasher},
     mem::{replace, transmute},
@@ -55,6 +55,27 @@ pub fn deserialize_auto_pairs<'de, D>(deserializer: D) -> Result<Option<AutoPair
     Ok(Option::<AutoPairConfig>::deserialize(deserializer)?.and_then(AutoPairConfig::into))
 }
 
+fn deserialize_block_comment_tokens<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<BlockCommentToken>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BlockCommentTokens {
+        One(BlockCommentToken),
+        Many(Vec<BlockCommentToken>),
+    }
+
+    Ok(
+        Option::<BlockCommentTokens>::deserialize(deserializer)?.map(|tokens| match tokens {
+            BlockCommentTokens::One(token) => vec![token],
+            BlockCommentTokens::Many(tokens) => tokens,
+        }),
+    )
+}
+
 fn default_timeout() -> u64 {
     20
 }
@@ -82,6 +103,12 @@ pub struct LanguageConfiguration {
     pub shebangs: Vec<String>, // interpreter(s) associated with language
     pub roots: Vec<String>,        // these indicate project roots <.git, Cargo.toml>
     pub comment_token: Option<String>,
+    #[serde(
+        default,
+        skip_serializing,
+        deserialize_with = "deserialize_block_comment_tokens"
+    )]
+    pub block_comment_tokens: Option<Vec<BlockCommentToken>>,
     pub max_line_length: Option<usize>,
 
     #[serde(default, skip_serializing, deserialize_with = "deserialize_lsp_config")]
@@ -108,6 +135,9 @@ pub struct LanguageConfiguration {
     // tags_config OnceCell<> https://github.com/tree-sitter/tree-sitter/pull/583
     #[serde(skip_serializing_if = "Option::is_none")]
     pub language_server: Option<LanguageServerConfiguration>,
+    /// Multiple language servers for this language. Takes precedence over `language_server`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub language_servers: Vec<LanguageServerConfiguration>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub indent: Option<IndentationConfiguration>,
 
@@ -115,6 +145,8 @@ pub struct LanguageConfiguration {
     pub(crate) indent_query: OnceCell<Option<Query>>,
     #[serde(skip)]
     pub(crate) textobject_query: OnceCell<Option<TextObjectQuery>>,
+    #[serde(skip)]
+    pub(crate) fold_query: OnceCell<Option<Query>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub debugger: Option<DebugAdapterConfig>,
 
@@ -126,6 +158,35 @@ pub struct LanguageConfiguration {
     pub auto_pairs: Option<AutoPairs>,
 
     pub rulers: Option<Vec<u16>>, // if set, override editor's rulers
+
+    /// Soft wrapping of long lines. Overrides the global setting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_wrap: Option<SoftWrap>,
+
+    /// Highlight semantic tokens of the language server on top of the tree-sitter
+    /// highlights.
+    #[serde(default)]
+    pub semantic_tokens: bool,
+    /// Expand and shrink selections with the selection ranges of the language server instead
+    /// of the tree-sitter tree.
+    #[serde(default)]
+    pub selection_ranges: bool,
+}
+
+/// The start and end tokens of a block comment, like `/*` and `*/`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockCommentToken {
+    pub start: String,
+    pub end: String,
+}
+
+impl Default for BlockCommentToken {
+    fn default() -> Self {
+        Self {
+            start: "/*".to_string(),
+            end: "*/".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -200,9 +261,73 @@ fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LanguageServerFeature {
+    Format,
+    GotoDefinition,
+    GotoTypeDefinition,
+    GotoReference,
+    GotoImplementation,
+    GotoDeclaration,
+    SignatureHelp,
+    Hover,
+    DocumentHighlight,
+    Completion,
+    CodeAction,
+    WorkspaceCommand,
+    DocumentSymbols,
+    WorkspaceSymbols,
+    Diagnostics,
+    RenameSymbol,
+    InlayHints,
+    SemanticTokens,
+    FoldingRange,
+    CallHierarchy,
+    TypeHierarchy,
+    CodeLens,
+    DocumentLinks,
+    SelectionRange,
+}
+
+impl fmt::Display for LanguageServerFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use LanguageServerFeature::*;
+        let feature = match self {
+            Format => "format",
+            GotoDefinition => "goto-definition",
+            GotoTypeDefinition => "goto-type-definition",
+            GotoReference => "goto-reference",
+            GotoImplementation => "goto-implementation",
+            GotoDeclaration => "goto-declaration",
+            SignatureHelp => "signature-help",
+            Hover => "hover",
+            DocumentHighlight => "document-highlight",
+            Completion => "completion",
+            CodeAction => "code-action",
+            WorkspaceCommand => "workspace-command",
+            DocumentSymbols => "document-symbols",
+            WorkspaceSymbols => "workspace-symbols",
+            Diagnostics => "diagnostics",
+            RenameSymbol => "rename-symbol",
+            InlayHints => "inlay-hints",
+            SemanticTokens => "semantic-tokens",
+            FoldingRange => "folding-range",
+            CallHierarchy => "call-hierarchy",
+            TypeHierarchy => "type-hierarchy",
+            CodeLens => "code-lens",
+            DocumentLinks => "document-links",
+            SelectionRange => "selection-range",
+        };
+        write!(f, "{}", feature)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct LanguageServerConfiguration {
+    /// Name used to identify the server in messages. Defaults to `command`.
+    pub name: Option<String>,
     pub command: String,
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -212,6 +337,27 @@ pub struct LanguageServerConfiguration {
     #[serde(default = "default_timeout")]
     pub timeout: u64,
     pub language_id: Option<String>,
+    /// Server specific initialization options. Overrides the language's `config`.
+    #[serde(default, skip_serializing, deserialize_with = "deserialize_lsp_config")]
+    pub config: Option<serde_json::Value>,
+    /// If non-empty, the server is only used for these features.
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub only_features: HashSet<LanguageServerFeature>,
+    /// The server is never used for these features.
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub except_features: HashSet<LanguageServerFeature>,
+}
+
+impl LanguageServerConfiguration {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.command)
+    }
+
+    /// Whether the configuration allows this server to be used for `feature`.
+    pub fn supports_feature(&self, feature: LanguageServerFeature) -> bool {
+        (self.only_features.is_empty() || self.only_features.contains(&feature))
+            && !self.except_features.contains(&feature)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -284,6 +430,15 @@ pub struct IndentationConfiguration {
     pub unit: String,
 }
 
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SoftWrap {
+    /// Soft wrap lines that exceed the width of the view. Defaults to false.
+    pub enable: Option<bool>,
+    /// Text drawn at the start of each wrapped continuation row. Defaults to "↪ ".
+    pub wrap_indicator: Option<String>,
+}
+
 /// Configuration for auto pairs
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields, untagged)]
@@ -377,7 +532,7 @@ pub fn byte_range(&self) -> std::ops::Range<usize> {
 /// Neovim chose 64 for this value somewhat arbitrarily (<https://github.com/neovim/neovim/pull/18397>).
 /// 64 is too low for some languages though. In particular, it breaks some highlighting for record fields in Erlang record definitions.
 /// This number can be increased if new syntax highlight breakages are found, as long as the performance penalty is not too high.
-const TREE_SITTER_MATCH_LIMIT: u32 = 256;
+pub(crate) const TREE_SITTER_MATCH_LIMIT: u32 = 256;
 
 impl TextObjectQuery {
     /// Run the query on the given node and return sub nodes which match given
@@ -524,10 +679,25 @@ pub fn textobject_query(&self) -> Option<&TextObjectQuery> {
             .as_ref()
     }
 
+    pub fn fold_query(&self) -> Option<&Query> {
+        self.fold_query
+            .get_or_init(|| self.load_query("folds.scm"))
+            .as_ref()
+    }
+
     pub fn scope(&self) -> &str {
         &self.scope
     }
 
+    /// The language servers configured for this language. `language-servers` takes
+    /// precedence over the single `language-server` entry.
+    pub fn language_servers(&self) -> &[LanguageServerConfiguration] {
+        match &self.language_server {
+            Some(config) if self.language_servers.is_empty() => std::slice::from_ref(config),
+            _ => &self.language_servers,
+        }
+    }
+
     fn load_query(&self, kind: &str) -> Option<Query> {
         let query_text = read_query(&self.language_id, kind);
         if query_text.is_empty() {
@@ -1043,6 +1213,33 @@ pub fn tree(&self) -> &Tree {
         self.layers[self.root].tree()
     }
 
+    /// Returns the language configuration of the deepest injection layer that contains
+    /// the given byte range, which is the language of the text within it.
+    pub fn language_config_for_byte_range(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Option<Arc<LanguageConfiguration>> {
+        let layer = self
+            .layers
+            .values()
+            .filter(|layer| {
+                layer
+                    .ranges
+                    .iter()
+                    .any(|range| range.start_byte <= start && end <= range.end_byte)
+            })
+            .max_by_key(|layer| layer.depth)?;
+
+        self.loader
+            .language_configs()
+            .find(|config| {
+                matches!(config.highlight_config.get(), Some(Some(highlight_config))
+                    if Arc::ptr_eq(highlight_config, &layer.config))
+            })
+            .cloned()
+    }
+
     /// Iterate over the highlighted regions for a given slice of source code.
     pub fn highlight_iter<'a>(
         &'a self,
@@ -2526,4 +2723,32 @@ fn test_load_runtime_file() {
         let results = load_runtime_file("rust", "does-not-exist");
         assert!(results.is_err());
     }
+
+    #[test]
+    fn test_language_servers_config() {
+        let config: LanguageConfiguration = toml::from_str(
+            r#"
+            name = "test"
+            scope = "source.test"
+            file-types = ["test"]
+            roots = []
+            language-server = { command = "ignored" }
+            language-servers = [
+              { command = "main-lsp", except-features = ["format"] },
+              { name = "linter", command = "lint-lsp", only-features = ["diagnostics", "format"] },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let servers = config.language_servers();
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].name(), "main-lsp");
+        assert_eq!(servers[1].name(), "linter");
+
+        assert!(servers[0].supports_feature(LanguageServerFeature::Hover));
+        assert!(!servers[0].supports_feature(LanguageServerFeature::Format));
+        assert!(servers[1].supports_feature(LanguageServerFeature::Format));
+        assert!(!servers[1].supports_feature(LanguageServerFeature::Completion));
+    }
 }
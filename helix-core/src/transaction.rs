@@ -55,6 +55,16 @@ pub fn changes(&self) -> &[Operation] {
         &self.changes
     }
 
+    /// The length of the document the changes apply to.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The length of the document after the changes.
+    pub(crate) fn len_after(&self) -> usize {
+        self.len_after
+    }
+
     // Changeset builder operations: delete/insert/retain
     pub(crate) fn delete(&mut self, n: usize) {
         use Operation::*;
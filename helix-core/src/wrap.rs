@@ -1,7 +1,326 @@
+use std::borrow::Cow;
+
 use smartstring::{LazyCompact, SmartString};
 
+use crate::{
+    graphemes::{grapheme_width, prev_grapheme_boundary, RopeGraphemes},
+    line_ending::rope_end_without_line_ending,
+    LineEnding, Position, RopeSlice,
+};
+
 /// Given a slice of text, return the text re-wrapped to fit it
 /// within the given width.
 pub fn reflow_hard_wrap(text: &str, max_line_len: usize) -> SmartString<LazyCompact> {
     textwrap::refill(text, max_line_len).into()
 }
+
+/// Describes how document lines are laid out into visual rows when soft wrapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrapFormat {
+    /// Number of columns available to display a row of text.
+    pub width: usize,
+    pub tab_width: usize,
+    /// Number of columns taken by the indicator rendered at the start of every
+    /// continuation row.
+    pub indicator_width: usize,
+}
+
+impl WrapFormat {
+    /// The number of text columns available on the given visual row of a line.
+    fn row_width(&self, row: usize) -> usize {
+        let width = if row == 0 {
+            self.width
+        } else {
+            self.width.saturating_sub(self.indicator_width)
+        };
+        width.max(1)
+    }
+}
+
+/// The number of columns `grapheme` takes up when placed at column `col` of a row.
+/// Line endings take up a single column so that a cursor placed on them is visible.
+fn grapheme_cols(grapheme: RopeSlice, col: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        tab_width - (col % tab_width)
+    } else if LineEnding::from_rope_slice(&grapheme).is_some() {
+        1
+    } else {
+        grapheme_width(&Cow::from(grapheme))
+    }
+}
+
+/// Virtual text (such as inlay hints) displayed within a line, as pairs of the char
+/// index relative to the start of the line and the number of columns taken up. The
+/// text is displayed in front of the grapheme at that index and the pairs are sorted
+/// by char index.
+pub type VirtualText = [(usize, usize)];
+
+/// Sums the widths of the virtual text displayed in front of the char at `char_idx`
+/// that hasn't been consumed from `virtual_text` yet.
+fn take_virtual_text<'a>(
+    virtual_text: &mut std::iter::Peekable<impl Iterator<Item = &'a (usize, usize)>>,
+    char_idx: usize,
+) -> usize {
+    let mut width = 0;
+    while let Some((_, text_width)) = virtual_text.next_if(|(idx, _)| *idx <= char_idx) {
+        width += text_width;
+    }
+    width
+}
+
+/// Computes where the visual rows of a soft-wrapped document line begin.
+///
+/// `line` is a single document line including its line ending. The returned char
+/// indices are relative to the start of `line`, one for every row after the first,
+/// so an empty result means the line fits on a single row. Rows are broken after
+/// whitespace where possible and between graphemes for words that don't fit a row.
+/// Virtual text is always kept on the same row as the grapheme following it.
+pub fn soft_wrap_breaks(
+    line: RopeSlice,
+    format: &WrapFormat,
+    virtual_text: &VirtualText,
+) -> Vec<usize> {
+    let mut breaks = Vec::new();
+    let mut row_width = format.row_width(0);
+    let mut char_idx = 0;
+    let mut col = 0;
+    // char index and column directly after the last whitespace in the current row
+    let mut word_start: Option<(usize, usize)> = None;
+    let mut virtual_text = virtual_text.iter().peekable();
+
+    for grapheme in RopeGraphemes::new(line) {
+        let virtual_width = take_virtual_text(&mut virtual_text, char_idx);
+        let mut width =
+            virtual_width + grapheme_cols(grapheme, col + virtual_width, format.tab_width);
+        while col > 0 && col + width > row_width {
+            let (break_idx, break_col) = word_start.take().unwrap_or((char_idx, col));
+            breaks.push(break_idx);
+            row_width = format.row_width(breaks.len());
+            col -= break_col;
+            width = virtual_width + grapheme_cols(grapheme, col + virtual_width, format.tab_width);
+        }
+
+        col += width;
+        char_idx += grapheme.len_chars();
+        if grapheme == " " || grapheme == "\t" {
+            word_start = Some((char_idx, col));
+        }
+    }
+
+    breaks
+}
+
+/// Returns the visual row and column of `char_idx` (relative to the start of `line`)
+/// within a line that was soft-wrapped at `breaks`.
+///
+/// Columns on continuation rows include the width of the wrap indicator, and the
+/// column of `char_idx` is past any virtual text displayed in front of it.
+pub fn visual_coords_in_wrapped_line(
+    line: RopeSlice,
+    breaks: &[usize],
+    char_idx: usize,
+    format: &WrapFormat,
+    virtual_text: &VirtualText,
+) -> Position {
+    let row = breaks.partition_point(|&break_idx| break_idx <= char_idx);
+    let row_start = row.checked_sub(1).map_or(0, |i| breaks[i]);
+
+    let mut col = 0;
+    let mut virtual_text = virtual_text
+        .iter()
+        .skip_while(|(idx, _)| *idx < row_start)
+        .peekable();
+    let mut grapheme_idx = row_start;
+    for grapheme in RopeGraphemes::new(line.slice(row_start..char_idx)) {
+        col += take_virtual_text(&mut virtual_text, grapheme_idx);
+        col += grapheme_cols(grapheme, col, format.tab_width);
+        grapheme_idx += grapheme.len_chars();
+    }
+    col += take_virtual_text(&mut virtual_text, char_idx);
+
+    if row > 0 {
+        col += format.indicator_width;
+    }
+
+    Position::new(row, col)
+}
+
+/// Returns the char index (relative to the start of `line`) displayed at the given
+/// visual row and column of a line that was soft-wrapped at `breaks`.
+///
+/// Rows past the last row of the line are clamped to the last row. Columns past the
+/// end of a row select the last grapheme of that row, or the position just before the
+/// line ending on the last row. Columns on virtual text select the grapheme following it.
+pub fn pos_at_visual_coords_in_wrapped_line(
+    line: RopeSlice,
+    breaks: &[usize],
+    coords: Position,
+    format: &WrapFormat,
+    virtual_text: &VirtualText,
+) -> usize {
+    let row = coords.row.min(breaks.len());
+    let row_start = row.checked_sub(1).map_or(0, |i| breaks[i]);
+    let is_last_row = row == breaks.len();
+    let row_end = if is_last_row {
+        rope_end_without_line_ending(&line)
+    } else {
+        breaks[row]
+    };
+
+    let mut cols_remaining = if row > 0 {
+        coords.col.saturating_sub(format.indicator_width)
+    } else {
+        coords.col
+    };
+    let mut col = 0;
+    let mut char_idx = row_start;
+    let mut virtual_text = virtual_text
+        .iter()
+        .skip_while(|(idx, _)| *idx < row_start)
+        .peekable();
+
+    for grapheme in RopeGraphemes::new(line.slice(row_start..row_end)) {
+        let virtual_width = take_virtual_text(&mut virtual_text, char_idx);
+        let width = virtual_width + grapheme_cols(grapheme, col + virtual_width, format.tab_width);
+        // If pos is in the middle of a wider grapheme (tab for example)
+        // return the starting offset.
+        if width > cols_remaining {
+            break;
+        }
+        cols_remaining -= width;
+        col += width;
+        char_idx += grapheme.len_chars();
+    }
+
+    // `row_end` is displayed at the start of the next row, stay on this one
+    if !is_last_row && char_idx == row_end && row_end > row_start {
+        char_idx = prev_grapheme_boundary(line, char_idx);
+    }
+
+    char_idx
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Rope;
+
+    const FORMAT: WrapFormat = WrapFormat {
+        width: 10,
+        tab_width: 4,
+        indicator_width: 2,
+    };
+
+    #[test]
+    fn test_soft_wrap_breaks() {
+        let breaks = |text: &str| soft_wrap_breaks(Rope::from(text).slice(..), &FORMAT, &[]);
+
+        // fits on a single row, including the line ending
+        assert!(breaks("short\n").is_empty());
+        assert!(breaks("123456789\n").is_empty());
+        // the line ending itself is wrapped
+        assert_eq!(breaks("1234567890\n"), vec![10]);
+        // break after whitespace
+        assert_eq!(breaks("hello world foo\n"), vec![6, 12]);
+        // words longer than a row are split between graphemes
+        assert_eq!(breaks("abcdefghijklmnop\n"), vec![10]);
+        // continuation rows leave room for the wrap indicator
+        assert_eq!(breaks("abcdefghijklmnopqrstuvwxyz"), vec![10, 18]);
+        // tabs take up the rest of their tab stop and count as whitespace
+        assert_eq!(breaks("\t\tabcdef"), vec![2]);
+    }
+
+    #[test]
+    fn test_wrapped_coords_roundtrip() {
+        let text = Rope::from("hello world foo\n");
+        let line = text.slice(..);
+        let breaks = soft_wrap_breaks(line, &FORMAT, &[]);
+
+        assert_eq!(
+            visual_coords_in_wrapped_line(line, &breaks, 0, &FORMAT, &[]),
+            Position::new(0, 0)
+        );
+        assert_eq!(
+            visual_coords_in_wrapped_line(line, &breaks, 5, &FORMAT, &[]),
+            Position::new(0, 5)
+        );
+        // "world" starts the second row after the indicator
+        assert_eq!(
+            visual_coords_in_wrapped_line(line, &breaks, 6, &FORMAT, &[]),
+            Position::new(1, 2)
+        );
+        assert_eq!(
+            visual_coords_in_wrapped_line(line, &breaks, 15, &FORMAT, &[]),
+            Position::new(2, 5)
+        );
+
+        for char_idx in 0..line.len_chars() {
+            let coords = visual_coords_in_wrapped_line(line, &breaks, char_idx, &FORMAT, &[]);
+            let pos = pos_at_visual_coords_in_wrapped_line(line, &breaks, coords, &FORMAT, &[]);
+            assert_eq!(pos, char_idx.min(15));
+        }
+    }
+
+    #[test]
+    fn test_pos_at_visual_coords_in_wrapped_line() {
+        let text = Rope::from("hello world foo\n");
+        let line = text.slice(..);
+        let breaks = soft_wrap_breaks(line, &FORMAT, &[]);
+        let pos = |row, col| {
+            pos_at_visual_coords_in_wrapped_line(
+                line,
+                &breaks,
+                Position::new(row, col),
+                &FORMAT,
+                &[],
+            )
+        };
+
+        // past the end of a continued row: the trailing space of that row
+        assert_eq!(pos(0, 9), 5);
+        // inside the wrap indicator
+        assert_eq!(pos(1, 0), 6);
+        assert_eq!(pos(1, 3), 7);
+        // past the end of the last row: just before the line ending
+        assert_eq!(pos(2, 9), 15);
+        // rows past the end of the line are clamped
+        assert_eq!(pos(5, 0), 12);
+    }
+
+    #[test]
+    fn test_virtual_text() {
+        let format = WrapFormat {
+            width: 12,
+            ..FORMAT
+        };
+        let text = Rope::from("let x = 1;\n");
+        let line = text.slice(..);
+        // an inlay hint ": i32" displayed after `x`
+        let virtual_text = [(5, 5)];
+        let breaks = soft_wrap_breaks(line, &format, &virtual_text);
+        assert_eq!(breaks, vec![6]);
+
+        let coords = |char_idx| {
+            visual_coords_in_wrapped_line(line, &breaks, char_idx, &format, &virtual_text)
+        };
+        assert_eq!(coords(4), Position::new(0, 4));
+        // the hint is displayed in front of the space
+        assert_eq!(coords(5), Position::new(0, 10));
+        assert_eq!(coords(6), Position::new(1, 2));
+
+        let pos = |row, col| {
+            pos_at_visual_coords_in_wrapped_line(
+                line,
+                &breaks,
+                Position::new(row, col),
+                &format,
+                &virtual_text,
+            )
+        };
+        // columns on the hint select the grapheme following it
+        assert_eq!(pos(0, 7), 5);
+        for char_idx in 0..line.len_chars() {
+            assert_eq!(pos(coords(char_idx).row, coords(char_idx).col), char_idx);
+        }
+    }
+}
@@ -221,6 +221,13 @@ pub fn resume_application(&mut self) {
         self.thread_id = None;
     }
 
+    /// The active stack frame of the current thread.
+    pub fn current_stack_frame(&self) -> Option<&StackFrame> {
+        self.stack_frames
+            .get(&self.thread_id?)?
+            .get(self.active_frame?)
+    }
+
     /// Execute a RPC request on the debugger.
     pub fn call<R: crate::types::Request>(
         &self,
@@ -371,6 +378,43 @@ pub async fn set_breakpoints(
         Ok(response.breakpoints)
     }
 
+    pub async fn set_function_breakpoints(
+        &self,
+        breakpoints: Vec<FunctionBreakpoint>,
+    ) -> Result<Vec<Breakpoint>> {
+        let args = requests::SetFunctionBreakpointsArguments { breakpoints };
+
+        let response = self
+            .request::<requests::SetFunctionBreakpoints>(args)
+            .await?;
+        Ok(response.breakpoints)
+    }
+
+    /// Asks whether a data breakpoint can be set on the variable `name` of the container
+    /// `variables_reference`, or on the expression `name` without a container.
+    pub fn data_breakpoint_info(
+        &self,
+        variables_reference: Option<usize>,
+        name: String,
+    ) -> impl Future<Output = Result<Value>> {
+        let args = requests::DataBreakpointInfoArguments {
+            variables_reference,
+            name,
+        };
+
+        self.call::<requests::DataBreakpointInfo>(args)
+    }
+
+    pub async fn set_data_breakpoints(
+        &self,
+        breakpoints: Vec<DataBreakpoint>,
+    ) -> Result<Vec<Breakpoint>> {
+        let args = requests::SetDataBreakpointsArguments { breakpoints };
+
+        let response = self.request::<requests::SetDataBreakpoints>(args).await?;
+        Ok(response.breakpoints)
+    }
+
     pub async fn configuration_done(&self) -> Result<()> {
         self.request::<requests::ConfigurationDone>(()).await
     }
@@ -454,19 +498,52 @@ pub fn pause(&self, thread_id: ThreadId) -> impl Future<Output = Result<Value>>
         self.call::<requests::Pause>(args)
     }
 
-    pub async fn eval(
+    pub fn eval(
         &self,
         expression: String,
         frame_id: Option<usize>,
-    ) -> Result<requests::EvaluateResponse> {
+        context: Option<String>,
+    ) -> impl Future<Output = Result<Value>> {
         let args = requests::EvaluateArguments {
             expression,
             frame_id,
-            context: None,
+            context,
+            format: None,
+        };
+
+        self.call::<requests::Evaluate>(args)
+    }
+
+    pub fn set_variable(
+        &self,
+        variables_reference: usize,
+        name: String,
+        value: String,
+    ) -> impl Future<Output = Result<Value>> {
+        let args = requests::SetVariableArguments {
+            variables_reference,
+            name,
+            value,
+            format: None,
+        };
+
+        self.call::<requests::SetVariable>(args)
+    }
+
+    pub fn set_expression(
+        &self,
+        expression: String,
+        value: String,
+        frame_id: Option<usize>,
+    ) -> impl Future<Output = Result<Value>> {
+        let args = requests::SetExpressionArguments {
+            expression,
+            value,
+            frame_id,
             format: None,
         };
 
-        self.request::<requests::Evaluate>(args).await
+        self.call::<requests::SetExpression>(args)
     }
 
     pub fn set_exception_breakpoints(
@@ -0,0 +1,279 @@
+//! Debug configurations of VS Code's `.vscode/launch.json`.
+
+use crate::Result;
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+use std::path::Path;
+
+/// Properties of a launch.json configuration which are interpreted by VS Code instead of
+/// being passed to the debug adapter.
+const EDITOR_PROPERTIES: &[&str] = &[
+    "name",
+    "type",
+    "request",
+    "debugServer",
+    "preLaunchTask",
+    "postDebugTask",
+    "presentation",
+    "internalConsoleOptions",
+    "serverReadyAction",
+    "windows",
+    "osx",
+    "linux",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaunchConfiguration {
+    pub name: String,
+    /// The VS Code debugger type, e.g. `lldb` or `go`.
+    pub ty: String,
+    /// `launch` or `attach`.
+    pub request: String,
+    /// The port of an already running debug adapter to connect to.
+    pub debug_server: Option<u16>,
+    /// The arguments of the launch or attach request.
+    pub args: Map<String, Value>,
+}
+
+impl LaunchConfiguration {
+    fn from_value(value: Value) -> Option<Self> {
+        let mut properties = match value {
+            Value::Object(properties) => properties,
+            _ => return None,
+        };
+        // the properties of the current platform override the others
+        if let Some(Value::Object(platform)) = properties.remove(platform()) {
+            properties.extend(platform);
+        }
+
+        let string = |key: &str| properties.get(key)?.as_str().map(String::from);
+        let name = string("name")?;
+        let ty = string("type")?;
+        let request = string("request")?;
+        let debug_server = properties
+            .get("debugServer")
+            .and_then(Value::as_u64)
+            .and_then(|port| u16::try_from(port).ok());
+
+        properties.retain(|key, _| !EDITOR_PROPERTIES.contains(&key.as_str()));
+        Some(Self {
+            name,
+            ty,
+            request,
+            debug_server,
+            args: properties,
+        })
+    }
+
+    /// The name of the debug adapter in the language configuration that handles this
+    /// configuration.
+    pub fn adapter_name(&self) -> &str {
+        match self.ty.as_str() {
+            "lldb" | "lldb-dap" | "codelldb" => "lldb-vscode",
+            "coreclr" => "netcoredbg",
+            "node" | "node2" | "pwa-node" => "node-debug2",
+            ty => ty,
+        }
+    }
+
+    /// Replaces the `${variable}` references in the string arguments. `${env:NAME}` is
+    /// replaced with an environment variable, the other variables are looked up with
+    /// `resolve`. Unknown variables are kept.
+    pub fn substitute_variables(&mut self, resolve: impl Fn(&str) -> Option<String>) {
+        for value in self.args.values_mut() {
+            substitute(value, &resolve);
+        }
+    }
+}
+
+fn platform() -> &'static str {
+    if cfg!(windows) {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "osx"
+    } else {
+        "linux"
+    }
+}
+
+fn substitute(value: &mut Value, resolve: &impl Fn(&str) -> Option<String>) {
+    match value {
+        Value::String(string) => *string = substitute_str(string, resolve),
+        Value::Array(values) => {
+            for value in values {
+                substitute(value, resolve);
+            }
+        }
+        Value::Object(properties) => {
+            for value in properties.values_mut() {
+                substitute(value, resolve);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn substitute_str(mut string: &str, resolve: &impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(string.len());
+    while let Some(start) = string.find("${") {
+        let end = match string[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = &string[start + 2..end];
+        let value = match name.strip_prefix("env:") {
+            Some(name) => Some(std::env::var(name).unwrap_or_default()),
+            None => resolve(name),
+        };
+        result.push_str(&string[..start]);
+        match value {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&string[start..=end]),
+        }
+        string = &string[end + 1..];
+    }
+    result.push_str(string);
+    result
+}
+
+/// Removes the comments and trailing commas VS Code allows in its JSON files.
+fn strip_jsonc(content: &str) -> String {
+    let mut stripped = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            stripped.push(c);
+            match c {
+                '\\' => stripped.extend(chars.next()),
+                '"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        stripped.push(c);
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = None;
+                for c in chars.by_ref() {
+                    if prev == Some('*') && c == '/' {
+                        break;
+                    }
+                    prev = Some(c);
+                }
+                stripped.push(' ');
+            }
+            ('"', _) => {
+                in_string = true;
+                stripped.push(c);
+            }
+            _ => stripped.push(c),
+        }
+    }
+
+    // comments are gone, so a comma is trailing if the next character that isn't
+    // whitespace closes an object or array
+    let mut result = String::with_capacity(stripped.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in stripped.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = stripped[i + 1..].trim_start().chars().next();
+            if matches!(next, Some('}' | ']')) {
+                continue;
+            }
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Parses the configurations of a launch.json file, skipping the ones which lack a name,
+/// type or request.
+pub fn parse(content: &str) -> Result<Vec<LaunchConfiguration>> {
+    let mut root: Value = serde_json::from_str(&strip_jsonc(content))?;
+    let configurations = match root.get_mut("configurations").map(Value::take) {
+        Some(Value::Array(configurations)) => configurations,
+        _ => return Ok(Vec::new()),
+    };
+    Ok(configurations
+        .into_iter()
+        .filter_map(LaunchConfiguration::from_value)
+        .collect())
+}
+
+/// Loads the configurations of the `.vscode/launch.json` file of the workspace `root`.
+pub fn load(root: &Path) -> Result<Vec<LaunchConfiguration>> {
+    let path = root.join(".vscode").join("launch.json");
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    parse(&std::fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        let content = r#"{
+            // Use IntelliSense to learn about possible attributes.
+            "version": "0.2.0",
+            "configurations": [
+                {
+                    "name": "Debug // tests",
+                    "type": "lldb",
+                    "request": "launch",
+                    /* the test binary */
+                    "program": "${workspaceFolder}/target/debug/app",
+                    "args": ["${file}", "${unknown}",],
+                    "preLaunchTask": "build",
+                    "linux": { "program": "app-linux" },
+                    "osx": { "program": "app-osx" },
+                    "windows": { "program": "app-windows" },
+                },
+                { "name": "Incomplete" },
+            ],
+        }"#;
+        let mut configurations = parse(content).unwrap();
+        assert_eq!(configurations.len(), 1);
+
+        let configuration = &mut configurations[0];
+        assert_eq!(configuration.name, "Debug // tests");
+        assert_eq!(configuration.adapter_name(), "lldb-vscode");
+        assert_eq!(configuration.request, "launch");
+        assert_eq!(
+            configuration.args.get("program"),
+            Some(&json!(format!("app-{}", platform())))
+        );
+        assert!(!configuration.args.contains_key("preLaunchTask"));
+
+        configuration.substitute_variables(|name| match name {
+            "file" => Some("/src/main.rs".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            configuration.args.get("args"),
+            Some(&json!(["/src/main.rs", "${unknown}"]))
+        );
+    }
+}
@@ -1,4 +1,5 @@
 mod client;
+pub mod launch;
 mod transport;
 mod types;
 
@@ -92,6 +92,26 @@ pub fn log_file() -> PathBuf {
     cache_dir().join("helix.log")
 }
 
+pub fn undo_dir() -> PathBuf {
+    cache_dir().join("undo")
+}
+
+pub fn session_dir() -> PathBuf {
+    cache_dir().join("sessions")
+}
+
+pub fn breakpoints_dir() -> PathBuf {
+    cache_dir().join("breakpoints")
+}
+
+pub fn snippet_dir() -> PathBuf {
+    config_dir().join("snippets")
+}
+
+pub fn plugin_dir() -> PathBuf {
+    config_dir().join("plugins")
+}
+
 pub fn find_local_config_dirs() -> Vec<PathBuf> {
     let current_dir = std::env::current_dir().expect("unable to determine current directory");
     let mut directories = Vec::new();
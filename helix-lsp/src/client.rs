@@ -4,16 +4,17 @@
     Call, Error, OffsetEncoding, Result,
 };
 
-use helix_core::{find_root, ChangeSet, Rope};
+use helix_core::{find_root, syntax::LanguageServerFeature, ChangeSet, Rope};
 use helix_loader::{self, VERSION_AND_GIT_HASH};
 use lsp_types as lsp;
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::future::Future;
+use std::path::Path;
 use std::process::Stdio;
 use std::sync::{
-    atomic::{AtomicU64, Ordering},
+    atomic::{AtomicBool, AtomicU64, Ordering},
     Arc,
 };
 use tokio::{
@@ -28,22 +29,28 @@
 #[derive(Debug)]
 pub struct Client {
     id: usize,
+    name: String,
     _process: Child,
     server_tx: UnboundedSender<Payload>,
     request_counter: AtomicU64,
     pub(crate) capabilities: OnceCell<lsp::ServerCapabilities>,
+    /// Whether the server advertises `typeHierarchyProvider`, which `lsp::ServerCapabilities`
+    /// doesn't know about.
+    type_hierarchy_provider: AtomicBool,
     offset_encoding: OffsetEncoding,
     config: Option<Value>,
     root_path: std::path::PathBuf,
     root_uri: Option<lsp::Url>,
     workspace_folders: Vec<lsp::WorkspaceFolder>,
     req_timeout: u64,
+    watch_files: bool,
 }
 
 impl Client {
     #[allow(clippy::type_complexity)]
     #[allow(clippy::too_many_arguments)]
     pub fn start(
+        name: String,
         cmd: &str,
         args: &[String],
         config: Option<Value>,
@@ -52,6 +59,7 @@ pub fn start(
         id: usize,
         req_timeout: u64,
         doc_path: Option<&std::path::PathBuf>,
+        watch_files: bool,
     ) -> Result<(Self, UnboundedReceiver<(usize, Call)>, Arc<Notify>)> {
         // Resolve path to the binary
         let cmd = which::which(cmd).map_err(|err| anyhow::anyhow!(err))?;
@@ -100,13 +108,16 @@ pub fn start(
 
         let client = Self {
             id,
+            name,
             _process: process,
             server_tx,
             request_counter: AtomicU64::new(0),
             capabilities: OnceCell::new(),
+            type_hierarchy_provider: AtomicBool::new(false),
             offset_encoding: OffsetEncoding::Utf8,
             config,
             req_timeout,
+            watch_files,
 
             root_path,
             root_uri,
@@ -120,6 +131,10 @@ pub fn id(&self) -> usize {
         self.id
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     fn next_request_id(&self) -> jsonrpc::Id {
         let id = self.request_counter.fetch_add(1, Ordering::Relaxed);
         jsonrpc::Id::Num(id)
@@ -146,6 +161,153 @@ pub fn capabilities(&self) -> &lsp::ServerCapabilities {
             .expect("language server not yet initialized!")
     }
 
+    /// Whether the server advertises the capabilities needed for `feature`.
+    /// Always false while the server is still initializing.
+    pub fn supports_feature(&self, feature: LanguageServerFeature) -> bool {
+        let capabilities = match self.capabilities.get() {
+            Some(capabilities) => capabilities,
+            None => return false,
+        };
+
+        match feature {
+            LanguageServerFeature::Format => matches!(
+                capabilities.document_formatting_provider,
+                Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_))
+            ),
+            LanguageServerFeature::GotoDefinition => matches!(
+                capabilities.definition_provider,
+                Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_))
+            ),
+            LanguageServerFeature::GotoTypeDefinition => matches!(
+                capabilities.type_definition_provider,
+                Some(
+                    lsp::TypeDefinitionProviderCapability::Simple(true)
+                        | lsp::TypeDefinitionProviderCapability::Options(_),
+                )
+            ),
+            LanguageServerFeature::GotoReference => matches!(
+                capabilities.references_provider,
+                Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_))
+            ),
+            LanguageServerFeature::GotoImplementation => matches!(
+                capabilities.implementation_provider,
+                Some(
+                    lsp::ImplementationProviderCapability::Simple(true)
+                        | lsp::ImplementationProviderCapability::Options(_),
+                )
+            ),
+            LanguageServerFeature::GotoDeclaration => matches!(
+                capabilities.declaration_provider,
+                Some(
+                    lsp::DeclarationCapability::Simple(true)
+                        | lsp::DeclarationCapability::RegistrationOptions(_)
+                        | lsp::DeclarationCapability::Options(_),
+                )
+            ),
+            LanguageServerFeature::SignatureHelp => capabilities.signature_help_provider.is_some(),
+            LanguageServerFeature::Hover => matches!(
+                capabilities.hover_provider,
+                Some(
+                    lsp::HoverProviderCapability::Simple(true)
+                        | lsp::HoverProviderCapability::Options(_),
+                )
+            ),
+            LanguageServerFeature::DocumentHighlight => matches!(
+                capabilities.document_highlight_provider,
+                Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_))
+            ),
+            LanguageServerFeature::Completion => capabilities.completion_provider.is_some(),
+            LanguageServerFeature::CodeAction => matches!(
+                capabilities.code_action_provider,
+                Some(
+                    lsp::CodeActionProviderCapability::Simple(true)
+                        | lsp::CodeActionProviderCapability::Options(_),
+                )
+            ),
+            LanguageServerFeature::WorkspaceCommand => {
+                capabilities.execute_command_provider.is_some()
+            }
+            LanguageServerFeature::DocumentSymbols => matches!(
+                capabilities.document_symbol_provider,
+                Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_))
+            ),
+            LanguageServerFeature::WorkspaceSymbols => matches!(
+                capabilities.workspace_symbol_provider,
+                Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_))
+            ),
+            // diagnostics are pushed by the server
+            LanguageServerFeature::Diagnostics => true,
+            LanguageServerFeature::RenameSymbol => matches!(
+                capabilities.rename_provider,
+                Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_))
+            ),
+            LanguageServerFeature::InlayHints => matches!(
+                capabilities.inlay_hint_provider,
+                Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_))
+            ),
+            LanguageServerFeature::FoldingRange => matches!(
+                capabilities.folding_range_provider,
+                Some(
+                    lsp::FoldingRangeProviderCapability::Simple(true)
+                        | lsp::FoldingRangeProviderCapability::FoldingProvider(_)
+                        | lsp::FoldingRangeProviderCapability::Options(_)
+                )
+            ),
+            LanguageServerFeature::CallHierarchy => matches!(
+                capabilities.call_hierarchy_provider,
+                Some(
+                    lsp::CallHierarchyServerCapability::Simple(true)
+                        | lsp::CallHierarchyServerCapability::Options(_)
+                )
+            ),
+            LanguageServerFeature::TypeHierarchy => {
+                self.type_hierarchy_provider.load(Ordering::Relaxed)
+            }
+            LanguageServerFeature::CodeLens => capabilities.code_lens_provider.is_some(),
+            LanguageServerFeature::DocumentLinks => capabilities.document_link_provider.is_some(),
+            LanguageServerFeature::SelectionRange => {
+                capabilities.selection_range_provider.is_some()
+            }
+            LanguageServerFeature::SemanticTokens => matches!(
+                self.semantic_tokens_options()
+                    .and_then(|options| options.full.as_ref()),
+                Some(
+                    lsp::SemanticTokensFullOptions::Bool(true)
+                        | lsp::SemanticTokensFullOptions::Delta { .. }
+                )
+            ),
+        }
+    }
+
+    fn semantic_tokens_options(&self) -> Option<&lsp::SemanticTokensOptions> {
+        match self.capabilities.get()?.semantic_tokens_provider.as_ref()? {
+            lsp::SemanticTokensServerCapabilities::SemanticTokensOptions(options) => Some(options),
+            lsp::SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(options) => {
+                Some(&options.semantic_tokens_options)
+            }
+        }
+    }
+
+    /// The token types and modifiers the indices in semantic tokens refer to.
+    pub fn semantic_tokens_legend(&self) -> Option<&lsp::SemanticTokensLegend> {
+        self.semantic_tokens_options()
+            .map(|options| &options.legend)
+    }
+
+    /// The options of the server for pulling diagnostics, if it supports the pull model.
+    pub fn diagnostic_options(&self) -> Option<&lsp::DiagnosticOptions> {
+        match self.capabilities.get()?.diagnostic_provider.as_ref()? {
+            lsp::DiagnosticServerCapabilities::Options(options) => Some(options),
+            lsp::DiagnosticServerCapabilities::RegistrationOptions(options) => {
+                Some(&options.diagnostic_options)
+            }
+        }
+    }
+
+    pub fn root_path(&self) -> &std::path::Path {
+        &self.root_path
+    }
+
     pub fn offset_encoding(&self) -> OffsetEncoding {
         self.offset_encoding
     }
@@ -304,12 +466,33 @@ pub(crate) async fn initialize(&self) -> Result<lsp::InitializeResult> {
                     execute_command: Some(lsp::DynamicRegistrationClientCapabilities {
                         dynamic_registration: Some(false),
                     }),
+                    inlay_hint: Some(lsp::InlayHintWorkspaceClientCapabilities {
+                        refresh_support: Some(false),
+                    }),
+                    semantic_tokens: Some(lsp::SemanticTokensWorkspaceClientCapabilities {
+                        refresh_support: Some(false),
+                    }),
+                    code_lens: Some(lsp::CodeLensWorkspaceClientCapabilities {
+                        refresh_support: Some(false),
+                    }),
+                    diagnostic: Some(lsp::DiagnosticWorkspaceClientCapabilities {
+                        refresh_support: Some(false),
+                    }),
+                    did_change_watched_files: Some(lsp::DidChangeWatchedFilesClientCapabilities {
+                        dynamic_registration: Some(self.watch_files),
+                        relative_pattern_support: Some(true),
+                    }),
+                    file_operations: Some(lsp::WorkspaceFileOperationsClientCapabilities {
+                        will_rename: Some(true),
+                        did_rename: Some(true),
+                        ..Default::default()
+                    }),
                     ..Default::default()
                 }),
                 text_document: Some(lsp::TextDocumentClientCapabilities {
                     completion: Some(lsp::CompletionClientCapabilities {
                         completion_item: Some(lsp::CompletionItemCapability {
-                            snippet_support: Some(false),
+                            snippet_support: Some(true),
                             resolve_support: Some(lsp::CompletionItemCapabilityResolveSupport {
                                 properties: vec![
                                     String::from("documentation"),
@@ -371,6 +554,84 @@ pub(crate) async fn initialize(&self) -> Result<lsp::InitializeResult> {
                     publish_diagnostics: Some(lsp::PublishDiagnosticsClientCapabilities {
                         ..Default::default()
                     }),
+                    diagnostic: Some(lsp::DiagnosticClientCapabilities {
+                        dynamic_registration: Some(false),
+                        related_document_support: Some(true),
+                    }),
+                    inlay_hint: Some(lsp::InlayHintClientCapabilities {
+                        dynamic_registration: Some(false),
+                        resolve_support: None,
+                    }),
+                    call_hierarchy: Some(lsp::CallHierarchyClientCapabilities {
+                        dynamic_registration: Some(false),
+                    }),
+                    type_hierarchy: Some(lsp::TypeHierarchyClientCapabilities {
+                        dynamic_registration: Some(false),
+                    }),
+                    code_lens: Some(lsp::CodeLensClientCapabilities {
+                        dynamic_registration: Some(false),
+                    }),
+                    document_link: Some(lsp::DocumentLinkClientCapabilities {
+                        dynamic_registration: Some(false),
+                        tooltip_support: Some(false),
+                    }),
+                    selection_range: Some(lsp::SelectionRangeClientCapabilities {
+                        dynamic_registration: Some(false),
+                    }),
+                    folding_range: Some(lsp::FoldingRangeClientCapabilities {
+                        dynamic_registration: Some(false),
+                        line_folding_only: Some(true),
+                        ..Default::default()
+                    }),
+                    semantic_tokens: Some(lsp::SemanticTokensClientCapabilities {
+                        dynamic_registration: Some(false),
+                        requests: lsp::SemanticTokensClientCapabilitiesRequests {
+                            range: Some(false),
+                            full: Some(lsp::SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                        },
+                        token_types: vec![
+                            lsp::SemanticTokenType::NAMESPACE,
+                            lsp::SemanticTokenType::TYPE,
+                            lsp::SemanticTokenType::CLASS,
+                            lsp::SemanticTokenType::ENUM,
+                            lsp::SemanticTokenType::INTERFACE,
+                            lsp::SemanticTokenType::STRUCT,
+                            lsp::SemanticTokenType::TYPE_PARAMETER,
+                            lsp::SemanticTokenType::PARAMETER,
+                            lsp::SemanticTokenType::VARIABLE,
+                            lsp::SemanticTokenType::PROPERTY,
+                            lsp::SemanticTokenType::ENUM_MEMBER,
+                            lsp::SemanticTokenType::EVENT,
+                            lsp::SemanticTokenType::FUNCTION,
+                            lsp::SemanticTokenType::METHOD,
+                            lsp::SemanticTokenType::MACRO,
+                            lsp::SemanticTokenType::KEYWORD,
+                            lsp::SemanticTokenType::MODIFIER,
+                            lsp::SemanticTokenType::COMMENT,
+                            lsp::SemanticTokenType::STRING,
+                            lsp::SemanticTokenType::NUMBER,
+                            lsp::SemanticTokenType::REGEXP,
+                            lsp::SemanticTokenType::OPERATOR,
+                            lsp::SemanticTokenType::DECORATOR,
+                        ],
+                        token_modifiers: vec![
+                            lsp::SemanticTokenModifier::DECLARATION,
+                            lsp::SemanticTokenModifier::DEFINITION,
+                            lsp::SemanticTokenModifier::READONLY,
+                            lsp::SemanticTokenModifier::STATIC,
+                            lsp::SemanticTokenModifier::DEPRECATED,
+                            lsp::SemanticTokenModifier::ABSTRACT,
+                            lsp::SemanticTokenModifier::ASYNC,
+                            lsp::SemanticTokenModifier::MODIFICATION,
+                            lsp::SemanticTokenModifier::DOCUMENTATION,
+                            lsp::SemanticTokenModifier::DEFAULT_LIBRARY,
+                        ],
+                        formats: vec![lsp::TokenFormat::RELATIVE],
+                        overlapping_token_support: Some(false),
+                        multiline_token_support: Some(false),
+                        server_cancel_support: Some(false),
+                        augments_syntax_tokens: Some(true),
+                    }),
                     ..Default::default()
                 }),
                 window: Some(lsp::WindowClientCapabilities {
@@ -387,7 +648,14 @@ pub(crate) async fn initialize(&self) -> Result<lsp::InitializeResult> {
             locale: None, // TODO
         };
 
-        self.request::<lsp::request::Initialize>(params).await
+        let response = self.call::<lsp::request::Initialize>(params).await?;
+        let type_hierarchy_provider = matches!(
+            response.pointer("/capabilities/typeHierarchyProvider"),
+            Some(Value::Bool(true) | Value::Object(_))
+        );
+        self.type_hierarchy_provider
+            .store(type_hierarchy_provider, Ordering::Relaxed);
+        Ok(serde_json::from_value(response)?)
     }
 
     pub async fn shutdown(&self) -> Result<()> {
@@ -423,6 +691,65 @@ pub fn did_change_configuration(&self, settings: Value) -> impl Future<Output =
         )
     }
 
+    pub fn did_change_watched_files(
+        &self,
+        changes: Vec<lsp::FileEvent>,
+    ) -> impl Future<Output = Result<()>> {
+        self.notify::<lsp::notification::DidChangeWatchedFiles>(lsp::DidChangeWatchedFilesParams {
+            changes,
+        })
+    }
+
+    /// Returns the file operation options the server registered for renames, if any.
+    fn file_operations_rename_options(
+        &self,
+        did: bool,
+    ) -> Option<&lsp::FileOperationRegistrationOptions> {
+        let file_operations = self
+            .capabilities
+            .get()?
+            .workspace
+            .as_ref()?
+            .file_operations
+            .as_ref()?;
+
+        if did {
+            file_operations.did_rename.as_ref()
+        } else {
+            file_operations.will_rename.as_ref()
+        }
+    }
+
+    pub fn will_rename(
+        &self,
+        old_path: &Path,
+        new_path: &Path,
+        is_dir: bool,
+    ) -> Option<impl Future<Output = Result<Option<lsp::WorkspaceEdit>>>> {
+        let options = self.file_operations_rename_options(false)?;
+        let files = file_renames(options, old_path, new_path, is_dir)?;
+
+        let request = self.call::<lsp::request::WillRenameFiles>(lsp::RenameFilesParams { files });
+
+        Some(async move {
+            let json = request.await?;
+            let response: Option<lsp::WorkspaceEdit> = serde_json::from_value(json)?;
+            Ok(response)
+        })
+    }
+
+    pub fn did_rename(
+        &self,
+        old_path: &Path,
+        new_path: &Path,
+        is_dir: bool,
+    ) -> Option<impl Future<Output = Result<()>>> {
+        let options = self.file_operations_rename_options(true)?;
+        let files = file_renames(options, old_path, new_path, is_dir)?;
+
+        Some(self.notify::<lsp::notification::DidRenameFiles>(lsp::RenameFilesParams { files }))
+    }
+
     // -------------------------------------------------------------------------------------------
     // Text document
     // -------------------------------------------------------------------------------------------
@@ -812,6 +1139,202 @@ pub fn text_document_range_formatting(
         })
     }
 
+    pub fn text_document_range_inlay_hints(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        range: lsp::Range,
+        work_done_token: Option<lsp::ProgressToken>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        let capabilities = self.capabilities.get().unwrap();
+
+        // Return early if the server does not support inlay hints.
+        match capabilities.inlay_hint_provider {
+            Some(lsp::OneOf::Left(true) | lsp::OneOf::Right(_)) => (),
+            _ => return None,
+        }
+
+        let params = lsp::InlayHintParams {
+            work_done_progress_params: lsp::WorkDoneProgressParams { work_done_token },
+            text_document,
+            range,
+        };
+
+        Some(self.call::<lsp::request::InlayHintRequest>(params))
+    }
+
+    pub fn text_document_folding_range(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        work_done_token: Option<lsp::ProgressToken>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        // Return early if the server does not support folding ranges.
+        if !self.supports_feature(LanguageServerFeature::FoldingRange) {
+            return None;
+        }
+
+        let params = lsp::FoldingRangeParams {
+            text_document,
+            work_done_progress_params: lsp::WorkDoneProgressParams { work_done_token },
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+
+        Some(self.call::<lsp::request::FoldingRangeRequest>(params))
+    }
+
+    pub fn prepare_call_hierarchy(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        position: lsp::Position,
+        work_done_token: Option<lsp::ProgressToken>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        // Return early if the server does not support call hierarchies.
+        if !self.supports_feature(LanguageServerFeature::CallHierarchy) {
+            return None;
+        }
+
+        let params = lsp::CallHierarchyPrepareParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document,
+                position,
+            },
+            work_done_progress_params: lsp::WorkDoneProgressParams { work_done_token },
+        };
+
+        Some(self.call::<lsp::request::CallHierarchyPrepare>(params))
+    }
+
+    pub fn call_hierarchy_incoming_calls(
+        &self,
+        item: lsp::CallHierarchyItem,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        if !self.supports_feature(LanguageServerFeature::CallHierarchy) {
+            return None;
+        }
+
+        let params = lsp::CallHierarchyIncomingCallsParams {
+            item,
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+
+        Some(self.call::<lsp::request::CallHierarchyIncomingCalls>(params))
+    }
+
+    pub fn call_hierarchy_outgoing_calls(
+        &self,
+        item: lsp::CallHierarchyItem,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        if !self.supports_feature(LanguageServerFeature::CallHierarchy) {
+            return None;
+        }
+
+        let params = lsp::CallHierarchyOutgoingCallsParams {
+            item,
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+
+        Some(self.call::<lsp::request::CallHierarchyOutgoingCalls>(params))
+    }
+
+    pub fn prepare_type_hierarchy(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        position: lsp::Position,
+        work_done_token: Option<lsp::ProgressToken>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        // Return early if the server does not support type hierarchies.
+        if !self.supports_feature(LanguageServerFeature::TypeHierarchy) {
+            return None;
+        }
+
+        let params = lsp::TypeHierarchyPrepareParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document,
+                position,
+            },
+            work_done_progress_params: lsp::WorkDoneProgressParams { work_done_token },
+        };
+
+        Some(self.call::<lsp::request::TypeHierarchyPrepare>(params))
+    }
+
+    pub fn type_hierarchy_supertypes(
+        &self,
+        item: lsp::TypeHierarchyItem,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        if !self.supports_feature(LanguageServerFeature::TypeHierarchy) {
+            return None;
+        }
+
+        let params = lsp::TypeHierarchySupertypesParams {
+            item,
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+
+        Some(self.call::<lsp::request::TypeHierarchySupertypes>(params))
+    }
+
+    pub fn type_hierarchy_subtypes(
+        &self,
+        item: lsp::TypeHierarchyItem,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        if !self.supports_feature(LanguageServerFeature::TypeHierarchy) {
+            return None;
+        }
+
+        let params = lsp::TypeHierarchySubtypesParams {
+            item,
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+
+        Some(self.call::<lsp::request::TypeHierarchySubtypes>(params))
+    }
+
+    pub fn text_document_semantic_tokens_full(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        work_done_token: Option<lsp::ProgressToken>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        // Return early if the server does not support semantic tokens.
+        if !self.supports_feature(LanguageServerFeature::SemanticTokens) {
+            return None;
+        }
+
+        let params = lsp::SemanticTokensParams {
+            work_done_progress_params: lsp::WorkDoneProgressParams { work_done_token },
+            partial_result_params: lsp::PartialResultParams::default(),
+            text_document,
+        };
+
+        Some(self.call::<lsp::request::SemanticTokensFullRequest>(params))
+    }
+
+    /// Requests the changes to the semantic tokens since the result with the
+    /// `previous_result_id`. Returns `None` if the server doesn't support deltas.
+    pub fn text_document_semantic_tokens_full_delta(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        previous_result_id: String,
+        work_done_token: Option<lsp::ProgressToken>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        match self.semantic_tokens_options()?.full {
+            Some(lsp::SemanticTokensFullOptions::Delta { delta: Some(true) }) => (),
+            _ => return None,
+        }
+
+        let params = lsp::SemanticTokensDeltaParams {
+            work_done_progress_params: lsp::WorkDoneProgressParams { work_done_token },
+            partial_result_params: lsp::PartialResultParams::default(),
+            text_document,
+            previous_result_id,
+        };
+
+        Some(self.call::<lsp::request::SemanticTokensFullDeltaRequest>(params))
+    }
+
     pub fn text_document_document_highlight(
         &self,
         text_document: lsp::TextDocumentIdentifier,
@@ -934,6 +1457,31 @@ pub fn goto_implementation(
         ))
     }
 
+    pub fn goto_declaration(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        position: lsp::Position,
+        work_done_token: Option<lsp::ProgressToken>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        let capabilities = self.capabilities.get().unwrap();
+
+        // Return early if the server does not support goto-declaration.
+        match capabilities.declaration_provider {
+            Some(
+                lsp::DeclarationCapability::Simple(true)
+                | lsp::DeclarationCapability::RegistrationOptions(_)
+                | lsp::DeclarationCapability::Options(_),
+            ) => (),
+            _ => return None,
+        }
+
+        Some(self.goto_request::<lsp::request::GotoDeclaration>(
+            text_document,
+            position,
+            work_done_token,
+        ))
+    }
+
     pub fn goto_reference(
         &self,
         text_document: lsp::TextDocumentIdentifier,
@@ -1002,7 +1550,7 @@ pub fn workspace_symbols(&self, query: String) -> Option<impl Future<Output = Re
             partial_result_params: lsp::PartialResultParams::default(),
         };
 
-        Some(self.call::<lsp::request::WorkspaceSymbol>(params))
+        Some(self.call::<lsp::request::WorkspaceSymbolRequest>(params))
     }
 
     pub fn code_actions(
@@ -1033,6 +1581,151 @@ pub fn code_actions(
         Some(self.call::<lsp::request::CodeActionRequest>(params))
     }
 
+    /// Pulls the diagnostics of a document, see [`Client::diagnostic_options`].
+    pub fn text_document_diagnostic(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        previous_result_id: Option<String>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        // Return early if the server does not support pulling diagnostics.
+        let options = self.diagnostic_options()?;
+
+        let params = lsp::DocumentDiagnosticParams {
+            text_document,
+            identifier: options.identifier.clone(),
+            previous_result_id,
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+
+        Some(self.call::<lsp::request::DocumentDiagnosticRequest>(params))
+    }
+
+    /// Pulls the diagnostics of the whole workspace, sending the result ids of the
+    /// documents pulled before so that the server only reports the changed ones.
+    pub fn workspace_diagnostic(
+        &self,
+        previous_result_ids: Vec<lsp::PreviousResultId>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        // Return early if the server does not support pulling workspace diagnostics.
+        let options = self.diagnostic_options()?;
+        if !options.workspace_diagnostics {
+            return None;
+        }
+
+        let params = lsp::WorkspaceDiagnosticParams {
+            identifier: options.identifier.clone(),
+            previous_result_ids,
+            work_done_progress_params: lsp::WorkDoneProgressParams::default(),
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+
+        Some(self.call::<lsp::request::WorkspaceDiagnosticRequest>(params))
+    }
+
+    pub fn text_document_code_lens(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        work_done_token: Option<lsp::ProgressToken>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        let capabilities = self.capabilities.get().unwrap();
+
+        // Return early if the server does not support code lenses.
+        capabilities.code_lens_provider.as_ref()?;
+
+        let params = lsp::CodeLensParams {
+            text_document,
+            work_done_progress_params: lsp::WorkDoneProgressParams { work_done_token },
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+
+        Some(self.call::<lsp::request::CodeLensRequest>(params))
+    }
+
+    /// Requests the command of a code lens that was sent without one.
+    pub fn code_lens_resolve(
+        &self,
+        code_lens: lsp::CodeLens,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        let capabilities = self.capabilities.get().unwrap();
+
+        // Return early if the server does not support resolving code lenses.
+        match capabilities.code_lens_provider {
+            Some(lsp::CodeLensOptions {
+                resolve_provider: Some(true),
+            }) => (),
+            _ => return None,
+        }
+
+        Some(self.call::<lsp::request::CodeLensResolve>(code_lens))
+    }
+
+    pub fn text_document_document_link(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        work_done_token: Option<lsp::ProgressToken>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        let capabilities = self.capabilities.get().unwrap();
+
+        // Return early if the server does not support document links.
+        capabilities.document_link_provider.as_ref()?;
+
+        let params = lsp::DocumentLinkParams {
+            text_document,
+            work_done_progress_params: lsp::WorkDoneProgressParams { work_done_token },
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+
+        Some(self.call::<lsp::request::DocumentLinkRequest>(params))
+    }
+
+    /// Requests the target of a document link that was sent without one.
+    pub fn document_link_resolve(
+        &self,
+        document_link: lsp::DocumentLink,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        let capabilities = self.capabilities.get().unwrap();
+
+        // Return early if the server does not support resolving document links.
+        match capabilities.document_link_provider {
+            Some(lsp::DocumentLinkOptions {
+                resolve_provider: Some(true),
+                ..
+            }) => (),
+            _ => return None,
+        }
+
+        Some(self.call::<lsp::request::DocumentLinkResolve>(document_link))
+    }
+
+    pub fn text_document_selection_range(
+        &self,
+        text_document: lsp::TextDocumentIdentifier,
+        positions: Vec<lsp::Position>,
+        work_done_token: Option<lsp::ProgressToken>,
+    ) -> Option<impl Future<Output = Result<Value>>> {
+        let capabilities = self.capabilities.get().unwrap();
+
+        // Return early if the server does not support selection ranges.
+        match capabilities.selection_range_provider {
+            Some(
+                lsp::SelectionRangeProviderCapability::Simple(true)
+                | lsp::SelectionRangeProviderCapability::Options(_)
+                | lsp::SelectionRangeProviderCapability::RegistrationOptions(_),
+            ) => (),
+            _ => return None,
+        }
+
+        let params = lsp::SelectionRangeParams {
+            text_document,
+            positions,
+            work_done_progress_params: lsp::WorkDoneProgressParams { work_done_token },
+            partial_result_params: lsp::PartialResultParams::default(),
+        };
+
+        Some(self.call::<lsp::request::SelectionRangeRequest>(params))
+    }
+
     pub fn rename_symbol(
         &self,
         text_document: lsp::TextDocumentIdentifier,
@@ -1085,3 +1778,158 @@ pub fn command(&self, command: lsp::Command) -> Option<impl Future<Output = Resu
         Some(self.call::<lsp::request::ExecuteCommand>(params))
     }
 }
+
+/// Builds the rename of `old_path` to `new_path` if it matches any of the filters the
+/// server registered for the file operation.
+fn file_renames(
+    options: &lsp::FileOperationRegistrationOptions,
+    old_path: &Path,
+    new_path: &Path,
+    is_dir: bool,
+) -> Option<Vec<lsp::FileRename>> {
+    if !options
+        .filters
+        .iter()
+        .any(|filter| file_operation_filter_matches(filter, old_path, is_dir))
+    {
+        return None;
+    }
+
+    let old_uri = lsp::Url::from_file_path(old_path).ok()?;
+    let new_uri = lsp::Url::from_file_path(new_path).ok()?;
+
+    Some(vec![lsp::FileRename {
+        old_uri: old_uri.to_string(),
+        new_uri: new_uri.to_string(),
+    }])
+}
+
+fn file_operation_filter_matches(
+    filter: &lsp::FileOperationFilter,
+    path: &Path,
+    is_dir: bool,
+) -> bool {
+    if !matches!(filter.scheme.as_deref(), None | Some("file")) {
+        return false;
+    }
+
+    let pattern = &filter.pattern;
+    match pattern.matches {
+        Some(lsp::FileOperationPatternKind::File) if is_dir => return false,
+        Some(lsp::FileOperationPatternKind::Folder) if !is_dir => return false,
+        _ => (),
+    }
+
+    let ignore_case = matches!(
+        pattern.options,
+        Some(lsp::FileOperationPatternOptions {
+            ignore_case: Some(true)
+        })
+    );
+
+    match globset::GlobBuilder::new(&pattern.glob)
+        .literal_separator(true)
+        .case_insensitive(ignore_case)
+        .build()
+    {
+        Ok(glob) => glob.compile_matcher().is_match(path),
+        Err(err) => {
+            log::warn!("invalid file operation glob '{}': {}", pattern.glob, err);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn filter(value: Value) -> lsp::FileOperationFilter {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_file_operation_filter_matches() {
+        let path = Path::new("/project/src/Main.rs");
+
+        let rust_files = filter(json!({ "pattern": { "glob": "**/*.rs" } }));
+        assert!(file_operation_filter_matches(&rust_files, path, false));
+        assert!(!file_operation_filter_matches(
+            &rust_files,
+            Path::new("/project/README.md"),
+            false
+        ));
+
+        let file_scheme = filter(json!({ "scheme": "file", "pattern": { "glob": "**/*.rs" } }));
+        assert!(file_operation_filter_matches(&file_scheme, path, false));
+        let untitled_scheme =
+            filter(json!({ "scheme": "untitled", "pattern": { "glob": "**/*.rs" } }));
+        assert!(!file_operation_filter_matches(
+            &untitled_scheme,
+            path,
+            false
+        ));
+
+        let files = filter(json!({ "pattern": { "glob": "**/src/**", "matches": "file" } }));
+        assert!(file_operation_filter_matches(&files, path, false));
+        assert!(!file_operation_filter_matches(
+            &files,
+            Path::new("/project/src/module"),
+            true
+        ));
+        let folders = filter(json!({ "pattern": { "glob": "**/src/**", "matches": "folder" } }));
+        assert!(!file_operation_filter_matches(&folders, path, false));
+        assert!(file_operation_filter_matches(
+            &folders,
+            Path::new("/project/src/module"),
+            true
+        ));
+
+        let case_sensitive = filter(json!({ "pattern": { "glob": "**/main.rs" } }));
+        assert!(!file_operation_filter_matches(&case_sensitive, path, false));
+        let ignore_case = filter(json!({
+            "pattern": { "glob": "**/main.rs", "options": { "ignoreCase": true } }
+        }));
+        assert!(file_operation_filter_matches(&ignore_case, path, false));
+
+        // `*` doesn't match across directories
+        let src_files = filter(json!({ "pattern": { "glob": "/project/src/*.rs" } }));
+        assert!(file_operation_filter_matches(&src_files, path, false));
+        assert!(!file_operation_filter_matches(
+            &src_files,
+            Path::new("/project/src/nested/lib.rs"),
+            false
+        ));
+    }
+
+    #[test]
+    fn test_file_renames() {
+        let options = lsp::FileOperationRegistrationOptions {
+            filters: vec![filter(
+                json!({ "pattern": { "glob": "**/*.rs", "matches": "file" } }),
+            )],
+        };
+        let old_path = Path::new("/project/src/old.rs");
+        let new_path = Path::new("/project/src/new.rs");
+
+        assert_eq!(
+            file_renames(&options, old_path, new_path, false),
+            Some(vec![lsp::FileRename {
+                old_uri: "file:///project/src/old.rs".to_string(),
+                new_uri: "file:///project/src/new.rs".to_string(),
+            }])
+        );
+        // the filter only matches files
+        assert_eq!(file_renames(&options, old_path, new_path, true), None);
+        assert_eq!(
+            file_renames(
+                &options,
+                Path::new("/project/old.md"),
+                Path::new("/project/new.md"),
+                false
+            ),
+            None
+        );
+    }
+}
@@ -13,7 +13,7 @@
 use tokio::sync::mpsc::UnboundedReceiver;
 
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::HashMap,
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
@@ -280,6 +280,108 @@ pub fn generate_transaction_from_edits(
             }),
         )
     }
+
+    /// Applies the edits of a semantic tokens delta to the previous tokens.
+    pub fn apply_semantic_tokens_edits(
+        tokens: &mut Vec<lsp::SemanticToken>,
+        mut edits: Vec<lsp::SemanticTokensEdit>,
+    ) {
+        // The edits index into the flat array of integers with five per token. Apply
+        // them back to front so that the indices of the remaining edits stay valid.
+        edits.sort_unstable_by_key(|edit| std::cmp::Reverse(edit.start));
+        for edit in edits {
+            let start = (edit.start as usize / 5).min(tokens.len());
+            let end = (start + edit.delete_count as usize / 5).min(tokens.len());
+            tokens.splice(start..end, edit.data.unwrap_or_default());
+        }
+    }
+
+    /// Converts the relative positions of semantic tokens to char ranges in the
+    /// document, paired with the token type and modifiers bitset. Tokens that are out of
+    /// bounds are skipped.
+    pub fn semantic_tokens_to_ranges(
+        doc: &Rope,
+        tokens: &[lsp::SemanticToken],
+        offset_encoding: OffsetEncoding,
+    ) -> Vec<(std::ops::Range<usize>, u32, u32)> {
+        let (mut line, mut character) = (0u32, 0u32);
+        tokens
+            .iter()
+            .filter_map(|token| {
+                if token.delta_line > 0 {
+                    line = line.saturating_add(token.delta_line);
+                    character = 0;
+                }
+                character = character.saturating_add(token.delta_start);
+
+                let start = lsp::Position::new(line, character);
+                let end = lsp::Position::new(line, character.saturating_add(token.length));
+                let start = lsp_pos_to_pos(doc, start, offset_encoding)?;
+                let end = lsp_pos_to_pos(doc, end, offset_encoding)?;
+                Some((start..end, token.token_type, token.token_modifiers_bitset))
+            })
+            .collect()
+    }
+
+    /// Returns the theme scope of a semantic token, for example `type.enum` or
+    /// `variable.mutable`, or `None` if the token type has no matching scope.
+    ///
+    /// The token type and the bits of `modifiers` are indices into the `legend`.
+    pub fn semantic_token_scope(
+        legend: &lsp::SemanticTokensLegend,
+        token_type: u32,
+        modifiers: u32,
+    ) -> Option<String> {
+        let token_type = legend.token_types.get(token_type as usize)?;
+        let mut scope = match token_type.as_str() {
+            "namespace" => "namespace",
+            "type" | "class" | "interface" | "struct" | "union" | "typeAlias" => "type",
+            "enum" => "type.enum",
+            "enumMember" => "type.enum.variant",
+            "typeParameter" => "type.parameter",
+            "builtinType" => "type.builtin",
+            "parameter" => "variable.parameter",
+            "variable" | "event" => "variable",
+            "property" => "variable.other.member",
+            "selfKeyword" | "selfTypeKeyword" => "variable.builtin",
+            "function" => "function",
+            "method" => "function.method",
+            "macro" => "function.macro",
+            "keyword" | "modifier" => "keyword",
+            "comment" => "comment",
+            "string" => "string",
+            "regexp" => "string.regexp",
+            "number" => "constant.numeric",
+            "boolean" => "constant.builtin.boolean",
+            "operator" => "operator",
+            "decorator" | "attribute" => "attribute",
+            "lifetime" => "label",
+            _ => return None,
+        }
+        .to_string();
+
+        // Modifiers narrow the scope, falling back to the broader scope in themes
+        // that don't define them.
+        let modifiers = legend
+            .token_modifiers
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i < 32 && modifiers & (1 << i) != 0)
+            .map(|(_, modifier)| modifier.as_str());
+        for modifier in modifiers {
+            let suffix = match modifier {
+                "defaultLibrary" => "builtin",
+                "mutable" => "mutable",
+                "static" => "static",
+                "readonly" => "readonly",
+                "deprecated" => "deprecated",
+                _ => continue,
+            };
+            scope.push('.');
+            scope.push_str(suffix);
+        }
+        Some(scope)
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -288,6 +390,8 @@ pub enum MethodCall {
     ApplyWorkspaceEdit(lsp::ApplyWorkspaceEditParams),
     WorkspaceFolders,
     WorkspaceConfiguration(lsp::ConfigurationParams),
+    RegisterCapability(lsp::RegistrationParams),
+    UnregisterCapability(lsp::UnregistrationParams),
 }
 
 impl MethodCall {
@@ -307,6 +411,14 @@ pub fn parse(method: &str, params: jsonrpc::Params) -> Result<MethodCall> {
                 let params: lsp::ConfigurationParams = params.parse()?;
                 Self::WorkspaceConfiguration(params)
             }
+            lsp::request::RegisterCapability::METHOD => {
+                let params: lsp::RegistrationParams = params.parse()?;
+                Self::RegisterCapability(params)
+            }
+            lsp::request::UnregisterCapability::METHOD => {
+                let params: lsp::UnregistrationParams = params.parse()?;
+                Self::UnregisterCapability(params)
+            }
             _ => {
                 return Err(Error::Unhandled);
             }
@@ -362,10 +474,13 @@ pub fn parse(method: &str, params: jsonrpc::Params) -> Result<Notification> {
 
 #[derive(Debug)]
 pub struct Registry {
-    inner: HashMap<LanguageId, (usize, Arc<Client>)>,
+    /// Running clients, keyed by language scope and server name.
+    inner: HashMap<(LanguageId, String), (usize, Arc<Client>)>,
 
     counter: AtomicUsize,
     pub incoming: SelectAll<UnboundedReceiverStream<(usize, Call)>>,
+    /// Whether the editor watches files for the language servers.
+    watch_files: bool,
 }
 
 impl Default for Registry {
@@ -380,9 +495,16 @@ pub fn new() -> Self {
             inner: HashMap::new(),
             counter: AtomicUsize::new(0),
             incoming: SelectAll::new(),
+            watch_files: false,
         }
     }
 
+    /// Sets whether language servers started from now on may ask the editor to watch files
+    /// for them, instead of watching files themselves.
+    pub fn set_watch_files(&mut self, watch_files: bool) {
+        self.watch_files = watch_files;
+    }
+
     pub fn get_by_id(&self, id: usize) -> Option<&Client> {
         self.inner
             .values()
@@ -390,67 +512,90 @@ pub fn get_by_id(&self, id: usize) -> Option<&Client> {
             .map(|(_, client)| client.as_ref())
     }
 
+    /// Like `get_by_id`, but the client can be moved into a future making several requests.
+    pub fn get_shared_by_id(&self, id: usize) -> Option<Arc<Client>> {
+        self.inner
+            .values()
+            .find(|(client_id, _)| client_id == &id)
+            .map(|(_, client)| client.clone())
+    }
+
     pub fn remove_by_id(&mut self, id: usize) {
         self.inner.retain(|_, (client_id, _)| client_id != &id)
     }
 
-    pub fn restart(
+    fn start_client(
         &mut self,
         language_config: &LanguageConfiguration,
+        ls_config: &LanguageServerConfiguration,
         doc_path: Option<&std::path::PathBuf>,
-    ) -> Result<Option<Arc<Client>>> {
-        let config = match &language_config.language_server {
-            Some(config) => config,
-            None => return Ok(None),
-        };
+    ) -> Result<(usize, Arc<Client>)> {
+        let id = self.counter.fetch_add(1, Ordering::Relaxed);
 
-        let scope = language_config.scope.clone();
+        let NewClientResult(client, incoming) =
+            start_client(id, language_config, ls_config, doc_path, self.watch_files)?;
+        self.incoming.push(UnboundedReceiverStream::new(incoming));
 
-        match self.inner.entry(scope) {
-            Entry::Vacant(_) => Ok(None),
-            Entry::Occupied(mut entry) => {
-                // initialize a new client
-                let id = self.counter.fetch_add(1, Ordering::Relaxed);
+        Ok((id, client))
+    }
 
-                let NewClientResult(client, incoming) =
-                    start_client(id, language_config, config, doc_path)?;
-                self.incoming.push(UnboundedReceiverStream::new(incoming));
+    /// Restarts the running language servers of `language_config`, returning the new clients.
+    /// A server that fails to start again keeps running, and doesn't stop the others from
+    /// being restarted.
+    pub fn restart(
+        &mut self,
+        language_config: &LanguageConfiguration,
+        doc_path: Option<&std::path::PathBuf>,
+    ) -> Vec<Result<Arc<Client>>> {
+        let mut clients = Vec::new();
 
-                let (_, old_client) = entry.insert((id, client.clone()));
+        for config in language_config.language_servers() {
+            let key = (language_config.scope.clone(), config.name().to_string());
+            if !self.inner.contains_key(&key) {
+                continue;
+            }
 
+            let (id, client) = match self.start_client(language_config, config, doc_path) {
+                Ok(client) => client,
+                Err(err) => {
+                    let err = anyhow::anyhow!("{}: {}", config.name(), err);
+                    clients.push(Err(Error::Other(err)));
+                    continue;
+                }
+            };
+            if let Some((_, old_client)) = self.inner.insert(key, (id, client.clone())) {
                 tokio::spawn(async move {
                     let _ = old_client.force_shutdown().await;
                 });
-
-                Ok(Some(client))
             }
+
+            clients.push(Ok(client));
         }
+
+        clients
     }
 
+    /// Returns a client for every language server configured for `language_config`,
+    /// starting the ones that aren't running yet.
     pub fn get(
         &mut self,
         language_config: &LanguageConfiguration,
         doc_path: Option<&std::path::PathBuf>,
-    ) -> Result<Option<Arc<Client>>> {
-        let config = match &language_config.language_server {
-            Some(config) => config,
-            None => return Ok(None),
-        };
-
-        match self.inner.entry(language_config.scope.clone()) {
-            Entry::Occupied(entry) => Ok(Some(entry.get().1.clone())),
-            Entry::Vacant(entry) => {
-                // initialize a new client
-                let id = self.counter.fetch_add(1, Ordering::Relaxed);
-
-                let NewClientResult(client, incoming) =
-                    start_client(id, language_config, config, doc_path)?;
-                self.incoming.push(UnboundedReceiverStream::new(incoming));
+    ) -> Vec<Result<Arc<Client>>> {
+        language_config
+            .language_servers()
+            .iter()
+            .map(|config| {
+                let key = (language_config.scope.clone(), config.name().to_string());
+                if let Some((_, client)) = self.inner.get(&key) {
+                    return Ok(client.clone());
+                }
 
-                entry.insert((id, client.clone()));
-                Ok(Some(client))
-            }
-        }
+                let (id, client) = self.start_client(language_config, config, doc_path)?;
+                self.inner.insert(key, (id, client.clone()));
+                Ok(client)
+            })
+            .collect()
     }
 
     pub fn iter_clients(&self) -> impl Iterator<Item = &Arc<Client>> {
@@ -545,16 +690,19 @@ fn start_client(
     config: &LanguageConfiguration,
     ls_config: &LanguageServerConfiguration,
     doc_path: Option<&std::path::PathBuf>,
+    watch_files: bool,
 ) -> Result<NewClientResult> {
     let (client, incoming, initialize_notify) = Client::start(
+        ls_config.name().to_string(),
         &ls_config.command,
         &ls_config.args,
-        config.config.clone(),
+        ls_config.config.clone().or_else(|| config.config.clone()),
         ls_config.environment.clone(),
         &config.roots,
         id,
         ls_config.timeout,
         doc_path,
+        watch_files,
     )?;
 
     let client = Arc::new(client);
@@ -573,7 +721,11 @@ fn start_client(
             .await;
 
         if let Err(e) = value {
-            log::error!("failed to initialize language server: {}", e);
+            log::error!(
+                "failed to initialize language server {}: {}",
+                _client.name(),
+                e
+            );
             return;
         }
 
@@ -618,4 +770,75 @@ macro_rules! test_case {
         test_case!("test\n\n\n\ncase", (4, 5) => None);
         test_case!("", (u32::MAX, u32::MAX) => None);
     }
+
+    #[test]
+    fn decodes_semantic_tokens() {
+        let token = |delta_line, delta_start, length, token_type, token_modifiers_bitset| {
+            lsp::SemanticToken {
+                delta_line,
+                delta_start,
+                length,
+                token_type,
+                token_modifiers_bitset,
+            }
+        };
+        let mut tokens = vec![token(0, 4, 3, 0, 0), token(1, 4, 1, 1, 1)];
+        // change the length of the first token, insert a token after it and change the
+        // modifiers of the last one
+        apply_semantic_tokens_edits(
+            &mut tokens,
+            vec![
+                lsp::SemanticTokensEdit {
+                    start: 0,
+                    delete_count: 5,
+                    data: Some(vec![token(0, 4, 4, 0, 0)]),
+                },
+                lsp::SemanticTokensEdit {
+                    start: 5,
+                    delete_count: 5,
+                    data: Some(vec![token(0, 7, 3, 2, 0), token(1, 4, 1, 1, 2)]),
+                },
+            ],
+        );
+        assert_eq!(
+            tokens,
+            vec![
+                token(0, 4, 4, 0, 0),
+                token(0, 7, 3, 2, 0),
+                token(1, 4, 1, 1, 2)
+            ]
+        );
+
+        let doc = Rope::from("let äbcd = Foo::Bar;\nlet x = 1;");
+        assert_eq!(
+            semantic_tokens_to_ranges(&doc, &tokens, OffsetEncoding::Utf16),
+            vec![(4..8, 0, 0), (11..14, 2, 0), (25..26, 1, 2)]
+        );
+
+        let legend = lsp::SemanticTokensLegend {
+            token_types: vec![
+                lsp::SemanticTokenType::VARIABLE,
+                lsp::SemanticTokenType::ENUM_MEMBER,
+                "punctuation".into(),
+            ],
+            token_modifiers: vec![
+                lsp::SemanticTokenModifier::DEFAULT_LIBRARY,
+                "mutable".into(),
+            ],
+        };
+        assert_eq!(
+            semantic_token_scope(&legend, 0, 0).as_deref(),
+            Some("variable")
+        );
+        assert_eq!(
+            semantic_token_scope(&legend, 0, 3).as_deref(),
+            Some("variable.builtin.mutable")
+        );
+        assert_eq!(
+            semantic_token_scope(&legend, 1, 0).as_deref(),
+            Some("type.enum.variant")
+        );
+        assert_eq!(semantic_token_scope(&legend, 2, 0), None);
+        assert_eq!(semantic_token_scope(&legend, 3, 0), None);
+    }
 }
@@ -1,36 +1,39 @@
 use arc_swap::{access::Map, ArcSwap};
 use futures_util::Stream;
 use helix_core::{
-    diagnostic::{DiagnosticTag, NumberOrString},
-    path::get_relative_path,
-    pos_at_coords, syntax, Selection,
+    path::get_relative_path, pos_at_coords, snippets::UserSnippets, syntax, Selection,
 };
-use helix_lsp::{lsp, util::lsp_pos_to_pos, LspProgressMap};
+use helix_lsp::{lsp, LspProgressMap};
 use helix_view::{
     align_view,
     document::DocumentSavedEventResult,
     editor::{ConfigEvent, EditorEvent},
+    file_watcher::{FileChange, FileEvent},
     graphics::Rect,
+    session::{self, Session},
     theme,
     tree::Layout,
-    Align, Editor,
+    Align, DocumentId, Editor,
 };
 use serde_json::json;
 use tui::backend::Backend;
 
 use crate::{
     args::Args,
-    commands::apply_workspace_edit,
-    compositor::{Compositor, Event},
+    commands::{self, apply_workspace_edit},
+    compositor::{self, Compositor, Event},
     config::Config,
     job::Jobs,
     keymap::Keymaps,
-    ui::{self, overlay::overlayed},
+    ui::{self, overlay::overlayed, PromptEvent},
 };
 
 use log::{debug, error, warn};
 use std::{
+    cell::RefCell,
+    collections::HashSet,
     io::{stdin, stdout, Write},
+    rc::Rc,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -83,6 +86,8 @@ pub struct Application {
     jobs: Jobs,
     lsp_progress: LspProgressMap,
     last_render: Instant,
+    /// The documents changed on disk whose reload waits for confirmation.
+    reload_prompts: Rc<RefCell<HashSet<DocumentId>>>,
 }
 
 #[cfg(feature = "integration")]
@@ -172,10 +177,16 @@ pub fn new(
             area,
             theme_loader.clone(),
             syn_loader.clone(),
-            Box::new(Map::new(Arc::clone(&config), |config: &Config| {
+            Arc::new(Map::new(Arc::clone(&config), |config: &Config| {
                 &config.editor
             })),
         );
+        // plugins of the user would affect the integration tests
+        #[cfg(not(feature = "integration"))]
+        editor.plugins.start(&crate::plugin::HOST);
+        if let Err(err) = editor.load_breakpoints() {
+            editor.set_error(format!("Failed to restore breakpoints: {}", err));
+        }
 
         let keys = Box::new(Map::new(Arc::clone(&config), |config: &Config| {
             &config.keys
@@ -235,6 +246,17 @@ pub fn new(
             }
         } else if stdin().is_tty() || cfg!(feature = "integration") {
             editor.new_file(Action::VerticalSplit);
+
+            if config.load().editor.persistent_session {
+                let path = session::session_file(&std::env::current_dir()?);
+                if path.exists() {
+                    if let Err(err) =
+                        Session::load(&path).and_then(|session| session.restore(&mut editor))
+                    {
+                        editor.set_error(format!("Failed to restore session: {}", err));
+                    }
+                }
+            }
         } else if cfg!(target_os = "macos") {
             // On Linux and Windows, we allow the output of a command to be piped into the new buffer.
             // This doesn't currently work on macOS because of the following issue:
@@ -268,6 +290,7 @@ pub fn new(
             jobs: Jobs::new(),
             lsp_progress: LspProgressMap::new(),
             last_render: Instant::now(),
+            reload_prompts: Rc::default(),
         };
 
         Ok(app)
@@ -288,6 +311,8 @@ async fn render(&mut self) {
         // to ensure that there are no tasks running that want to block rendering
         drop(cx.editor.redraw_handle.1.write().await);
         cx.editor.needs_redraw = false;
+        // draw the breakpoints on the lines they were moved to by the edits
+        cx.editor.shift_breakpoints();
         {
             // exhaust any leftover redraw notifications
             let notify = cx.editor.redraw_handle.0.notified();
@@ -391,6 +416,10 @@ pub fn handle_config_events(&mut self, config_event: ConfigEvent) {
         // Update all the relevant members in the editor after updating
         // the configuration.
         self.editor.refresh_config();
+        commands::compute_inlay_hints_for_all_views(&mut self.editor, &mut self.jobs);
+        commands::compute_semantic_tokens_for_all_views(&mut self.editor, &mut self.jobs);
+        commands::compute_code_lenses_for_all_views(&mut self.editor, &mut self.jobs);
+        commands::compute_document_highlights(&mut self.editor, &mut self.jobs);
     }
 
     /// Refresh theme after config change
@@ -418,6 +447,7 @@ fn refresh_config(&mut self) {
         match Config::load_default() {
             Ok(config) => {
                 self.refresh_theme(&config);
+                self.editor.snippets = UserSnippets::load();
 
                 // Store new config
                 self.config.store(Arc::new(config));
@@ -467,6 +497,13 @@ pub async fn handle_signals(&mut self, signal: i32) {
     }
 
     pub async fn handle_idle_timeout(&mut self) {
+        commands::compute_inlay_hints_for_all_views(&mut self.editor, &mut self.jobs);
+        commands::compute_semantic_tokens_for_all_views(&mut self.editor, &mut self.jobs);
+        commands::compute_code_lenses_for_all_views(&mut self.editor, &mut self.jobs);
+        commands::compute_document_links_for_all_views(&mut self.editor, &mut self.jobs);
+        commands::pull_diagnostics_for_all_views(&mut self.editor, &mut self.jobs);
+        commands::compute_document_highlights(&mut self.editor, &mut self.jobs);
+
         let mut cx = crate::compositor::Context {
             editor: &mut self.editor,
             jobs: &mut self.jobs,
@@ -478,6 +515,106 @@ pub async fn handle_idle_timeout(&mut self) {
         }
     }
 
+    /// Notifies the language servers of the changed files they watch, and reloads the
+    /// documents changed on disk. Modified documents are only reloaded after confirmation.
+    pub fn handle_file_events(&mut self, events: Vec<FileEvent>) {
+        for (server_id, changes) in self.editor.file_watcher.language_server_events(&events) {
+            if let Some(language_server) = self.editor.language_servers.get_by_id(server_id) {
+                tokio::spawn(language_server.did_change_watched_files(changes));
+            }
+        }
+
+        // modified documents are reloaded together after a single confirmation, unless
+        // they are waiting for the confirmation of an earlier change
+        let scrolloff = self.editor.config().scrolloff;
+        let mut modified = Vec::new();
+        for event in events {
+            if event.change == FileChange::Deleted {
+                continue;
+            }
+            let doc = match self.editor.document_by_path(&event.path) {
+                Some(doc) => doc,
+                None => continue,
+            };
+            // The documents written by the editor itself are noticed as well.
+            match doc.is_changed_on_disk() {
+                Ok(true) => (),
+                Ok(false) => continue,
+                Err(err) => {
+                    log::error!("failed to compare {:?} to the file: {}", event.path, err);
+                    continue;
+                }
+            }
+
+            let doc_id = doc.id();
+            let name = doc.display_name().into_owned();
+            let view_ids: Vec<_> = doc.selections().keys().copied().collect();
+            if view_ids.is_empty() {
+                continue;
+            }
+
+            if !doc.is_modified() {
+                match commands::typed::reload_document(
+                    &mut self.editor,
+                    doc_id,
+                    &view_ids,
+                    scrolloff,
+                ) {
+                    Ok(()) => self.editor.set_status(format!("Reloaded {}", name)),
+                    Err(err) => self.editor.set_error(err.to_string()),
+                }
+            } else if self.reload_prompts.borrow_mut().insert(doc_id) {
+                modified.push((doc_id, name, view_ids));
+            }
+        }
+
+        if modified.is_empty() {
+            return;
+        }
+        let names: Vec<_> = modified.iter().map(|(_, name, _)| name.as_str()).collect();
+        let message = format!(
+            "{} changed on disk, discard changes and reload? (y/n):",
+            names.join(", ")
+        );
+        let reload_prompts = self.reload_prompts.clone();
+        let prompt = ui::Prompt::new(
+            message.into(),
+            None,
+            ui::completers::none,
+            move |cx: &mut compositor::Context, input: &str, event: PromptEvent| {
+                if event == PromptEvent::Update {
+                    return;
+                }
+                let mut reload_prompts = reload_prompts.borrow_mut();
+                for (doc_id, _, _) in &modified {
+                    reload_prompts.remove(doc_id);
+                }
+                if event != PromptEvent::Validate || input != "y" {
+                    return;
+                }
+
+                let scrolloff = cx.editor.config().scrolloff;
+                for (doc_id, _, view_ids) in &modified {
+                    // views may have been closed in the meantime
+                    let view_ids: Vec<_> = view_ids
+                        .iter()
+                        .copied()
+                        .filter(|view_id| cx.editor.tree.contains(*view_id))
+                        .collect();
+                    if !cx.editor.documents.contains_key(doc_id) || view_ids.is_empty() {
+                        continue;
+                    }
+                    if let Err(err) =
+                        commands::typed::reload_document(cx.editor, *doc_id, &view_ids, scrolloff)
+                    {
+                        cx.editor.set_error(err.to_string());
+                    }
+                }
+            },
+        );
+        self.compositor.push(Box::new(prompt));
+    }
+
     pub fn handle_document_write(&mut self, doc_save_event: DocumentSavedEventResult) {
         let doc_save_event = match doc_save_event {
             Ok(event) => event,
@@ -529,8 +666,12 @@ pub fn handle_document_write(&mut self, doc_save_event: DocumentSavedEventResult
             let id = doc.id();
             doc.detect_language(loader);
             let _ = self.editor.refresh_language_server(id);
+            self.editor.watch_document_paths();
         }
 
+        self.editor.plugins.document_saved(&doc_save_event.path);
+        commands::refresh_code_lenses(&mut self.editor, &mut self.jobs, doc_save_event.doc_id);
+
         // TODO: fix being overwritten by lsp
         self.editor.set_status(format!(
             "'{}' written, {}L {}B",
@@ -563,12 +704,35 @@ pub async fn handle_editor_event(&mut self, event: EditorEvent) -> bool {
                     self.last_render = Instant::now();
                 }
             }
+            EditorEvent::PluginEvent((event, params)) => {
+                let mut cx = crate::compositor::Context {
+                    editor: &mut self.editor,
+                    jobs: &mut self.jobs,
+                    scroll: None,
+                };
+                crate::plugin::notify_event(&mut cx, event, params);
+                self.render().await;
+            }
+            EditorEvent::FileEvents(events) => {
+                self.handle_file_events(events);
+                self.render().await;
+            }
             EditorEvent::DebuggerEvent(payload) => {
                 let needs_render = self.editor.handle_debugger_message(payload).await;
                 if needs_render {
                     self.render().await;
                 }
             }
+            EditorEvent::TerminalEvent(event) => {
+                use futures_util::{FutureExt, StreamExt};
+
+                self.editor.terminals.handle_event(event);
+                // process the output that is already queued before rendering
+                while let Some(Some(event)) = self.editor.terminals.events.next().now_or_never() {
+                    self.editor.terminals.handle_event(event);
+                }
+                self.render().await;
+            }
             EditorEvent::IdleTimer => {
                 self.editor.clear_idle_timer();
                 self.handle_idle_timeout().await;
@@ -609,6 +773,8 @@ pub async fn handle_terminal_events(
             event => self.compositor.handle_event(&event.into(), &mut cx),
         };
 
+        self.editor.plugins.set_mode(self.editor.mode);
+
         if should_redraw && !self.editor.should_close() {
             self.render().await;
         }
@@ -652,9 +818,10 @@ pub async fn handle_language_server_message(
                             tokio::spawn(language_server.did_change_configuration(config.clone()));
                         }
 
-                        let docs = self.editor.documents().filter(|doc| {
-                            doc.language_server().map(|server| server.id()) == Some(server_id)
-                        });
+                        let docs = self
+                            .editor
+                            .documents()
+                            .filter(|doc| doc.supports_language_server(server_id));
 
                         // trigger textDocument/didOpen for docs that are already open
                         for doc in docs {
@@ -663,8 +830,10 @@ pub async fn handle_language_server_message(
                                 None => continue, // skip documents with no path
                             };
 
-                            let language_id =
-                                doc.language_id().map(ToOwned::to_owned).unwrap_or_default();
+                            let language_id = doc
+                                .language_id(language_server.name())
+                                .map(ToOwned::to_owned)
+                                .unwrap_or_default();
 
                             tokio::spawn(language_server.text_document_did_open(
                                 url,
@@ -673,126 +842,32 @@ pub async fn handle_language_server_message(
                                 language_id,
                             ));
                         }
-                    }
-                    Notification::PublishDiagnostics(mut params) => {
-                        let path = params.uri.to_file_path().unwrap();
-                        let doc = self.editor.document_by_path_mut(&path);
-
-                        if let Some(doc) = doc {
-                            let lang_conf = doc.language_config();
-                            let text = doc.text();
-
-                            let diagnostics = params
-                                .diagnostics
-                                .iter()
-                                .filter_map(|diagnostic| {
-                                    use helix_core::diagnostic::{Diagnostic, Range, Severity::*};
-                                    use lsp::DiagnosticSeverity;
-
-                                    let language_server = if let Some(language_server) = doc.language_server() {
-                                        language_server
-                                    } else {
-                                        log::warn!("Discarding diagnostic because language server is not initialized: {:?}", diagnostic);
-                                        return None;
-                                    };
-
-                                    // TODO: convert inside server
-                                    let start = if let Some(start) = lsp_pos_to_pos(
-                                        text,
-                                        diagnostic.range.start,
-                                        language_server.offset_encoding(),
-                                    ) {
-                                        start
-                                    } else {
-                                        log::warn!("lsp position out of bounds - {:?}", diagnostic);
-                                        return None;
-                                    };
-
-                                    let end = if let Some(end) = lsp_pos_to_pos(
-                                        text,
-                                        diagnostic.range.end,
-                                        language_server.offset_encoding(),
-                                    ) {
-                                        end
-                                    } else {
-                                        log::warn!("lsp position out of bounds - {:?}", diagnostic);
-                                        return None;
-                                    };
-
-                                    let severity =
-                                        diagnostic.severity.map(|severity| match severity {
-                                            DiagnosticSeverity::ERROR => Error,
-                                            DiagnosticSeverity::WARNING => Warning,
-                                            DiagnosticSeverity::INFORMATION => Info,
-                                            DiagnosticSeverity::HINT => Hint,
-                                            severity => unreachable!(
-                                                "unrecognized diagnostic severity: {:?}",
-                                                severity
-                                            ),
-                                        });
-
-                                    if let Some(lang_conf) = lang_conf {
-                                        if let Some(severity) = severity {
-                                            if severity < lang_conf.diagnostic_severity {
-                                                return None;
-                                            }
-                                        }
-                                    };
-
-                                    let code = match diagnostic.code.clone() {
-                                        Some(x) => match x {
-                                            lsp::NumberOrString::Number(x) => {
-                                                Some(NumberOrString::Number(x))
-                                            }
-                                            lsp::NumberOrString::String(x) => {
-                                                Some(NumberOrString::String(x))
-                                            }
-                                        },
-                                        None => None,
-                                    };
-
-                                    let tags = if let Some(ref tags) = diagnostic.tags {
-                                        let new_tags = tags.iter().filter_map(|tag| {
-                                            match *tag {
-                                                lsp::DiagnosticTag::DEPRECATED => Some(DiagnosticTag::Deprecated),
-                                                lsp::DiagnosticTag::UNNECESSARY => Some(DiagnosticTag::Unnecessary),
-                                                _ => None
-                                            }
-                                        }).collect();
-
-                                        new_tags
-                                    } else {
-                                        Vec::new()
-                                    };
-
-                                    Some(Diagnostic {
-                                        range: Range { start, end },
-                                        line: diagnostic.range.start.line as usize,
-                                        message: diagnostic.message.clone(),
-                                        severity,
-                                        code,
-                                        tags,
-                                        source: diagnostic.source.clone(),
-                                        data: diagnostic.data.clone(),
-                                    })
-                                })
-                                .collect();
-
-                            doc.set_diagnostics(diagnostics);
-                        }
 
-                        // Sort diagnostics first by severity and then by line numbers.
-                        // Note: The `lsp::DiagnosticSeverity` enum is already defined in decreasing order
-                        params
-                            .diagnostics
-                            .sort_unstable_by_key(|d| (d.severity, d.range.start));
-
-                        // Insert the original lsp::Diagnostics here because we may have no open document
-                        // for diagnosic message and so we can't calculate the exact position.
-                        // When using them later in the diagnostics picker, we calculate them on-demand.
-                        self.editor
-                            .diagnostics
-                            .insert(params.uri, params.diagnostics);
+                        commands::compute_inlay_hints_for_all_views(
+                            &mut self.editor,
+                            &mut self.jobs,
+                        );
+                        commands::compute_semantic_tokens_for_all_views(
+                            &mut self.editor,
+                            &mut self.jobs,
+                        );
+                        commands::compute_code_lenses_for_all_views(
+                            &mut self.editor,
+                            &mut self.jobs,
+                        );
+                        commands::compute_document_links_for_all_views(
+                            &mut self.editor,
+                            &mut self.jobs,
+                        );
+                        commands::pull_diagnostics_for_all_views(&mut self.editor, &mut self.jobs);
+                    }
+                    Notification::PublishDiagnostics(params) => {
+                        commands::handle_diagnostics(
+                            &mut self.editor,
+                            server_id,
+                            params.uri,
+                            params.diagnostics,
+                        );
                     }
                     Notification::ShowMessage(params) => {
                         log::warn!("unhandled window/showMessage: {:?}", params);
@@ -894,10 +969,8 @@ pub async fn handle_language_server_message(
                             .editor
                             .documents_mut()
                             .filter_map(|doc| {
-                                if doc.language_server().map(|server| server.id())
-                                    == Some(server_id)
-                                {
-                                    doc.set_diagnostics(Vec::new());
+                                if doc.supports_language_server(server_id) {
+                                    doc.set_diagnostics(server_id, Vec::new());
                                     doc.url()
                                 } else {
                                     None
@@ -906,10 +979,22 @@ pub async fn handle_language_server_message(
                             .collect();
 
                         for url in urls {
-                            self.editor.diagnostics.remove(&url);
+                            if let Some(diagnostics) = self.editor.diagnostics.get_mut(&url) {
+                                diagnostics.retain(|(_, id)| *id != server_id);
+                                if diagnostics.is_empty() {
+                                    self.editor.diagnostics.remove(&url);
+                                }
+                            }
                         }
 
+                        // Forget the results of the diagnostics pulled from the server.
+                        self.editor.diagnostic_result_ids.remove(&server_id);
+                        self.editor.pending_workspace_diagnostics.remove(&server_id);
+
                         // Remove the language server from the registry.
+                        self.editor
+                            .file_watcher
+                            .unregister_language_server(server_id);
                         self.editor.language_servers.remove_by_id(server_id);
                     }
                 }
@@ -968,6 +1053,8 @@ pub async fn handle_language_server_message(
                         Ok(json!(language_server.workspace_folders()))
                     }
                     MethodCall::WorkspaceConfiguration(params) => {
+                        let language_server =
+                            self.editor.language_servers.get_by_id(server_id).unwrap();
                         let result: Vec<_> = params
                             .items
                             .iter()
@@ -976,14 +1063,15 @@ pub async fn handle_language_server_message(
                                     Some(scope) => {
                                         let path = scope.to_file_path().ok()?;
                                         let doc = self.editor.document_by_path(path)?;
-                                        doc.language_config()?.config.as_ref()?
+                                        let language_config = doc.language_config()?;
+                                        language_config
+                                            .language_servers()
+                                            .iter()
+                                            .find(|config| config.name() == language_server.name())
+                                            .and_then(|config| config.config.as_ref())
+                                            .or(language_config.config.as_ref())?
                                     }
-                                    None => self
-                                        .editor
-                                        .language_servers
-                                        .get_by_id(server_id)
-                                        .unwrap()
-                                        .config()?,
+                                    None => language_server.config()?,
                                 };
                                 if let Some(section) = item.section.as_ref() {
                                     for part in section.split('.') {
@@ -995,6 +1083,45 @@ pub async fn handle_language_server_message(
                             .collect();
                         Ok(json!(result))
                     }
+                    MethodCall::RegisterCapability(params) => {
+                        use lsp::notification::Notification as _;
+
+                        let root = match self.editor.language_servers.get_by_id(server_id) {
+                            Some(language_server) => language_server.root_path().to_path_buf(),
+                            None => return,
+                        };
+                        for registration in params.registrations {
+                            if registration.method
+                                != lsp::notification::DidChangeWatchedFiles::METHOD
+                            {
+                                warn!("unhandled capability registration: {}", registration.method);
+                                continue;
+                            }
+                            let options = registration.register_options.map(
+                                serde_json::from_value::<
+                                    lsp::DidChangeWatchedFilesRegistrationOptions,
+                                >,
+                            );
+                            match options {
+                                Some(Ok(options)) => self.editor.file_watcher.register(
+                                    server_id,
+                                    registration.id,
+                                    &root,
+                                    &options.watchers,
+                                ),
+                                _ => warn!("invalid options to register {}", registration.method),
+                            }
+                        }
+                        Ok(serde_json::Value::Null)
+                    }
+                    MethodCall::UnregisterCapability(params) => {
+                        for unregistration in params.unregisterations {
+                            self.editor
+                                .file_watcher
+                                .unregister(server_id, &unregistration.id);
+                        }
+                        Ok(serde_json::Value::Null)
+                    }
                 };
 
                 let language_server = match self.editor.language_servers.get_by_id(server_id) {
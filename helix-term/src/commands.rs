@@ -16,12 +16,15 @@
     indent,
     indent::IndentStyle,
     line_ending::{get_line_ending_of_str, line_end_char_index, str_is_line_ending},
+    link::{self, Link},
     match_brackets,
     movement::{self, Direction},
     object, pos_at_coords, pos_at_visual_coords,
     regex::{self, Regex, RegexBuilder},
     search::{self, CharMatcher},
-    selection, shellwords, surround, textobject,
+    selection, shellwords, surround,
+    syntax::{BlockCommentToken, LanguageServerFeature},
+    textobject,
     tree_sitter::Node,
     unicode::width::UnicodeWidthChar,
     visual_coords_at_pos, LineEnding, Position, Range, Rope, RopeGraphemes, RopeSlice, Selection,
@@ -167,15 +170,15 @@ pub fn execute(&self, cx: &mut Context) {
         match &self {
             Self::Typable { name, args, doc: _ } => {
                 let args: Vec<Cow<str>> = args.iter().map(Cow::from).collect();
-                if let Some(command) = typed::TYPABLE_COMMAND_MAP.get(name.as_str()) {
-                    let mut cx = compositor::Context {
-                        editor: cx.editor,
-                        jobs: cx.jobs,
-                        scroll: None,
-                    };
-                    if let Err(e) = (command.fun)(&mut cx, &args[..], PromptEvent::Validate) {
-                        cx.editor.set_error(format!("{}", e));
-                    }
+                let mut cx = compositor::Context {
+                    editor: cx.editor,
+                    jobs: cx.jobs,
+                    scroll: None,
+                };
+                if let Err(e) =
+                    crate::plugin::execute_command(&mut cx, name, &args[..], PromptEvent::Validate)
+                {
+                    cx.editor.set_error(format!("{}", e));
                 }
             }
             Self::Static { fun, .. } => (fun)(cx),
@@ -253,6 +256,7 @@ pub fn doc(&self) -> &str {
         search_selection, "Use current selection as search pattern",
         make_search_word_bounded, "Modify current search to make it word bounded",
         global_search, "Global search in workspace folder",
+        global_replace, "Global search and replace in workspace folder",
         extend_line, "Select current line, if already selected, extend to another line based on the anchor",
         extend_line_below, "Select current line, if already selected, extend to next line",
         extend_line_above, "Select current line, if already selected, extend to previous line",
@@ -270,7 +274,10 @@ pub fn doc(&self) -> &str {
         command_mode, "Enter command mode",
         file_picker, "Open file picker",
         file_picker_in_current_directory, "Open file picker at current working directory",
+        toggle_explorer, "Open or focus the file explorer sidebar, close it if focused",
+        toggle_terminal, "Open a terminal in a split or move the focus to it",
         code_action, "Perform code action",
+        code_lens, "Execute code lens on current line",
         buffer_picker, "Open buffer picker",
         jumplist_picker, "Open jumplist picker",
         symbol_picker, "Open symbol picker",
@@ -291,9 +298,14 @@ pub fn doc(&self) -> &str {
         add_newline_below, "Add newline below",
         goto_type_definition, "Goto type definition",
         goto_implementation, "Goto implementation",
+        goto_declaration, "Goto declaration",
+        incoming_calls_picker, "Open incoming calls picker",
+        outgoing_calls_picker, "Open outgoing calls picker",
+        supertypes_picker, "Open supertypes picker",
+        subtypes_picker, "Open subtypes picker",
         goto_file_start, "Goto line number <n> else file start",
         goto_file_end, "Goto file end",
-        goto_file, "Goto files in selection",
+        goto_file, "Goto files or links in selection",
         goto_file_hsplit, "Goto files in selection (hsplit)",
         goto_file_vsplit, "Goto files in selection (vsplit)",
         goto_reference, "Goto references",
@@ -313,6 +325,8 @@ pub fn doc(&self) -> &str {
         goto_prev_change, "Goto previous change",
         goto_first_change, "Goto first change",
         goto_last_change, "Goto last change",
+        goto_next_tabstop, "Goto next snippet tabstop",
+        goto_prev_tabstop, "Goto previous snippet tabstop",
         goto_line_start, "Goto line start",
         goto_line_end, "Goto line end",
         goto_next_buffer, "Goto next buffer",
@@ -363,7 +377,10 @@ pub fn doc(&self) -> &str {
         remove_primary_selection, "Remove primary selection",
         completion, "Invoke completion popup",
         hover, "Show docs for item under cursor",
+        toggle_inlay_hints, "Toggle inlay hints",
         toggle_comments, "Comment/uncomment selections",
+        toggle_line_comments, "Line comment/uncomment selections",
+        toggle_block_comments, "Block comment/uncomment selections",
         rotate_selections_forward, "Rotate selections forward",
         rotate_selections_backward, "Rotate selections backward",
         rotate_selection_contents_forward, "Rotate selection contents forward",
@@ -399,6 +416,11 @@ pub fn doc(&self) -> &str {
         align_view_bottom, "Align view bottom",
         scroll_up, "Scroll view up",
         scroll_down, "Scroll view down",
+        fold, "Close fold at cursor",
+        unfold, "Open folds at cursor",
+        toggle_fold, "Toggle fold at cursor",
+        fold_all, "Close all folds",
+        unfold_all, "Open all folds",
         match_brackets, "Goto matching bracket",
         surround_add, "Surround add",
         surround_replace, "Surround replace",
@@ -428,10 +450,14 @@ pub fn doc(&self) -> &str {
         dap_terminate, "End debug session",
         dap_edit_condition, "Edit breakpoint condition on current line",
         dap_edit_log, "Edit breakpoint log message on current line",
+        dap_edit_hit_condition, "Edit breakpoint hit condition on current line",
+        dap_breakpoints, "List breakpoints",
         dap_switch_thread, "Switch current thread",
         dap_switch_stack_frame, "Switch stack frame",
         dap_enable_exceptions, "Enable exception breakpoints",
         dap_disable_exceptions, "Disable exception breakpoints",
+        dap_toggle_console, "Open or focus the debug console, close it if focused",
+        dap_toggle_panel, "Open or focus the watch, variables and call stack panel, close it if focused",
         shell_pipe, "Pipe selections through shell command",
         shell_pipe_to, "Pipe selections into shell command ignoring output",
         shell_insert_output, "Insert shell command output before selections",
@@ -475,9 +501,11 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
                 .collect::<Vec<String>>();
             typed::TYPABLE_COMMAND_MAP
                 .get(name)
-                .map(|cmd| MappableCommand::Typable {
-                    name: cmd.name.to_owned(),
-                    doc: format!(":{} {:?}", cmd.name, args),
+                .map(|cmd| cmd.name)
+                .or_else(|| helix_view::plugin::command(name).map(|(_, cmd)| cmd.name.as_str()))
+                .map(|name| MappableCommand::Typable {
+                    name: name.to_owned(),
+                    doc: format!(":{} {:?}", name, args),
                     args,
                 })
                 .ok_or_else(|| anyhow!("No TypableCommand named '{}'", s))
@@ -542,7 +570,45 @@ fn move_impl<F>(cx: &mut Context, move_fn: F, dir: Direction, behaviour: Movemen
     doc.set_selection(view.id, selection);
 }
 
-use helix_core::movement::{move_horizontally, move_vertically};
+use helix_core::{
+    movement::{move_horizontally, move_vertically, move_vertically_wrapped},
+    wrap::WrapFormat,
+};
+
+/// Moves vertically by visual rows when the document is soft wrapped or has closed folds
+/// and by lines otherwise.
+fn move_vertically_impl(cx: &mut Context, dir: Direction, behaviour: Movement) {
+    let (view, doc) = current_ref!(cx.editor);
+    let inlay_hints = doc.inlay_hints(view.id).cloned().unwrap_or_default();
+    let hidden = doc.hidden_lines(view.id);
+    let format = match view.wrap_format(doc) {
+        Some(format) => format,
+        // lay every line out as a single row
+        None if !hidden.is_empty() => WrapFormat {
+            width: usize::MAX,
+            tab_width: doc.tab_width(),
+            indicator_width: 0,
+        },
+        None => return move_impl(cx, move_vertically, dir, behaviour),
+    };
+    move_impl(
+        cx,
+        |text, range, dir, count, behaviour, _| {
+            move_vertically_wrapped(
+                text,
+                range,
+                dir,
+                count,
+                behaviour,
+                &format,
+                &hidden,
+                |line| inlay_hints.line_virtual_text(text, line),
+            )
+        },
+        dir,
+        behaviour,
+    )
+}
 
 fn move_char_left(cx: &mut Context) {
     move_impl(cx, move_horizontally, Direction::Backward, Movement::Move)
@@ -553,11 +619,11 @@ fn move_char_right(cx: &mut Context) {
 }
 
 fn move_line_up(cx: &mut Context) {
-    move_impl(cx, move_vertically, Direction::Backward, Movement::Move)
+    move_vertically_impl(cx, Direction::Backward, Movement::Move)
 }
 
 fn move_line_down(cx: &mut Context) {
-    move_impl(cx, move_vertically, Direction::Forward, Movement::Move)
+    move_vertically_impl(cx, Direction::Forward, Movement::Move)
 }
 
 fn extend_char_left(cx: &mut Context) {
@@ -569,11 +635,11 @@ fn extend_char_right(cx: &mut Context) {
 }
 
 fn extend_line_up(cx: &mut Context) {
-    move_impl(cx, move_vertically, Direction::Backward, Movement::Extend)
+    move_vertically_impl(cx, Direction::Backward, Movement::Extend)
 }
 
 fn extend_line_down(cx: &mut Context) {
-    move_impl(cx, move_vertically, Direction::Forward, Movement::Extend)
+    move_vertically_impl(cx, Direction::Forward, Movement::Extend)
 }
 
 fn goto_line_end_impl(view: &mut View, doc: &mut Document, movement: Movement) {
@@ -889,6 +955,8 @@ fn goto_window(cx: &mut Context, align: Align) {
     }
     .max(view.offset.row + scrolloff)
     .min(last_line.saturating_sub(scrolloff));
+    // don't move into a closed fold
+    let line = doc.hidden_lines(view.id).visible_line(line);
 
     let pos = doc.text().line_to_char(line);
     let text = doc.text().slice(..);
@@ -1039,34 +1107,112 @@ fn goto_file_impl(cx: &mut Context, action: Action) {
     if selections.len() == 1 && primary.len() == 1 {
         let count = cx.count();
         let text_slice = text.slice(..);
-        // In this case it selects the WORD under the cursor
-        let current_word = textobject::textobject_word(
-            text_slice,
-            primary,
-            textobject::TextObject::Inside,
-            count,
-            true,
-        );
-        // Trims some surrounding chars so that the actual file is opened.
-        let surrounding_chars: &[_] = &['\'', '"', '(', ')'];
+        let pos = primary.cursor(text_slice);
+
+        // The links of the language server take precedence over the text under the cursor.
+        if let Some(link) = doc.document_link_at(pos).cloned() {
+            open_document_link(cx, link, action);
+            return;
+        }
+
         paths.clear();
-        paths.push(
-            current_word
-                .fragment(text_slice)
-                .trim_matches(surrounding_chars)
-                .to_string(),
-        );
+        if let Some(url) = link::url_at(text_slice, pos) {
+            paths.push(text_slice.slice(url).to_string());
+        } else {
+            // In this case it selects the WORD under the cursor
+            let current_word = textobject::textobject_word(
+                text_slice,
+                primary,
+                textobject::TextObject::Inside,
+                count,
+                true,
+            );
+            // Trims some surrounding chars so that the actual file is opened.
+            let surrounding_chars: &[_] = &['\'', '"', '(', ')'];
+            paths.push(
+                current_word
+                    .fragment(text_slice)
+                    .trim_matches(surrounding_chars)
+                    .to_string(),
+            );
+        }
     }
     for sel in paths {
         let p = sel.trim();
         if !p.is_empty() {
-            if let Err(e) = cx.editor.open(&PathBuf::from(p), action) {
-                cx.editor.set_error(format!("Open file failed: {:?}", e));
-            }
+            open_link(cx.editor, Link::parse(p), action);
         }
     }
 }
 
+/// Opens a link: URLs are handed to the `url-opener`, files are opened at the linked position.
+fn open_link(editor: &mut Editor, link: Link, action: Action) {
+    let (path, line, column) = match link {
+        Link::Url(url) => {
+            open_url(editor, &url);
+            return;
+        }
+        Link::Path { path, line, column } => (path, line, column),
+    };
+
+    if let Err(e) = editor.open(&path, action) {
+        editor.set_error(format!("Open file failed: {:?}", e));
+        return;
+    }
+
+    let line = match line {
+        Some(line) => line,
+        None => return,
+    };
+    let (view, doc) = current!(editor);
+    let text = doc.text().slice(..);
+    // the position is 1-based
+    let line = line
+        .saturating_sub(1)
+        .min(text.len_lines().saturating_sub(1));
+    let pos = (text.line_to_char(line) + column.unwrap_or(1).saturating_sub(1))
+        .min(line_end_char_index(&text, line));
+    doc.set_selection(view.id, Selection::point(pos));
+    align_view(doc, view, Align::Center);
+}
+
+fn open_url(editor: &mut Editor, url: &str) {
+    use std::process::Stdio;
+    use tokio::process::Command;
+
+    let opener = editor.config().url_opener.clone();
+    let (program, args) = match opener.split_first() {
+        Some(opener) => opener,
+        None => {
+            editor.set_error("No `url-opener` configured");
+            return;
+        }
+    };
+
+    let child = Command::new(program)
+        .args(args)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            tokio::spawn(async move {
+                match child.wait().await {
+                    Ok(status) if !status.success() => {
+                        log::warn!("url opener exited with {}", status)
+                    }
+                    Err(err) => log::warn!("failed to wait for the url opener: {}", err),
+                    _ => (),
+                }
+            });
+            editor.set_status(format!("Opened {}", url));
+        }
+        Err(err) => editor.set_error(format!("Failed to open '{}': {}", url, err)),
+    }
+}
+
 fn extend_word_impl<F>(cx: &mut Context, extend_fn: F)
 where
     F: Fn(RopeSlice, Range, usize) -> Range,
@@ -1389,20 +1535,27 @@ pub fn scroll(cx: &mut Context, offset: usize, direction: Direction) {
 
     let scrolloff = config.scrolloff.min(height / 2);
 
+    // a closed fold scrolls by as a single line
+    let hidden = doc.hidden_lines(view.id);
     view.offset.row = match direction {
-        Forward => view.offset.row + offset,
-        Backward => view.offset.row.saturating_sub(offset),
+        Forward => hidden.line_below(view.offset.row, offset),
+        Backward => hidden.line_above(view.offset.row, offset),
     }
     .min(doc_last_line);
 
     // recalculate last line
     let last_line = view.last_line(doc);
+    let first_line = if hidden.is_hidden(view.offset.row) {
+        hidden.next_visible_line(view.offset.row)
+    } else {
+        view.offset.row
+    };
 
     // clamp into viewport
-    let line = cursor
-        .row
-        .max(view.offset.row + scrolloff)
-        .min(last_line.saturating_sub(scrolloff));
+    let line = hidden
+        .visible_line(cursor.row)
+        .max(hidden.line_below(first_line, scrolloff))
+        .min(hidden.line_above(last_line, scrolloff));
 
     // If cursor needs moving, replace primary selection
     if line != cursor.row {
@@ -1672,6 +1825,11 @@ fn search_impl(
         };
 
         doc.set_selection(view.id, selection);
+        // reveal the match if it's in a closed fold
+        let line = doc.text().char_to_line(start);
+        if doc.hidden_lines(view.id).is_hidden(line) {
+            doc.open_folds_at_line(view.id, line);
+        }
         view.ensure_cursor_in_view_center(doc, scrolloff);
     };
 }
@@ -2016,6 +2174,166 @@ fn label(&self, current_path: &Self::Data) -> Spans {
     cx.jobs.callback(show_picker);
 }
 
+/// Searches the workspace for lines matching `regex` and computes their replacement.
+/// `case_insensitive` must be the flag `regex` was built with, so that the lines found by
+/// the searcher are the ones the regex replaces matches in.
+fn global_replace_matches(
+    regex: &Regex,
+    case_insensitive: bool,
+    replacement: &str,
+    file_picker_config: &helix_view::editor::FilePickerConfig,
+) -> Vec<ui::ReplaceMatch> {
+    let matcher = match RegexMatcherBuilder::new()
+        .case_insensitive(case_insensitive)
+        .build(regex.as_str())
+    {
+        Ok(matcher) => matcher,
+        Err(err) => {
+            log::error!("Global replace error: {}", err);
+            return Vec::new();
+        }
+    };
+    let searcher = SearcherBuilder::new()
+        .binary_detection(BinaryDetection::quit(b'\x00'))
+        .build();
+    let (all_matches_sx, all_matches_rx) = std::sync::mpsc::channel();
+
+    let search_root =
+        std::env::current_dir().expect("Global replace error: Failed to get current dir");
+    WalkBuilder::new(search_root)
+        .hidden(file_picker_config.hidden)
+        .parents(file_picker_config.parents)
+        .ignore(file_picker_config.ignore)
+        .follow_links(file_picker_config.follow_symlinks)
+        .git_ignore(file_picker_config.git_ignore)
+        .git_global(file_picker_config.git_global)
+        .git_exclude(file_picker_config.git_exclude)
+        .max_depth(file_picker_config.max_depth)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build_parallel()
+        .run(|| {
+            let mut searcher = searcher.clone();
+            let matcher = matcher.clone();
+            let all_matches_sx = all_matches_sx.clone();
+            Box::new(move |entry: Result<DirEntry, ignore::Error>| -> WalkState {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(_) => return WalkState::Continue,
+                };
+
+                match entry.file_type() {
+                    Some(entry) if entry.is_file() => {}
+                    // skip everything else
+                    _ => return WalkState::Continue,
+                };
+
+                let result = searcher.search_path(
+                    &matcher,
+                    entry.path(),
+                    sinks::UTF8(|line_num, line| {
+                        let line = line.trim_end_matches(&['\r', '\n'][..]);
+                        let replaced = regex.replace_all(line, replacement);
+                        if replaced != line {
+                            let _ = all_matches_sx.send(ui::ReplaceMatch {
+                                path: entry.path().to_path_buf(),
+                                line_num: line_num as usize - 1,
+                                line: line.to_string(),
+                                replacement: replaced.into_owned(),
+                                excluded: std::cell::Cell::new(false),
+                            });
+                        }
+
+                        Ok(true)
+                    }),
+                );
+
+                if let Err(err) = result {
+                    log::error!("Global replace error: {}, {}", entry.path().display(), err);
+                }
+                WalkState::Continue
+            })
+        });
+
+    drop(all_matches_sx);
+    all_matches_rx.into_iter().collect()
+}
+
+fn global_replace(cx: &mut Context) {
+    let config = cx.editor.config();
+    let smart_case = config.search.smart_case;
+    let file_picker_config = config.file_picker.clone();
+
+    let reg = cx.register.unwrap_or('/');
+    let completions = search_completions(cx, Some(reg));
+
+    let prompt = Prompt::new(
+        "global-replace:".into(),
+        Some(reg),
+        move |_editor: &Editor, input: &str| {
+            completions
+                .iter()
+                .filter(|comp| comp.starts_with(input))
+                .map(|comp| (0.., std::borrow::Cow::Owned(comp.clone())))
+                .collect()
+        },
+        move |cx: &mut compositor::Context, input: &str, event: PromptEvent| {
+            if event != PromptEvent::Validate || input.is_empty() {
+                return;
+            }
+
+            let case_insensitive = smart_case && !input.chars().any(char::is_uppercase);
+            let regex = match RegexBuilder::new(input)
+                .case_insensitive(case_insensitive)
+                .build()
+            {
+                Ok(regex) => regex,
+                Err(err) => {
+                    cx.editor.set_error(format!("Invalid regex: {}", err));
+                    return;
+                }
+            };
+
+            let file_picker_config = file_picker_config.clone();
+            let call: job::Callback = Callback::EditorCompositor(Box::new(
+                move |_editor: &mut Editor, compositor: &mut Compositor| {
+                    let prompt = Prompt::new(
+                        format!("replace '{}' with:", regex.as_str()).into(),
+                        None,
+                        ui::completers::none,
+                        move |cx: &mut compositor::Context, input: &str, event: PromptEvent| {
+                            if event != PromptEvent::Validate {
+                                return;
+                            }
+
+                            let matches = global_replace_matches(
+                                &regex,
+                                case_insensitive,
+                                input,
+                                &file_picker_config,
+                            );
+                            if matches.is_empty() {
+                                cx.editor.set_status("No matches found");
+                                return;
+                            }
+
+                            let call: job::Callback = Callback::EditorCompositor(Box::new(
+                                move |_editor: &mut Editor, compositor: &mut Compositor| {
+                                    let picker = ui::ReplacePicker::new(matches);
+                                    compositor.push(Box::new(overlayed(picker)));
+                                },
+                            ));
+                            cx.jobs.callback(async move { Ok(call) });
+                        },
+                    );
+                    compositor.push(Box::new(prompt));
+                },
+            ));
+            cx.jobs.callback(async move { Ok(call) });
+        },
+    );
+    cx.push_layer(Box::new(prompt));
+}
+
 enum Extend {
     Above,
     Below,
@@ -2043,17 +2361,19 @@ fn extend_line_impl(cx: &mut Context, extend: Extend) {
     let (view, doc) = current!(cx.editor);
 
     let text = doc.text();
+    // closed folds are selected as a whole
+    let hidden_lines = doc.hidden_lines(view.id);
     let selection = doc.selection(view.id).clone().transform(|range| {
         let (start_line, end_line) = range.line_range(text.slice(..));
 
         let start = text.line_to_char(match extend {
-            Extend::Above => start_line.saturating_sub(count - 1),
+            Extend::Above => hidden_lines.line_above(start_line, count - 1),
             Extend::Below => start_line,
         });
         let end = text.line_to_char(
             match extend {
-                Extend::Above => end_line + 1, // the start of next line
-                Extend::Below => end_line + count,
+                Extend::Above => hidden_lines.line_below(end_line, 1), // the start of next line
+                Extend::Below => hidden_lines.line_below(end_line, count),
             }
             .min(text.len_lines()),
         );
@@ -2061,10 +2381,17 @@ fn extend_line_impl(cx: &mut Context, extend: Extend) {
         // extend to previous/next line if current line is selected
         let (anchor, head) = if range.from() == start && range.to() == end {
             match extend {
-                Extend::Above => (end, text.line_to_char(start_line.saturating_sub(count))),
+                Extend::Above => (
+                    end,
+                    text.line_to_char(hidden_lines.line_above(start_line, count)),
+                ),
                 Extend::Below => (
                     start,
-                    text.line_to_char((end_line + count + 1).min(text.len_lines())),
+                    text.line_to_char(
+                        hidden_lines
+                            .line_below(end_line, count + 1)
+                            .min(text.len_lines()),
+                    ),
                 ),
             }
         } else {
@@ -2296,6 +2623,32 @@ fn file_picker_in_current_directory(cx: &mut Context) {
     cx.push_layer(Box::new(overlayed(picker)));
 }
 
+fn toggle_explorer(cx: &mut Context) {
+    cx.callback = Some(Box::new(|compositor, cx| {
+        if let Some(editor_view) = compositor.find::<ui::EditorView>() {
+            editor_view.toggle_explorer(cx.editor);
+        }
+    }));
+}
+
+fn toggle_terminal(cx: &mut Context) {
+    let view_id = cx
+        .editor
+        .tree
+        .views()
+        .find(|(view, _)| view.terminal.is_some())
+        .map(|(view, _)| view.id);
+    match view_id {
+        Some(view_id) => cx.editor.focus(view_id),
+        None => {
+            if let Err(err) = ui::terminal::spawn(cx.editor, "") {
+                cx.editor
+                    .set_error(format!("Failed to start terminal: {}", err));
+            }
+        }
+    }
+}
+
 fn buffer_picker(cx: &mut Context) {
     let current = view!(cx.editor).doc;
 
@@ -2748,6 +3101,34 @@ fn goto_last_modification(cx: &mut Context) {
     }
 }
 
+fn goto_next_tabstop(cx: &mut Context) {
+    goto_tabstop_impl(cx, Direction::Forward)
+}
+
+fn goto_prev_tabstop(cx: &mut Context) {
+    goto_tabstop_impl(cx, Direction::Backward)
+}
+
+fn goto_tabstop_impl(cx: &mut Context, direction: Direction) {
+    let (view, doc) = current!(cx.editor);
+    let mut active_snippet = match doc.active_snippet.take() {
+        Some(active_snippet) => active_snippet,
+        None => return,
+    };
+
+    let text = doc.text().slice(..);
+    let selection = match direction {
+        Direction::Forward => active_snippet.next_tabstop(text),
+        Direction::Backward => active_snippet.prev_tabstop(text),
+    };
+    doc.set_selection(view.id, selection);
+
+    // the snippet is done once the final tabstop is reached
+    if !active_snippet.is_done() {
+        doc.active_snippet = Some(active_snippet);
+    }
+}
+
 fn goto_last_modified_file(cx: &mut Context) {
     let view = view!(cx.editor);
     let alternate_file = view
@@ -3002,7 +3383,10 @@ fn language_server_completion(cx: &mut Context, ch: char) {
         use helix_lsp::lsp;
         // if ch matches completion char, trigger completion
         let doc = doc_mut!(cx.editor);
-        let language_server = match doc.language_server() {
+        let language_server = match doc
+            .language_servers_with_feature(LanguageServerFeature::Completion)
+            .next()
+        {
             Some(language_server) => language_server,
             None => return,
         };
@@ -3026,10 +3410,13 @@ fn signature_help(cx: &mut Context, ch: char) {
         use helix_lsp::lsp;
         // if ch matches signature_help char, trigger
         let doc = doc_mut!(cx.editor);
-        // The language_server!() macro is not used here since it will
+        // The language_server_with_feature!() macro is not used here since it will
         // print an "LSP not active for current buffer" message on
         // every keypress.
-        let language_server = match doc.language_server() {
+        let language_server = match doc
+            .language_servers_with_feature(LanguageServerFeature::SignatureHelp)
+            .next()
+        {
             Some(language_server) => language_server,
             None => return,
         };
@@ -3072,6 +3459,18 @@ fn insert(doc: &Rope, selection: &Selection, ch: char) -> Option<Transaction> {
     use helix_core::auto_pairs;
 
     pub fn insert_char(cx: &mut Context, c: char) {
+        // typing over the placeholder of a snippet tabstop replaces it
+        let (view, doc) = current!(cx.editor);
+        if doc.active_snippet.as_ref().map_or(false, |snippet| {
+            snippet.is_placeholder_selected(doc.selection(view.id))
+        }) {
+            let transaction =
+                Transaction::change_by_selection(doc.text(), doc.selection(view.id), |range| {
+                    (range.from(), range.to(), None)
+                });
+            apply_transaction(&transaction, doc, view);
+        }
+
         let (view, doc) = current_ref!(cx.editor);
         let text = doc.text();
         let selection = doc.selection(view.id);
@@ -3097,6 +3496,11 @@ pub fn insert_char(cx: &mut Context, c: char) {
 
     pub fn insert_tab(cx: &mut Context) {
         let (view, doc) = current!(cx.editor);
+        if doc.active_snippet.is_some() {
+            goto_next_tabstop(cx);
+            return;
+        }
+
         // TODO: round out to nearest indentation level (for example a line with 3 spaces should
         // indent by one to reach 4 spaces).
 
@@ -3844,7 +4248,10 @@ fn format_selections(cx: &mut Context) {
     // via lsp if available
     // TODO: else via tree-sitter indentation calculations
 
-    let language_server = match doc.language_server() {
+    let language_server = match doc
+        .language_servers_with_feature(LanguageServerFeature::Format)
+        .next()
+    {
         Some(language_server) => language_server,
         None => return,
     };
@@ -4011,24 +4418,9 @@ fn remove_primary_selection(cx: &mut Context) {
 pub fn completion(cx: &mut Context) {
     use helix_lsp::{lsp, util::pos_to_lsp_pos};
 
-    let (view, doc) = current!(cx.editor);
-
-    let language_server = match doc.language_server() {
-        Some(language_server) => language_server,
-        None => return,
-    };
-
-    let offset_encoding = language_server.offset_encoding();
+    let (view, doc) = current_ref!(cx.editor);
     let text = doc.text().slice(..);
     let cursor = doc.selection(view.id).primary().cursor(text);
-
-    let pos = pos_to_lsp_pos(doc.text(), cursor, offset_encoding);
-
-    let future = match language_server.completion(doc.identifier(), pos, None) {
-        Some(future) => future,
-        None => return,
-    };
-
     let trigger_offset = cursor;
 
     // TODO: trigger_offset should be the cursor offset but we also need a starting offset from where we want to apply
@@ -4040,6 +4432,44 @@ pub fn completion(cx: &mut Context) {
     let offset = iter.take_while(|ch| chars::char_is_word(*ch)).count();
     let start_offset = cursor.saturating_sub(offset);
 
+    let snippet_items = ui::Completion::user_snippet_items(cx.editor, doc);
+
+    let language_server = doc
+        .language_servers_with_feature(LanguageServerFeature::Completion)
+        .next();
+    let language_server = match language_server {
+        Some(language_server) => language_server,
+        None => {
+            if snippet_items.is_empty() {
+                return;
+            }
+            // without a language server only the user's snippets are offered
+            cx.callback = Some(Box::new(
+                move |compositor: &mut Compositor, cx: &mut compositor::Context| {
+                    let size = compositor.size();
+                    let ui = compositor.find::<ui::EditorView>().unwrap();
+                    ui.set_completion(
+                        cx.editor,
+                        snippet_items,
+                        helix_lsp::OffsetEncoding::Utf8,
+                        start_offset,
+                        trigger_offset,
+                        size,
+                    );
+                },
+            ));
+            return;
+        }
+    };
+
+    let offset_encoding = language_server.offset_encoding();
+    let pos = pos_to_lsp_pos(doc.text(), cursor, offset_encoding);
+
+    let future = match language_server.completion(doc.identifier(), pos, None) {
+        Some(future) => future,
+        None => return,
+    };
+
     cx.callback(
         future,
         move |editor, compositor, response: Option<lsp::CompletionResponse>| {
@@ -4048,7 +4478,7 @@ pub fn completion(cx: &mut Context) {
                 return;
             }
 
-            let items = match response {
+            let mut items = match response {
                 Some(lsp::CompletionResponse::Array(items)) => items,
                 // TODO: do something with is_incomplete
                 Some(lsp::CompletionResponse::List(lsp::CompletionList {
@@ -4057,6 +4487,7 @@ pub fn completion(cx: &mut Context) {
                 })) => items,
                 None => Vec::new(),
             };
+            items.extend(snippet_items);
 
             if items.is_empty() {
                 // editor.set_error("No completion available");
@@ -4077,13 +4508,58 @@ pub fn completion(cx: &mut Context) {
 }
 
 // comments
+
+/// Returns the line comment token and block comment tokens of the language under the
+/// primary selection, which can be a language injected into the document's language.
+fn comment_tokens(doc: &Document, view_id: ViewId) -> (Option<String>, Vec<BlockCommentToken>) {
+    let text = doc.text().slice(..);
+    let range = doc.selection(view_id).primary();
+    let injected = doc.syntax().and_then(|syntax| {
+        syntax.language_config_for_byte_range(
+            text.char_to_byte(range.from()),
+            text.char_to_byte(range.to()),
+        )
+    });
+
+    match injected.as_deref().or_else(|| doc.language_config()) {
+        Some(config) => (
+            config.comment_token.clone(),
+            config.block_comment_tokens.clone().unwrap_or_default(),
+        ),
+        None => (None, Vec::new()),
+    }
+}
+
 fn toggle_comments(cx: &mut Context) {
     let (view, doc) = current!(cx.editor);
-    let token = doc
-        .language_config()
-        .and_then(|lc| lc.comment_token.as_ref())
-        .map(|tc| tc.as_ref());
-    let transaction = comment::toggle_line_comments(doc.text(), doc.selection(view.id), token);
+    let (token, block_tokens) = comment_tokens(doc, view.id);
+
+    // languages without line comments are commented with block comments
+    let transaction = if token.is_none() && !block_tokens.is_empty() {
+        comment::toggle_block_comments(doc.text(), doc.selection(view.id), &block_tokens)
+    } else {
+        comment::toggle_line_comments(doc.text(), doc.selection(view.id), token.as_deref())
+    };
+
+    apply_transaction(&transaction, doc, view);
+    exit_select_mode(cx);
+}
+
+fn toggle_line_comments(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    let (token, _) = comment_tokens(doc, view.id);
+    let transaction =
+        comment::toggle_line_comments(doc.text(), doc.selection(view.id), token.as_deref());
+
+    apply_transaction(&transaction, doc, view);
+    exit_select_mode(cx);
+}
+
+fn toggle_block_comments(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    let (_, block_tokens) = comment_tokens(doc, view.id);
+    let transaction =
+        comment::toggle_block_comments(doc.text(), doc.selection(view.id), &block_tokens);
 
     apply_transaction(&transaction, doc, view);
     exit_select_mode(cx);
@@ -4156,20 +4632,26 @@ fn rotate_selection_contents_backward(cx: &mut Context) {
 fn expand_selection(cx: &mut Context) {
     let motion = |editor: &mut Editor| {
         let (view, doc) = current!(editor);
+        let text = doc.text().slice(..);
+        let current_selection = doc.selection(view.id);
 
-        if let Some(syntax) = doc.syntax() {
-            let text = doc.text().slice(..);
-
-            let current_selection = doc.selection(view.id);
-            let selection = object::expand_selection(syntax, text, current_selection.clone());
+        // prefer the selection ranges of the language server where they are enabled
+        let selection = match (selection_ranges(doc, current_selection), doc.syntax()) {
+            (Some(candidates), _) => {
+                object::expand_selection_to_ranges(current_selection.clone(), &candidates)
+            }
+            (None, Some(syntax)) => {
+                object::expand_selection(syntax, text, current_selection.clone())
+            }
+            (None, None) => return,
+        };
 
-            // check if selection is different from the last one
-            if *current_selection != selection {
-                // save current selection so it can be restored using shrink_selection
-                view.object_selections.push(current_selection.clone());
+        // check if selection is different from the last one
+        if *current_selection != selection {
+            // save current selection so it can be restored using shrink_selection
+            view.object_selections.push(current_selection.clone());
 
-                doc.set_selection(view.id, selection);
-            }
+            doc.set_selection(view.id, selection);
         }
     };
     motion(cx.editor);
@@ -4192,11 +4674,17 @@ fn shrink_selection(cx: &mut Context) {
             }
         }
         // if not previous selection, shrink to first child
-        if let Some(syntax) = doc.syntax() {
-            let text = doc.text().slice(..);
-            let selection = object::shrink_selection(syntax, text, current_selection.clone());
-            doc.set_selection(view.id, selection);
-        }
+        let text = doc.text().slice(..);
+        let selection = match (selection_ranges(doc, current_selection), doc.syntax()) {
+            (Some(candidates), _) => {
+                object::shrink_selection_to_ranges(current_selection.clone(), &candidates)
+            }
+            (None, Some(syntax)) => {
+                object::shrink_selection(syntax, text, current_selection.clone())
+            }
+            (None, None) => return,
+        };
+        doc.set_selection(view.id, selection);
     };
     motion(cx.editor);
     cx.editor.last_motion = Some(Motion(Box::new(motion)));
@@ -4449,6 +4937,78 @@ fn scroll_down(cx: &mut Context) {
     scroll(cx, cx.count(), Direction::Forward);
 }
 
+/// The lines of the cursors in a view. Cursors in closed folds are on the first line of
+/// the fold.
+fn cursor_lines(doc: &Document, view: &View) -> Vec<usize> {
+    let text = doc.text().slice(..);
+    let hidden_lines = doc.hidden_lines(view.id);
+    let mut lines: Vec<_> = doc
+        .selection(view.id)
+        .iter()
+        .map(|range| hidden_lines.visible_line(range.cursor_line(text)))
+        .collect();
+    lines.dedup();
+    lines
+}
+
+fn fold(cx: &mut Context) {
+    with_fold_ranges(cx, |doc, view, folds| {
+        for line in cursor_lines(doc, view) {
+            // the line may have been hidden by the fold of another cursor
+            if doc.hidden_lines(view.id).is_hidden(line) {
+                continue;
+            }
+            // close the innermost fold containing the line that isn't closed yet
+            let text = doc.text().slice(..);
+            let closed = doc.folds(view.id);
+            let fold = folds
+                .iter()
+                .filter(|fold| fold.lines(text).contains(&line))
+                .filter(|fold| !closed.map_or(false, |closed| closed.is_closed(text, fold)))
+                .min_by_key(|fold| fold.lines(text).len())
+                .copied();
+            if let Some(fold) = fold {
+                doc.close_fold(view.id, fold);
+            }
+        }
+    });
+}
+
+fn unfold(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    for line in cursor_lines(doc, view) {
+        doc.open_folds_at_line(view.id, line);
+    }
+}
+
+fn toggle_fold(cx: &mut Context) {
+    let (view, doc) = current_ref!(cx.editor);
+    let text = doc.text().slice(..);
+    let line = doc.selection(view.id).primary().cursor_line(text);
+    let is_folded = doc.folds(view.id).map_or(false, |folds| {
+        folds.iter().any(|fold| fold.lines(text).contains(&line))
+    });
+
+    if is_folded {
+        unfold(cx);
+    } else {
+        fold(cx);
+    }
+}
+
+fn fold_all(cx: &mut Context) {
+    with_fold_ranges(cx, |doc, view, folds| {
+        for fold in folds {
+            doc.close_fold(view.id, fold);
+        }
+    });
+}
+
+fn unfold_all(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    doc.folds_mut(view.id).clear();
+}
+
 fn goto_ts_object_impl(cx: &mut Context, object: &'static str, direction: Direction) {
     let count = cx.count();
     let motion = move |editor: &mut Editor| {
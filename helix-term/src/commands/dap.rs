@@ -4,23 +4,36 @@
     job::{Callback, Jobs},
     ui::{self, overlay::overlayed, FilePicker, Picker, Popup, Prompt, PromptEvent, Text},
 };
-use dap::{StackFrame, Thread, ThreadStates};
-use helix_core::syntax::{DebugArgumentValue, DebugConfigCompletion, DebugTemplate};
-use helix_dap::{self as dap, Client};
+use dap::{
+    launch,
+    requests::{DataBreakpointInfoResponse, EvaluateResponse},
+    StackFrame, Thread, ThreadStates,
+};
+use helix_core::syntax::{
+    DebugAdapterConfig, DebugArgumentValue, DebugConfigCompletion, DebugTemplate,
+};
+use helix_dap::{self as dap, launch::LaunchConfiguration, Client};
 use helix_lsp::block_on;
-use helix_view::editor::Breakpoint;
+use helix_view::{
+    debug_console::OutputKind,
+    editor::{Breakpoint, DataBreakpoint},
+};
 
 use serde_json::{to_value, Value};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tui::text::Spans;
 
+use futures_util::future::BoxFuture;
 use std::collections::HashMap;
 use std::future::Future;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail};
 
-use helix_view::handlers::dap::{breakpoints_changed, jump_to_stack_frame, select_thread_id};
+use helix_view::handlers::dap::{
+    breakpoints_changed, data_breakpoints_changed, function_breakpoints_changed,
+    jump_to_stack_frame, select_thread_id,
+};
 
 impl ui::menu::Item for StackFrame {
     type Data = ();
@@ -30,11 +43,24 @@ fn label(&self, _data: &Self::Data) -> Spans {
     }
 }
 
-impl ui::menu::Item for DebugTemplate {
+/// An entry of the launch picker.
+enum LaunchItem {
+    /// A template of the language's debug adapter.
+    Template(DebugTemplate),
+    /// A configuration of the workspace's `.vscode/launch.json`.
+    Configuration(LaunchConfiguration),
+}
+
+impl ui::menu::Item for LaunchItem {
     type Data = ();
 
     fn label(&self, _data: &Self::Data) -> Spans {
-        self.name.as_str().into()
+        match self {
+            Self::Template(template) => template.name.as_str().into(),
+            Self::Configuration(configuration) => {
+                format!("{} (launch.json)", configuration.name).into()
+            }
+        }
     }
 }
 
@@ -94,6 +120,7 @@ fn thread_picker(
 }
 
 fn get_breakpoint_at_current_line(editor: &mut Editor) -> Option<(usize, Breakpoint)> {
+    editor.shift_breakpoints();
     let (view, doc) = current!(editor);
     let text = doc.text().slice(..);
 
@@ -129,6 +156,88 @@ fn dap_callback<T, F>(
     jobs.callback(callback);
 }
 
+/// Evaluates an expression in the REPL context of the active stack frame, echoing the
+/// expression and its result in the debug console.
+pub(crate) fn dap_evaluate(editor: &mut Editor, jobs: &mut Jobs, expression: String) {
+    editor
+        .debug_console
+        .push(OutputKind::Input, &format!("{}\n", expression));
+    editor.debug_console.push_history(expression.clone());
+
+    let debugger = match editor.debugger.as_mut() {
+        Some(debugger) => debugger,
+        None => {
+            editor
+                .debug_console
+                .push(OutputKind::Error, "No active debug session\n");
+            editor.set_error("No active debug session");
+            return;
+        }
+    };
+
+    // without a stopped frame the expression is evaluated in the global scope
+    let frame_id = debugger.current_stack_frame().map(|frame| frame.id);
+
+    let call = debugger.eval(expression, frame_id, Some("repl".to_string()));
+    jobs.callback(async move {
+        let response = match call.await {
+            Ok(json) => serde_json::from_value::<EvaluateResponse>(json).map_err(dap::Error::from),
+            Err(err) => Err(err),
+        };
+        let call: Callback =
+            Callback::Editor(Box::new(move |editor: &mut Editor| match response {
+                Ok(response) => {
+                    editor
+                        .debug_console
+                        .push(OutputKind::Result, &format!("{}\n", response.result));
+                    editor.set_status(response.result);
+                }
+                Err(err) => {
+                    editor
+                        .debug_console
+                        .push(OutputKind::Error, &format!("{}\n", err));
+                    editor.set_error(err.to_string());
+                }
+            }));
+        Ok(call)
+    });
+}
+
+/// Sets the value of the variable or watch expression at `path` in the debug panel.
+pub(crate) fn dap_set_value(editor: &mut Editor, jobs: &mut Jobs, path: &[String], value: String) {
+    let debugger = debugger!(editor);
+    let node = match editor.debug_panel.node(path) {
+        Some(node) => node,
+        None => return,
+    };
+
+    let caps = debugger.capabilities();
+    let call: BoxFuture<'static, dap::Result<Value>> =
+        if node.container_reference > 0 && caps.supports_set_variable == Some(true) {
+            Box::pin(debugger.set_variable(node.container_reference, node.name.clone(), value))
+        } else if let (Some(expression), Some(true)) =
+            (&node.evaluate_name, caps.supports_set_expression)
+        {
+            let frame_id = debugger.current_stack_frame().map(|frame| frame.id);
+            Box::pin(debugger.set_expression(expression.clone(), value, frame_id))
+        } else {
+            editor.set_error("The debugger can't set the value of this variable");
+            return;
+        };
+
+    dap_callback(jobs, call, |editor, _compositor, _response: Value| {
+        // the value can change other variables as well
+        refresh_debug_panel(editor);
+    });
+}
+
+/// Re-evaluates the watch expressions and fetches the variables of the active stack frame.
+pub(crate) fn refresh_debug_panel(editor: &mut Editor) {
+    if let Some(debugger) = editor.debugger.as_ref() {
+        block_on(editor.debug_panel.refresh(debugger));
+    }
+}
+
 pub fn dap_start_impl(
     cx: &mut compositor::Context,
     name: Option<&str>,
@@ -142,29 +251,6 @@ pub fn dap_start_impl(
         .and_then(|config| config.debugger.as_ref())
         .ok_or_else(|| anyhow!("No debug adapter available for language"))?;
 
-    let result = match socket {
-        Some(socket) => block_on(Client::tcp(socket, 0)),
-        None => block_on(Client::process(
-            &config.transport,
-            &config.command,
-            config.args.iter().map(|arg| arg.as_str()).collect(),
-            config.port_arg.as_deref(),
-            0,
-        )),
-    };
-
-    let (mut debugger, events) = match result {
-        Ok(r) => r,
-        Err(e) => bail!("Failed to start debug session: {}", e),
-    };
-
-    let request = debugger.initialize(config.name.clone());
-    if let Err(e) = block_on(request) {
-        bail!("Failed to initialize debug adapter: {}", e);
-    }
-
-    debugger.quirks = config.quirks.clone();
-
     // TODO: avoid refetching all of this... pass a config in
     let template = match name {
         Some(name) => config.templates.iter().find(|t| t.name == name),
@@ -222,6 +308,42 @@ pub fn dap_start_impl(
     args.insert("cwd", to_value(std::env::current_dir().unwrap())?);
 
     let args = to_value(args).unwrap();
+    let request = template.request.clone();
+    let config = config.clone();
+    start_session(cx, &config, socket, &request, args)
+}
+
+/// Starts the debug adapter, or connects to it at `socket`, and sends the launch or
+/// attach `request`.
+fn start_session(
+    cx: &mut compositor::Context,
+    config: &DebugAdapterConfig,
+    socket: Option<std::net::SocketAddr>,
+    request: &str,
+    args: Value,
+) -> Result<(), anyhow::Error> {
+    let result = match socket {
+        Some(socket) => block_on(Client::tcp(socket, 0)),
+        None => block_on(Client::process(
+            &config.transport,
+            &config.command,
+            config.args.iter().map(|arg| arg.as_str()).collect(),
+            config.port_arg.as_deref(),
+            0,
+        )),
+    };
+
+    let (mut debugger, events) = match result {
+        Ok(r) => r,
+        Err(e) => bail!("Failed to start debug session: {}", e),
+    };
+
+    let initialize = debugger.initialize(config.name.clone());
+    if let Err(e) = block_on(initialize) {
+        bail!("Failed to initialize debug adapter: {}", e);
+    }
+
+    debugger.quirks = config.quirks.clone();
 
     let callback = |_editor: &mut Editor, _compositor: &mut Compositor, _response: Value| {
         // if let Err(e) = result {
@@ -229,7 +351,7 @@ pub fn dap_start_impl(
         // }
     };
 
-    match &template.request[..] {
+    match request {
         "launch" => {
             let call = debugger.launch(args);
             dap_callback(cx.jobs, call, callback);
@@ -242,38 +364,143 @@ pub fn dap_start_impl(
     };
 
     // TODO: either await "initialized" or buffer commands until event is received
+    cx.editor.debug_panel.clear();
+    // data breakpoints are only valid in the session they were set in
+    cx.editor.data_breakpoints.clear();
     cx.editor.debugger = Some(debugger);
     let stream = UnboundedReceiverStream::new(events);
     cx.editor.debugger_events.push(stream);
     Ok(())
 }
 
+/// The values of the variables that can be used in launch.json configurations.
+fn launch_variables(editor: &Editor) -> HashMap<&'static str, String> {
+    let mut variables = HashMap::new();
+    let root = helix_core::find_root(None, &[]);
+    let to_string = |path: &Path| path.to_string_lossy().into_owned();
+
+    variables.insert("workspaceFolder", to_string(&root));
+    if let Some(name) = root.file_name() {
+        variables.insert("workspaceFolderBasename", to_string(Path::new(name)));
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        variables.insert("cwd", to_string(&cwd));
+    }
+    variables.insert("pathSeparator", std::path::MAIN_SEPARATOR.to_string());
+
+    let (view, doc) = current_ref!(editor);
+    let text = doc.text().slice(..);
+    let selection = doc.selection(view.id).primary();
+    let line = text.char_to_line(selection.cursor(text));
+    variables.insert("lineNumber", (line + 1).to_string());
+    variables.insert("selectedText", selection.fragment(text).into_owned());
+
+    if let Some(path) = doc.path() {
+        variables.insert("file", to_string(path));
+        if let Some(name) = path.file_name() {
+            variables.insert("fileBasename", to_string(Path::new(name)));
+        }
+        if let Some(stem) = path.file_stem() {
+            variables.insert("fileBasenameNoExtension", to_string(Path::new(stem)));
+        }
+        if let Some(extension) = path.extension() {
+            let extension = format!(".{}", extension.to_string_lossy());
+            variables.insert("fileExtname", extension);
+        }
+        if let Some(dir) = path.parent() {
+            variables.insert("fileDirname", to_string(dir));
+        }
+        let relative = path.strip_prefix(&root).unwrap_or(path);
+        variables.insert("relativeFile", to_string(relative));
+        if let Some(dir) = relative.parent() {
+            variables.insert("relativeFileDirname", to_string(dir));
+        }
+    }
+    variables
+}
+
+/// Starts a debug session for a configuration of the workspace's launch.json. The debug
+/// adapter is chosen by the type of the configuration, falling back to the adapter of
+/// the current document's language.
+fn dap_start_configuration(
+    cx: &mut compositor::Context,
+    mut configuration: LaunchConfiguration,
+) -> Result<(), anyhow::Error> {
+    let loader = cx.editor.syn_loader.clone();
+    let config = loader
+        .language_configs()
+        .filter_map(|config| config.debugger.as_ref())
+        .find(|debugger| debugger.name == configuration.adapter_name())
+        .or_else(|| {
+            doc!(cx.editor)
+                .language_config()
+                .and_then(|config| config.debugger.as_ref())
+        })
+        .cloned()
+        .ok_or_else(|| anyhow!("No debug adapter for type '{}'", configuration.ty))?;
+
+    let variables = launch_variables(cx.editor);
+    configuration.substitute_variables(|name| variables.get(name).cloned());
+    if !configuration.args.contains_key("cwd") {
+        configuration
+            .args
+            .insert("cwd".to_string(), to_value(std::env::current_dir()?)?);
+    }
+
+    let socket = configuration
+        .debug_server
+        .map(|port| std::net::SocketAddr::from(([127, 0, 0, 1], port)));
+    let args = Value::Object(configuration.args);
+    start_session(cx, &config, socket, &configuration.request, args)
+}
+
 pub fn dap_launch(cx: &mut Context) {
     if cx.editor.debugger.is_some() {
         cx.editor.set_error("Debugger is already running");
         return;
     }
 
-    let doc = doc!(cx.editor);
-
-    let config = match doc
-        .language_config()
-        .and_then(|config| config.debugger.as_ref())
-    {
-        Some(c) => c,
-        None => {
+    let configurations = match launch::load(&helix_core::find_root(None, &[])) {
+        Ok(configurations) => configurations,
+        Err(e) => {
             cx.editor
-                .set_error("No debug adapter available for language");
-            return;
+                .set_error(format!("Failed to load launch.json: {}", e));
+            Vec::new()
         }
     };
 
-    let templates = config.templates.clone();
+    let doc = doc!(cx.editor);
+    let templates = doc
+        .language_config()
+        .and_then(|config| config.debugger.as_ref())
+        .map(|config| config.templates.clone())
+        .unwrap_or_default();
+
+    if configurations.is_empty() && templates.is_empty() {
+        cx.editor
+            .set_error("No debug adapter available for language");
+        return;
+    }
+
+    let items = configurations
+        .into_iter()
+        .map(LaunchItem::Configuration)
+        .chain(templates.into_iter().map(LaunchItem::Template))
+        .collect();
 
     cx.push_layer(Box::new(overlayed(Picker::new(
-        templates,
+        items,
         (),
-        |cx, template, _action| {
+        |cx, item, _action| {
+            let template = match item {
+                LaunchItem::Template(template) => template,
+                LaunchItem::Configuration(configuration) => {
+                    if let Err(e) = dap_start_configuration(cx, configuration.clone()) {
+                        cx.editor.set_error(e.to_string());
+                    }
+                    return;
+                }
+            };
             let completions = template.completion.clone();
             let name = template.name.clone();
             let callback = Box::pin(async move {
@@ -372,8 +599,8 @@ pub fn dap_toggle_breakpoint(cx: &mut Context) {
 }
 
 pub fn dap_toggle_breakpoint_impl(cx: &mut Context, path: PathBuf, line: usize) {
-    // TODO: need to map breakpoints over edits and update them?
-    // we shouldn't really allow editing while debug is running though
+    // match the breakpoints against the current lines of the document
+    cx.editor.shift_breakpoints();
 
     let breakpoints = cx.editor.breakpoints.entry(path.clone()).or_default();
     // TODO: always keep breakpoints sorted and use binary search to determine insertion point
@@ -389,14 +616,166 @@ pub fn dap_toggle_breakpoint_impl(cx: &mut Context, path: PathBuf, line: usize)
         });
     }
 
-    let debugger = debugger!(cx.editor);
+    sync_breakpoints(cx.editor, path);
+}
 
+/// Saves the breakpoints of the workspace.
+fn persist_breakpoints(editor: &Editor) {
+    if let Err(err) = editor.save_breakpoints() {
+        log::error!("failed to save breakpoints: {}", err);
+    }
+}
+
+/// Saves the breakpoints and sends the ones of `path` to the debugger.
+pub(crate) fn sync_breakpoints(editor: &mut Editor, path: PathBuf) {
+    persist_breakpoints(editor);
+
+    let debugger = debugger!(editor);
+    let breakpoints = editor.breakpoints.entry(path.clone()).or_default();
     if let Err(e) = breakpoints_changed(debugger, path, breakpoints) {
-        cx.editor
-            .set_error(format!("Failed to set breakpoints: {}", e));
+        editor.set_error(format!("Failed to set breakpoints: {}", e));
+    }
+}
+
+/// Saves the breakpoints and sends the function breakpoints to the debugger.
+pub(crate) fn sync_function_breakpoints(editor: &mut Editor) {
+    persist_breakpoints(editor);
+
+    let debugger = debugger!(editor);
+    if debugger.capabilities().supports_function_breakpoints != Some(true) {
+        editor.set_error("The debugger doesn't support function breakpoints");
+        return;
+    }
+    if let Err(e) = function_breakpoints_changed(debugger, &mut editor.function_breakpoints) {
+        editor.set_error(e.to_string());
+    }
+}
+
+pub(crate) fn sync_data_breakpoints(editor: &mut Editor) {
+    let debugger = debugger!(editor);
+    if let Err(e) = data_breakpoints_changed(debugger, &mut editor.data_breakpoints) {
+        editor.set_error(e.to_string());
+    }
+}
+
+/// Sets or removes a data breakpoint on the variable or watch expression at `path` in
+/// the debug panel.
+pub(crate) fn dap_toggle_data_breakpoint(editor: &mut Editor, path: &[String]) {
+    let debugger = debugger!(editor);
+    if debugger.capabilities().supports_data_breakpoints != Some(true) {
+        editor.set_error("The debugger doesn't support data breakpoints");
+        return;
+    }
+    let node = match editor.debug_panel.node(path) {
+        Some(node) if !node.is_scope() && !node.error => node,
+        _ => return,
+    };
+
+    // watch expressions don't have a container and are looked up as expressions
+    let container = match node.container_reference {
+        0 => None,
+        reference => Some(reference),
+    };
+    let info = block_on(debugger.data_breakpoint_info(container, node.name.clone()))
+        .and_then(|json| Ok(serde_json::from_value::<DataBreakpointInfoResponse>(json)?));
+    let info = match info {
+        Ok(info) => info,
+        Err(e) => {
+            editor.set_error(format!("Failed to get data breakpoint info: {}", e));
+            return;
+        }
+    };
+    let data_id = match info.data_id {
+        Some(data_id) => data_id,
+        None => {
+            editor.set_error(format!("Can't set a data breakpoint: {}", info.description));
+            return;
+        }
+    };
+
+    let breakpoints = &mut editor.data_breakpoints;
+    match breakpoints
+        .iter()
+        .position(|breakpoint| breakpoint.data_id == data_id)
+    {
+        Some(pos) => {
+            breakpoints.remove(pos);
+        }
+        None => breakpoints.push(DataBreakpoint {
+            verified: false,
+            message: None,
+            data_id,
+            description: info.description,
+            enabled: true,
+        }),
+    }
+    sync_data_breakpoints(editor);
+}
+
+/// Identifies a breakpoint of any kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakpointId {
+    Source { path: PathBuf, line: usize },
+    Function(String),
+    Data(String),
+}
+
+/// Enables or disables a breakpoint, or removes it if `enabled` is `None`.
+pub(crate) fn update_breakpoint(editor: &mut Editor, id: &BreakpointId, enabled: Option<bool>) {
+    match id {
+        BreakpointId::Source { path, line } => {
+            let breakpoints = match editor.breakpoints.get_mut(path) {
+                Some(breakpoints) => breakpoints,
+                None => return,
+            };
+            let pos = match breakpoints.iter().position(|b| b.line == *line) {
+                Some(pos) => pos,
+                None => return,
+            };
+            match enabled {
+                Some(enabled) => breakpoints[pos].enabled = enabled,
+                None => {
+                    breakpoints.remove(pos);
+                }
+            }
+            sync_breakpoints(editor, path.clone());
+        }
+        BreakpointId::Function(name) => {
+            let breakpoints = &mut editor.function_breakpoints;
+            let pos = match breakpoints.iter().position(|b| &b.name == name) {
+                Some(pos) => pos,
+                None => return,
+            };
+            match enabled {
+                Some(enabled) => breakpoints[pos].enabled = enabled,
+                None => {
+                    breakpoints.remove(pos);
+                }
+            }
+            sync_function_breakpoints(editor);
+        }
+        BreakpointId::Data(data_id) => {
+            let breakpoints = &mut editor.data_breakpoints;
+            let pos = match breakpoints.iter().position(|b| &b.data_id == data_id) {
+                Some(pos) => pos,
+                None => return,
+            };
+            match enabled {
+                Some(enabled) => breakpoints[pos].enabled = enabled,
+                None => {
+                    breakpoints.remove(pos);
+                }
+            }
+            sync_data_breakpoints(editor);
+        }
     }
 }
 
+pub fn dap_breakpoints(cx: &mut Context) {
+    let picker = ui::BreakpointPicker::new(cx.editor);
+    cx.push_layer(Box::new(overlayed(picker)));
+}
+
 pub fn dap_continue(cx: &mut Context) {
     let debugger = debugger!(cx.editor);
 
@@ -579,93 +958,93 @@ pub fn dap_disable_exceptions(cx: &mut Context) {
     )
 }
 
-// TODO: both edit condition and edit log need to be stable: we might get new breakpoints from the debugger which can change offsets
-pub fn dap_edit_condition(cx: &mut Context) {
-    if let Some((pos, breakpoint)) = get_breakpoint_at_current_line(cx.editor) {
-        let path = match doc!(cx.editor).path() {
-            Some(path) => path.clone(),
-            None => return,
-        };
-        let callback = Box::pin(async move {
-            let call: Callback = Callback::EditorCompositor(Box::new(move |editor, compositor| {
-                let mut prompt = Prompt::new(
-                    "condition:".into(),
-                    None,
-                    ui::completers::none,
-                    move |cx, input: &str, event: PromptEvent| {
-                        if event != PromptEvent::Validate {
-                            return;
-                        }
-
-                        let breakpoints = &mut cx.editor.breakpoints.get_mut(&path).unwrap();
-                        breakpoints[pos].condition = match input {
-                            "" => None,
-                            input => Some(input.to_owned()),
-                        };
+pub fn dap_toggle_console(cx: &mut Context) {
+    cx.callback = Some(Box::new(|compositor, _cx| {
+        if let Some(editor_view) = compositor.find::<ui::EditorView>() {
+            editor_view.toggle_debug_console();
+        }
+    }));
+}
 
-                        let debugger = debugger!(cx.editor);
+pub fn dap_toggle_panel(cx: &mut Context) {
+    cx.callback = Some(Box::new(|compositor, _cx| {
+        if let Some(editor_view) = compositor.find::<ui::EditorView>() {
+            editor_view.toggle_debug_panel();
+        }
+    }));
+}
 
-                        if let Err(e) = breakpoints_changed(debugger, path.clone(), breakpoints) {
-                            cx.editor
-                                .set_error(format!("Failed to set breakpoints: {}", e));
-                        }
-                    },
-                );
-                if let Some(condition) = breakpoint.condition {
-                    prompt.insert_str(&condition, editor)
-                }
-                compositor.push(Box::new(prompt));
-            }));
-            Ok(call)
-        });
-        cx.jobs.callback(callback);
-    }
+pub fn dap_edit_condition(cx: &mut Context) {
+    edit_breakpoint(cx, "condition:", |breakpoint| &mut breakpoint.condition);
+}
+
+pub fn dap_edit_hit_condition(cx: &mut Context) {
+    edit_breakpoint(cx, "hit-condition:", |breakpoint| {
+        &mut breakpoint.hit_condition
+    });
 }
 
 pub fn dap_edit_log(cx: &mut Context) {
-    if let Some((pos, breakpoint)) = get_breakpoint_at_current_line(cx.editor) {
-        let path = match doc!(cx.editor).path() {
-            Some(path) => path.clone(),
-            None => return,
-        };
-        let callback = Box::pin(async move {
-            let call: Callback = Callback::EditorCompositor(Box::new(move |editor, compositor| {
-                let mut prompt = Prompt::new(
-                    "log-message:".into(),
-                    None,
-                    ui::completers::none,
-                    move |cx, input: &str, event: PromptEvent| {
-                        if event != PromptEvent::Validate {
-                            return;
-                        }
-
-                        let breakpoints = &mut cx.editor.breakpoints.get_mut(&path).unwrap();
-                        breakpoints[pos].log_message = match input {
+    edit_breakpoint(cx, "log-message:", |breakpoint| &mut breakpoint.log_message);
+}
+
+// TODO: editing breakpoints needs to be stable: we might get new breakpoints from the debugger which can change offsets
+/// Prompts for a property of the breakpoint on the current line, an empty input removes
+/// the property.
+fn edit_breakpoint(
+    cx: &mut Context,
+    prompt: &'static str,
+    field: fn(&mut Breakpoint) -> &mut Option<String>,
+) {
+    let (pos, mut breakpoint) = match get_breakpoint_at_current_line(cx.editor) {
+        Some(breakpoint) => breakpoint,
+        None => return,
+    };
+    let path = match doc!(cx.editor).path() {
+        Some(path) => path.clone(),
+        None => return,
+    };
+    let value = field(&mut breakpoint).take();
+
+    let callback = Box::pin(async move {
+        let call: Callback = Callback::EditorCompositor(Box::new(move |editor, compositor| {
+            let mut prompt = Prompt::new(
+                prompt.into(),
+                None,
+                ui::completers::none,
+                move |cx, input: &str, event: PromptEvent| {
+                    if event != PromptEvent::Validate {
+                        return;
+                    }
+
+                    let breakpoint = cx
+                        .editor
+                        .breakpoints
+                        .get_mut(&path)
+                        .and_then(|breakpoints| breakpoints.get_mut(pos));
+                    if let Some(breakpoint) = breakpoint {
+                        *field(breakpoint) = match input {
                             "" => None,
                             input => Some(input.to_owned()),
                         };
-
-                        let debugger = debugger!(cx.editor);
-                        if let Err(e) = breakpoints_changed(debugger, path.clone(), breakpoints) {
-                            cx.editor
-                                .set_error(format!("Failed to set breakpoints: {}", e));
-                        }
-                    },
-                );
-                if let Some(log_message) = breakpoint.log_message {
-                    prompt.insert_str(&log_message, editor);
-                }
-                compositor.push(Box::new(prompt));
-            }));
-            Ok(call)
-        });
-        cx.jobs.callback(callback);
-    }
+                        sync_breakpoints(cx.editor, path.clone());
+                    }
+                },
+            );
+            if let Some(value) = value {
+                prompt.insert_str(&value, editor);
+            }
+            compositor.push(Box::new(prompt));
+        }));
+        Ok(call)
+    });
+    cx.jobs.callback(callback);
 }
 
 pub fn dap_switch_thread(cx: &mut Context) {
     thread_picker(cx, |editor, thread| {
         block_on(select_thread_id(editor, thread.id, true));
+        refresh_debug_panel(editor);
     })
 }
 pub fn dap_switch_stack_frame(cx: &mut Context) {
@@ -698,6 +1077,7 @@ pub fn dap_switch_stack_frame(cx: &mut Context) {
             if let Some(frame) = &frame {
                 jump_to_stack_frame(cx.editor, frame);
             }
+            refresh_debug_panel(cx.editor);
         },
         move |_editor, frame| {
             frame
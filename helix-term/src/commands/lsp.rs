@@ -1,43 +1,129 @@
+use futures_util::{future::join_all, FutureExt};
 use helix_lsp::{
     block_on,
     lsp::{self, CodeAction, CodeActionOrCommand, DiagnosticSeverity, NumberOrString},
-    util::{diagnostic_to_lsp_diagnostic, lsp_pos_to_pos, lsp_range_to_range, range_to_lsp_range},
+    util::{
+        apply_semantic_tokens_edits, diagnostic_to_lsp_diagnostic, lsp_pos_to_pos,
+        lsp_range_to_range, pos_to_lsp_pos, range_to_lsp_range, semantic_token_scope,
+        semantic_tokens_to_ranges,
+    },
     OffsetEncoding,
 };
 use tui::text::{Span, Spans};
 
-use super::{align_view, push_jump, Align, Context, Editor, Open};
+use super::{align_view, open_link, push_jump, Align, Context, Editor, Open};
 
-use helix_core::{path, Selection};
-use helix_view::{apply_transaction, document::Mode, editor::Action, theme::Style};
+use helix_core::{fold::Fold, link, path, syntax::LanguageServerFeature, Selection};
+use helix_view::{
+    apply_transaction,
+    document::{
+        CodeLens, DocumentInlayHints, DocumentLink, DocumentSemanticTokens, InlayHint,
+        InlayHintKind, Mode,
+    },
+    editor::{Action, ConfigEvent},
+    theme::Style,
+    Document, DocumentId, View,
+};
 
 use crate::{
     compositor::{self, Compositor},
+    job::{Callback, Jobs},
     ui::{
-        self, lsp::SignatureHelp, overlay::overlayed, FileLocation, FilePicker, Popup, PromptEvent,
+        self,
+        hierarchy::{prepare_hierarchy_entries, HierarchyDirection, HierarchyPicker},
+        lsp::SignatureHelp,
+        overlay::overlayed,
+        FileLocation, FilePicker, Popup, PromptEvent,
     },
 };
 
 use std::{
-    borrow::Cow, cmp::Ordering, collections::BTreeMap, fmt::Write, path::PathBuf, sync::Arc,
+    borrow::Cow, cmp::Ordering, collections::BTreeMap, fmt::Write, future::Future, path::PathBuf,
+    sync::Arc,
 };
 
-/// Gets the language server that is attached to a document, and
-/// if it's not active displays a status message. Using this macro
-/// in a context where the editor automatically queries the LSP
+/// Gets the first language server that is attached to a document and
+/// supports the given feature. If there is none, displays a status message.
+/// Using this macro in a context where the editor automatically queries the LSP
 /// (instead of when the user explicitly does so via a keybind like
 /// `gd`) will spam the "LSP inactive" status message confusingly.
 #[macro_export]
-macro_rules! language_server {
-    ($editor:expr, $doc:expr) => {
-        match $doc.language_server() {
+macro_rules! language_server_with_feature {
+    ($editor:expr, $doc:expr, $feature:expr) => {{
+        let language_server = $doc.language_servers_with_feature($feature).next();
+        match language_server {
             Some(language_server) => language_server,
             None => {
-                $editor.set_status("Language server not active for current buffer");
+                $editor.set_status(format!("No active language server supports {}", $feature));
                 return;
             }
         }
+    }};
+}
+
+/// Calls `f` with the foldable ranges of the current document, then keeps the cursor
+/// of the current view in view. The ranges come from the tree-sitter fold query of the
+/// language if it has one, otherwise they are requested from a language server.
+pub(crate) fn with_fold_ranges<F>(cx: &mut Context, f: F)
+where
+    F: FnOnce(&mut Document, &mut View, Vec<Fold>) + Send + 'static,
+{
+    fn apply<F>(view: &mut View, doc: &mut Document, folds: Vec<Fold>, scrolloff: usize, f: F)
+    where
+        F: FnOnce(&mut Document, &mut View, Vec<Fold>),
+    {
+        f(doc, view, folds);
+        view.offset.row = doc.hidden_lines(view.id).visible_line(view.offset.row);
+        view.ensure_cursor_in_view(doc, scrolloff);
+    }
+
+    let scrolloff = cx.editor.config().scrolloff;
+    let (view, doc) = current!(cx.editor);
+    if let Some(folds) = doc.syntax_folds() {
+        apply(view, doc, folds, scrolloff, f);
+        return;
+    }
+
+    let language_server =
+        language_server_with_feature!(cx.editor, doc, LanguageServerFeature::FoldingRange);
+    let future = match language_server.text_document_folding_range(doc.identifier(), None) {
+        Some(future) => future,
+        None => {
+            cx.editor
+                .set_error("Language server does not support folding ranges");
+            return;
+        }
     };
+
+    let (view_id, doc_id) = (view.id, doc.id());
+    cx.callback(
+        future,
+        move |editor, _compositor, response: Option<Vec<lsp::FoldingRange>>| {
+            let scrolloff = editor.config().scrolloff;
+            // the view or document may have been closed in the meantime
+            if !editor.tree.contains(view_id) || !editor.documents.contains_key(&doc_id) {
+                return;
+            }
+            let view = editor.tree.get_mut(view_id);
+            let doc = editor.documents.get_mut(&doc_id).unwrap();
+
+            let text = doc.text().slice(..);
+            let last_line = text.len_lines().saturating_sub(1);
+            let mut folds: Vec<_> = response
+                .unwrap_or_default()
+                .into_iter()
+                .map(|range| Fold {
+                    start: text.line_to_char((range.start_line as usize).min(last_line)),
+                    end: text.line_to_char((range.end_line as usize).min(last_line)),
+                })
+                .filter(|fold| fold.is_multiline(text))
+                .collect();
+            folds.sort_unstable();
+            folds.dedup();
+
+            apply(view, doc, folds, scrolloff, f);
+        },
+    );
 }
 
 impl ui::menu::Item for lsp::Location {
@@ -74,20 +160,30 @@ fn label(&self, cwdir: &Self::Data) -> Spans {
     }
 }
 
-impl ui::menu::Item for lsp::SymbolInformation {
+struct SymbolInformationItem {
+    symbol: lsp::SymbolInformation,
+    offset_encoding: OffsetEncoding,
+}
+
+impl ui::menu::Item for SymbolInformationItem {
     /// Path to currently focussed document
     type Data = Option<lsp::Url>;
 
     fn label(&self, current_doc_path: &Self::Data) -> Spans {
-        if current_doc_path.as_ref() == Some(&self.location.uri) {
-            self.name.as_str().into()
+        if current_doc_path.as_ref() == Some(&self.symbol.location.uri) {
+            self.symbol.name.as_str().into()
         } else {
-            match self.location.uri.to_file_path() {
+            match self.symbol.location.uri.to_file_path() {
                 Ok(path) => {
                     let get_relative_path = path::get_relative_path(path.as_path());
-                    format!("{} ({})", &self.name, get_relative_path.to_string_lossy()).into()
+                    format!(
+                        "{} ({})",
+                        &self.symbol.name,
+                        get_relative_path.to_string_lossy()
+                    )
+                    .into()
                 }
-                Err(_) => format!("{} ({})", &self.name, &self.location.uri).into(),
+                Err(_) => format!("{} ({})", &self.symbol.name, &self.symbol.location.uri).into(),
             }
         }
     }
@@ -103,6 +199,7 @@ struct DiagnosticStyles {
 struct PickerDiagnostic {
     url: lsp::Url,
     diag: lsp::Diagnostic,
+    offset_encoding: OffsetEncoding,
 }
 
 impl ui::menu::Item for PickerDiagnostic {
@@ -150,7 +247,7 @@ fn label(&self, (styles, format): &Self::Data) -> Spans {
     }
 }
 
-fn location_to_file_location(location: &lsp::Location) -> FileLocation {
+pub(crate) fn location_to_file_location(location: &lsp::Location) -> FileLocation {
     let path = location.uri.to_file_path().unwrap();
     let line = Some((
         location.range.start.line as usize,
@@ -160,7 +257,7 @@ fn location_to_file_location(location: &lsp::Location) -> FileLocation {
 }
 
 // TODO: share with symbol picker(symbol.location)
-fn jump_to_location(
+pub(crate) fn jump_to_location(
     editor: &mut Editor,
     location: &lsp::Location,
     offset_encoding: OffsetEncoding,
@@ -199,15 +296,15 @@ fn jump_to_location(
 }
 
 fn sym_picker(
-    symbols: Vec<lsp::SymbolInformation>,
+    symbols: Vec<SymbolInformationItem>,
     current_path: Option<lsp::Url>,
-    offset_encoding: OffsetEncoding,
-) -> FilePicker<lsp::SymbolInformation> {
+) -> FilePicker<SymbolInformationItem> {
     // TODO: drop current_path comparison and instead use workspace: bool flag?
     FilePicker::new(
         symbols,
         current_path.clone(),
-        move |cx, symbol, action| {
+        move |cx, item, action| {
+            let symbol = &item.symbol;
             let (view, doc) = current!(cx.editor);
             push_jump(view, doc);
 
@@ -232,7 +329,7 @@ fn sym_picker(
             let (view, doc) = current!(cx.editor);
 
             if let Some(range) =
-                lsp_range_to_range(doc.text(), symbol.location.range, offset_encoding)
+                lsp_range_to_range(doc.text(), symbol.location.range, item.offset_encoding)
             {
                 // we flip the range so that the cursor sits on the start of the symbol
                 // (for example start of the function).
@@ -240,7 +337,7 @@ fn sym_picker(
                 align_view(doc, view, Align::Center);
             }
         },
-        move |_editor, symbol| Some(location_to_file_location(&symbol.location)),
+        move |_editor, item| Some(location_to_file_location(&item.symbol.location)),
     )
     .truncate_start(false)
 }
@@ -253,10 +350,9 @@ enum DiagnosticsFormat {
 
 fn diag_picker(
     cx: &Context,
-    diagnostics: BTreeMap<lsp::Url, Vec<lsp::Diagnostic>>,
+    diagnostics: BTreeMap<lsp::Url, Vec<(lsp::Diagnostic, usize)>>,
     current_path: Option<lsp::Url>,
     format: DiagnosticsFormat,
-    offset_encoding: OffsetEncoding,
 ) -> FilePicker<PickerDiagnostic> {
     // TODO: drop current_path comparison and instead use workspace: bool flag?
 
@@ -264,11 +360,16 @@ fn diag_picker(
     let mut flat_diag = Vec::new();
     for (url, diags) in diagnostics {
         flat_diag.reserve(diags.len());
-        for diag in diags {
-            flat_diag.push(PickerDiagnostic {
-                url: url.clone(),
-                diag,
-            });
+        for (diag, language_server_id) in diags {
+            // skip diagnostics of language servers that have exited since
+            if let Some(language_server) = cx.editor.language_servers.get_by_id(language_server_id)
+            {
+                flat_diag.push(PickerDiagnostic {
+                    url: url.clone(),
+                    diag,
+                    offset_encoding: language_server.offset_encoding(),
+                });
+            }
         }
     }
 
@@ -282,7 +383,13 @@ fn diag_picker(
     FilePicker::new(
         flat_diag,
         (styles, format),
-        move |cx, PickerDiagnostic { url, diag }, action| {
+        move |cx,
+              PickerDiagnostic {
+                  url,
+                  diag,
+                  offset_encoding,
+              },
+              action| {
             if current_path.as_ref() == Some(url) {
                 let (view, doc) = current!(cx.editor);
                 push_jump(view, doc);
@@ -293,14 +400,14 @@ fn diag_picker(
 
             let (view, doc) = current!(cx.editor);
 
-            if let Some(range) = lsp_range_to_range(doc.text(), diag.range, offset_encoding) {
+            if let Some(range) = lsp_range_to_range(doc.text(), diag.range, *offset_encoding) {
                 // we flip the range so that the cursor sits on the start of the symbol
                 // (for example start of the function).
                 doc.set_selection(view.id, Selection::single(range.head, range.anchor));
                 align_view(doc, view, Align::Center);
             }
         },
-        move |_editor, PickerDiagnostic { url, diag }| {
+        move |_editor, PickerDiagnostic { url, diag, .. }| {
             let location = lsp::Location::new(url.clone(), diag.range);
             Some(location_to_file_location(&location))
         },
@@ -310,93 +417,143 @@ fn diag_picker(
 
 pub fn symbol_picker(cx: &mut Context) {
     fn nested_to_flat(
-        list: &mut Vec<lsp::SymbolInformation>,
+        list: &mut Vec<SymbolInformationItem>,
         file: &lsp::TextDocumentIdentifier,
         symbol: lsp::DocumentSymbol,
+        offset_encoding: OffsetEncoding,
     ) {
         #[allow(deprecated)]
-        list.push(lsp::SymbolInformation {
-            name: symbol.name,
-            kind: symbol.kind,
-            tags: symbol.tags,
-            deprecated: symbol.deprecated,
-            location: lsp::Location::new(file.uri.clone(), symbol.selection_range),
-            container_name: None,
+        list.push(SymbolInformationItem {
+            symbol: lsp::SymbolInformation {
+                name: symbol.name,
+                kind: symbol.kind,
+                tags: symbol.tags,
+                deprecated: symbol.deprecated,
+                location: lsp::Location::new(file.uri.clone(), symbol.selection_range),
+                container_name: None,
+            },
+            offset_encoding,
         });
         for child in symbol.children.into_iter().flatten() {
-            nested_to_flat(list, file, child);
+            nested_to_flat(list, file, child, offset_encoding);
         }
     }
     let doc = doc!(cx.editor);
 
-    let language_server = language_server!(cx.editor, doc);
-    let current_url = doc.url();
-    let offset_encoding = language_server.offset_encoding();
-
-    let future = match language_server.document_symbols(doc.identifier()) {
-        Some(future) => future,
-        None => {
-            cx.editor
-                .set_error("Language server does not support document symbols");
-            return;
-        }
-    };
+    // merge the symbols of all language servers that support them
+    let futures: Vec<_> = doc
+        .language_servers_with_feature(LanguageServerFeature::DocumentSymbols)
+        .filter_map(|language_server| {
+            let offset_encoding = language_server.offset_encoding();
+            let doc_id = doc.identifier();
+            let request = language_server.document_symbols(doc.identifier())?;
 
-    cx.callback(
-        future,
-        move |editor, compositor, response: Option<lsp::DocumentSymbolResponse>| {
-            if let Some(symbols) = response {
+            Some(async move {
+                let json = request.await?;
+                let response: Option<lsp::DocumentSymbolResponse> = serde_json::from_value(json)?;
                 // lsp has two ways to represent symbols (flat/nested)
                 // convert the nested variant to flat, so that we have a homogeneous list
-                let symbols = match symbols {
-                    lsp::DocumentSymbolResponse::Flat(symbols) => symbols,
-                    lsp::DocumentSymbolResponse::Nested(symbols) => {
-                        let doc = doc!(editor);
+                let symbols = match response {
+                    Some(lsp::DocumentSymbolResponse::Flat(symbols)) => symbols
+                        .into_iter()
+                        .map(|symbol| SymbolInformationItem {
+                            symbol,
+                            offset_encoding,
+                        })
+                        .collect(),
+                    Some(lsp::DocumentSymbolResponse::Nested(symbols)) => {
                         let mut flat_symbols = Vec::new();
                         for symbol in symbols {
-                            nested_to_flat(&mut flat_symbols, &doc.identifier(), symbol)
+                            nested_to_flat(&mut flat_symbols, &doc_id, symbol, offset_encoding)
                         }
                         flat_symbols
                     }
+                    None => Vec::new(),
                 };
+                Ok::<_, anyhow::Error>(symbols)
+            })
+        })
+        .collect();
 
-                let picker = sym_picker(symbols, current_url, offset_encoding);
-                compositor.push(Box::new(overlayed(picker)))
-            }
-        },
-    )
+    if futures.is_empty() {
+        cx.editor
+            .set_error("No active language server supports document symbols");
+        return;
+    }
+
+    let current_url = doc.url();
+    cx.jobs.callback(async move {
+        let symbols = collect_responses(futures).await;
+        let call = move |_editor: &mut Editor, compositor: &mut Compositor| {
+            let picker = sym_picker(symbols, current_url);
+            compositor.push(Box::new(overlayed(picker)))
+        };
+        Ok(Callback::EditorCompositor(Box::new(call)))
+    });
 }
 
 pub fn workspace_symbol_picker(cx: &mut Context) {
     let doc = doc!(cx.editor);
     let current_url = doc.url();
-    let language_server = language_server!(cx.editor, doc);
-    let offset_encoding = language_server.offset_encoding();
-    let future = match language_server.workspace_symbols("".to_string()) {
-        Some(future) => future,
-        None => {
-            cx.editor
-                .set_error("Language server does not support workspace symbols");
-            return;
-        }
-    };
 
-    cx.callback(
-        future,
-        move |_editor, compositor, response: Option<Vec<lsp::SymbolInformation>>| {
-            if let Some(symbols) = response {
-                let picker = sym_picker(symbols, current_url, offset_encoding);
-                compositor.push(Box::new(overlayed(picker)))
-            }
-        },
-    )
+    // merge the symbols of all language servers that support them
+    let futures: Vec<_> = doc
+        .language_servers_with_feature(LanguageServerFeature::WorkspaceSymbols)
+        .filter_map(|language_server| {
+            let offset_encoding = language_server.offset_encoding();
+            let request = language_server.workspace_symbols("".to_string())?;
+
+            Some(async move {
+                let json = request.await?;
+                let response: Option<Vec<lsp::SymbolInformation>> = serde_json::from_value(json)?;
+                let symbols = response
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|symbol| SymbolInformationItem {
+                        symbol,
+                        offset_encoding,
+                    })
+                    .collect();
+                Ok::<_, anyhow::Error>(symbols)
+            })
+        })
+        .collect();
+
+    if futures.is_empty() {
+        cx.editor
+            .set_error("No active language server supports workspace symbols");
+        return;
+    }
+
+    cx.jobs.callback(async move {
+        let symbols = collect_responses(futures).await;
+        let call = move |_editor: &mut Editor, compositor: &mut Compositor| {
+            let picker = sym_picker(symbols, current_url);
+            compositor.push(Box::new(overlayed(picker)))
+        };
+        Ok(Callback::EditorCompositor(Box::new(call)))
+    });
+}
+
+/// Awaits the requests sent to multiple language servers and concatenates their
+/// results. Failed requests are logged and skipped so that one misbehaving server
+/// doesn't hide the results of the others.
+async fn collect_responses<T>(
+    futures: Vec<impl std::future::Future<Output = anyhow::Result<Vec<T>>>>,
+) -> Vec<T> {
+    let mut items = Vec::new();
+    for response in join_all(futures).await {
+        match response {
+            Ok(response) => items.extend(response),
+            Err(err) => log::error!("language server request failed: {}", err),
+        }
+    }
+    items
 }
 
 pub fn diagnostics_picker(cx: &mut Context) {
     let doc = doc!(cx.editor);
-    let language_server = language_server!(cx.editor, doc);
     if let Some(current_url) = doc.url() {
-        let offset_encoding = language_server.offset_encoding();
         let diagnostics = cx
             .editor
             .diagnostics
@@ -408,7 +565,6 @@ pub fn diagnostics_picker(cx: &mut Context) {
             [(current_url.clone(), diagnostics)].into(),
             Some(current_url),
             DiagnosticsFormat::HideSourcePath,
-            offset_encoding,
         );
         cx.push_layer(Box::new(overlayed(picker)));
     }
@@ -416,24 +572,375 @@ pub fn diagnostics_picker(cx: &mut Context) {
 
 pub fn workspace_diagnostics_picker(cx: &mut Context) {
     let doc = doc!(cx.editor);
-    let language_server = language_server!(cx.editor, doc);
     let current_url = doc.url();
-    let offset_encoding = language_server.offset_encoding();
     let diagnostics = cx.editor.diagnostics.clone();
     let picker = diag_picker(
         cx,
         diagnostics,
         current_url,
         DiagnosticsFormat::ShowSourcePath,
-        offset_encoding,
     );
     cx.push_layer(Box::new(overlayed(picker)));
 }
 
-impl ui::menu::Item for lsp::CodeActionOrCommand {
+/// Sets the diagnostics of a document received from a language server, published by the
+/// server or pulled from it.
+pub fn handle_diagnostics(
+    editor: &mut Editor,
+    server_id: usize,
+    uri: lsp::Url,
+    lsp_diagnostics: Vec<lsp::Diagnostic>,
+) {
+    let doc = match uri.to_file_path() {
+        Ok(path) => editor.document_by_path_mut(&path),
+        Err(_) => None,
+    };
+
+    if let Some(doc) = doc {
+        // Ignore servers that are configured not to provide diagnostics.
+        if doc.supports_language_server(server_id)
+            && !doc
+                .language_servers_with_feature(LanguageServerFeature::Diagnostics)
+                .any(|server| server.id() == server_id)
+        {
+            return;
+        }
+
+        let lang_conf = doc.language_config();
+        let text = doc.text();
+
+        let diagnostics = lsp_diagnostics
+            .iter()
+            .filter_map(|diagnostic| {
+                use helix_core::diagnostic::{
+                    Diagnostic, DiagnosticTag, NumberOrString, Range, Severity::*,
+                };
+                use lsp::DiagnosticSeverity;
+
+                let language_server =
+                    if let Some(language_server) = doc.language_server_by_id(server_id) {
+                        language_server
+                    } else {
+                        log::warn!(
+                        "Discarding diagnostic because language server is not initialized: {:?}",
+                        diagnostic
+                    );
+                        return None;
+                    };
+
+                // TODO: convert inside server
+                let start = if let Some(start) = lsp_pos_to_pos(
+                    text,
+                    diagnostic.range.start,
+                    language_server.offset_encoding(),
+                ) {
+                    start
+                } else {
+                    log::warn!("lsp position out of bounds - {:?}", diagnostic);
+                    return None;
+                };
+
+                let end = if let Some(end) = lsp_pos_to_pos(
+                    text,
+                    diagnostic.range.end,
+                    language_server.offset_encoding(),
+                ) {
+                    end
+                } else {
+                    log::warn!("lsp position out of bounds - {:?}", diagnostic);
+                    return None;
+                };
+
+                let severity = diagnostic.severity.map(|severity| match severity {
+                    DiagnosticSeverity::ERROR => Error,
+                    DiagnosticSeverity::WARNING => Warning,
+                    DiagnosticSeverity::INFORMATION => Info,
+                    DiagnosticSeverity::HINT => Hint,
+                    severity => unreachable!("unrecognized diagnostic severity: {:?}", severity),
+                });
+
+                if let Some(lang_conf) = lang_conf {
+                    if let Some(severity) = severity {
+                        if severity < lang_conf.diagnostic_severity {
+                            return None;
+                        }
+                    }
+                };
+
+                let code = match diagnostic.code.clone() {
+                    Some(x) => match x {
+                        lsp::NumberOrString::Number(x) => Some(NumberOrString::Number(x)),
+                        lsp::NumberOrString::String(x) => Some(NumberOrString::String(x)),
+                    },
+                    None => None,
+                };
+
+                let tags = if let Some(ref tags) = diagnostic.tags {
+                    let new_tags = tags
+                        .iter()
+                        .filter_map(|tag| match *tag {
+                            lsp::DiagnosticTag::DEPRECATED => Some(DiagnosticTag::Deprecated),
+                            lsp::DiagnosticTag::UNNECESSARY => Some(DiagnosticTag::Unnecessary),
+                            _ => None,
+                        })
+                        .collect();
+
+                    new_tags
+                } else {
+                    Vec::new()
+                };
+
+                Some(Diagnostic {
+                    range: Range { start, end },
+                    line: diagnostic.range.start.line as usize,
+                    message: diagnostic.message.clone(),
+                    severity,
+                    code,
+                    tags,
+                    source: diagnostic.source.clone(),
+                    data: diagnostic.data.clone(),
+                    language_server_id: server_id,
+                })
+            })
+            .collect();
+
+        doc.set_diagnostics(server_id, diagnostics);
+    }
+
+    // Insert the original lsp::Diagnostics here because we may have no open document
+    // for diagnosic message and so we can't calculate the exact position.
+    // When using them later in the diagnostics picker, we calculate them on-demand.
+    let diagnostics = editor.diagnostics.entry(uri).or_default();
+    // Replace only the diagnostics previously published by this server.
+    diagnostics.retain(|(_, id)| *id != server_id);
+    diagnostics.extend(
+        lsp_diagnostics
+            .into_iter()
+            .map(|diagnostic| (diagnostic, server_id)),
+    );
+
+    // Sort diagnostics first by severity and then by line numbers.
+    // Note: The `lsp::DiagnosticSeverity` enum is already defined in decreasing order
+    diagnostics.sort_unstable_by_key(|(d, _)| (d.severity, d.range.start));
+}
+
+/// Pulls the diagnostics of every document shown in a view, and of the whole workspace,
+/// from the language servers that support the pull model.
+pub fn pull_diagnostics_for_all_views(editor: &mut Editor, jobs: &mut Jobs) {
+    let mut doc_ids: Vec<_> = editor.tree.views().map(|(view, _)| view.doc).collect();
+    doc_ids.sort_unstable();
+    doc_ids.dedup();
+
+    for doc_id in doc_ids {
+        pull_diagnostics(editor, jobs, doc_id);
+    }
+
+    pull_workspace_diagnostics(editor, jobs);
+}
+
+/// Pulls the diagnostics of a document from the language servers that support the pull
+/// model.
+fn pull_diagnostics(editor: &Editor, jobs: &mut Jobs, doc_id: DocumentId) {
+    let doc = match editor.documents.get(&doc_id) {
+        Some(doc) => doc,
+        None => return,
+    };
+    let url = match doc.url() {
+        Some(url) => url,
+        None => return,
+    };
+    for language_server in doc.language_servers_with_feature(LanguageServerFeature::Diagnostics) {
+        let language_server_id = language_server.id();
+        let previous_result_id = editor
+            .diagnostic_result_ids
+            .get(&language_server_id)
+            .and_then(|result_ids| result_ids.get(&url))
+            .cloned();
+        let request =
+            match language_server.text_document_diagnostic(doc.identifier(), previous_result_id) {
+                Some(request) => request,
+                None => continue,
+            };
+        let url = url.clone();
+        let version = doc.version();
+
+        jobs.callback(async move {
+            let json = request.await?;
+            let response: lsp::DocumentDiagnosticReportResult = serde_json::from_value(json)?;
+
+            let call = move |editor: &mut Editor| {
+                // Drop the response if the document changed since the request: a newer
+                // request is on its way, and its response may have arrived first.
+                match editor.document(doc_id) {
+                    Some(doc) if doc.version() == version => (),
+                    _ => return,
+                }
+
+                let (report, related_documents) = match response {
+                    lsp::DocumentDiagnosticReportResult::Report(
+                        lsp::DocumentDiagnosticReport::Full(report),
+                    ) => (
+                        lsp::DocumentDiagnosticReportKind::Full(
+                            report.full_document_diagnostic_report,
+                        ),
+                        report.related_documents,
+                    ),
+                    lsp::DocumentDiagnosticReportResult::Report(
+                        lsp::DocumentDiagnosticReport::Unchanged(report),
+                    ) => (
+                        lsp::DocumentDiagnosticReportKind::Unchanged(
+                            report.unchanged_document_diagnostic_report,
+                        ),
+                        report.related_documents,
+                    ),
+                    // partial results are not requested
+                    lsp::DocumentDiagnosticReportResult::Partial(_) => return,
+                };
+
+                handle_diagnostic_report(editor, language_server_id, url, report);
+                for (url, report) in related_documents.into_iter().flatten() {
+                    handle_diagnostic_report(editor, language_server_id, url, report);
+                }
+            };
+            Ok(Callback::Editor(Box::new(call)))
+        });
+    }
+}
+
+/// Pulls the diagnostics of the whole workspace from the language servers that support it,
+/// unless a request is still pending.
+fn pull_workspace_diagnostics(editor: &mut Editor, jobs: &mut Jobs) {
+    // the servers with the diagnostics feature enabled for the language of an open document
+    let mut language_servers: Vec<_> = editor
+        .documents()
+        .flat_map(|doc| doc.language_servers_with_feature(LanguageServerFeature::Diagnostics))
+        .collect();
+    language_servers.sort_unstable_by_key(|language_server| language_server.id());
+    language_servers.dedup_by_key(|language_server| language_server.id());
+
+    let mut requests = Vec::new();
+    for language_server in language_servers {
+        let language_server_id = language_server.id();
+        if editor
+            .pending_workspace_diagnostics
+            .contains(&language_server_id)
+        {
+            continue;
+        }
+        let previous_result_ids = editor
+            .diagnostic_result_ids
+            .get(&language_server_id)
+            .into_iter()
+            .flatten()
+            .map(|(uri, value)| lsp::PreviousResultId {
+                uri: uri.clone(),
+                value: value.clone(),
+            })
+            .collect();
+        if let Some(request) = language_server.workspace_diagnostic(previous_result_ids) {
+            requests.push((language_server_id, request));
+        }
+    }
+
+    for (language_server_id, request) in requests {
+        editor
+            .pending_workspace_diagnostics
+            .insert(language_server_id);
+
+        jobs.callback(async move {
+            let response: anyhow::Result<lsp::WorkspaceDiagnosticReportResult> = match request.await
+            {
+                Ok(json) => serde_json::from_value(json).map_err(anyhow::Error::from),
+                Err(err) => Err(err.into()),
+            };
+
+            let call = move |editor: &mut Editor| {
+                editor
+                    .pending_workspace_diagnostics
+                    .remove(&language_server_id);
+
+                let items = match response {
+                    Ok(lsp::WorkspaceDiagnosticReportResult::Report(report)) => report.items,
+                    // partial results are not requested
+                    Ok(lsp::WorkspaceDiagnosticReportResult::Partial(_)) => return,
+                    Err(err) => {
+                        log::debug!("workspace diagnostics request failed: {}", err);
+                        return;
+                    }
+                };
+
+                for item in items {
+                    let (url, version, report) = match item {
+                        lsp::WorkspaceDocumentDiagnosticReport::Full(report) => (
+                            report.uri,
+                            report.version,
+                            lsp::DocumentDiagnosticReportKind::Full(
+                                report.full_document_diagnostic_report,
+                            ),
+                        ),
+                        lsp::WorkspaceDocumentDiagnosticReport::Unchanged(report) => (
+                            report.uri,
+                            report.version,
+                            lsp::DocumentDiagnosticReportKind::Unchanged(
+                                report.unchanged_document_diagnostic_report,
+                            ),
+                        ),
+                    };
+
+                    // Skip the reports for other versions of the open documents.
+                    let outdated = match (url.to_file_path(), version) {
+                        (Ok(path), Some(version)) => match editor.document_by_path(&path) {
+                            Some(doc) => doc.version() as i64 != version,
+                            None => false,
+                        },
+                        _ => false,
+                    };
+                    if !outdated {
+                        handle_diagnostic_report(editor, language_server_id, url, report);
+                    }
+                }
+            };
+            Ok(Callback::Editor(Box::new(call)))
+        });
+    }
+}
+
+/// Records the result id of a pulled diagnostic report and sets the diagnostics of a
+/// full report.
+fn handle_diagnostic_report(
+    editor: &mut Editor,
+    language_server_id: usize,
+    url: lsp::Url,
+    report: lsp::DocumentDiagnosticReportKind,
+) {
+    let (result_id, items) = match report {
+        lsp::DocumentDiagnosticReportKind::Full(report) => (report.result_id, Some(report.items)),
+        lsp::DocumentDiagnosticReportKind::Unchanged(report) => (Some(report.result_id), None),
+    };
+
+    let result_ids = editor
+        .diagnostic_result_ids
+        .entry(language_server_id)
+        .or_default();
+    match result_id {
+        Some(result_id) => result_ids.insert(url.clone(), result_id),
+        None => result_ids.remove(&url),
+    };
+
+    if let Some(items) = items {
+        handle_diagnostics(editor, language_server_id, url, items);
+    }
+}
+
+struct CodeActionOrCommandItem {
+    lsp_item: lsp::CodeActionOrCommand,
+    language_server_id: usize,
+}
+
+impl ui::menu::Item for CodeActionOrCommandItem {
     type Data = ();
     fn label(&self, _data: &Self::Data) -> Spans {
-        match self {
+        match &self.lsp_item {
             lsp::CodeActionOrCommand::CodeAction(action) => action.title.as_str().into(),
             lsp::CodeActionOrCommand::Command(command) => command.title.as_str().into(),
         }
@@ -500,119 +1007,148 @@ fn action_fixes_diagnostics(action: &CodeActionOrCommand) -> bool {
 pub fn code_action(cx: &mut Context) {
     let (view, doc) = current!(cx.editor);
 
-    let language_server = language_server!(cx.editor, doc);
-
     let selection_range = doc.selection(view.id).primary();
-    let offset_encoding = language_server.offset_encoding();
 
-    let range = range_to_lsp_range(doc.text(), selection_range, offset_encoding);
+    // request code actions from all language servers that support them
+    let futures: Vec<_> = doc
+        .language_servers_with_feature(LanguageServerFeature::CodeAction)
+        .filter_map(|language_server| {
+            let offset_encoding = language_server.offset_encoding();
+            let language_server_id = language_server.id();
+            let range = range_to_lsp_range(doc.text(), selection_range, offset_encoding);
+
+            let request = language_server.code_actions(
+                doc.identifier(),
+                range,
+                // Filter and convert overlapping diagnostics of this language server
+                lsp::CodeActionContext {
+                    diagnostics: doc
+                        .diagnostics()
+                        .iter()
+                        .filter(|&diag| {
+                            diag.language_server_id == language_server_id
+                                && selection_range.overlaps(&helix_core::Range::new(
+                                    diag.range.start,
+                                    diag.range.end,
+                                ))
+                        })
+                        .map(|diag| diagnostic_to_lsp_diagnostic(doc.text(), diag, offset_encoding))
+                        .collect(),
+                    only: None,
+                    trigger_kind: Some(lsp::CodeActionTriggerKind::INVOKED),
+                },
+            )?;
+
+            Some(async move {
+                let json = request.await?;
+                let response: Option<lsp::CodeActionResponse> = serde_json::from_value(json)?;
+                let actions = response
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|lsp_item| CodeActionOrCommandItem {
+                        lsp_item,
+                        language_server_id,
+                    })
+                    .collect();
+                Ok::<_, anyhow::Error>(actions)
+            })
+        })
+        .collect();
 
-    let future = match language_server.code_actions(
-        doc.identifier(),
-        range,
-        // Filter and convert overlapping diagnostics
-        lsp::CodeActionContext {
-            diagnostics: doc
-                .diagnostics()
-                .iter()
-                .filter(|&diag| {
-                    selection_range
-                        .overlaps(&helix_core::Range::new(diag.range.start, diag.range.end))
-                })
-                .map(|diag| diagnostic_to_lsp_diagnostic(doc.text(), diag, offset_encoding))
-                .collect(),
-            only: None,
-        },
-    ) {
-        Some(future) => future,
-        None => {
-            cx.editor
-                .set_error("Language server does not support code actions");
-            return;
-        }
-    };
+    if futures.is_empty() {
+        cx.editor
+            .set_error("No active language server supports code actions");
+        return;
+    }
 
-    cx.callback(
-        future,
-        move |editor, compositor, response: Option<lsp::CodeActionResponse>| {
-            let mut actions = match response {
-                Some(a) => a,
-                None => return,
-            };
+    cx.jobs.callback(async move {
+        let mut actions = collect_responses(futures).await;
 
-            // remove disabled code actions
-            actions.retain(|action| {
-                matches!(
-                    action,
-                    CodeActionOrCommand::Command(_)
-                        | CodeActionOrCommand::CodeAction(CodeAction { disabled: None, .. })
-                )
-            });
+        // remove disabled code actions
+        actions.retain(|action| {
+            matches!(
+                action.lsp_item,
+                CodeActionOrCommand::Command(_)
+                    | CodeActionOrCommand::CodeAction(CodeAction { disabled: None, .. })
+            )
+        });
 
+        // Sort codeactions into a useful order. This behaviour is only partially described in the LSP spec.
+        // Many details are modeled after vscode because langauge servers are usually tested against it.
+        // VScode sorts the codeaction two times:
+        //
+        // First the codeactions that fix some diagnostics are moved to the front.
+        // If both codeactions fix some diagnostics (or both fix none) the codeaction
+        // that is marked with `is_preffered` is shown first. The codeactions are then shown in seperate
+        // submenus that only contain a certain category (see `action_category`) of actions.
+        //
+        // Below this done in in a single sorting step
+        actions.sort_by(|action1, action2| {
+            let (action1, action2) = (&action1.lsp_item, &action2.lsp_item);
+            // sort actions by category
+            let order = action_category(action1).cmp(&action_category(action2));
+            if order != Ordering::Equal {
+                return order;
+            }
+            // within the categories sort by relevancy.
+            // Modeled after the `codeActionsComparator` function in vscode:
+            // https://github.com/microsoft/vscode/blob/eaec601dd69aeb4abb63b9601a6f44308c8d8c6e/src/vs/editor/contrib/codeAction/browser/codeAction.ts
+
+            // if one code action fixes a diagnostic but the other one doesn't show it first
+            let order = action_fixes_diagnostics(action1)
+                .cmp(&action_fixes_diagnostics(action2))
+                .reverse();
+            if order != Ordering::Equal {
+                return order;
+            }
+
+            // if one of the codeactions is marked as prefered show it first
+            // otherwise keep the original LSP sorting
+            action_prefered(action1)
+                .cmp(&action_prefered(action2))
+                .reverse()
+        });
+
+        let call = move |editor: &mut Editor, compositor: &mut Compositor| {
             if actions.is_empty() {
                 editor.set_status("No code actions available");
                 return;
             }
 
-            // Sort codeactions into a useful order. This behaviour is only partially described in the LSP spec.
-            // Many details are modeled after vscode because langauge servers are usually tested against it.
-            // VScode sorts the codeaction two times:
-            //
-            // First the codeactions that fix some diagnostics are moved to the front.
-            // If both codeactions fix some diagnostics (or both fix none) the codeaction
-            // that is marked with `is_preffered` is shown first. The codeactions are then shown in seperate
-            // submenus that only contain a certain category (see `action_category`) of actions.
-            //
-            // Below this done in in a single sorting step
-            actions.sort_by(|action1, action2| {
-                // sort actions by category
-                let order = action_category(action1).cmp(&action_category(action2));
-                if order != Ordering::Equal {
-                    return order;
-                }
-                // within the categories sort by relevancy.
-                // Modeled after the `codeActionsComparator` function in vscode:
-                // https://github.com/microsoft/vscode/blob/eaec601dd69aeb4abb63b9601a6f44308c8d8c6e/src/vs/editor/contrib/codeAction/browser/codeAction.ts
-
-                // if one code action fixes a diagnostic but the other one doesn't show it first
-                let order = action_fixes_diagnostics(action1)
-                    .cmp(&action_fixes_diagnostics(action2))
-                    .reverse();
-                if order != Ordering::Equal {
-                    return order;
-                }
-
-                // if one of the codeactions is marked as prefered show it first
-                // otherwise keep the original LSP sorting
-                action_prefered(action1)
-                    .cmp(&action_prefered(action2))
-                    .reverse()
-            });
-
-            let mut picker = ui::Menu::new(actions, (), move |editor, code_action, event| {
+            let mut picker = ui::Menu::new(actions, (), move |editor, action, event| {
                 if event != PromptEvent::Validate {
                     return;
                 }
 
                 // always present here
-                let code_action = code_action.unwrap();
+                let action = action.unwrap();
 
-                match code_action {
+                match &action.lsp_item {
                     lsp::CodeActionOrCommand::Command(command) => {
                         log::debug!("code action command: {:?}", command);
-                        execute_lsp_command(editor, command.clone());
+                        execute_lsp_command(editor, action.language_server_id, command.clone());
                     }
                     lsp::CodeActionOrCommand::CodeAction(code_action) => {
                         log::debug!("code action: {:?}", code_action);
                         if let Some(ref workspace_edit) = code_action.edit {
                             log::debug!("edit: {:?}", workspace_edit);
+                            let offset_encoding = match editor
+                                .language_servers
+                                .get_by_id(action.language_server_id)
+                            {
+                                Some(language_server) => language_server.offset_encoding(),
+                                None => {
+                                    editor.set_error("Language server is no longer active");
+                                    return;
+                                }
+                            };
                             apply_workspace_edit(editor, offset_encoding, workspace_edit);
                         }
 
                         // if code action provides both edit and command first the edit
                         // should be applied and then the command
                         if let Some(command) = &code_action.command {
-                            execute_lsp_command(editor, command.clone());
+                            execute_lsp_command(editor, action.language_server_id, command.clone());
                         }
                     }
                 }
@@ -621,8 +1157,9 @@ pub fn code_action(cx: &mut Context) {
 
             let popup = Popup::new("code-action", picker).with_scrollbar(false);
             compositor.replace_or_push("code-action", popup);
-        },
-    )
+        };
+        Ok(Callback::EditorCompositor(Box::new(call)))
+    });
 }
 
 impl ui::menu::Item for lsp::Command {
@@ -632,9 +1169,14 @@ fn label(&self, _data: &Self::Data) -> Spans {
     }
 }
 
-pub fn execute_lsp_command(editor: &mut Editor, cmd: lsp::Command) {
-    let doc = doc!(editor);
-    let language_server = language_server!(editor, doc);
+pub fn execute_lsp_command(editor: &mut Editor, language_server_id: usize, cmd: lsp::Command) {
+    let language_server = match editor.language_servers.get_by_id(language_server_id) {
+        Some(language_server) => language_server,
+        None => {
+            editor.set_error("Language server is no longer active");
+            return;
+        }
+    };
 
     // the command is executed on the server and communicated back
     // to the client asynchronously using workspace edits
@@ -831,6 +1373,50 @@ pub fn apply_workspace_edit(
     }
 }
 
+/// Moves the file or directory at `old_path` to `new_path` with [`Editor::move_path`]
+/// once the edits the language servers return for `willRenameFiles` were applied.
+/// `on_moved` is called with the result of the move.
+pub fn move_path(
+    editor: &Editor,
+    jobs: &mut Jobs,
+    old_path: PathBuf,
+    new_path: PathBuf,
+    on_moved: impl FnOnce(&mut Editor, &mut Compositor, anyhow::Result<()>) + Send + 'static,
+) {
+    let is_dir = old_path.is_dir();
+
+    // give the language servers a chance to update references before the path is moved
+    let requests: Vec<_> = editor
+        .language_servers
+        .iter_clients()
+        .filter_map(|client| {
+            let offset_encoding = client.offset_encoding();
+            let request = client.will_rename(&old_path, &new_path, is_dir)?;
+            Some(async move { (offset_encoding, request.await) })
+        })
+        .collect();
+
+    let callback = async move {
+        let responses = join_all(requests).await;
+        let call: Callback = Callback::EditorCompositor(Box::new(
+            move |editor: &mut Editor, compositor: &mut Compositor| {
+                for (offset_encoding, response) in responses {
+                    match response {
+                        Ok(Some(edit)) => apply_workspace_edit(editor, offset_encoding, &edit),
+                        Ok(None) => (),
+                        Err(err) => log::error!("willRenameFiles request failed: {}", err),
+                    }
+                }
+
+                let result = editor.move_path(&old_path, &new_path, is_dir);
+                on_moved(editor, compositor, result);
+            },
+        ));
+        Ok(call)
+    };
+    jobs.callback(callback);
+}
+
 fn goto_impl(
     editor: &mut Editor,
     compositor: &mut Compositor,
@@ -877,7 +1463,8 @@ fn to_locations(definitions: Option<lsp::GotoDefinitionResponse>) -> Vec<lsp::Lo
 
 pub fn goto_definition(cx: &mut Context) {
     let (view, doc) = current!(cx.editor);
-    let language_server = language_server!(cx.editor, doc);
+    let language_server =
+        language_server_with_feature!(cx.editor, doc, LanguageServerFeature::GotoDefinition);
     let offset_encoding = language_server.offset_encoding();
 
     let pos = doc.position(view.id, offset_encoding);
@@ -902,7 +1489,8 @@ pub fn goto_definition(cx: &mut Context) {
 
 pub fn goto_type_definition(cx: &mut Context) {
     let (view, doc) = current!(cx.editor);
-    let language_server = language_server!(cx.editor, doc);
+    let language_server =
+        language_server_with_feature!(cx.editor, doc, LanguageServerFeature::GotoTypeDefinition);
     let offset_encoding = language_server.offset_encoding();
 
     let pos = doc.position(view.id, offset_encoding);
@@ -927,7 +1515,8 @@ pub fn goto_type_definition(cx: &mut Context) {
 
 pub fn goto_implementation(cx: &mut Context) {
     let (view, doc) = current!(cx.editor);
-    let language_server = language_server!(cx.editor, doc);
+    let language_server =
+        language_server_with_feature!(cx.editor, doc, LanguageServerFeature::GotoImplementation);
     let offset_encoding = language_server.offset_encoding();
 
     let pos = doc.position(view.id, offset_encoding);
@@ -950,9 +1539,101 @@ pub fn goto_implementation(cx: &mut Context) {
     );
 }
 
+pub fn goto_declaration(cx: &mut Context) {
+    let (view, doc) = current!(cx.editor);
+    let language_server =
+        language_server_with_feature!(cx.editor, doc, LanguageServerFeature::GotoDeclaration);
+    let offset_encoding = language_server.offset_encoding();
+
+    let pos = doc.position(view.id, offset_encoding);
+
+    let future = match language_server.goto_declaration(doc.identifier(), pos, None) {
+        Some(future) => future,
+        None => {
+            cx.editor
+                .set_error("Language server does not support goto-declaration");
+            return;
+        }
+    };
+
+    cx.callback(
+        future,
+        move |editor, compositor, response: Option<lsp::request::GotoDeclarationResponse>| {
+            let items = to_locations(response);
+            goto_impl(editor, compositor, items, offset_encoding);
+        },
+    );
+}
+
+fn hierarchy_picker(cx: &mut Context, direction: HierarchyDirection) {
+    let feature = match direction {
+        HierarchyDirection::IncomingCalls | HierarchyDirection::OutgoingCalls => {
+            LanguageServerFeature::CallHierarchy
+        }
+        HierarchyDirection::Supertypes | HierarchyDirection::Subtypes => {
+            LanguageServerFeature::TypeHierarchy
+        }
+    };
+    let (view, doc) = current!(cx.editor);
+    let language_server = language_server_with_feature!(cx.editor, doc, feature);
+    let language_server_id = language_server.id();
+    let offset_encoding = language_server.offset_encoding();
+    let pos = doc.position(view.id, offset_encoding);
+    let text_document = doc.identifier();
+
+    let future = cx
+        .editor
+        .language_servers
+        .get_shared_by_id(language_server_id)
+        .and_then(|language_server| {
+            prepare_hierarchy_entries(language_server, direction, text_document, pos)
+        });
+    let future = match future {
+        Some(future) => future,
+        None => {
+            cx.editor.set_error(format!(
+                "Language server does not support {}",
+                direction.description()
+            ));
+            return;
+        }
+    };
+
+    cx.jobs.callback(async move {
+        let entries = future.await?;
+        let call = move |editor: &mut Editor, compositor: &mut Compositor| {
+            if entries.is_empty() {
+                editor.set_status(format!("No {} found", direction.description()));
+                return;
+            }
+            let picker =
+                HierarchyPicker::new(entries, direction, language_server_id, offset_encoding);
+            compositor.push(Box::new(overlayed(picker)));
+        };
+        Ok(Callback::EditorCompositor(Box::new(call)))
+    });
+}
+
+pub fn incoming_calls_picker(cx: &mut Context) {
+    hierarchy_picker(cx, HierarchyDirection::IncomingCalls);
+}
+
+pub fn outgoing_calls_picker(cx: &mut Context) {
+    hierarchy_picker(cx, HierarchyDirection::OutgoingCalls);
+}
+
+pub fn supertypes_picker(cx: &mut Context) {
+    hierarchy_picker(cx, HierarchyDirection::Supertypes);
+}
+
+pub fn subtypes_picker(cx: &mut Context) {
+    hierarchy_picker(cx, HierarchyDirection::Subtypes);
+}
+
 pub fn goto_reference(cx: &mut Context) {
     let (view, doc) = current!(cx.editor);
-    let language_server = language_server!(cx.editor, doc);
+    let language_server =
+        language_server_with_feature!(cx.editor, doc, LanguageServerFeature::GotoReference);
     let offset_encoding = language_server.offset_encoding();
 
     let pos = doc.position(view.id, offset_encoding);
@@ -989,14 +1670,17 @@ pub fn signature_help_impl(cx: &mut Context, invoked: SignatureHelpInvoked) {
     let (view, doc) = current!(cx.editor);
     let was_manually_invoked = invoked == SignatureHelpInvoked::Manual;
 
-    let language_server = match doc.language_server() {
+    let language_server = doc
+        .language_servers_with_feature(LanguageServerFeature::SignatureHelp)
+        .next();
+    let language_server = match language_server {
         Some(language_server) => language_server,
         None => {
             // Do not show the message if signature help was invoked
             // automatically on backspace, trigger characters, etc.
             if was_manually_invoked {
                 cx.editor
-                    .set_status("Language server not active for current buffer");
+                    .set_status("No active language server supports signature-help");
             }
             return;
         }
@@ -1107,7 +1791,8 @@ pub fn signature_help_impl(cx: &mut Context, invoked: SignatureHelpInvoked) {
 
 pub fn hover(cx: &mut Context) {
     let (view, doc) = current!(cx.editor);
-    let language_server = language_server!(cx.editor, doc);
+    let language_server =
+        language_server_with_feature!(cx.editor, doc, LanguageServerFeature::Hover);
     let offset_encoding = language_server.offset_encoding();
 
     // TODO: factor out a doc.position_identifier() that returns lsp::TextDocumentPositionIdentifier
@@ -1186,7 +1871,8 @@ pub fn rename_symbol(cx: &mut Context) {
             }
 
             let (view, doc) = current!(cx.editor);
-            let language_server = language_server!(cx.editor, doc);
+            let language_server =
+                language_server_with_feature!(cx.editor, doc, LanguageServerFeature::RenameSymbol);
             let offset_encoding = language_server.offset_encoding();
 
             let pos = doc.position(view.id, offset_encoding);
@@ -1210,7 +1896,8 @@ pub fn rename_symbol(cx: &mut Context) {
 
 pub fn select_references_to_symbol_under_cursor(cx: &mut Context) {
     let (view, doc) = current!(cx.editor);
-    let language_server = language_server!(cx.editor, doc);
+    let language_server =
+        language_server_with_feature!(cx.editor, doc, LanguageServerFeature::DocumentHighlight);
     let offset_encoding = language_server.offset_encoding();
 
     let pos = doc.position(view.id, offset_encoding);
@@ -1233,8 +1920,6 @@ pub fn select_references_to_symbol_under_cursor(cx: &mut Context) {
                 _ => return,
             };
             let (view, doc) = current!(editor);
-            let language_server = language_server!(editor, doc);
-            let offset_encoding = language_server.offset_encoding();
             let text = doc.text();
             let pos = doc.selection(view.id).primary().head;
 
@@ -1256,3 +1941,690 @@ pub fn select_references_to_symbol_under_cursor(cx: &mut Context) {
         },
     );
 }
+
+pub fn toggle_inlay_hints(cx: &mut Context) {
+    let mut config = (*cx.editor.config()).clone();
+    config.lsp.display_inlay_hints = !config.lsp.display_inlay_hints;
+
+    if let Err(err) = cx
+        .editor
+        .config_events
+        .0
+        .send(ConfigEvent::Update(Box::new(config)))
+    {
+        log::error!("failed to toggle inlay hints: {}", err);
+    }
+}
+
+/// Highlights the references of the symbol under the cursor of the focused view.
+pub fn compute_document_highlights(editor: &mut Editor, jobs: &mut Jobs) {
+    if !editor.config().lsp.auto_document_highlight {
+        for doc in editor.documents_mut() {
+            doc.clear_document_highlights();
+        }
+        return;
+    }
+
+    let (view, doc) = current_ref!(editor);
+    let language_server = match doc
+        .language_servers_with_feature(LanguageServerFeature::DocumentHighlight)
+        .next()
+    {
+        Some(language_server) => language_server,
+        None => return,
+    };
+    let offset_encoding = language_server.offset_encoding();
+    let pos = doc.position(view.id, offset_encoding);
+    let request =
+        match language_server.text_document_document_highlight(doc.identifier(), pos, None) {
+            Some(request) => request,
+            None => return,
+        };
+    let view_id = view.id;
+    let doc_id = doc.id();
+    let version = doc.version();
+
+    jobs.callback(async move {
+        let json = request.await?;
+        let response: Option<Vec<lsp::DocumentHighlight>> = serde_json::from_value(json)?;
+
+        let call = move |editor: &mut Editor| {
+            let doc = match editor.document_mut(doc_id) {
+                Some(doc) => doc,
+                None => return,
+            };
+            // The highlights are outdated if the document changed since the request.
+            if doc.version() != version {
+                return;
+            }
+
+            let text = doc.text();
+            let highlights = response
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|highlight| lsp_range_to_range(text, highlight.range, offset_encoding))
+                .map(|range| range.from()..range.to())
+                .collect();
+            doc.set_document_highlights(view_id, highlights);
+        };
+        Ok(Callback::Editor(Box::new(call)))
+    });
+}
+
+/// Requests the inlay hints around the visible lines of every view, or clears them if
+/// they are disabled.
+pub fn compute_inlay_hints_for_all_views(editor: &mut Editor, jobs: &mut Jobs) {
+    if !editor.config().lsp.display_inlay_hints {
+        for doc in editor.documents_mut() {
+            doc.clear_inlay_hints();
+        }
+        return;
+    }
+
+    for (view, _) in editor.tree.views() {
+        let doc = match editor.documents.get(&view.doc) {
+            Some(doc) => doc,
+            None => continue,
+        };
+        if let Some(callback) = compute_inlay_hints_for_view(view, doc) {
+            jobs.callback(callback);
+        }
+    }
+}
+
+fn compute_inlay_hints_for_view(
+    view: &View,
+    doc: &Document,
+) -> Option<impl Future<Output = anyhow::Result<Callback>>> {
+    let view_id = view.id;
+    let doc_id = doc.id();
+    let language_server = doc
+        .language_servers_with_feature(LanguageServerFeature::InlayHints)
+        .next()?;
+    let offset_encoding = language_server.offset_encoding();
+
+    // Also request the hints of a page above and below the view so that they are
+    // already there when scrolling.
+    let text = doc.text();
+    let height = view.inner_height();
+    let first_line = view.offset.row.saturating_sub(height);
+    let last_line = (view.offset.row + height * 2).min(text.len_lines());
+    let range = helix_core::Range::new(text.line_to_char(first_line), text.line_to_char(last_line));
+
+    let request = language_server.text_document_range_inlay_hints(
+        doc.identifier(),
+        range_to_lsp_range(text, range, offset_encoding),
+        None,
+    )?;
+    let version = doc.version();
+
+    Some(async move {
+        let json = request.await?;
+        let response: Option<Vec<lsp::InlayHint>> = serde_json::from_value(json)?;
+
+        let call = move |editor: &mut Editor| {
+            let doc = match editor.document_mut(doc_id) {
+                Some(doc) => doc,
+                None => return,
+            };
+            // The hints are outdated if the document changed since the request.
+            if doc.version() != version {
+                return;
+            }
+
+            let text = doc.text();
+            let mut hints: Vec<_> = response
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|hint| {
+                    let char_idx = lsp_pos_to_pos(text, hint.position, offset_encoding)?;
+                    let mut label = match hint.label {
+                        lsp::InlayHintLabel::String(label) => label,
+                        lsp::InlayHintLabel::LabelParts(parts) => {
+                            parts.into_iter().map(|part| part.value).collect()
+                        }
+                    };
+                    if hint.padding_left == Some(true) {
+                        label.insert(0, ' ');
+                    }
+                    if hint.padding_right == Some(true) {
+                        label.push(' ');
+                    }
+                    let kind = match hint.kind {
+                        Some(lsp::InlayHintKind::TYPE) => InlayHintKind::Type,
+                        Some(lsp::InlayHintKind::PARAMETER) => InlayHintKind::Parameter,
+                        _ => InlayHintKind::Other,
+                    };
+                    Some(InlayHint {
+                        char_idx,
+                        label,
+                        kind,
+                    })
+                })
+                .collect();
+            hints.sort_by_key(|hint| hint.char_idx);
+
+            doc.set_inlay_hints(
+                view_id,
+                DocumentInlayHints {
+                    lines: first_line..last_line,
+                    hints,
+                },
+            );
+        };
+        Ok(Callback::Editor(Box::new(call)))
+    })
+}
+
+/// Requests the semantic tokens of every document shown in a view, or clears them if
+/// they are disabled for the language of the document.
+pub fn compute_semantic_tokens_for_all_views(editor: &mut Editor, jobs: &mut Jobs) {
+    let mut doc_ids: Vec<_> = editor.tree.views().map(|(view, _)| view.doc).collect();
+    doc_ids.sort_unstable();
+    doc_ids.dedup();
+
+    for doc_id in doc_ids {
+        let doc = match editor.documents.get_mut(&doc_id) {
+            Some(doc) => doc,
+            None => continue,
+        };
+        if !doc
+            .language_config()
+            .map_or(false, |config| config.semantic_tokens)
+        {
+            doc.clear_semantic_tokens();
+            continue;
+        }
+        if let Some(callback) = compute_semantic_tokens(doc) {
+            jobs.callback(callback);
+        }
+    }
+}
+
+fn compute_semantic_tokens(
+    doc: &Document,
+) -> Option<impl Future<Output = anyhow::Result<Callback>>> {
+    let doc_id = doc.id();
+    let language_server = doc
+        .language_servers_with_feature(LanguageServerFeature::SemanticTokens)
+        .next()?;
+    let language_server_id = language_server.id();
+    let offset_encoding = language_server.offset_encoding();
+    let legend = language_server.semantic_tokens_legend()?.clone();
+
+    // Only request the changes since the previous tokens if they are from the same server.
+    let previous_result_id = doc
+        .semantic_tokens()
+        .filter(|tokens| tokens.language_server_id == language_server_id)
+        .and_then(|tokens| tokens.result_id.clone());
+    let delta_request = previous_result_id.clone().and_then(|result_id| {
+        language_server.text_document_semantic_tokens_full_delta(doc.identifier(), result_id, None)
+    });
+    let (request, previous_result_id) = match delta_request {
+        Some(request) => (request.boxed(), previous_result_id),
+        None => (
+            language_server
+                .text_document_semantic_tokens_full(doc.identifier(), None)?
+                .boxed(),
+            None,
+        ),
+    };
+    let version = doc.version();
+
+    Some(async move {
+        let json = request.await?;
+        // Full results deserialize to the `Tokens` variant.
+        let response: Option<lsp::SemanticTokensFullDeltaResult> = serde_json::from_value(json)?;
+
+        let call = move |editor: &mut Editor| {
+            let doc = match editor.document_mut(doc_id) {
+                Some(doc) => doc,
+                None => return,
+            };
+            // The tokens are outdated if the document changed since the request.
+            if doc.version() != version {
+                return;
+            }
+
+            let (result_id, data) = match response {
+                Some(lsp::SemanticTokensFullDeltaResult::Tokens(tokens)) => {
+                    (tokens.result_id, tokens.data)
+                }
+                Some(lsp::SemanticTokensFullDeltaResult::TokensDelta(
+                    lsp::SemanticTokensDelta { result_id, edits },
+                )) => {
+                    // A delta only applies to the result it was requested for.
+                    let mut data = match doc.semantic_tokens() {
+                        Some(tokens) if tokens.result_id == previous_result_id => {
+                            tokens.data.clone()
+                        }
+                        _ => return,
+                    };
+                    apply_semantic_tokens_edits(&mut data, edits);
+                    (result_id, data)
+                }
+                Some(lsp::SemanticTokensFullDeltaResult::PartialTokensDelta { .. }) => return,
+                None => {
+                    doc.clear_semantic_tokens();
+                    return;
+                }
+            };
+
+            let mut scopes: Vec<String> = Vec::new();
+            let mut spans = Vec::new();
+            for (range, token_type, modifiers) in
+                semantic_tokens_to_ranges(doc.text(), &data, offset_encoding)
+            {
+                let scope = match semantic_token_scope(&legend, token_type, modifiers) {
+                    Some(scope) if !range.is_empty() => scope,
+                    _ => continue,
+                };
+                let index = match scopes.iter().position(|s| *s == scope) {
+                    Some(index) => index,
+                    None => {
+                        scopes.push(scope);
+                        scopes.len() - 1
+                    }
+                };
+                spans.push((index, range));
+            }
+
+            doc.set_semantic_tokens(DocumentSemanticTokens {
+                language_server_id,
+                result_id,
+                data,
+                scopes,
+                spans,
+            });
+        };
+        Ok(Callback::Editor(Box::new(call)))
+    })
+}
+
+/// Requests the code lenses of every document shown in a view that hasn't requested
+/// them yet, or clears them if they are disabled.
+pub fn compute_code_lenses_for_all_views(editor: &mut Editor, jobs: &mut Jobs) {
+    if !editor.config().lsp.display_code_lenses {
+        for doc in editor.documents_mut() {
+            doc.clear_code_lenses();
+        }
+        return;
+    }
+
+    let mut doc_ids: Vec<_> = editor.tree.views().map(|(view, _)| view.doc).collect();
+    doc_ids.sort_unstable();
+    doc_ids.dedup();
+
+    for doc_id in doc_ids {
+        let requested = match editor.documents.get(&doc_id) {
+            Some(doc) => doc.code_lenses().is_some(),
+            None => continue,
+        };
+        if !requested {
+            refresh_code_lenses(editor, jobs, doc_id);
+        }
+    }
+}
+
+/// Requests the code lenses of a document again, keeping the current ones displayed
+/// until the response arrives.
+pub fn refresh_code_lenses(editor: &mut Editor, jobs: &mut Jobs, doc_id: DocumentId) {
+    if !editor.config().lsp.display_code_lenses {
+        return;
+    }
+    let doc = match editor.documents.get(&doc_id) {
+        Some(doc) => doc,
+        None => return,
+    };
+    if let Some(callback) = compute_code_lenses(editor, doc) {
+        jobs.callback(callback);
+        // mark the lenses as requested so that they are not requested on every idle timeout
+        let doc = doc_mut!(editor, &doc_id);
+        if doc.code_lenses().is_none() {
+            doc.set_code_lenses(Vec::new());
+        }
+    }
+}
+
+fn compute_code_lenses(
+    editor: &Editor,
+    doc: &Document,
+) -> Option<impl Future<Output = anyhow::Result<Callback>>> {
+    let doc_id = doc.id();
+    let language_server = doc
+        .language_servers_with_feature(LanguageServerFeature::CodeLens)
+        .next()?;
+    let language_server_id = language_server.id();
+    let offset_encoding = language_server.offset_encoding();
+    let request = language_server.text_document_code_lens(doc.identifier(), None)?;
+    let language_server = editor
+        .language_servers
+        .get_shared_by_id(language_server_id)?;
+    let version = doc.version();
+
+    Some(async move {
+        let json = request.await?;
+        let response: Option<Vec<lsp::CodeLens>> = serde_json::from_value(json)?;
+
+        // Lenses sent without a command are resolved before they are displayed.
+        let lenses = join_all(response.unwrap_or_default().into_iter().map(|lens| {
+            let resolve = match lens.command {
+                Some(_) => None,
+                None => language_server.code_lens_resolve(lens.clone()),
+            };
+            async move {
+                let resolve = match resolve {
+                    Some(resolve) => resolve,
+                    None => return lens,
+                };
+                match resolve.await.map(serde_json::from_value::<lsp::CodeLens>) {
+                    Ok(Ok(resolved)) => resolved,
+                    _ => lens,
+                }
+            }
+        }))
+        .await;
+
+        let call = move |editor: &mut Editor| {
+            let doc = match editor.document_mut(doc_id) {
+                Some(doc) => doc,
+                None => return,
+            };
+            // The lenses are outdated if the document changed since the request.
+            if doc.version() != version {
+                // request them again on the next idle timeout if there are none to keep
+                if matches!(doc.code_lenses(), Some([])) {
+                    doc.clear_code_lenses();
+                }
+                return;
+            }
+
+            let text = doc.text();
+            let code_lenses = lenses
+                .into_iter()
+                .filter_map(|lens| {
+                    Some(CodeLens {
+                        char_idx: lsp_pos_to_pos(text, lens.range.start, offset_encoding)?,
+                        language_server_id,
+                        lens,
+                    })
+                })
+                .collect();
+            doc.set_code_lenses(code_lenses);
+        };
+        Ok(Callback::Editor(Box::new(call)))
+    })
+}
+
+impl ui::menu::Item for CodeLens {
+    type Data = ();
+
+    fn label(&self, _data: &Self::Data) -> Spans {
+        self.title().unwrap_or_default().into()
+    }
+}
+
+/// Picks one of the code lenses on the cursor line and executes its command.
+pub fn code_lens(cx: &mut Context) {
+    let (view, doc) = current_ref!(cx.editor);
+    let line = doc
+        .selection(view.id)
+        .primary()
+        .cursor_line(doc.text().slice(..));
+    let code_lenses: Vec<_> = doc
+        .line_code_lenses(line)
+        .iter()
+        .filter(|code_lens| code_lens.title().is_some())
+        .cloned()
+        .collect();
+
+    if code_lenses.is_empty() {
+        cx.editor.set_status("No code lenses on the current line");
+        return;
+    }
+
+    let mut picker = ui::Menu::new(code_lenses, (), move |editor, code_lens, event| {
+        if event != PromptEvent::Validate {
+            return;
+        }
+
+        // always present here
+        let code_lens = code_lens.unwrap();
+        if let Some(command) = &code_lens.lens.command {
+            log::debug!("code lens command: {:?}", command);
+            execute_lsp_command(editor, code_lens.language_server_id, command.clone());
+        }
+    });
+    picker.move_down(); // pre-select the first item
+
+    let popup = Popup::new("code-lens", picker).with_scrollbar(false);
+    cx.push_layer(Box::new(popup));
+}
+
+pub fn compute_document_links_for_all_views(editor: &mut Editor, jobs: &mut Jobs) {
+    let mut doc_ids: Vec<_> = editor.tree.views().map(|(view, _)| view.doc).collect();
+    doc_ids.sort_unstable();
+    doc_ids.dedup();
+
+    for doc_id in doc_ids {
+        let doc = match editor.documents.get(&doc_id) {
+            Some(doc) => doc,
+            None => continue,
+        };
+        if let Some(callback) = compute_document_links(doc) {
+            jobs.callback(callback);
+        }
+    }
+}
+
+fn compute_document_links(
+    doc: &Document,
+) -> Option<impl Future<Output = anyhow::Result<Callback>>> {
+    let doc_id = doc.id();
+    let language_server = doc
+        .language_servers_with_feature(LanguageServerFeature::DocumentLinks)
+        .next()?;
+    let language_server_id = language_server.id();
+    let offset_encoding = language_server.offset_encoding();
+    let request = language_server.text_document_document_link(doc.identifier(), None)?;
+    let version = doc.version();
+
+    Some(async move {
+        let json = request.await?;
+        let response: Option<Vec<lsp::DocumentLink>> = serde_json::from_value(json)?;
+
+        let call = move |editor: &mut Editor| {
+            let doc = match editor.document_mut(doc_id) {
+                Some(doc) => doc,
+                None => return,
+            };
+            // The links are outdated if the document changed since the request.
+            if doc.version() != version {
+                return;
+            }
+
+            let text = doc.text();
+            let document_links = response
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|link| {
+                    let range = lsp_range_to_range(text, link.range, offset_encoding)?;
+                    Some(DocumentLink {
+                        range: range.from()..range.to(),
+                        language_server_id,
+                        link,
+                    })
+                })
+                .collect();
+            doc.set_document_links(document_links);
+        };
+        Ok(Callback::Editor(Box::new(call)))
+    })
+}
+
+fn document_link_target(target: &lsp::Url) -> link::Link {
+    match target.to_file_path() {
+        Ok(path) => {
+            let (line, column) = link::fragment_position(target.fragment().unwrap_or_default());
+            link::Link::Path { path, line, column }
+        }
+        Err(_) => link::Link::Url(target.to_string()),
+    }
+}
+
+/// Opens the target of a document link, resolving it first if the server sent it without one.
+pub(crate) fn open_document_link(cx: &mut Context, link: DocumentLink, action: Action) {
+    if let Some(target) = &link.link.target {
+        open_link(cx.editor, document_link_target(target), action);
+        return;
+    }
+
+    let resolve = cx
+        .editor
+        .language_servers
+        .get_by_id(link.language_server_id)
+        .and_then(|language_server| language_server.document_link_resolve(link.link));
+    let resolve = match resolve {
+        Some(resolve) => resolve,
+        None => {
+            cx.editor.set_error("Link has no target");
+            return;
+        }
+    };
+
+    cx.callback(
+        resolve,
+        move |editor, _compositor, resolved: lsp::DocumentLink| match &resolved.target {
+            Some(target) => open_link(editor, document_link_target(target), action),
+            None => editor.set_error("Link has no target"),
+        },
+    );
+}
+
+/// Requests the selection ranges of the language server around each range of `selection`,
+/// blocking until they arrive. The ranges are ordered from the innermost to the outermost.
+/// Returns `None` if the language doesn't use selection ranges or the request failed.
+pub(crate) fn selection_ranges(
+    doc: &Document,
+    selection: &Selection,
+) -> Option<Vec<Vec<helix_core::Range>>> {
+    if !matches!(doc.language_config(), Some(config) if config.selection_ranges) {
+        return None;
+    }
+    let language_server = doc
+        .language_servers_with_feature(LanguageServerFeature::SelectionRange)
+        .next()?;
+    let offset_encoding = language_server.offset_encoding();
+    let text = doc.text();
+
+    let positions = selection
+        .iter()
+        .map(|range| pos_to_lsp_pos(text, range.from(), offset_encoding))
+        .collect();
+    let request =
+        language_server.text_document_selection_range(doc.identifier(), positions, None)?;
+    let json = match tokio::task::block_in_place(|| helix_lsp::block_on(request)) {
+        Ok(json) => json,
+        Err(err) => {
+            log::error!("selection range request failed: {}", err);
+            return None;
+        }
+    };
+    let selection_ranges: Vec<lsp::SelectionRange> = serde_json::from_value(json).ok()?;
+
+    let ranges = selection_ranges
+        .iter()
+        .map(|selection_range| {
+            let mut ranges = Vec::new();
+            let mut next = Some(selection_range);
+            while let Some(current) = next {
+                ranges.extend(lsp_range_to_range(text, current.range, offset_encoding));
+                next = current.parent.as_deref();
+            }
+            ranges
+        })
+        .collect();
+    Some(ranges)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arc_swap::{access::Map, ArcSwap};
+    use helix_core::syntax::{self, Configuration};
+    use helix_view::{graphics::Rect, theme};
+    use std::sync::Arc;
+
+    fn editor() -> Editor {
+        let config = Arc::new(ArcSwap::from_pointee(crate::config::Config::default()));
+        Editor::new(
+            Rect::new(0, 0, 80, 24),
+            Arc::new(theme::Loader::new("", "")),
+            Arc::new(syntax::Loader::new(Configuration {
+                language: Vec::new(),
+            })),
+            Arc::new(Map::new(config, |config: &crate::config::Config| {
+                &config.editor
+            })),
+        )
+    }
+
+    fn full_report(
+        result_id: Option<&str>,
+        messages: &[&str],
+    ) -> lsp::DocumentDiagnosticReportKind {
+        let items = messages
+            .iter()
+            .map(|message| lsp::Diagnostic {
+                message: message.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        lsp::DocumentDiagnosticReportKind::Full(lsp::FullDocumentDiagnosticReport {
+            result_id: result_id.map(String::from),
+            items,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_handle_diagnostic_report() {
+        let mut editor = editor();
+        let url = lsp::Url::parse("file:///src/main.rs").unwrap();
+        let messages = |editor: &Editor| -> Vec<(String, usize)> {
+            let mut messages: Vec<_> = editor.diagnostics[&url]
+                .iter()
+                .map(|(diagnostic, id)| (diagnostic.message.clone(), *id))
+                .collect();
+            messages.sort();
+            messages
+        };
+        let result_id = |editor: &Editor, server_id: usize| {
+            editor
+                .diagnostic_result_ids
+                .get(&server_id)
+                .and_then(|result_ids| result_ids.get(&url))
+                .cloned()
+        };
+
+        // a full report sets the diagnostics of its server and records its result id
+        handle_diagnostic_report(&mut editor, 1, url.clone(), full_report(Some("1"), &["a"]));
+        handle_diagnostic_report(&mut editor, 2, url.clone(), full_report(None, &["b"]));
+        assert_eq!(messages(&editor), [("a".into(), 1), ("b".into(), 2)]);
+        assert_eq!(result_id(&editor, 1).as_deref(), Some("1"));
+        assert_eq!(result_id(&editor, 2), None);
+
+        // an unchanged report keeps the diagnostics and updates the result id
+        let unchanged =
+            lsp::DocumentDiagnosticReportKind::Unchanged(lsp::UnchangedDocumentDiagnosticReport {
+                result_id: "2".to_string(),
+            });
+        handle_diagnostic_report(&mut editor, 1, url.clone(), unchanged);
+        assert_eq!(messages(&editor), [("a".into(), 1), ("b".into(), 2)]);
+        assert_eq!(result_id(&editor, 1).as_deref(), Some("2"));
+
+        // a full report without a result id replaces the diagnostics and forgets the old id
+        handle_diagnostic_report(&mut editor, 1, url.clone(), full_report(None, &["c"]));
+        assert_eq!(messages(&editor), [("b".into(), 2), ("c".into(), 1)]);
+        assert_eq!(result_id(&editor, 1), None);
+    }
+}
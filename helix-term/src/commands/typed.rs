@@ -6,7 +6,8 @@
 
 use helix_view::{
     apply_transaction,
-    editor::{Action, CloseError, ConfigEvent},
+    editor::{Action, CloseError, ConfigEvent, FunctionBreakpoint},
+    session::{self, Session},
 };
 use ui::completers::{self, Completer};
 
@@ -31,7 +32,8 @@ fn quit(cx: &mut compositor::Context, args: &[Cow<str>], event: PromptEvent) ->
 
     // last view and we have unsaved changes
     if cx.editor.tree.views().count() == 1 {
-        buffers_remaining_impl(cx.editor)?
+        buffers_remaining_impl(cx.editor)?;
+        persist_session(cx.editor);
     }
 
     cx.block_try_flush_writes()?;
@@ -52,6 +54,9 @@ fn force_quit(
     ensure!(args.is_empty(), ":quit! takes no arguments");
 
     cx.block_try_flush_writes()?;
+    if cx.editor.tree.views().count() == 1 {
+        persist_session(cx.editor);
+    }
     cx.editor.close(view!(cx.editor).id);
 
     Ok(())
@@ -678,6 +683,7 @@ fn quit_all_impl(cx: &mut compositor::Context, force: bool) -> anyhow::Result<()
     if !force {
         buffers_remaining_impl(cx.editor)?;
     }
+    persist_session(cx.editor);
 
     // close all views
     let views: Vec<_> = cx.editor.tree.views().map(|(view, _)| view.id).collect();
@@ -748,6 +754,60 @@ fn force_cquit(
     quit_all_impl(cx, true)
 }
 
+/// Saves the session of the working directory when quitting if `persistent-session`
+/// is enabled.
+fn persist_session(editor: &Editor) {
+    if !editor.config().persistent_session {
+        return;
+    }
+    let result = Session::capture(editor).and_then(|session| {
+        let path = session::session_file(&session.cwd);
+        session.save(&path)
+    });
+    if let Err(err) = result {
+        log::error!("failed to save session: {}", err);
+    }
+}
+
+fn session_path(arg: Option<&Cow<str>>) -> anyhow::Result<PathBuf> {
+    match arg {
+        Some(path) => Ok(helix_core::path::expand_tilde(Path::new(path.as_ref()))),
+        None => Ok(session::session_file(&std::env::current_dir()?)),
+    }
+}
+
+fn session_save(
+    cx: &mut compositor::Context,
+    args: &[Cow<str>],
+    event: PromptEvent,
+) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    let path = session_path(args.first())?;
+    Session::capture(cx.editor)?.save(&path)?;
+    cx.editor
+        .set_status(format!("Saved session to {}", path.display()));
+    Ok(())
+}
+
+fn session_load(
+    cx: &mut compositor::Context,
+    args: &[Cow<str>],
+    event: PromptEvent,
+) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    let path = session_path(args.first())?;
+    Session::load(&path)?.restore(cx.editor)?;
+    cx.editor
+        .set_status(format!("Loaded session from {}", path.display()));
+    Ok(())
+}
+
 fn theme(
     cx: &mut compositor::Context,
     args: &[Cow<str>],
@@ -1036,6 +1096,51 @@ fn reload(
         })
 }
 
+fn move_buffer(
+    cx: &mut compositor::Context,
+    args: &[Cow<str>],
+    event: PromptEvent,
+) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    ensure!(args.len() == 1, "Bad arguments. Usage: `:move new_path`");
+
+    let doc = doc!(cx.editor);
+    let old_path = doc
+        .path()
+        .context("Scratch buffer cannot be moved. Use :write instead")?
+        .clone();
+    let mut new_path = helix_core::path::get_canonicalized_path(Path::new(args[0].as_ref()))?;
+    if new_path.is_dir() {
+        if let Some(file_name) = old_path.file_name() {
+            new_path.push(file_name);
+        }
+    }
+    ensure!(
+        !new_path.exists(),
+        "'{}' already exists",
+        new_path.display()
+    );
+
+    move_path(
+        cx.editor,
+        cx.jobs,
+        old_path,
+        new_path.clone(),
+        move |editor, _compositor, result| match result {
+            Ok(()) => editor.set_status(format!(
+                "Moved to '{}'",
+                helix_core::path::get_relative_path(&new_path).display()
+            )),
+            Err(err) => editor.set_error(format!("Failed to move file: {}", err)),
+        },
+    );
+
+    Ok(())
+}
+
 fn reload_all(
     cx: &mut compositor::Context,
     _args: &[Cow<str>],
@@ -1064,22 +1169,34 @@ fn reload_all(
         .collect();
 
     for (doc_id, view_ids) in docs_view_ids {
-        let doc = doc_mut!(cx.editor, &doc_id);
+        reload_document(cx.editor, doc_id, &view_ids, scrolloff)?;
+    }
+
+    Ok(())
+}
 
-        // Every doc is guaranteed to have at least 1 view at this point.
-        let view = view_mut!(cx.editor, view_ids[0]);
+/// Reloads a document from disk, using the first of `view_ids` for the history.
+pub(crate) fn reload_document(
+    editor: &mut Editor,
+    doc_id: DocumentId,
+    view_ids: &[ViewId],
+    scrolloff: usize,
+) -> anyhow::Result<()> {
+    let doc = doc_mut!(editor, &doc_id);
 
-        // Ensure that the view is synced with the document's history.
-        view.sync_changes(doc);
+    // Every doc is guaranteed to have at least 1 view at this point.
+    let view = view_mut!(editor, view_ids[0]);
 
-        let redraw_handle = cx.editor.redraw_handle.clone();
-        doc.reload(view, &cx.editor.diff_providers, redraw_handle)?;
+    // Ensure that the view is synced with the document's history.
+    view.sync_changes(doc);
 
-        for view_id in view_ids {
-            let view = view_mut!(cx.editor, view_id);
-            if view.doc.eq(&doc_id) {
-                view.ensure_cursor_in_view(doc, scrolloff);
-            }
+    let redraw_handle = editor.redraw_handle.clone();
+    doc.reload(view, &editor.diff_providers, redraw_handle)?;
+
+    for &view_id in view_ids {
+        let view = view_mut!(editor, view_id);
+        if view.doc.eq(&doc_id) {
+            view.ensure_cursor_in_view(doc, scrolloff);
         }
     }
 
@@ -1115,14 +1232,18 @@ fn lsp_workspace_command(
 
     let (_, doc) = current!(cx.editor);
 
-    let language_server = match doc.language_server() {
+    let language_server = doc
+        .language_servers_with_feature(LanguageServerFeature::WorkspaceCommand)
+        .next();
+    let language_server = match language_server {
         Some(language_server) => language_server,
         None => {
             cx.editor
-                .set_status("Language server not active for current buffer");
+                .set_status("No active language server supports workspace commands");
             return Ok(());
         }
     };
+    let language_server_id = language_server.id();
 
     let options = match &language_server.capabilities().execute_command_provider {
         Some(options) => options,
@@ -1145,8 +1266,8 @@ fn lsp_workspace_command(
         let callback = async move {
             let call: job::Callback = Callback::EditorCompositor(Box::new(
                 move |_editor: &mut Editor, compositor: &mut Compositor| {
-                    let picker = ui::Picker::new(commands, (), |cx, command, _action| {
-                        execute_lsp_command(cx.editor, command.clone());
+                    let picker = ui::Picker::new(commands, (), move |cx, command, _action| {
+                        execute_lsp_command(cx.editor, language_server_id, command.clone());
                     });
                     compositor.push(Box::new(overlayed(picker)))
                 },
@@ -1159,6 +1280,7 @@ fn lsp_workspace_command(
         if options.commands.iter().any(|c| c == &command) {
             execute_lsp_command(
                 cx.editor,
+                language_server_id,
                 helix_lsp::lsp::Command {
                     title: command.clone(),
                     arguments: None,
@@ -1190,7 +1312,25 @@ fn lsp_restart(
         .context("LSP not defined for the current document")?;
 
     let scope = config.scope.clone();
-    cx.editor.language_servers.restart(config, doc.path())?;
+    let old_server_ids: Vec<_> = doc
+        .language_servers()
+        .map(|language_server| language_server.id())
+        .collect();
+    // the servers that fail to start again are reported after the others are restarted
+    let failures: Vec<_> = cx
+        .editor
+        .language_servers
+        .restart(config, doc.path())
+        .into_iter()
+        .filter_map(|result| result.err())
+        .map(|err| err.to_string())
+        .collect();
+
+    // the result ids of the pulled diagnostics aren't known to the new servers
+    for server_id in old_server_ids {
+        cx.editor.diagnostic_result_ids.remove(&server_id);
+        cx.editor.pending_workspace_diagnostics.remove(&server_id);
+    }
 
     // This collect is needed because refresh_language_server would need to re-borrow editor.
     let document_ids_to_refresh: Vec<DocumentId> = cx
@@ -1206,6 +1346,12 @@ fn lsp_restart(
         cx.editor.refresh_language_server(document_id);
     }
 
+    if !failures.is_empty() {
+        bail!(
+            "Failed to restart language servers: {}",
+            failures.join(", ")
+        );
+    }
     Ok(())
 }
 
@@ -1325,20 +1471,40 @@ fn debug_eval(
         return Ok(());
     }
 
-    if let Some(debugger) = cx.editor.debugger.as_mut() {
-        let (frame, thread_id) = match (debugger.active_frame, debugger.thread_id) {
-            (Some(frame), Some(thread_id)) => (frame, thread_id),
-            _ => {
-                bail!("Cannot find current stack frame to access variables")
-            }
-        };
+    dap_evaluate(cx.editor, cx.jobs, args.join(" "));
+    Ok(())
+}
 
-        // TODO: support no frame_id
+fn debug_function_breakpoint(
+    cx: &mut compositor::Context,
+    args: &[Cow<str>],
+    event: PromptEvent,
+) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
 
-        let frame_id = debugger.stack_frames[&thread_id][frame].id;
-        let response = helix_lsp::block_on(debugger.eval(args.join(" "), Some(frame_id)))?;
-        cx.editor.set_status(response.result);
+    ensure!(!args.is_empty(), "Function name not provided");
+    let name = args.join(" ");
+
+    let breakpoints = &mut cx.editor.function_breakpoints;
+    match breakpoints
+        .iter()
+        .position(|breakpoint| breakpoint.name == name)
+    {
+        Some(pos) => {
+            breakpoints.remove(pos);
+        }
+        None => breakpoints.push(FunctionBreakpoint {
+            verified: false,
+            message: None,
+            name,
+            condition: None,
+            hit_condition: None,
+            enabled: true,
+        }),
     }
+    sync_function_breakpoints(cx.editor);
     Ok(())
 }
 
@@ -1808,6 +1974,18 @@ fn run_shell_command(
     Ok(())
 }
 
+fn terminal(
+    cx: &mut compositor::Context,
+    args: &[Cow<str>],
+    event: PromptEvent,
+) -> anyhow::Result<()> {
+    if event != PromptEvent::Validate {
+        return Ok(());
+    }
+
+    ui::terminal::spawn(cx.editor, &args.join(" "))
+}
+
 pub const TYPABLE_COMMAND_LIST: &[TypableCommand] = &[
         TypableCommand {
             name: "quit",
@@ -2008,6 +2186,20 @@ fn run_shell_command(
             fun: force_cquit,
             completer: None,
         },
+        TypableCommand {
+            name: "session-save",
+            aliases: &[],
+            doc: "Save the open buffers, splits, cursor positions, registers and working directory. Accepts an optional path, defaults to the session of the working directory.",
+            fun: session_save,
+            completer: Some(completers::filename),
+        },
+        TypableCommand {
+            name: "session-load",
+            aliases: &[],
+            doc: "Restore a session saved with :session-save. Accepts an optional path, defaults to the session of the working directory.",
+            fun: session_load,
+            completer: Some(completers::filename),
+        },
         TypableCommand {
             name: "theme",
             aliases: &[],
@@ -2127,6 +2319,13 @@ fn run_shell_command(
             fun: reload_all,
             completer: None,
         },
+        TypableCommand {
+            name: "move",
+            aliases: &["rename-file"],
+            doc: "Move the current buffer and its file to a new path, letting language servers update references.",
+            fun: move_buffer,
+            completer: Some(completers::filename),
+        },
         TypableCommand {
             name: "update",
             aliases: &[],
@@ -2176,6 +2375,13 @@ fn run_shell_command(
             fun: debug_eval,
             completer: None,
         },
+        TypableCommand {
+            name: "debug-function-breakpoint",
+            aliases: &[],
+            doc: "Toggle a breakpoint on the function with the given name.",
+            fun: debug_function_breakpoint,
+            completer: None,
+        },
         TypableCommand {
             name: "vsplit",
             aliases: &["vs"],
@@ -2323,6 +2529,13 @@ fn run_shell_command(
             fun: run_shell_command,
             completer: Some(completers::directory),
         },
+        TypableCommand {
+            name: "terminal",
+            aliases: &["term"],
+            doc: "Open a terminal in a split running a shell command, or your shell if no command is given.",
+            fun: terminal,
+            completer: None,
+        },
     ];
 
 pub static TYPABLE_COMMAND_MAP: Lazy<HashMap<&'static str, &'static TypableCommand>> =
@@ -2352,12 +2565,18 @@ pub(super) fn command_mode(cx: &mut Context) {
 
             if words.is_empty() || (words.len() == 1 && !shellwords.ends_with_whitespace()) {
                 // If the command has not been finished yet, complete commands.
+                let plugin_commands = helix_view::plugin::manifests()
+                    .iter()
+                    .flat_map(|manifest| manifest.commands.iter())
+                    .map(|command| command.name.as_str());
                 let mut matches: Vec<_> = typed::TYPABLE_COMMAND_LIST
                     .iter()
-                    .filter_map(|command| {
+                    .map(|command| command.name)
+                    .chain(plugin_commands)
+                    .filter_map(|name| {
                         FUZZY_MATCHER
-                            .fuzzy_match(command.name, input)
-                            .map(|score| (command.name, score))
+                            .fuzzy_match(name, input)
+                            .map(|score| (name, score))
                     })
                     .collect();
 
@@ -2413,12 +2632,14 @@ pub(super) fn command_mode(cx: &mut Context) {
                 return;
             }
 
-            // Handle typable commands
-            if let Some(cmd) = typed::TYPABLE_COMMAND_MAP.get(parts[0]) {
+            // Handle typable commands, including the ones of plugins
+            if typed::TYPABLE_COMMAND_MAP.contains_key(parts[0])
+                || helix_view::plugin::command(parts[0]).is_some()
+            {
                 let shellwords = Shellwords::from(input);
                 let args = shellwords.words();
 
-                if let Err(e) = (cmd.fun)(cx, &args[1..], event) {
+                if let Err(e) = crate::plugin::execute_command(cx, parts[0], &args[1..], event) {
                     cx.editor.set_error(format!("{}", e));
                 }
             } else if event == PromptEvent::Validate {
@@ -2439,6 +2660,10 @@ pub(super) fn command_mode(cx: &mut Context) {
             return Some(format!("{}\nAliases: {}", doc, aliases.join(", ")).into());
         }
 
+        if let Some((manifest, command)) = helix_view::plugin::command(part) {
+            return Some(format!("{}\nPlugin: {}", command.doc, manifest.name).into());
+        }
+
         None
     });
 
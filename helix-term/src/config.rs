@@ -1,4 +1,4 @@
-use crate::keymap::{default::default, merge_keys, Keymap};
+use crate::keymap::{default::default, Keymap};
 use helix_view::document::Mode;
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -43,18 +43,44 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 }
 
 impl Config {
+    /// Loads the config file, or the default config if it doesn't exist, along with the
+    /// keybindings of plugins.
     pub fn load(config_path: PathBuf) -> Result<Config, ConfigLoadError> {
-        match std::fs::read_to_string(config_path) {
-            Ok(config) => toml::from_str(&config)
-                .map(merge_keys)
-                .map_err(ConfigLoadError::BadConfig),
-            Err(err) => Err(ConfigLoadError::Error(err)),
-        }
+        let config = match std::fs::read_to_string(config_path) {
+            Ok(text) => {
+                let mut config: Config =
+                    toml::from_str(&text).map_err(ConfigLoadError::BadConfig)?;
+                // Without a `keys` table the keys are the defaults, which must not replace
+                // the keybindings of plugins.
+                let table: toml::Value =
+                    toml::from_str(&text).map_err(ConfigLoadError::BadConfig)?;
+                if table.get("keys").is_none() {
+                    config.keys = HashMap::new();
+                }
+                config
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Config {
+                keys: HashMap::new(),
+                ..Config::default()
+            },
+            Err(err) => return Err(ConfigLoadError::Error(err)),
+        };
+        Ok(config.merge_keys(crate::plugin::keymaps()))
     }
 
     pub fn load_default() -> Result<Config, ConfigLoadError> {
         Config::load(helix_loader::config_file())
     }
+
+    /// Merges the keybindings of plugins into the default ones, and the keybindings of the
+    /// config into the result, so that the config has the last word.
+    fn merge_keys(mut self, plugin_keys: HashMap<Mode, Keymap>) -> Config {
+        let config_keys = std::mem::replace(&mut self.keys, default());
+        for (mode, keys) in plugin_keys.into_iter().chain(config_keys) {
+            self.keys.entry(mode).or_default().merge(keys);
+        }
+        self
+    }
 }
 
 #[cfg(test)]
@@ -104,4 +130,46 @@ fn keys_resolve_to_correct_defaults() {
         let default_keys = Config::default().keys;
         assert_eq!(default_keys, default());
     }
+
+    #[test]
+    fn config_keys_override_plugin_keys() {
+        use crate::keymap;
+        use crate::keymap::KeyTrie;
+        use crate::{commands::MappableCommand, key};
+        use helix_core::hashmap;
+        use helix_view::document::Mode;
+
+        let plugin_keys = hashmap! {
+            Mode::Normal => Keymap::new(keymap!({ "Normal mode"
+                "j" => goto_file_start,
+                "k" => goto_file_end,
+            })),
+        };
+        let config = Config {
+            keys: hashmap! {
+                Mode::Normal => Keymap::new(keymap!({ "Normal mode"
+                    "j" => goto_line_start,
+                })),
+            },
+            ..Default::default()
+        }
+        .merge_keys(plugin_keys);
+
+        let keymap = config.keys[&Mode::Normal].root();
+        assert_eq!(
+            keymap.search(&[key!('j')]),
+            Some(&KeyTrie::Leaf(MappableCommand::goto_line_start)),
+            "Config key should replace plugin key"
+        );
+        assert_eq!(
+            keymap.search(&[key!('k')]),
+            Some(&KeyTrie::Leaf(MappableCommand::goto_file_end)),
+            "Plugin key should replace default key"
+        );
+        assert_eq!(
+            keymap.search(&[key!('h')]),
+            Some(&KeyTrie::Leaf(MappableCommand::move_char_left)),
+            "Default key should be kept"
+        );
+    }
 }
@@ -178,9 +178,10 @@ pub fn languages_all() -> std::io::Result<()> {
     for lang in &syn_loader_conf.language {
         column(&lang.language_id, Color::Reset);
 
+        // only the first language server fits in the table
         let lsp = lang
-            .language_server
-            .as_ref()
+            .language_servers()
+            .first()
             .map(|lsp| lsp.command.to_string());
         check_binary(lsp);
 
@@ -250,12 +251,12 @@ pub fn language(lang_str: String) -> std::io::Result<()> {
         }
     };
 
-    probe_protocol(
-        "language server",
-        lang.language_server
-            .as_ref()
-            .map(|lsp| lsp.command.to_string()),
-    )?;
+    if lang.language_servers().is_empty() {
+        probe_protocol("language server", None)?;
+    }
+    for lsp in lang.language_servers() {
+        probe_protocol("language server", Some(lsp.command.to_string()))?;
+    }
 
     probe_protocol(
         "debug adapter",
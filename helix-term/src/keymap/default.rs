@@ -47,6 +47,7 @@ pub fn default() -> HashMap<Mode, Keymap> {
             "y" => goto_type_definition,
             "r" => goto_reference,
             "i" => goto_implementation,
+            "D" => goto_declaration,
             "t" => goto_window_top,
             "c" => goto_window_center,
             "b" => goto_window_bottom,
@@ -209,14 +210,26 @@ pub fn default() -> HashMap<Mode, Keymap> {
         "space" => { "Space"
             "f" => file_picker,
             "F" => file_picker_in_current_directory,
+            "e" => toggle_explorer,
+            "t" => toggle_terminal,
             "b" => buffer_picker,
             "j" => jumplist_picker,
             "s" => symbol_picker,
             "S" => workspace_symbol_picker,
             "d" => diagnostics_picker,
             "D" => workspace_diagnostics_picker,
+            "i" => { "Hierarchy"
+                "i" => incoming_calls_picker,
+                "o" => outgoing_calls_picker,
+                "s" => supertypes_picker,
+                "S" => subtypes_picker,
+            },
             "a" => code_action,
+            "l" => code_lens,
             "'" => last_picker,
+            "c" => toggle_comments,
+            "C" => toggle_block_comments,
+            "A-c" => toggle_line_comments,
             "g" => { "Debug (experimental)" sticky=true
                 "l" => dap_launch,
                 "b" => dap_toggle_breakpoint,
@@ -229,6 +242,8 @@ pub fn default() -> HashMap<Mode, Keymap> {
                 "t" => dap_terminate,
                 "C-c" => dap_edit_condition,
                 "C-l" => dap_edit_log,
+                "H" => dap_edit_hit_condition,
+                "B" => dap_breakpoints,
                 "s" => { "Switch"
                     "t" => dap_switch_thread,
                     "f" => dap_switch_stack_frame,
@@ -236,6 +251,8 @@ pub fn default() -> HashMap<Mode, Keymap> {
                 },
                 "e" => dap_enable_exceptions,
                 "E" => dap_disable_exceptions,
+                "r" => dap_toggle_console,
+                "p" => dap_toggle_panel,
             },
             "w" => { "Window"
                 "C-w" | "w" => rotate_view,
@@ -265,6 +282,7 @@ pub fn default() -> HashMap<Mode, Keymap> {
             "P" => paste_clipboard_before,
             "R" => replace_selections_with_clipboard,
             "/" => global_search,
+            "A-/" => global_replace,
             "k" => hover,
             "r" => rename_symbol,
             "h" => select_references_to_symbol_under_cursor,
@@ -282,6 +300,12 @@ pub fn default() -> HashMap<Mode, Keymap> {
             "C-u" | "backspace" => half_page_up,
             "C-d" | "space" => half_page_down,
 
+            "f" => fold,
+            "o" => unfold,
+            "a" => toggle_fold,
+            "M" => fold_all,
+            "R" => unfold_all,
+
             "/" => search,
             "?" => rsearch,
             "n" => search_next,
@@ -299,6 +323,12 @@ pub fn default() -> HashMap<Mode, Keymap> {
             "C-u" | "backspace" => half_page_up,
             "C-d" | "space" => half_page_down,
 
+            "f" => fold,
+            "o" => unfold,
+            "a" => toggle_fold,
+            "M" => fold_all,
+            "R" => unfold_all,
+
             "/" => search,
             "?" => rsearch,
             "n" => search_next,
@@ -359,6 +389,7 @@ pub fn default() -> HashMap<Mode, Keymap> {
         "C-d" | "del" => delete_char_forward,
         "C-j" | "ret" => insert_newline,
         "tab" => insert_tab,
+        "S-tab" => goto_prev_tabstop,
 
         "up" => move_line_up,
         "down" => move_line_down,
@@ -0,0 +1,291 @@
+//! The editor side of the plugin API, see [`helix_view::plugin`].
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+use helix_core::{path::get_canonicalized_path, Range, Selection, Transaction};
+use helix_lsp::jsonrpc::{self, Params};
+use helix_view::{
+    apply_transaction,
+    document::Mode,
+    editor::Action,
+    plugin::{
+        self, ApplyParams, CommandParams, DocumentParams, Host, Notifier, OpenParams, PluginEvent,
+        StatusParams, API_VERSION,
+    },
+    DocumentId, Editor, ViewId,
+};
+use serde_json::{json, Value};
+
+use crate::{commands::typed, compositor, keymap::Keymap, ui::PromptEvent};
+
+/// The functions plugins call, passed to them when they are loaded.
+pub static HOST: Host = Host {
+    api_version: API_VERSION,
+    request: host_request,
+    free: host_free,
+};
+
+thread_local! {
+    /// The context of the notification plugins are currently handling, which their
+    /// requests operate on.
+    static CONTEXT: Cell<Option<*mut ()>> = Cell::new(None);
+}
+
+/// Notifies a plugin, which can make requests operating on `cx` until it returns.
+pub fn notify(cx: &mut compositor::Context, notifier: Notifier, method: &str, params: &Value) {
+    let previous =
+        CONTEXT.with(|context| context.replace(Some(cx as *mut compositor::Context as *mut ())));
+    notifier.notify(method, params);
+    CONTEXT.with(|context| context.set(previous));
+}
+
+/// Notifies the plugins subscribed to an event queued by the editor.
+pub fn notify_event(cx: &mut compositor::Context, event: PluginEvent, params: Value) {
+    for notifier in cx.editor.plugins.event_notifiers(event) {
+        notify(cx, notifier, event.method(), &params);
+    }
+}
+
+/// Handles a request made through [`Host::request`], returning the JSON response.
+fn request(method: &str, params: &str) -> Value {
+    let result = CONTEXT.with(|context| {
+        let context = context.get().ok_or_else(|| {
+            internal_error("requests can only be made while handling a notification")
+        })?;
+        let params = match params.trim() {
+            "" => Params::None,
+            params => serde_json::from_str(params).map_err(|err| {
+                jsonrpc::Error::invalid_params(format!("Invalid params: {}.", err))
+            })?,
+        };
+        // Safety: the context was set by `notify`, which doesn't use it while the plugin
+        // runs, and is reset before `notify` returns.
+        let cx = unsafe { &mut *(context as *mut compositor::Context) };
+        handle_request(cx, method, params)
+    });
+    match result {
+        Ok(result) => json!({ "result": result }),
+        Err(error) => json!({ "error": error }),
+    }
+}
+
+unsafe extern "C" fn host_request(method: *const c_char, params: *const c_char) -> *mut c_char {
+    let response = catch_unwind(AssertUnwindSafe(|| {
+        let method = CStr::from_ptr(method).to_string_lossy();
+        let params = if params.is_null() {
+            Cow::Borrowed("")
+        } else {
+            CStr::from_ptr(params).to_string_lossy()
+        };
+        request(&method, &params)
+    }))
+    .unwrap_or_else(|_| json!({ "error": internal_error("the request panicked") }));
+    // JSON escapes NUL characters in strings
+    CString::new(response.to_string())
+        .expect("JSON contains no NUL bytes")
+        .into_raw()
+}
+
+unsafe extern "C" fn host_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// The keybindings of all plugins.
+pub fn keymaps() -> HashMap<Mode, Keymap> {
+    let mut keymaps: HashMap<Mode, Keymap> = HashMap::new();
+    for manifest in plugin::manifests() {
+        let keys = match &manifest.keys {
+            Some(keys) => keys.clone(),
+            None => continue,
+        };
+        match keys.try_into::<HashMap<Mode, Keymap>>() {
+            Ok(keys) => {
+                for (mode, keymap) in keys {
+                    keymaps.entry(mode).or_default().merge(keymap);
+                }
+            }
+            Err(err) => log::error!("invalid keys in plugin {}: {}", manifest.name, err),
+        }
+    }
+    keymaps
+}
+
+/// Runs a typable command, either a builtin one or one provided by a plugin.
+pub fn execute_command(
+    cx: &mut compositor::Context,
+    name: &str,
+    args: &[Cow<str>],
+    event: PromptEvent,
+) -> anyhow::Result<()> {
+    if let Some(command) = typed::TYPABLE_COMMAND_MAP.get(name) {
+        (command.fun)(cx, args, event)
+    } else if plugin::command(name).is_some() {
+        if event == PromptEvent::Validate {
+            let notifier = cx.editor.plugins.command_notifier(name)?;
+            let params = json!({ "command": name, "args": args });
+            notify(cx, notifier, "command/execute", &params);
+        }
+        Ok(())
+    } else {
+        anyhow::bail!("no such command: '{}'", name)
+    }
+}
+
+fn internal_error<E: std::fmt::Display>(err: E) -> jsonrpc::Error {
+    jsonrpc::Error {
+        code: jsonrpc::ErrorCode::InternalError,
+        message: err.to_string(),
+        data: None,
+    }
+}
+
+/// The document a request refers to and a view to use for its selection.
+fn document_view(
+    editor: &Editor,
+    path: Option<&Path>,
+) -> Result<(DocumentId, ViewId), jsonrpc::Error> {
+    let current_view_id = view!(editor).id;
+    let path = match path {
+        Some(path) => get_canonicalized_path(path).map_err(internal_error)?,
+        None => return Ok((view!(editor).doc, current_view_id)),
+    };
+    let doc = editor.document_by_path(&path).ok_or_else(|| {
+        jsonrpc::Error::invalid_params(format!("document {} is not open", path.display()))
+    })?;
+
+    let selections = doc.selections();
+    let view_id = if selections.contains_key(&current_view_id) {
+        // use current if possible
+        current_view_id
+    } else {
+        selections
+            .keys()
+            .next()
+            .copied()
+            .ok_or_else(|| internal_error("document is not shown in any view"))?
+    };
+    Ok((doc.id(), view_id))
+}
+
+fn document_info(editor: &Editor, params: DocumentParams) -> Result<Value, jsonrpc::Error> {
+    let (doc_id, view_id) = document_view(editor, params.path.as_deref())?;
+    let doc = &editor.documents[&doc_id];
+    let selection = doc.selection(view_id);
+    Ok(json!({
+        "path": doc.path(),
+        "language": doc.language_name(),
+        "version": doc.version(),
+        "modified": doc.is_modified(),
+        "selection": selection
+            .iter()
+            .map(|range| plugin::SelectionRange { anchor: range.anchor, head: range.head })
+            .collect::<Vec<_>>(),
+        "primary": selection.primary_index(),
+    }))
+}
+
+fn apply(editor: &mut Editor, params: ApplyParams) -> Result<Value, jsonrpc::Error> {
+    let (doc_id, view_id) = document_view(editor, params.path.as_deref())?;
+    let doc = doc_mut!(editor, &doc_id);
+
+    if let Some(version) = params.version {
+        if version != doc.version() {
+            return Err(jsonrpc::Error::invalid_params(format!(
+                "document version is {}, not {}",
+                doc.version(),
+                version
+            )));
+        }
+    }
+
+    let mut changes = params.changes;
+    changes.sort_by_key(|change| change.from);
+    let mut len = doc.text().len_chars();
+    let mut last = 0;
+    for change in &changes {
+        if change.from < last || change.to < change.from || change.to > doc.text().len_chars() {
+            return Err(jsonrpc::Error::invalid_params(
+                "changes must be in bounds and must not overlap",
+            ));
+        }
+        last = change.to;
+        len = len - (change.to - change.from) + change.text.chars().count();
+    }
+
+    let mut transaction = Transaction::change(
+        doc.text(),
+        changes
+            .into_iter()
+            .map(|change| (change.from, change.to, Some(change.text.into()))),
+    );
+    if let Some(ranges) = params.selection {
+        if ranges.is_empty() || ranges.iter().any(|r| r.anchor.max(r.head) > len) {
+            return Err(jsonrpc::Error::invalid_params("selection is out of bounds"));
+        }
+        let ranges = ranges
+            .iter()
+            .map(|range| Range::new(range.anchor, range.head))
+            .collect();
+        transaction = transaction.with_selection(Selection::new(ranges, 0));
+    }
+
+    let view = view_mut!(editor, view_id);
+    apply_transaction(&transaction, doc, view);
+    doc.append_changes_to_history(view);
+    Ok(json!({ "version": doc.version() }))
+}
+
+/// Handles a request of a plugin.
+pub fn handle_request(
+    cx: &mut compositor::Context,
+    method: &str,
+    params: Params,
+) -> Result<Value, jsonrpc::Error> {
+    match method {
+        "editor/status" => {
+            let params: StatusParams = params.parse()?;
+            if params.error {
+                cx.editor.set_error(params.message);
+            } else {
+                cx.editor.set_status(params.message);
+            }
+            Ok(Value::Null)
+        }
+        "editor/mode" => Ok(json!(cx.editor.mode.to_string())),
+        "editor/open" => {
+            let params: OpenParams = params.parse()?;
+            cx.editor
+                .open(&params.path, Action::Replace)
+                .map_err(internal_error)?;
+            Ok(Value::Null)
+        }
+        "editor/command" => {
+            let params: CommandParams = params.parse()?;
+            let args: Vec<Cow<str>> = params.args.iter().map(Cow::from).collect();
+            execute_command(cx, &params.command, &args, PromptEvent::Validate)
+                .map_err(internal_error)?;
+            Ok(Value::Null)
+        }
+        "document/info" => document_info(cx.editor, params.parse().unwrap_or_default()),
+        "document/text" => {
+            let params: DocumentParams = params.parse().unwrap_or_default();
+            let (doc_id, _) = document_view(cx.editor, params.path.as_deref())?;
+            let doc = &cx.editor.documents[&doc_id];
+            Ok(json!({ "text": doc.text().to_string(), "version": doc.version() }))
+        }
+        "document/apply" => apply(cx.editor, params.parse()?),
+        _ => Err(jsonrpc::Error {
+            code: jsonrpc::ErrorCode::MethodNotFound,
+            message: format!("unknown method {}", method),
+            data: None,
+        }),
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{
+    alt,
+    commands::{update_breakpoint, BreakpointId},
+    compositor::{Component, Context, Event, EventResult},
+    ctrl,
+    ui::{menu::Item, FilePicker},
+};
+use helix_core::{path::get_relative_path, Position, Selection};
+use helix_view::{
+    align_view,
+    graphics::{CursorKind, Modifier, Rect, Style},
+    Align, Editor,
+};
+use tui::{
+    buffer::Buffer as Surface,
+    text::{Span, Spans},
+};
+
+use std::cell::Cell;
+
+/// A breakpoint of any kind listed by the [`BreakpointPicker`].
+#[derive(Debug)]
+pub struct BreakpointItem {
+    pub id: BreakpointId,
+    /// Describes a data breakpoint.
+    pub description: Option<String>,
+    pub enabled: Cell<bool>,
+    pub deleted: Cell<bool>,
+}
+
+impl Item for BreakpointItem {
+    type Data = ();
+
+    fn label(&self, _data: &Self::Data) -> Spans {
+        let marker = if self.enabled.get() { "[x]" } else { "[ ]" };
+        let text = match &self.id {
+            BreakpointId::Source { path, line } => format!(
+                "{} {}:{}",
+                marker,
+                get_relative_path(path).to_string_lossy(),
+                line + 1
+            ),
+            BreakpointId::Function(name) => format!("{} function {}", marker, name),
+            BreakpointId::Data(data_id) => format!(
+                "{} data {}",
+                marker,
+                self.description.as_deref().unwrap_or(data_id)
+            ),
+        };
+        let style = if self.deleted.get() {
+            Style::default().add_modifier(Modifier::CROSSED_OUT)
+        } else {
+            Style::default()
+        };
+        Span::styled(text, style).into()
+    }
+}
+
+/// Picker listing the source, function and data breakpoints, which can be enabled,
+/// disabled and deleted from it.
+pub struct BreakpointPicker {
+    picker: FilePicker<BreakpointItem>,
+}
+
+impl BreakpointPicker {
+    pub fn new(editor: &Editor) -> Self {
+        let item = |id, description, enabled| BreakpointItem {
+            id,
+            description,
+            enabled: Cell::new(enabled),
+            deleted: Cell::new(false),
+        };
+
+        let mut sources: Vec<_> = editor
+            .breakpoints
+            .iter()
+            .flat_map(|(path, breakpoints)| {
+                breakpoints
+                    .iter()
+                    .map(move |breakpoint| (path, breakpoint.line, breakpoint.enabled))
+            })
+            .collect();
+        sources.sort();
+
+        let mut items: Vec<_> = sources
+            .into_iter()
+            .map(|(path, line, enabled)| {
+                let id = BreakpointId::Source {
+                    path: path.clone(),
+                    line,
+                };
+                item(id, None, enabled)
+            })
+            .collect();
+        for breakpoint in &editor.function_breakpoints {
+            let id = BreakpointId::Function(breakpoint.name.clone());
+            items.push(item(id, None, breakpoint.enabled));
+        }
+        for breakpoint in &editor.data_breakpoints {
+            let id = BreakpointId::Data(breakpoint.data_id.clone());
+            let description = Some(breakpoint.description.clone());
+            items.push(item(id, description, breakpoint.enabled));
+        }
+
+        let picker = FilePicker::new(
+            items,
+            (),
+            |cx, item: &BreakpointItem, action| {
+                let (path, line) = match &item.id {
+                    BreakpointId::Source { path, line } => (path, *line),
+                    _ => return,
+                };
+                if let Err(e) = cx.editor.open(path, action) {
+                    cx.editor
+                        .set_error(format!("Failed to open file '{}': {}", path.display(), e));
+                    return;
+                }
+
+                let (view, doc) = current!(cx.editor);
+                let text = doc.text();
+                let line = line.min(text.len_lines().saturating_sub(1));
+                let pos = text.line_to_char(line);
+                doc.set_selection(view.id, Selection::point(pos));
+                align_view(doc, view, Align::Center);
+            },
+            |_editor, item| match &item.id {
+                BreakpointId::Source { path, line } => {
+                    Some((path.clone().into(), Some((*line, *line))))
+                }
+                _ => None,
+            },
+        );
+
+        Self { picker }
+    }
+}
+
+impl Component for BreakpointPicker {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let key_event = match event {
+            Event::Key(event) => *event,
+            _ => return self.picker.handle_event(event, cx),
+        };
+
+        match key_event {
+            ctrl!('x') => {
+                if let Some(item) = self.picker.selection() {
+                    if !item.deleted.get() {
+                        item.enabled.set(!item.enabled.get());
+                        update_breakpoint(cx.editor, &item.id, Some(item.enabled.get()));
+                    }
+                }
+            }
+            alt!('d') => {
+                if let Some(item) = self.picker.selection() {
+                    if !item.deleted.get() {
+                        item.deleted.set(true);
+                        update_breakpoint(cx.editor, &item.id, None);
+                    }
+                }
+            }
+            _ => return self.picker.handle_event(event, cx),
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        self.picker.render(area, surface, cx)
+    }
+
+    fn cursor(&self, area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
+        self.picker.cursor(area, editor)
+    }
+
+    fn required_size(&mut self, viewport: (u16, u16)) -> Option<(u16, u16)> {
+        self.picker.required_size(viewport)
+    }
+}
@@ -5,7 +5,11 @@
 
 use std::borrow::Cow;
 
-use helix_core::{Change, Transaction};
+use helix_core::{
+    snippets::{self, ActiveSnippet, Snippet},
+    syntax::LanguageServerFeature,
+    Change, Transaction,
+};
 use helix_view::{
     graphics::Rect,
     input::{KeyCode, KeyEvent},
@@ -81,6 +85,20 @@ fn row(&self, _data: &Self::Data) -> menu::Row {
     }
 }
 
+/// Marks completion items of user snippets, which aren't resolved by language servers.
+const USER_SNIPPET_DATA: &str = "helix-user-snippet";
+
+/// Marks completion items of the choices of the active snippet's current tabstop.
+const SNIPPET_CHOICE_DATA: &str = "helix-snippet-choice";
+
+fn is_user_snippet(item: &CompletionItem) -> bool {
+    item.data.as_ref().and_then(|data| data.as_str()) == Some(USER_SNIPPET_DATA)
+}
+
+fn is_snippet_choice(item: &CompletionItem) -> bool {
+    item.data.as_ref().and_then(|data| data.as_str()) == Some(SNIPPET_CHOICE_DATA)
+}
+
 /// Wraps a Menu.
 pub struct Completion {
     popup: Popup<Menu<CompletionItem>>,
@@ -112,7 +130,24 @@ fn item_to_transaction(
                 offset_encoding: helix_lsp::OffsetEncoding,
                 start_offset: usize,
                 trigger_offset: usize,
-            ) -> Transaction {
+            ) -> (Transaction, Option<ActiveSnippet>) {
+                if is_snippet_choice(item) {
+                    let transaction = match &doc.active_snippet {
+                        Some(snippet) => snippet.choose(doc.text(), &item.label),
+                        None => Transaction::new(doc.text()),
+                    };
+                    return (transaction, None);
+                }
+                if item.insert_text_format == Some(lsp::InsertTextFormat::SNIPPET) {
+                    return snippet_to_transaction(
+                        doc,
+                        view_id,
+                        item,
+                        offset_encoding,
+                        start_offset,
+                    );
+                }
+
                 let transaction = if let Some(edit) = &item.text_edit {
                     let edit = match edit {
                         lsp::CompletionTextEdit::Edit(edit) => edit.clone(),
@@ -153,7 +188,48 @@ fn item_to_transaction(
                     })
                 };
 
-                transaction
+                (transaction, None)
+            }
+
+            fn snippet_to_transaction(
+                doc: &Document,
+                view_id: ViewId,
+                item: &CompletionItem,
+                offset_encoding: helix_lsp::OffsetEncoding,
+                start_offset: usize,
+            ) -> (Transaction, Option<ActiveSnippet>) {
+                let text = doc.text();
+                let selection = doc.selection(view_id);
+                let primary_cursor = selection.primary().cursor(text.slice(..));
+
+                let (snippet, start, end) = if let Some(edit) = &item.text_edit {
+                    let (range, new_text) = match edit {
+                        lsp::CompletionTextEdit::Edit(edit) => (edit.range, &edit.new_text),
+                        lsp::CompletionTextEdit::InsertAndReplace(item) => {
+                            (item.replace, &item.new_text)
+                        }
+                    };
+                    let start = util::lsp_pos_to_pos(text, range.start, offset_encoding);
+                    let end = util::lsp_pos_to_pos(text, range.end, offset_encoding);
+                    match (start, end) {
+                        (Some(start), Some(end)) => (new_text, start, end),
+                        _ => return (Transaction::new(text), None),
+                    }
+                } else {
+                    // replace the word typed so far
+                    let snippet = item.insert_text.as_ref().unwrap_or(&item.label);
+                    (snippet, start_offset, primary_cursor)
+                };
+
+                snippets::expand(
+                    text,
+                    selection,
+                    &Snippet::parse(snippet),
+                    start as i128 - primary_cursor as i128,
+                    end as i128 - primary_cursor as i128,
+                    doc.indent_style.as_str(),
+                    doc.path().map(|path| path.as_path()),
+                )
             }
 
             fn completion_changes(transaction: &Transaction, trigger_offset: usize) -> Vec<Change> {
@@ -177,7 +253,7 @@ fn completion_changes(transaction: &Transaction, trigger_offset: usize) -> Vec<C
                     // always present here
                     let item = item.unwrap();
 
-                    let transaction = item_to_transaction(
+                    let (transaction, _) = item_to_transaction(
                         doc,
                         view.id,
                         item,
@@ -185,6 +261,8 @@ fn completion_changes(transaction: &Transaction, trigger_offset: usize) -> Vec<C
                         start_offset,
                         trigger_offset,
                     );
+                    // only preview the text, the tabstops are selected once accepted
+                    let transaction = Transaction::from(transaction.changes().clone());
 
                     // initialize a savepoint
                     doc.savepoint();
@@ -199,7 +277,7 @@ fn completion_changes(transaction: &Transaction, trigger_offset: usize) -> Vec<C
                     // always present here
                     let item = item.unwrap();
 
-                    let transaction = item_to_transaction(
+                    let (transaction, active_snippet) = item_to_transaction(
                         doc,
                         view.id,
                         item,
@@ -209,6 +287,10 @@ fn completion_changes(transaction: &Transaction, trigger_offset: usize) -> Vec<C
                     );
 
                     apply_transaction(&transaction, doc, view);
+                    // a choice is made within the active snippet
+                    if !is_snippet_choice(item) {
+                        doc.active_snippet = active_snippet;
+                    }
 
                     editor.last_completion = Some(CompleteAction {
                         trigger_offset,
@@ -257,11 +339,61 @@ fn completion_changes(transaction: &Transaction, trigger_offset: usize) -> Vec<C
         completion
     }
 
+    /// Completion items for the user's snippets of the document's language.
+    pub fn user_snippet_items(editor: &Editor, doc: &Document) -> Vec<CompletionItem> {
+        let language = match doc.language_name() {
+            Some(language) => language,
+            None => return Vec::new(),
+        };
+
+        editor
+            .snippets
+            .language_snippets(language)
+            .iter()
+            .flat_map(|(name, snippet)| {
+                let body = snippet.body();
+                snippet.prefix.iter().map(move |prefix| CompletionItem {
+                    label: prefix.clone(),
+                    kind: Some(lsp::CompletionItemKind::SNIPPET),
+                    detail: Some(snippet.description.clone().unwrap_or_else(|| name.clone())),
+                    documentation: Some(lsp::Documentation::MarkupContent(lsp::MarkupContent {
+                        kind: lsp::MarkupKind::Markdown,
+                        value: format!("```{}\n{}\n```", language, body),
+                    })),
+                    filter_text: Some(prefix.clone()),
+                    insert_text: Some(body.clone()),
+                    insert_text_format: Some(lsp::InsertTextFormat::SNIPPET),
+                    data: Some(USER_SNIPPET_DATA.into()),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    /// Completion items for the choices of the active snippet's current tabstop.
+    pub fn snippet_choice_items(choices: &[String]) -> Vec<CompletionItem> {
+        choices
+            .iter()
+            .map(|choice| CompletionItem {
+                label: choice.clone(),
+                kind: Some(lsp::CompletionItemKind::VALUE),
+                data: Some(SNIPPET_CHOICE_DATA.into()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
     fn resolve_completion_item(
         doc: &Document,
         completion_item: lsp::CompletionItem,
     ) -> Option<CompletionItem> {
-        let language_server = doc.language_server()?;
+        if is_user_snippet(&completion_item) || is_snippet_choice(&completion_item) {
+            return None;
+        }
+
+        let language_server = doc
+            .language_servers_with_feature(LanguageServerFeature::Completion)
+            .next()?;
 
         let future = language_server.resolve_completion_item(completion_item)?;
         let response = helix_lsp::block_on(future);
@@ -329,11 +461,20 @@ pub fn ensure_item_resolved(&mut self, cx: &mut commands::Context) -> bool {
         // > The returned completion item should have the documentation property filled in.
         // https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocument_completion
         let current_item = match self.popup.contents().selection() {
-            Some(item) if item.documentation.is_none() => item.clone(),
+            Some(item)
+                if item.documentation.is_none()
+                    && !is_user_snippet(item)
+                    && !is_snippet_choice(item) =>
+            {
+                item.clone()
+            }
             _ => return false,
         };
 
-        let language_server = match doc!(cx.editor).language_server() {
+        let language_server = match doc!(cx.editor)
+            .language_servers_with_feature(LanguageServerFeature::Completion)
+            .next()
+        {
             Some(language_server) => language_server,
             None => return false,
         };
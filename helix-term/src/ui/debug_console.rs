@@ -0,0 +1,289 @@
+use crate::{
+    commands,
+    compositor::{Component, Context, Event, EventResult},
+    ctrl, key,
+    ui::Panel,
+};
+use helix_core::{
+    unicode::{segmentation::GraphemeCursor, width::UnicodeWidthStr},
+    Position,
+};
+use helix_view::{
+    debug_console::OutputKind,
+    graphics::{CursorKind, Modifier, Rect},
+    input::{KeyEvent, MouseButton, MouseEvent, MouseEventKind},
+    keyboard::KeyCode,
+    Editor,
+};
+use tui::buffer::Buffer as Surface;
+
+const PROMPT: &str = "> ";
+
+/// A panel below the editor views showing the output of the debug session, with an
+/// input line to evaluate expressions in the current stack frame.
+pub struct DebugConsoleView {
+    input: String,
+    /// Byte offset of the cursor in `input`.
+    cursor: usize,
+    /// The history entry currently displayed in the input line.
+    history_pos: Option<usize>,
+    /// Number of lines scrolled up from the end of the output.
+    scroll: usize,
+    focused: bool,
+    /// The area the console was last rendered to, used to handle mouse events.
+    area: Rect,
+}
+
+impl Default for DebugConsoleView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugConsoleView {
+    pub fn new() -> Self {
+        Self {
+            input: String::new(),
+            cursor: 0,
+            history_pos: None,
+            scroll: 0,
+            focused: true,
+            area: Rect::default(),
+        }
+    }
+
+    /// Number of output lines that fit between the title and the input line.
+    fn page_size(&self) -> usize {
+        self.area.height.saturating_sub(2) as usize
+    }
+
+    fn scroll_up(&mut self, count: usize, editor: &Editor) {
+        let max = editor
+            .debug_console
+            .lines()
+            .len()
+            .saturating_sub(self.page_size());
+        self.scroll = (self.scroll + count).min(max);
+    }
+
+    fn scroll_down(&mut self, count: usize) {
+        self.scroll = self.scroll.saturating_sub(count);
+    }
+
+    fn prev_boundary(&self) -> usize {
+        let mut cursor = GraphemeCursor::new(self.cursor, self.input.len(), false);
+        match cursor.prev_boundary(&self.input, 0) {
+            Ok(Some(pos)) => pos,
+            _ => self.cursor,
+        }
+    }
+
+    fn next_boundary(&self) -> usize {
+        let mut cursor = GraphemeCursor::new(self.cursor, self.input.len(), false);
+        match cursor.next_boundary(&self.input, 0) {
+            Ok(Some(pos)) => pos,
+            _ => self.cursor,
+        }
+    }
+
+    fn insert_str(&mut self, s: &str) {
+        self.input.insert_str(self.cursor, s);
+        self.cursor += s.len();
+    }
+
+    fn set_input(&mut self, input: String) {
+        self.cursor = input.len();
+        self.input = input;
+    }
+
+    fn history_prev(&mut self, editor: &Editor) {
+        let history = editor.debug_console.history();
+        let pos = match self.history_pos {
+            _ if history.is_empty() => return,
+            None => history.len() - 1,
+            Some(pos) => pos.saturating_sub(1),
+        };
+        self.history_pos = Some(pos);
+        self.set_input(history[pos].clone());
+    }
+
+    fn history_next(&mut self, editor: &Editor) {
+        let history = editor.debug_console.history();
+        match self.history_pos {
+            Some(pos) if pos + 1 < history.len() => {
+                self.history_pos = Some(pos + 1);
+                self.set_input(history[pos + 1].clone());
+            }
+            // moving past the most recent entry returns to an empty line
+            Some(_) => {
+                self.history_pos = None;
+                self.set_input(String::new());
+            }
+            None => (),
+        }
+    }
+
+    fn evaluate(&mut self, cx: &mut Context) {
+        if self.input.trim().is_empty() {
+            return;
+        }
+        let expression = std::mem::take(&mut self.input);
+        self.cursor = 0;
+        self.history_pos = None;
+        self.scroll = 0;
+        commands::dap_evaluate(cx.editor, cx.jobs, expression);
+    }
+
+    fn handle_mouse_event(&mut self, event: &MouseEvent, cx: &mut Context) -> EventResult {
+        let scroll_lines = cx.editor.config().scroll_lines.unsigned_abs();
+        match event.kind {
+            MouseEventKind::ScrollUp => self.scroll_up(scroll_lines, cx.editor),
+            MouseEventKind::ScrollDown => self.scroll_down(scroll_lines),
+            MouseEventKind::Down(MouseButton::Left) => self.focus(),
+            _ => return EventResult::Ignored(None),
+        }
+        EventResult::Consumed(None)
+    }
+}
+
+impl Panel for DebugConsoleView {
+    fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    fn focus(&mut self) {
+        self.focused = true;
+    }
+
+    fn unfocus(&mut self) {
+        self.focused = false;
+    }
+
+    fn area(&self) -> Rect {
+        self.area
+    }
+}
+
+impl Component for DebugConsoleView {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let key_event = match event {
+            Event::Key(event) => *event,
+            Event::Mouse(event) => return self.handle_mouse_event(event, cx),
+            Event::Paste(contents) => {
+                // the input is a single line
+                self.insert_str(&contents.replace(['\r', '\n'], " "));
+                return EventResult::Consumed(None);
+            }
+            _ => return EventResult::Ignored(None),
+        };
+
+        match key_event {
+            key!(Enter) => self.evaluate(cx),
+            key!(Up) | ctrl!('p') => self.history_prev(cx.editor),
+            key!(Down) | ctrl!('n') => self.history_next(cx.editor),
+            key!(Left) | ctrl!('b') => self.cursor = self.prev_boundary(),
+            key!(Right) | ctrl!('f') => self.cursor = self.next_boundary(),
+            key!(Home) | ctrl!('a') => self.cursor = 0,
+            key!(End) | ctrl!('e') => self.cursor = self.input.len(),
+            key!(Backspace) | ctrl!('h') => {
+                let pos = self.prev_boundary();
+                self.input.replace_range(pos..self.cursor, "");
+                self.cursor = pos;
+            }
+            key!(Delete) | ctrl!('d') => {
+                let pos = self.next_boundary();
+                self.input.replace_range(self.cursor..pos, "");
+            }
+            ctrl!('u') => {
+                self.input.replace_range(..self.cursor, "");
+                self.cursor = 0;
+            }
+            ctrl!('k') => self.input.truncate(self.cursor),
+            key!(PageUp) => self.scroll_up(self.page_size() / 2, cx.editor),
+            key!(PageDown) => self.scroll_down(self.page_size() / 2),
+            ctrl!('l') => {
+                cx.editor.debug_console.clear();
+                self.scroll = 0;
+            }
+            key!(Esc) | ctrl!('c') => self.unfocus(),
+            KeyEvent {
+                code: KeyCode::Char(c),
+                ..
+            } => {
+                let mut buf = [0; 4];
+                self.insert_str(c.encode_utf8(&mut buf));
+            }
+            // swallow all other keys so they don't edit the document behind the console
+            _ => (),
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        self.area = area;
+        let theme = &cx.editor.theme;
+        let text_style = theme.get("ui.text");
+        let border_style = theme.get("ui.window");
+
+        surface.clear_with(area, theme.get("ui.background"));
+
+        for x in area.left()..area.right() {
+            surface[(x, area.y)]
+                .set_symbol(tui::symbols::line::HORIZONTAL)
+                .set_style(border_style);
+        }
+        surface.set_stringn(
+            area.x,
+            area.y,
+            " Debug console ",
+            area.width as usize,
+            text_style.add_modifier(Modifier::BOLD),
+        );
+
+        let lines = cx.editor.debug_console.lines();
+        let height = self.page_size();
+        self.scroll = self.scroll.min(lines.len().saturating_sub(height));
+        let end = lines.len() - self.scroll;
+        let start = end.saturating_sub(height);
+        for (row, line) in lines.range(start..end).enumerate() {
+            let style = match line.kind {
+                OutputKind::Stderr | OutputKind::Error => theme.get("error"),
+                OutputKind::Important => theme.get("warning"),
+                OutputKind::Input => theme.get("ui.text.focus"),
+                OutputKind::Console => theme.get("comment"),
+                OutputKind::Stdout | OutputKind::Result => text_style,
+            };
+            surface.set_stringn(
+                area.x,
+                area.y + 1 + row as u16,
+                line.text.replace('\t', "    "),
+                area.width as usize,
+                style,
+            );
+        }
+
+        if area.height > 1 {
+            let y = area.bottom() - 1;
+            surface.set_stringn(area.x, y, PROMPT, area.width as usize, text_style);
+            surface.set_stringn(
+                area.x + PROMPT.len() as u16,
+                y,
+                &self.input,
+                (area.width as usize).saturating_sub(PROMPT.len()),
+                text_style,
+            );
+        }
+    }
+
+    fn cursor(&self, _area: Rect, _editor: &Editor) -> (Option<Position>, CursorKind) {
+        let column = self.area.x as usize
+            + PROMPT.len()
+            + UnicodeWidthStr::width(&self.input[..self.cursor]);
+        let position = Position::new(
+            self.area.bottom().saturating_sub(1) as usize,
+            column.min(self.area.right().saturating_sub(1) as usize),
+        );
+        (Some(position), CursorKind::Block)
+    }
+}
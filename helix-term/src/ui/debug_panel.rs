@@ -0,0 +1,467 @@
+use crate::{
+    commands,
+    compositor::{Component, Compositor, Context, Event, EventResult},
+    ctrl, key, shift,
+    ui::{self, Panel, Prompt, PromptEvent},
+};
+use helix_dap::ThreadId;
+use helix_view::{
+    debug_panel::{DebugPanel, VariableNode},
+    graphics::{Modifier, Rect},
+    handlers::dap::{jump_to_stack_frame, select_thread_id},
+    input::{MouseButton, MouseEvent, MouseEventKind},
+    Editor,
+};
+use tui::buffer::Buffer as Surface;
+
+enum RowKind {
+    Header,
+    /// A hint displayed in place of an empty section.
+    Placeholder,
+    /// A watch expression, scope or variable identified by its path.
+    Node(Vec<String>),
+    /// A frame of the call stack of the current thread, by index.
+    Frame(usize),
+    Thread(ThreadId),
+}
+
+struct Row {
+    kind: RowKind,
+    depth: usize,
+    icon: &'static str,
+    text: String,
+    value: Option<String>,
+    /// Whether the value is an error message.
+    error: bool,
+    /// Whether the row is the active stack frame or the current thread.
+    active: bool,
+}
+
+impl Row {
+    fn header(text: &str) -> Self {
+        Self::text(RowKind::Header, text)
+    }
+
+    fn placeholder(text: &str) -> Self {
+        Self {
+            depth: 1,
+            ..Self::text(RowKind::Placeholder, text)
+        }
+    }
+
+    fn text(kind: RowKind, text: &str) -> Self {
+        Self {
+            kind,
+            depth: 0,
+            icon: "",
+            text: text.to_string(),
+            value: None,
+            error: false,
+            active: false,
+        }
+    }
+}
+
+/// Appends the rows of `nodes` and of the children of the expanded nodes.
+fn push_nodes(rows: &mut Vec<Row>, panel: &DebugPanel, nodes: &[VariableNode], depth: usize) {
+    for node in nodes {
+        let expanded = panel.is_expanded(&node.path);
+        let icon = match node.has_children() {
+            true if expanded => "▾ ",
+            true => "▸ ",
+            false => "  ",
+        };
+        let value = if node.is_scope() {
+            None
+        } else {
+            Some(node.value.clone())
+        };
+        rows.push(Row {
+            kind: RowKind::Node(node.path.clone()),
+            depth,
+            icon,
+            text: node.name.clone(),
+            value,
+            error: node.error,
+            active: false,
+        });
+        if expanded {
+            push_nodes(rows, panel, &node.children, depth + 1);
+        }
+    }
+}
+
+/// The rows of the panel: the watch expressions, the variables of the active stack
+/// frame, the call stack and the threads.
+fn rows(editor: &Editor) -> Vec<Row> {
+    let panel = &editor.debug_panel;
+    let debugger = editor.debugger.as_ref();
+    let mut rows = Vec::new();
+
+    rows.push(Row::header("Watch"));
+    for expression in panel.watches() {
+        match panel.watch(expression) {
+            Some(node) => push_nodes(&mut rows, panel, std::slice::from_ref(node), 1),
+            None => rows.push(Row {
+                depth: 1,
+                icon: "  ",
+                ..Row::text(
+                    RowKind::Node(DebugPanel::watch_path(expression)),
+                    expression,
+                )
+            }),
+        }
+    }
+    if panel.watches().is_empty() {
+        rows.push(Row::placeholder("Press a to add an expression"));
+    }
+
+    rows.push(Row::header("Variables"));
+    match debugger {
+        None => rows.push(Row::placeholder("No debug session")),
+        Some(debugger) if debugger.thread_id.is_none() => rows.push(Row::placeholder("Running")),
+        Some(_) => push_nodes(&mut rows, panel, panel.scopes(), 1),
+    }
+
+    rows.push(Row::header("Call stack"));
+    let frames = debugger.and_then(|debugger| {
+        let frames = debugger.stack_frames.get(&debugger.thread_id?)?;
+        Some((frames, debugger.active_frame))
+    });
+    if let Some((frames, active_frame)) = frames {
+        for (idx, frame) in frames.iter().enumerate() {
+            let location = frame
+                .source
+                .as_ref()
+                .and_then(|source| source.path.as_ref())
+                .and_then(|path| path.file_name())
+                .map(|name| format!("{}:{}", name.to_string_lossy(), frame.line));
+            rows.push(Row {
+                depth: 1,
+                value: location,
+                active: active_frame == Some(idx),
+                ..Row::text(RowKind::Frame(idx), &frame.name)
+            });
+        }
+    }
+
+    rows.push(Row::header("Threads"));
+    if let Some(debugger) = debugger {
+        for thread in &panel.threads {
+            rows.push(Row {
+                depth: 1,
+                value: debugger.thread_states.get(&thread.id).cloned(),
+                active: debugger.thread_id == Some(thread.id),
+                ..Row::text(RowKind::Thread(thread.id), &thread.name)
+            });
+        }
+    }
+
+    rows
+}
+
+/// A sidebar next to the editor views showing the watch expressions, variables,
+/// call stack and threads of the debug session.
+pub struct DebugPanelView {
+    selected: usize,
+    /// Index of the first row displayed.
+    offset: usize,
+    focused: bool,
+    /// The area the panel was last rendered to, used to handle mouse events.
+    area: Rect,
+}
+
+impl Default for DebugPanelView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugPanelView {
+    pub fn new() -> Self {
+        Self {
+            selected: 0,
+            offset: 0,
+            focused: true,
+            area: Rect::default(),
+        }
+    }
+
+    fn page_size(&self) -> usize {
+        self.area.height as usize
+    }
+
+    fn move_up(&mut self, count: usize) {
+        self.selected = self.selected.saturating_sub(count);
+    }
+
+    fn move_down(&mut self, count: usize, editor: &Editor) {
+        let len = rows(editor).len();
+        self.selected = (self.selected + count).min(len.saturating_sub(1));
+    }
+
+    fn selected_kind(&self, editor: &Editor) -> Option<RowKind> {
+        rows(editor)
+            .into_iter()
+            .nth(self.selected)
+            .map(|row| row.kind)
+    }
+
+    /// Expands or collapses the selected variable, or switches to the selected stack
+    /// frame or thread.
+    fn activate_selected(&mut self, editor: &mut Editor) {
+        match self.selected_kind(editor) {
+            Some(RowKind::Node(path)) => {
+                if let Some(debugger) = editor.debugger.as_ref() {
+                    helix_lsp::block_on(editor.debug_panel.toggle_expanded(debugger, &path));
+                }
+            }
+            Some(RowKind::Frame(idx)) => {
+                let debugger = match editor.debugger.as_mut() {
+                    Some(debugger) => debugger,
+                    None => return,
+                };
+                debugger.active_frame = Some(idx);
+                if let Some(frame) = debugger.current_stack_frame().cloned() {
+                    jump_to_stack_frame(editor, &frame);
+                }
+                commands::refresh_debug_panel(editor);
+            }
+            Some(RowKind::Thread(thread_id)) => {
+                helix_lsp::block_on(select_thread_id(editor, thread_id, true));
+                commands::refresh_debug_panel(editor);
+            }
+            _ => (),
+        }
+    }
+
+    /// Collapses the selected variable if it's expanded.
+    fn collapse_selected(&mut self, editor: &mut Editor) {
+        if let Some(RowKind::Node(path)) = self.selected_kind(editor) {
+            if editor.debug_panel.is_expanded(&path) {
+                if let Some(debugger) = editor.debugger.as_ref() {
+                    helix_lsp::block_on(editor.debug_panel.toggle_expanded(debugger, &path));
+                }
+            }
+        }
+    }
+
+    fn selected_watch(&self, editor: &Editor) -> Option<String> {
+        match self.selected_kind(editor)? {
+            RowKind::Node(path) => DebugPanel::watch_expression(&path).map(String::from),
+            _ => None,
+        }
+    }
+
+    fn add_watch_prompt() -> Prompt {
+        Prompt::new(
+            "watch:".into(),
+            None,
+            ui::completers::none,
+            |cx: &mut Context, input: &str, event: PromptEvent| {
+                if event != PromptEvent::Validate || input.trim().is_empty() {
+                    return;
+                }
+                cx.editor.debug_panel.add_watch(input.trim().to_string());
+                commands::refresh_debug_panel(cx.editor);
+            },
+        )
+    }
+
+    fn set_value_prompt(&self, editor: &Editor) -> Option<Prompt> {
+        let path = match self.selected_kind(editor)? {
+            RowKind::Node(path) => path,
+            _ => return None,
+        };
+        let node = editor.debug_panel.node(&path)?;
+        if node.is_scope() || node.error {
+            return None;
+        }
+        let line = node.value.clone();
+        let prompt = Prompt::new(
+            format!("set {}: ", node.name).into(),
+            None,
+            ui::completers::none,
+            move |cx: &mut Context, input: &str, event: PromptEvent| {
+                if event != PromptEvent::Validate {
+                    return;
+                }
+                commands::dap_set_value(cx.editor, cx.jobs, &path, input.to_string());
+            },
+        )
+        .with_line(line, editor);
+        Some(prompt)
+    }
+
+    fn handle_mouse_event(&mut self, event: &MouseEvent, cx: &mut Context) -> EventResult {
+        let MouseEvent { kind, row, .. } = *event;
+        let scroll_lines = cx.editor.config().scroll_lines.unsigned_abs();
+        match kind {
+            MouseEventKind::ScrollUp => self.move_up(scroll_lines),
+            MouseEventKind::ScrollDown => self.move_down(scroll_lines, cx.editor),
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.focus();
+                let idx = self.offset + row.saturating_sub(self.area.y) as usize;
+                if idx < rows(cx.editor).len() {
+                    // clicking the selected row a second time activates it
+                    if idx == self.selected {
+                        self.activate_selected(cx.editor);
+                    } else {
+                        self.selected = idx;
+                    }
+                }
+            }
+            _ => return EventResult::Ignored(None),
+        }
+        EventResult::Consumed(None)
+    }
+}
+
+impl Panel for DebugPanelView {
+    fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    fn focus(&mut self) {
+        self.focused = true;
+    }
+
+    fn unfocus(&mut self) {
+        self.focused = false;
+    }
+
+    fn area(&self) -> Rect {
+        self.area
+    }
+}
+
+impl Component for DebugPanelView {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let key_event = match event {
+            Event::Key(event) => *event,
+            Event::Mouse(event) => return self.handle_mouse_event(event, cx),
+            _ => return EventResult::Ignored(None),
+        };
+
+        let push_prompt = |prompt: Prompt| -> EventResult {
+            EventResult::Consumed(Some(Box::new(|compositor: &mut Compositor, _| {
+                compositor.push(Box::new(prompt))
+            })))
+        };
+
+        match key_event {
+            key!('k') | key!(Up) | shift!(Tab) | ctrl!('p') => self.move_up(1),
+            key!('j') | key!(Down) | key!(Tab) | ctrl!('n') => self.move_down(1, cx.editor),
+            key!(PageUp) | ctrl!('u') => self.move_up(self.page_size() / 2),
+            key!(PageDown) | ctrl!('d') => self.move_down(self.page_size() / 2, cx.editor),
+            key!('g') | key!(Home) => self.selected = 0,
+            key!('G') | key!(End) => self.move_down(usize::MAX / 2, cx.editor),
+            key!('l') | key!(Right) | key!(Enter) => self.activate_selected(cx.editor),
+            key!('h') | key!(Left) => self.collapse_selected(cx.editor),
+            key!('a') => return push_prompt(Self::add_watch_prompt()),
+            key!('d') | key!(Delete) => {
+                if let Some(expression) = self.selected_watch(cx.editor) {
+                    cx.editor.debug_panel.remove_watch(&expression);
+                }
+            }
+            key!('e') => {
+                if let Some(prompt) = self.set_value_prompt(cx.editor) {
+                    return push_prompt(prompt);
+                }
+            }
+            key!('b') => {
+                if let Some(RowKind::Node(path)) = self.selected_kind(cx.editor) {
+                    commands::dap_toggle_data_breakpoint(cx.editor, &path);
+                }
+            }
+            key!('R') => commands::refresh_debug_panel(cx.editor),
+            key!(Esc) | key!('q') | ctrl!('c') => self.unfocus(),
+            // command mode and space mode work as in the editor
+            key!(':') | key!(' ') => return EventResult::Ignored(None),
+            // swallow all other keys so they don't edit the document behind the panel
+            _ => (),
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        self.area = area;
+        let theme = &cx.editor.theme;
+        let text_style = theme.get("ui.text");
+        let value_style = theme.try_get("variable").unwrap_or(text_style);
+        let error_style = theme.get("error");
+        let hint_style = theme.get("comment");
+        let active_style = text_style.patch(theme.get("ui.text.focus"));
+        let selected_style = if self.focused {
+            theme.get("ui.menu.selected")
+        } else {
+            theme.get("ui.text.focus")
+        };
+
+        surface.clear_with(area, theme.get("ui.background"));
+        // leave the first column for the border
+        let inner = area.clip_left(1);
+
+        let rows = rows(cx.editor);
+        self.selected = self.selected.min(rows.len().saturating_sub(1));
+        // keep the selected row in view
+        let height = self.page_size();
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if height > 0 && self.selected >= self.offset + height {
+            self.offset = self.selected + 1 - height;
+        }
+
+        for (row_idx, (idx, row)) in rows
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(height)
+            .enumerate()
+        {
+            let y = inner.y + row_idx as u16;
+            let (mut style, mut value_style) = match row.kind {
+                RowKind::Header => (text_style.add_modifier(Modifier::BOLD), value_style),
+                RowKind::Placeholder => (hint_style, value_style),
+                _ if row.active => (active_style, active_style),
+                _ if row.error => (text_style, error_style),
+                _ => (text_style, value_style),
+            };
+            if idx == self.selected {
+                style = style.patch(selected_style);
+                value_style = value_style.patch(selected_style);
+                surface.set_style(Rect::new(inner.x, y, inner.width, 1), selected_style);
+            }
+
+            let marker = if row.active { "●" } else { " " };
+            let line = format!(
+                "{}{}{}{}",
+                marker,
+                " ".repeat(row.depth * 2),
+                row.icon,
+                row.text
+            );
+            let (x, _) = surface.set_stringn(inner.x, y, line, inner.width as usize, style);
+            if let Some(value) = &row.value {
+                let separator = match row.kind {
+                    RowKind::Node(_) => " = ",
+                    _ => "  ",
+                };
+                let width = inner.right().saturating_sub(x) as usize;
+                let (x, _) = surface.set_stringn(x, y, separator, width, style);
+                let width = inner.right().saturating_sub(x) as usize;
+                // values can span multiple lines
+                let value = value.replace(['\n', '\t'], " ");
+                surface.set_stringn(x, y, value, width, value_style);
+            }
+        }
+
+        let border_style = theme.get("ui.window");
+        for y in area.top()..area.bottom() {
+            surface[(area.x, y)]
+                .set_symbol(tui::symbols::line::VERTICAL)
+                .set_style(border_style);
+        }
+    }
+}
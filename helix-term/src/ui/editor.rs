@@ -4,21 +4,25 @@
     job::{self, Callback},
     key,
     keymap::{KeymapResult, Keymaps},
-    ui::{Completion, ProgressSpinners},
+    ui::{self, Completion, DebugConsoleView, DebugPanelView, Explorer, ProgressSpinners},
 };
 
 use helix_core::{
+    fold::HiddenLines,
     graphemes::{
         ensure_grapheme_boundary_next_byte, next_grapheme_boundary, prev_grapheme_boundary,
     },
+    link,
     movement::Direction,
     syntax::{self, HighlightEvent},
-    unicode::width::UnicodeWidthStr,
-    visual_coords_at_pos, LineEnding, Position, Range, Selection, Transaction,
+    unicode::{segmentation::UnicodeSegmentation, width::UnicodeWidthStr},
+    visual_coords_at_pos,
+    wrap::{soft_wrap_breaks, WrapFormat},
+    LineEnding, Position, Range, Selection, Transaction,
 };
 use helix_view::{
     apply_transaction,
-    document::{Mode, SCRATCH_BUFFER_NAME},
+    document::{CodeLens, DocumentInlayHints, InlayHintKind, Mode, SCRATCH_BUFFER_NAME},
     editor::{CompleteAction, CursorShapeConfig},
     graphics::{Color, CursorKind, Modifier, Rect, Style},
     input::{KeyEvent, MouseButton, MouseEvent, MouseEventKind},
@@ -32,12 +36,31 @@
 use super::lsp::SignatureHelp;
 use super::statusline;
 
+/// A component docked next to the editor views, which receives the key events while
+/// it's focused.
+pub trait Panel: Component {
+    fn is_focused(&self) -> bool;
+    fn focus(&mut self);
+    fn unfocus(&mut self);
+    /// The area the panel was last rendered to.
+    fn area(&self) -> Rect;
+
+    /// Whether the given screen coordinates are within the panel.
+    fn contains(&self, column: u16, row: u16) -> bool {
+        let area = self.area();
+        (area.left()..area.right()).contains(&column) && (area.top()..area.bottom()).contains(&row)
+    }
+}
+
 pub struct EditorView {
     pub keymaps: Keymaps,
     on_next_key: Option<Box<dyn FnOnce(&mut commands::Context, KeyEvent)>>,
     pseudo_pending: Vec<KeyEvent>,
     last_insert: (commands::MappableCommand, Vec<InsertEvent>),
     pub(crate) completion: Option<Completion>,
+    pub(crate) explorer: Option<Explorer>,
+    pub(crate) debug_console: Option<DebugConsoleView>,
+    pub(crate) debug_panel: Option<DebugPanelView>,
     spinners: ProgressSpinners,
 }
 
@@ -62,6 +85,9 @@ pub fn new(keymaps: Keymaps) -> Self {
             pseudo_pending: Vec::new(),
             last_insert: (commands::MappableCommand::normal_mode, Vec::new()),
             completion: None,
+            explorer: None,
+            debug_console: None,
+            debug_panel: None,
             spinners: ProgressSpinners::default(),
         }
     }
@@ -104,13 +130,17 @@ pub fn render_view(
                     == doc.path()
             {
                 let line = frame.line - 1; // convert to 0-indexing
-                if line >= view.offset.row && line < view.offset.row + area.height as usize {
+                if let Some((_, rows)) = view
+                    .visible_line_rows(doc)
+                    .into_iter()
+                    .find(|(visible_line, _)| *visible_line == line)
+                {
                     surface.set_style(
                         Rect::new(
                             area.x,
-                            area.y + (line - view.offset.row) as u16,
+                            area.y + rows.start as u16,
                             area.width,
-                            1,
+                            rows.len() as u16,
                         ),
                         theme.get("ui.highlight"),
                     );
@@ -125,7 +155,13 @@ pub fn render_view(
             Self::highlight_cursorcolumn(doc, view, surface, theme);
         }
 
-        let mut highlights = Self::doc_syntax_highlights(doc, view.offset, inner.height, theme);
+        // closed folds can make more lines visible than there are rows
+        let height = (view.last_line(doc) + 1).saturating_sub(view.offset.row);
+        let mut highlights = Self::doc_syntax_highlights(doc, view.offset, height, theme);
+        let semantic_tokens = Self::doc_semantic_token_highlights(doc, view.offset, height, theme);
+        if !semantic_tokens.is_empty() {
+            highlights = Box::new(syntax::merge(highlights, semantic_tokens));
+        }
         for diagnostic in Self::doc_diagnostics_highlights(doc, theme) {
             // Most of the `diagnostic` Vecs are empty most of the time. Skipping
             // a merge for any empty Vec saves a significant amount of work.
@@ -134,6 +170,14 @@ pub fn render_view(
             }
             highlights = Box::new(syntax::merge(highlights, diagnostic));
         }
+        let references = Self::doc_document_highlights(doc, view, theme);
+        if !references.is_empty() {
+            highlights = Box::new(syntax::merge(highlights, references));
+        }
+        let links = Self::doc_link_highlights(doc, view.offset, height, theme);
+        if !links.is_empty() {
+            highlights = Box::new(syntax::merge(highlights, links));
+        }
         let highlights: Box<dyn Iterator<Item = HighlightEvent>> = if is_focused {
             Box::new(syntax::merge(
                 highlights,
@@ -149,7 +193,22 @@ pub fn render_view(
             Box::new(highlights)
         };
 
-        Self::render_text_highlights(doc, view.offset, inner, surface, theme, highlights, &config);
+        let soft_wrap = view.wrap_format(doc).zip(doc.soft_wrap_indicator());
+        Self::render_text_highlights(
+            doc,
+            view.offset,
+            inner,
+            surface,
+            theme,
+            highlights,
+            &config,
+            soft_wrap
+                .as_ref()
+                .map(|(format, indicator)| (format, indicator.as_str())),
+            doc.inlay_hints(view.id),
+            doc.code_lenses(),
+            &doc.hidden_lines(view.id),
+        );
         Self::render_gutter(editor, doc, view, view.area, surface, theme, is_focused);
         Self::render_rulers(editor, doc, view, inner, surface, theme);
 
@@ -216,7 +275,7 @@ pub fn render_rulers(
     pub fn doc_syntax_highlights<'doc>(
         doc: &'doc Document,
         offset: Position,
-        height: u16,
+        height: usize,
         _theme: &Theme,
     ) -> Box<dyn Iterator<Item = HighlightEvent> + 'doc> {
         let text = doc.text().slice(..);
@@ -225,9 +284,7 @@ pub fn doc_syntax_highlights<'doc>(
             // Calculate viewport byte ranges:
             // Saturating subs to make it inclusive zero indexing.
             let last_line = doc.text().len_lines().saturating_sub(1);
-            let last_visible_line = (offset.row + height as usize)
-                .saturating_sub(1)
-                .min(last_line);
+            let last_visible_line = (offset.row + height).saturating_sub(1).min(last_line);
             let start = text.line_to_byte(offset.row.min(last_line));
             let end = text.line_to_byte(last_visible_line + 1);
 
@@ -265,6 +322,112 @@ pub fn doc_syntax_highlights<'doc>(
         }
     }
 
+    /// Get highlight spans for the semantic tokens of a document on the lines starting at
+    /// `offset`.
+    pub fn doc_semantic_token_highlights(
+        doc: &Document,
+        offset: Position,
+        height: usize,
+        theme: &Theme,
+    ) -> Vec<(usize, std::ops::Range<usize>)> {
+        let semantic_tokens = match doc.semantic_tokens() {
+            Some(semantic_tokens) => semantic_tokens,
+            None => return Vec::new(),
+        };
+        let highlights: Vec<_> = semantic_tokens
+            .scopes
+            .iter()
+            .map(|scope| theme.find_highlight(scope))
+            .collect();
+
+        let text = doc.text();
+        let last_line = text.len_lines().saturating_sub(1);
+        let start = text.line_to_char(offset.row.min(last_line));
+        let end = text.line_to_char((offset.row + height).min(last_line + 1));
+        let spans = &semantic_tokens.spans;
+        let first = spans.partition_point(|(_, range)| range.end <= start);
+        let last = spans.partition_point(|(_, range)| range.start < end);
+
+        spans[first..last.max(first)]
+            .iter()
+            .filter_map(|(scope, range)| Some((highlights[*scope]?, range.clone())))
+            .collect()
+    }
+
+    /// Get highlight spans for the references to the symbol under the cursor, as long as the
+    /// cursor stays on one of them.
+    pub fn doc_document_highlights(
+        doc: &Document,
+        view: &View,
+        theme: &Theme,
+    ) -> Vec<(usize, std::ops::Range<usize>)> {
+        let highlights = doc.document_highlights(view.id);
+        let cursor = doc
+            .selection(view.id)
+            .primary()
+            .cursor(doc.text().slice(..));
+        if !highlights.iter().any(|range| range.contains(&cursor)) {
+            return Vec::new();
+        }
+        let scope = match theme.find_highlight("ui.highlight.reference") {
+            Some(scope) => scope,
+            None => return Vec::new(),
+        };
+
+        // drop any overlapping ranges, the spans must not overlap
+        let mut spans: Vec<(usize, std::ops::Range<usize>)> = Vec::new();
+        for range in highlights {
+            match spans.last() {
+                Some((_, last)) if range.start < last.end => (),
+                _ => spans.push((scope, range.clone())),
+            }
+        }
+        spans
+    }
+
+    /// Get highlight spans for the URLs and the links of the language server in the visible lines.
+    pub fn doc_link_highlights(
+        doc: &Document,
+        offset: Position,
+        height: usize,
+        theme: &Theme,
+    ) -> Vec<(usize, std::ops::Range<usize>)> {
+        let scope = match theme
+            .find_scope_index("ui.text.link")
+            .or_else(|| theme.find_scope_index("markup.link.url"))
+        {
+            Some(scope) => scope,
+            None => return Vec::new(),
+        };
+
+        let text = doc.text();
+        let last_line = text.len_lines().saturating_sub(1);
+        let start = text.line_to_char(offset.row.min(last_line));
+        let end = text.line_to_char((offset.row + height).min(last_line + 1));
+
+        let mut ranges: Vec<_> = link::find_urls(text.slice(start..end))
+            .into_iter()
+            .map(|range| start + range.start..start + range.end)
+            .collect();
+        ranges.extend(
+            doc.document_links()
+                .iter()
+                .map(|link| link.range.clone())
+                .filter(|range| range.start < end && range.end > start),
+        );
+        ranges.sort_unstable_by_key(|range| range.start);
+
+        // the spans must not overlap, e.g. where a server links a URL that is detected as well
+        let mut spans: Vec<(usize, std::ops::Range<usize>)> = Vec::new();
+        for range in ranges {
+            match spans.last_mut() {
+                Some((_, last)) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => spans.push((scope, range)),
+            }
+        }
+        spans
+    }
+
     /// Get highlight spans for document diagnostics
     pub fn doc_diagnostics_highlights(
         doc: &Document,
@@ -409,6 +572,7 @@ pub fn doc_selection_highlights(
         spans
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn render_text_highlights<H: Iterator<Item = HighlightEvent>>(
         doc: &Document,
         offset: Position,
@@ -417,6 +581,10 @@ pub fn render_text_highlights<H: Iterator<Item = HighlightEvent>>(
         theme: &Theme,
         highlights: H,
         config: &helix_view::editor::Config,
+        soft_wrap: Option<(&WrapFormat, &str)>,
+        inlay_hints: Option<&DocumentInlayHints>,
+        code_lenses: Option<&[CodeLens]>,
+        hidden_lines: &HiddenLines,
     ) {
         let whitespace = &config.whitespace;
         use helix_view::editor::WhitespaceRenderValue;
@@ -449,6 +617,58 @@ pub fn render_text_highlights<H: Iterator<Item = HighlightEvent>>(
 
         let text_style = theme.get("ui.text");
         let whitespace_style = theme.get("ui.virtual.whitespace");
+        let wrap_style = theme.try_get("ui.virtual.wrap").unwrap_or(whitespace_style);
+        let inlay_hint_style = theme
+            .try_get("ui.virtual.inlay-hint")
+            .unwrap_or(whitespace_style);
+        let inlay_hint_type_style = theme
+            .try_get_exact("ui.virtual.inlay-hint.type")
+            .unwrap_or(inlay_hint_style);
+        let inlay_hint_parameter_style = theme
+            .try_get_exact("ui.virtual.inlay-hint.parameter")
+            .unwrap_or(inlay_hint_style);
+
+        // inlay hints that haven't been drawn yet, starting at the first visible line
+        let first_visible_char = text.line_to_char(offset.row.min(text.len_lines() - 1));
+        let mut pending_inlay_hints = inlay_hints
+            .map_or(&[][..], |inlay_hints| &inlay_hints.hints)
+            .iter()
+            .skip_while(|hint| hint.char_idx < first_visible_char)
+            .peekable();
+
+        // code lenses that haven't been drawn yet, drawn at the end of their line
+        let code_lens_style = text_style.patch(
+            theme
+                .try_get("ui.virtual.code-lens")
+                .unwrap_or(inlay_hint_style),
+        );
+        let mut pending_code_lenses = code_lenses
+            .unwrap_or_default()
+            .iter()
+            .skip_while(|code_lens| code_lens.char_idx < first_visible_char)
+            .peekable();
+
+        // char ranges of the lines hidden by closed folds, with the number of lines
+        let fold_style =
+            text_style.patch(theme.try_get("ui.virtual.fold").unwrap_or(whitespace_style));
+        let mut hidden_chars = hidden_lines
+            .ranges()
+            .iter()
+            .map(|lines| {
+                let end = lines.end.min(text.len_lines());
+                (
+                    text.line_to_char(lines.start)..text.line_to_char(end),
+                    lines.len(),
+                )
+            })
+            .skip_while(|(range, _)| range.end <= first_visible_char)
+            .peekable();
+
+        // soft wrap breaks (as absolute char indices) of the line being rendered
+        let mut line_breaks: Option<Vec<usize>> = None;
+        let mut next_break = 0;
+        // column at which the text of the current row starts, past the wrap indicator
+        let mut row_start_x = 0;
 
         let mut is_in_indent_area = true;
         let mut last_line_indent_level = 0;
@@ -521,7 +741,94 @@ pub fn render_text_highlights<H: Iterator<Item = HighlightEvent>>(
 
                     use helix_core::graphemes::{grapheme_width, RopeGraphemes};
 
+                    let mut char_idx = start;
                     for grapheme in RopeGraphemes::new(text) {
+                        let pos = char_idx;
+                        char_idx += grapheme.len_chars();
+
+                        // lines hidden by closed folds are skipped along with their inlay hints
+                        while hidden_chars
+                            .next_if(|(range, _)| range.end <= pos)
+                            .is_some()
+                        {}
+                        if hidden_chars
+                            .peek()
+                            .map_or(false, |(range, _)| range.contains(&pos))
+                        {
+                            while pending_inlay_hints
+                                .next_if(|hint| hint.char_idx <= pos)
+                                .is_some()
+                            {}
+                            while pending_code_lenses
+                                .next_if(|code_lens| code_lens.char_idx <= pos)
+                                .is_some()
+                            {}
+                            continue;
+                        }
+
+                        if let Some((format, indicator)) = soft_wrap {
+                            let breaks = line_breaks.get_or_insert_with(|| {
+                                let doc_text = doc.text().slice(..);
+                                let doc_line = doc_text.char_to_line(pos.min(doc_text.len_chars()));
+                                let line_start = doc_text.line_to_char(doc_line);
+                                let virtual_text = inlay_hints
+                                    .map(|inlay_hints| {
+                                        inlay_hints.line_virtual_text(doc_text, doc_line)
+                                    })
+                                    .unwrap_or_default();
+                                soft_wrap_breaks(doc_text.line(doc_line), format, &virtual_text)
+                                    .into_iter()
+                                    .map(|break_idx| line_start + break_idx)
+                                    .collect()
+                            });
+
+                            if breaks.get(next_break) == Some(&pos) {
+                                next_break += 1;
+                                line += 1;
+                                // indent guides are only drawn on the first row of a line
+                                is_in_indent_area = false;
+
+                                if line >= viewport.height {
+                                    break 'outer;
+                                }
+
+                                surface.set_stringn(
+                                    viewport.x,
+                                    viewport.y + line,
+                                    indicator,
+                                    viewport.width as usize,
+                                    wrap_style,
+                                );
+                                visual_x = format.indicator_width;
+                                row_start_x = format.indicator_width;
+                            }
+                        }
+
+                        // inlay hints are drawn in front of the grapheme they precede
+                        while let Some(hint) =
+                            pending_inlay_hints.next_if(|hint| hint.char_idx <= pos)
+                        {
+                            let hint_style = text_style.patch(match hint.kind {
+                                InlayHintKind::Type => inlay_hint_type_style,
+                                InlayHintKind::Parameter => inlay_hint_parameter_style,
+                                InlayHintKind::Other => inlay_hint_style,
+                            });
+                            for hint_grapheme in hint.label.graphemes(true) {
+                                let width = grapheme_width(hint_grapheme);
+                                if visual_x >= offset.col
+                                    && visual_x + width <= viewport.width as usize + offset.col
+                                {
+                                    surface.set_string(
+                                        (viewport.x as usize + visual_x - offset.col) as u16,
+                                        viewport.y + line,
+                                        hint_grapheme,
+                                        hint_style,
+                                    );
+                                }
+                                visual_x += width;
+                            }
+                        }
+
                         let out_of_bounds = offset.col > visual_x
                             || visual_x >= viewport.width as usize + offset.col;
 
@@ -536,11 +843,50 @@ pub fn render_text_highlights<H: Iterator<Item = HighlightEvent>>(
                                 );
                             }
 
-                            draw_indent_guides(last_line_indent_level, line, surface);
+                            let mut draw_annotation = |x: usize, text: &str, style: Style| {
+                                if x >= offset.col && x < viewport.width as usize + offset.col {
+                                    surface.set_stringn(
+                                        (viewport.x as usize + x - offset.col) as u16,
+                                        viewport.y + line,
+                                        text,
+                                        viewport.width as usize + offset.col - x,
+                                        style,
+                                    );
+                                }
+                            };
+                            let mut x = visual_x + 2;
+
+                            // a closed fold is displayed after the end of its first line
+                            if let Some((_, hidden)) = hidden_chars
+                                .peek()
+                                .filter(|(range, _)| range.start == char_idx)
+                            {
+                                let marker = format!("⋯ {} lines", hidden);
+                                draw_annotation(x, &marker, fold_style);
+                                x += marker.width() + 2;
+                            }
+
+                            // followed by the code lenses of the line
+                            let titles: Vec<_> = std::iter::from_fn(|| {
+                                pending_code_lenses
+                                    .next_if(|code_lens| code_lens.char_idx < char_idx)
+                            })
+                            .filter_map(CodeLens::title)
+                            .collect();
+                            if !titles.is_empty() {
+                                draw_annotation(x, &titles.join(" | "), code_lens_style);
+                            }
+
+                            if row_start_x == 0 {
+                                draw_indent_guides(last_line_indent_level, line, surface);
+                            }
 
                             visual_x = 0;
+                            row_start_x = 0;
                             line += 1;
                             is_in_indent_area = true;
+                            line_breaks = None;
+                            next_break = 0;
 
                             // TODO: with proper iter this shouldn't be necessary
                             if line >= viewport.height {
@@ -553,7 +899,8 @@ pub fn render_text_highlights<H: Iterator<Item = HighlightEvent>>(
                             let (display_grapheme, width) = if grapheme == "\t" {
                                 is_whitespace = true;
                                 // make sure we display tab as appropriate amount of spaces
-                                let visual_tab_width = tab_width - (visual_x % tab_width);
+                                let visual_tab_width =
+                                    tab_width - ((visual_x - row_start_x) % tab_width);
                                 let grapheme_tab_width =
                                     helix_core::str_utils::char_to_byte_idx(&tab, visual_tab_width);
 
@@ -716,7 +1063,7 @@ pub fn render_gutter(
         is_focused: bool,
     ) {
         let text = doc.text().slice(..);
-        let last_line = view.last_line(doc);
+        let visible_lines = view.visible_line_rows(doc);
 
         // it's used inside an iterator so the collect isn't needless:
         // https://github.com/rust-lang/rust-clippy/issues/6164
@@ -739,10 +1086,12 @@ pub fn render_gutter(
             let mut gutter = gutter_type.style(editor, doc, view, theme, is_focused);
             let width = gutter_type.width(view, doc);
             text.reserve(width); // ensure there's enough space for the gutter
-            for (i, line) in (view.offset.row..(last_line + 1)).enumerate() {
+                                 // soft wrapped continuation rows are left blank
+            for (line, rows) in &visible_lines {
+                let line = *line;
                 let selected = cursors.contains(&line);
                 let x = viewport.x + offset;
-                let y = viewport.y + i as u16;
+                let y = viewport.y + rows.start as u16;
 
                 let gutter_style = if selected {
                     gutter_selected_style
@@ -827,7 +1176,6 @@ pub fn render_diagnostics(
     /// Apply the highlighting on the lines where a cursor is active
     pub fn highlight_cursorline(doc: &Document, view: &View, surface: &mut Surface, theme: &Theme) {
         let text = doc.text().slice(..);
-        let last_line = view.last_line(doc);
 
         let primary_line = doc.selection(view.id).primary().cursor_line(text);
 
@@ -846,12 +1194,12 @@ pub fn highlight_cursorline(doc: &Document, view: &View, surface: &mut Surface,
         let primary_style = theme.get("ui.cursorline.primary");
         let secondary_style = theme.get("ui.cursorline.secondary");
 
-        for line in view.offset.row..(last_line + 1) {
+        for (line, rows) in view.visible_line_rows(doc) {
             let area = Rect::new(
                 view.area.x,
-                view.area.y + (line - view.offset.row) as u16,
+                view.area.y + rows.start as u16,
                 view.area.width,
-                1,
+                rows.len() as u16,
             );
             if primary_line == line {
                 surface.set_style(area, primary_style);
@@ -889,8 +1237,14 @@ pub fn highlight_cursorcolumn(
         for range in selection.iter() {
             let is_primary = primary == *range;
 
-            let Position { row: _, col } =
-                visual_coords_at_pos(text, range.cursor(text), doc.tab_width());
+            let col = if view.wrap_format(doc).is_some() {
+                match view.screen_coords_at_pos(doc, text, range.cursor(text)) {
+                    Some(Position { col, .. }) => col,
+                    None => continue,
+                }
+            } else {
+                visual_coords_at_pos(text, range.cursor(text), doc.tab_width()).col
+            };
             // if the cursor is horizontally in the view
             if col >= offset && inner_area.width > (col - offset) as u16 {
                 let area = Rect::new(
@@ -1089,6 +1443,36 @@ pub fn set_completion(
         self.completion = Some(completion);
     }
 
+    /// Offers the choices of the active snippet's current tabstop in the completion
+    /// menu, once after the tabstop is selected in insert mode.
+    fn offer_snippet_choices(&mut self, editor: &mut Editor) {
+        if self.completion.is_some() || editor.mode != Mode::Insert {
+            return;
+        }
+        let (view, doc) = current!(editor);
+        let items = match doc
+            .active_snippet
+            .as_mut()
+            .and_then(|snippet| snippet.take_choices())
+        {
+            Some(choices) => Completion::snippet_choice_items(choices),
+            None => return,
+        };
+        let cursor = doc
+            .selection(view.id)
+            .primary()
+            .cursor(doc.text().slice(..));
+        let size = editor.tree.area();
+        self.set_completion(
+            editor,
+            items,
+            helix_lsp::OffsetEncoding::Utf8,
+            cursor,
+            cursor,
+            size,
+        );
+    }
+
     pub fn clear_completion(&mut self, editor: &mut Editor) {
         self.completion = None;
 
@@ -1098,6 +1482,93 @@ pub fn clear_completion(&mut self, editor: &mut Editor) {
         editor.clear_idle_timer(); // don't retrigger
     }
 
+    /// The open panels, in the order they receive events.
+    fn panels(&self) -> Vec<&dyn Panel> {
+        let mut panels: Vec<&dyn Panel> = Vec::new();
+        if let Some(console) = &self.debug_console {
+            panels.push(console);
+        }
+        if let Some(panel) = &self.debug_panel {
+            panels.push(panel);
+        }
+        if let Some(explorer) = &self.explorer {
+            panels.push(explorer);
+        }
+        panels
+    }
+
+    fn panels_mut(&mut self) -> Vec<&mut dyn Panel> {
+        let mut panels: Vec<&mut dyn Panel> = Vec::new();
+        if let Some(console) = &mut self.debug_console {
+            panels.push(console);
+        }
+        if let Some(panel) = &mut self.debug_panel {
+            panels.push(panel);
+        }
+        if let Some(explorer) = &mut self.explorer {
+            panels.push(explorer);
+        }
+        panels
+    }
+
+    /// Moves the focus from the panels back to the editor.
+    fn unfocus_panels(&mut self) {
+        for panel in self.panels_mut() {
+            panel.unfocus();
+        }
+    }
+
+    /// Opens the file explorer sidebar focused on the current document, or moves the
+    /// focus to it if it's already open. Closes it if it's already focused.
+    pub fn toggle_explorer(&mut self, editor: &Editor) {
+        if matches!(&self.explorer, Some(explorer) if explorer.is_focused()) {
+            self.explorer = None;
+            return;
+        }
+        self.unfocus_panels();
+        match &mut self.explorer {
+            Some(explorer) => explorer.focus(),
+            None => {
+                let root = helix_core::find_root(None, &[]);
+                let root = helix_core::path::get_canonicalized_path(&root).unwrap_or(root);
+                let config = &editor.config().file_picker;
+                let mut explorer = Explorer::new(root, config);
+                if let Some(path) = doc!(editor).path() {
+                    explorer.reveal(path, config);
+                }
+                self.explorer = Some(explorer);
+            }
+        }
+    }
+
+    /// Opens the debug console below the editor views, or moves the focus to it if it's
+    /// already open. Closes it if it's already focused.
+    pub fn toggle_debug_console(&mut self) {
+        if matches!(&self.debug_console, Some(console) if console.is_focused()) {
+            self.debug_console = None;
+            return;
+        }
+        self.unfocus_panels();
+        match &mut self.debug_console {
+            Some(console) => console.focus(),
+            None => self.debug_console = Some(DebugConsoleView::new()),
+        }
+    }
+
+    /// Opens the debug panel next to the editor views, or moves the focus to it if it's
+    /// already open. Closes it if it's already focused.
+    pub fn toggle_debug_panel(&mut self) {
+        if matches!(&self.debug_panel, Some(panel) if panel.is_focused()) {
+            self.debug_panel = None;
+            return;
+        }
+        self.unfocus_panels();
+        match &mut self.debug_panel {
+            Some(panel) => panel.focus(),
+            None => self.debug_panel = Some(DebugPanelView::new()),
+        }
+    }
+
     pub fn handle_idle_timeout(&mut self, cx: &mut commands::Context) -> EventResult {
         if let Some(completion) = &mut self.completion {
             return if completion.ensure_item_resolved(cx) {
@@ -1132,6 +1603,16 @@ fn handle_mouse_event(
             ..
         } = *event;
 
+        let terminal_view = cxt.editor.tree.views().find_map(|(view, _focus)| {
+            let area = view.area;
+            let contains = (area.left()..area.right()).contains(&column)
+                && (area.top()..area.bottom()).contains(&row);
+            view.terminal.filter(|_| contains).map(|id| (view.id, id))
+        });
+        if let Some((view_id, id)) = terminal_view {
+            return ui::terminal::handle_mouse_event(cxt.editor, view_id, id, event);
+        }
+
         let pos_and_view = |editor: &Editor, row, column| {
             editor.tree.views().find_map(|(view, _focus)| {
                 view.pos_at_screen_coords(&editor.documents[&view.doc], row, column)
@@ -1175,8 +1656,7 @@ fn handle_mouse_event(
                         None => return EventResult::Ignored(None),
                     };
 
-                    let line = coords.row + view.offset.row;
-                    if line < doc.text().len_lines() {
+                    if let Some(line) = view.line_at_row(doc, coords.row) {
                         commands::dap_toggle_breakpoint_impl(cxt, path, line);
                         return EventResult::Consumed(None);
                     }
@@ -1219,6 +1699,8 @@ fn handle_mouse_event(
                 commands::scroll(cxt, offset, direction);
 
                 cxt.editor.tree.focus = current_view;
+                // request the inlay hints of the newly visible lines once scrolling stops
+                cxt.editor.reset_idle_timer();
 
                 EventResult::Consumed(None)
             }
@@ -1250,8 +1732,10 @@ fn handle_mouse_event(
                     cxt.editor.focus(view_id);
 
                     let (view, doc) = current!(cxt.editor);
-                    let line = coords.row + view.offset.row;
-                    if let Ok(pos) = doc.text().try_line_to_char(line) {
+                    if let Some(pos) = view
+                        .line_at_row(doc, coords.row)
+                        .map(|line| doc.text().line_to_char(line))
+                    {
                         doc.set_selection(view_id, Selection::point(pos));
                         if modifiers == KeyModifiers::ALT {
                             commands::MappableCommand::dap_edit_log.execute(cxt);
@@ -1302,6 +1786,53 @@ fn handle_event(
         event: &Event,
         context: &mut crate::compositor::Context,
     ) -> EventResult {
+        // pending keys (of space mode for example) are completed in the editor
+        let is_pending = !self.keymaps.pending().is_empty();
+        let mut panels = self.panels_mut();
+        let target = match event {
+            Event::Key(_) | Event::Paste(_) if !is_pending => {
+                panels.iter().position(|panel| panel.is_focused())
+            }
+            Event::Mouse(event) => {
+                let target = panels
+                    .iter()
+                    .position(|panel| panel.contains(event.column, event.row));
+                if let MouseEventKind::Down(_) = event.kind {
+                    // clicking into the editor or into another panel moves the focus there
+                    for (idx, panel) in panels.iter_mut().enumerate() {
+                        if Some(idx) != target {
+                            panel.unfocus();
+                        }
+                    }
+                }
+                target
+            }
+            _ => None,
+        };
+        if let Some(idx) = target {
+            match panels[idx].handle_event(event, context) {
+                EventResult::Ignored(_) => (),
+                result => return result,
+            }
+        }
+
+        // keys and pasted text are sent to the terminal of the focused view
+        if let Some(id) = view!(context.editor).terminal {
+            match event {
+                Event::Key(key) if !is_pending => {
+                    ui::terminal::handle_key_event(context.editor, id, *key);
+                    return EventResult::Consumed(None);
+                }
+                Event::Paste(contents) => {
+                    if let Some(terminal) = context.editor.terminals.get_mut(id) {
+                        terminal.paste(contents);
+                    }
+                    return EventResult::Consumed(None);
+                }
+                _ => (),
+            }
+        }
+
         let mut cx = commands::Context {
             editor: context.editor,
             count: None,
@@ -1313,6 +1844,7 @@ fn handle_event(
 
         match event {
             Event::Paste(contents) => {
+                cx.editor.reset_idle_timer();
                 cx.count = cx.editor.count;
                 commands::paste_bracketed_value(&mut cx, contents.clone());
                 cx.editor.count = None;
@@ -1392,6 +1924,8 @@ fn handle_event(
                                     }
                                 }
                             }
+
+                            self.offer_snippet_choices(cx.editor);
                         }
                         mode => self.command_mode(mode, &mut cx, key),
                     }
@@ -1464,6 +1998,27 @@ fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
             editor_area = editor_area.clip_top(1);
         }
 
+        let debug_console_area = self.debug_console.as_ref().map(|_| {
+            let height = editor_area.height / 3;
+            let console_area = editor_area.clip_top(editor_area.height - height);
+            editor_area = editor_area.clip_bottom(height);
+            console_area
+        });
+
+        let debug_panel_area = self.debug_panel.as_ref().map(|_| {
+            let width = editor_area.width / 3;
+            let panel_area = editor_area.clip_left(editor_area.width - width);
+            editor_area = editor_area.clip_right(width);
+            panel_area
+        });
+
+        let explorer_area = self.explorer.as_ref().map(|_| {
+            let width = config.explorer.column_width.min(editor_area.width / 2);
+            let explorer_area = editor_area.with_width(width);
+            editor_area = editor_area.clip_left(width);
+            explorer_area
+        });
+
         // if the terminal size suddenly changed, we need to trigger a resize
         cx.editor.resize(editor_area);
 
@@ -1471,9 +2026,37 @@ fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
             Self::render_bufferline(cx.editor, area.with_height(1), surface);
         }
 
+        // the terminals are resized to their views before rendering them
+        for (view, _) in cx.editor.tree.views() {
+            if let Some(terminal) = view.terminal.and_then(|id| cx.editor.terminals.get_mut(id)) {
+                let screen_area = ui::terminal::screen_area(view);
+                terminal.resize(screen_area.height, screen_area.width);
+            }
+        }
+
         for (view, is_focused) in cx.editor.tree.views() {
-            let doc = cx.editor.document(view.doc).unwrap();
-            self.render_view(cx.editor, doc, view, area, surface, is_focused);
+            match view.terminal.and_then(|id| cx.editor.terminals.get(id)) {
+                Some(terminal) => {
+                    let theme = &cx.editor.theme;
+                    ui::terminal::render_view(theme, terminal, view, area, surface, is_focused);
+                }
+                None => {
+                    let doc = cx.editor.document(view.doc).unwrap();
+                    self.render_view(cx.editor, doc, view, area, surface, is_focused);
+                }
+            }
+        }
+
+        if let (Some(explorer), Some(explorer_area)) = (&mut self.explorer, explorer_area) {
+            explorer.render(explorer_area, surface, cx);
+        }
+
+        if let (Some(console), Some(console_area)) = (&mut self.debug_console, debug_console_area) {
+            console.render(console_area, surface, cx);
+        }
+
+        if let (Some(panel), Some(panel_area)) = (&mut self.debug_panel, debug_panel_area) {
+            panel.render(panel_area, surface, cx);
         }
 
         if config.auto_info {
@@ -1547,7 +2130,14 @@ fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
         }
     }
 
-    fn cursor(&self, _area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
+    fn cursor(&self, area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
+        if let Some(panel) = self.panels().into_iter().find(|panel| panel.is_focused()) {
+            return panel.cursor(area, editor);
+        }
+        let view = view!(editor);
+        if let Some(terminal) = view.terminal.and_then(|id| editor.terminals.get(id)) {
+            return ui::terminal::cursor(terminal, view);
+        }
         match editor.cursor() {
             // All block cursors are drawn manually
             (pos, CursorKind::Block) => (pos, CursorKind::Hidden),
@@ -1565,3 +2155,40 @@ fn canonicalize_key(key: &mut KeyEvent) {
         key.modifiers.remove(KeyModifiers::SHIFT)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arc_swap::ArcSwap;
+    use helix_core::Rope;
+    use std::sync::Arc;
+
+    #[test]
+    fn document_highlights() {
+        let mut doc = Document::from(
+            Rope::from("foo(foo, foobar)"),
+            None,
+            Arc::new(ArcSwap::new(
+                Arc::new(helix_view::editor::Config::default()),
+            )),
+        );
+        let view = View::new(doc.id(), Vec::new());
+        let theme: Theme = toml::from_str(r#""ui.highlight" = "red""#).unwrap();
+        let scope = theme.find_highlight("ui.highlight").unwrap();
+        doc.set_document_highlights(view.id, vec![4..7, 0..3, 9..15, 9..12]);
+
+        // the cursor isn't on a reference
+        doc.set_selection(view.id, Selection::point(7));
+        assert_eq!(
+            EditorView::doc_document_highlights(&doc, &view, &theme),
+            Vec::new()
+        );
+
+        // overlapping references are dropped
+        doc.set_selection(view.id, Selection::point(5));
+        assert_eq!(
+            EditorView::doc_document_highlights(&doc, &view, &theme),
+            vec![(scope, 0..3), (scope, 4..7), (scope, 9..12)]
+        );
+    }
+}
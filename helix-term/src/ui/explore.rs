@@ -0,0 +1,632 @@
+use crate::{
+    commands,
+    compositor::{Component, Compositor, Context, Event, EventResult},
+    ctrl,
+    job::Callback,
+    key, shift,
+    ui::{self, EditorView, Panel, Prompt, PromptEvent},
+};
+use helix_core::path::{get_canonicalized_path, get_relative_path};
+use helix_view::{
+    editor::{Action, FilePickerConfig},
+    graphics::{Modifier, Rect},
+    input::{MouseButton, MouseEvent, MouseEventKind},
+    Editor,
+};
+use tui::buffer::Buffer as Surface;
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A file or directory displayed in the [`Explorer`].
+#[derive(Debug, Clone)]
+struct ExplorerItem {
+    path: PathBuf,
+    is_dir: bool,
+    /// Nesting level below the root directory.
+    depth: usize,
+}
+
+impl ExplorerItem {
+    fn name(&self) -> Cow<'_, str> {
+        self.path.file_name().map_or_else(
+            || self.path.to_string_lossy(),
+            |name| name.to_string_lossy(),
+        )
+    }
+}
+
+/// A tree view of the workspace directory displayed as a sidebar next to the
+/// editor views.
+pub struct Explorer {
+    root: PathBuf,
+    /// The visible files and directories in display order.
+    items: Vec<ExplorerItem>,
+    /// Directories whose contents are displayed.
+    expanded: HashSet<PathBuf>,
+    selected: usize,
+    /// Index of the first item displayed.
+    offset: usize,
+    focused: bool,
+    /// The area the explorer was last rendered to, used to handle mouse events.
+    area: Rect,
+}
+
+impl Explorer {
+    pub fn new(root: PathBuf, config: &FilePickerConfig) -> Self {
+        let mut explorer = Self {
+            root,
+            items: Vec::new(),
+            expanded: HashSet::new(),
+            selected: 0,
+            offset: 0,
+            focused: true,
+            area: Rect::default(),
+        };
+        explorer.refresh(config);
+        explorer
+    }
+
+    /// Re-reads the contents of the root and all expanded directories from disk,
+    /// keeping the selected path selected if it still exists.
+    pub fn refresh(&mut self, config: &FilePickerConfig) {
+        let selected = self.selected_item().map(|item| item.path.clone());
+
+        self.items.clear();
+        self.expanded.retain(|dir| dir.is_dir());
+        let mut stack: Vec<_> = read_dir(&self.root, config)
+            .into_iter()
+            .rev()
+            .map(|(path, is_dir)| ExplorerItem {
+                path,
+                is_dir,
+                depth: 0,
+            })
+            .collect();
+        while let Some(item) = stack.pop() {
+            if item.is_dir && self.expanded.contains(&item.path) {
+                stack.extend(read_dir(&item.path, config).into_iter().rev().map(
+                    |(path, is_dir)| ExplorerItem {
+                        path,
+                        is_dir,
+                        depth: item.depth + 1,
+                    },
+                ));
+            }
+            self.items.push(item);
+        }
+
+        match selected {
+            Some(path) => self.select_path(&path),
+            None => self.selected = 0,
+        }
+    }
+
+    /// Expands the directories containing `path` and selects it.
+    pub fn reveal(&mut self, path: &Path, config: &FilePickerConfig) {
+        let ancestors = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root) && *dir != self.root);
+        self.expanded.extend(ancestors.map(Path::to_path_buf));
+        self.refresh(config);
+        self.select_path(path);
+    }
+
+    fn select_path(&mut self, path: &Path) {
+        if let Some(idx) = self.items.iter().position(|item| item.path == path) {
+            self.selected = idx;
+        } else {
+            self.selected = self.selected.min(self.items.len().saturating_sub(1));
+        }
+    }
+
+    fn selected_item(&self) -> Option<&ExplorerItem> {
+        self.items.get(self.selected)
+    }
+
+    fn move_up(&mut self, count: usize) {
+        self.selected = self.selected.saturating_sub(count);
+    }
+
+    fn move_down(&mut self, count: usize) {
+        self.selected = (self.selected + count).min(self.items.len().saturating_sub(1));
+    }
+
+    /// The number of rows available to display items, below the title row.
+    fn page_size(&self) -> usize {
+        self.area.height.saturating_sub(1) as usize
+    }
+
+    fn toggle_expanded(&mut self, config: &FilePickerConfig) {
+        let path = match self.selected_item() {
+            Some(item) if item.is_dir => item.path.clone(),
+            _ => return,
+        };
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        self.refresh(config);
+    }
+
+    fn expand_selected(&mut self, config: &FilePickerConfig) {
+        if matches!(self.selected_item(), Some(item) if item.is_dir && !self.expanded.contains(&item.path))
+        {
+            self.toggle_expanded(config);
+        }
+    }
+
+    /// Collapses the selected directory, or selects the parent directory if the
+    /// selected item is a file or already collapsed.
+    fn collapse_or_select_parent(&mut self, config: &FilePickerConfig) {
+        let item = match self.selected_item() {
+            Some(item) => item.clone(),
+            None => return,
+        };
+        if item.is_dir && self.expanded.contains(&item.path) {
+            self.toggle_expanded(config);
+        } else if let Some(parent) = item.path.parent() {
+            self.select_path(parent);
+        }
+    }
+
+    /// Opens the selected file, or expands/collapses the selected directory.
+    fn open_selected(&mut self, cx: &mut Context, action: Action) {
+        let item = match self.selected_item() {
+            Some(item) => item.clone(),
+            None => return,
+        };
+        if item.is_dir {
+            self.toggle_expanded(&cx.editor.config().file_picker);
+            return;
+        }
+
+        if let Err(err) = cx.editor.open(&item.path, action) {
+            cx.editor.set_error(format!(
+                "unable to open \"{}\": {}",
+                item.path.display(),
+                err
+            ));
+            return;
+        }
+        self.unfocus();
+    }
+
+    /// The directory new files are created in: the selected directory itself or the
+    /// directory containing the selected file.
+    fn selected_dir(&self) -> PathBuf {
+        match self.selected_item() {
+            Some(item) if item.is_dir => item.path.clone(),
+            Some(item) => item
+                .path
+                .parent()
+                .map_or_else(|| self.root.clone(), Path::to_path_buf),
+            None => self.root.clone(),
+        }
+    }
+
+    fn create_prompt(&self, editor: &Editor) -> Prompt {
+        let mut dir = get_relative_path(&self.selected_dir())
+            .to_string_lossy()
+            .into_owned();
+        if !dir.is_empty() && !dir.ends_with(std::path::MAIN_SEPARATOR) {
+            dir.push(std::path::MAIN_SEPARATOR);
+        }
+
+        Prompt::new(
+            "create (end with / for a directory): ".into(),
+            None,
+            ui::completers::filename,
+            |cx: &mut Context, input: &str, event: PromptEvent| {
+                if event != PromptEvent::Validate {
+                    return;
+                }
+                let path = helix_core::path::expand_tilde(Path::new(input));
+                let result = if input.ends_with('/') || input.ends_with(std::path::MAIN_SEPARATOR) {
+                    fs::create_dir_all(&path)
+                } else {
+                    create_file(&path)
+                };
+                match result {
+                    Ok(()) => refresh_explorer(cx, Some(path)),
+                    Err(err) => cx
+                        .editor
+                        .set_error(format!("unable to create \"{}\": {}", input, err)),
+                }
+            },
+        )
+        .with_line(dir, editor)
+    }
+
+    fn rename_prompt(&self, editor: &Editor) -> Option<Prompt> {
+        let old_path = self.selected_item()?.path.clone();
+        let line = get_relative_path(&old_path).to_string_lossy().into_owned();
+
+        let prompt = Prompt::new(
+            "rename to: ".into(),
+            None,
+            ui::completers::filename,
+            move |cx: &mut Context, input: &str, event: PromptEvent| {
+                if event != PromptEvent::Validate {
+                    return;
+                }
+                let new_path = helix_core::path::expand_tilde(Path::new(input));
+                let new_path = match get_canonicalized_path(&new_path) {
+                    Ok(new_path) => new_path,
+                    Err(err) => {
+                        cx.editor
+                            .set_error(format!("invalid path \"{}\": {}", input, err));
+                        return;
+                    }
+                };
+                let old_path = old_path.clone();
+                commands::move_path(
+                    cx.editor,
+                    cx.jobs,
+                    old_path.clone(),
+                    new_path.clone(),
+                    move |editor, compositor, result| match result {
+                        Ok(()) => reveal_in_explorer(editor, compositor, Some(new_path)),
+                        Err(err) => editor.set_error(format!(
+                            "unable to rename \"{}\": {}",
+                            old_path.display(),
+                            err
+                        )),
+                    },
+                );
+            },
+        )
+        .with_line(line, editor);
+        Some(prompt)
+    }
+
+    fn delete_prompt(&self) -> Option<Prompt> {
+        let item = self.selected_item()?.clone();
+        let prompt = format!(
+            "delete \"{}\"? (y/n): ",
+            get_relative_path(&item.path).display()
+        );
+
+        let prompt = Prompt::new(
+            prompt.into(),
+            None,
+            |_editor: &Editor, _input: &str| Vec::new(),
+            move |cx: &mut Context, input: &str, event: PromptEvent| {
+                if event != PromptEvent::Validate || input != "y" {
+                    return;
+                }
+                let result = if item.is_dir {
+                    fs::remove_dir_all(&item.path)
+                } else {
+                    fs::remove_file(&item.path)
+                };
+                match result {
+                    Ok(()) => refresh_explorer(cx, None),
+                    Err(err) => cx.editor.set_error(format!(
+                        "unable to delete \"{}\": {}",
+                        item.path.display(),
+                        err
+                    )),
+                }
+            },
+        );
+        Some(prompt)
+    }
+
+    fn handle_mouse_event(&mut self, event: &MouseEvent, cx: &mut Context) -> EventResult {
+        let MouseEvent { kind, row, .. } = *event;
+        match kind {
+            MouseEventKind::ScrollUp => {
+                self.move_up(cx.editor.config().scroll_lines.unsigned_abs())
+            }
+            MouseEventKind::ScrollDown => {
+                self.move_down(cx.editor.config().scroll_lines.unsigned_abs())
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.focus();
+                let idx = self.offset + (row.saturating_sub(self.area.y + 1)) as usize;
+                if row > self.area.y && idx < self.items.len() {
+                    // clicking the selected item a second time opens it
+                    if idx == self.selected {
+                        self.open_selected(cx, Action::Replace);
+                    } else {
+                        self.selected = idx;
+                    }
+                }
+            }
+            _ => return EventResult::Ignored(None),
+        }
+        EventResult::Consumed(None)
+    }
+}
+
+impl Panel for Explorer {
+    fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    fn focus(&mut self) {
+        self.focused = true;
+    }
+
+    fn unfocus(&mut self) {
+        self.focused = false;
+    }
+
+    fn area(&self) -> Rect {
+        self.area
+    }
+}
+
+impl Component for Explorer {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let key_event = match event {
+            Event::Key(event) => *event,
+            Event::Mouse(event) => return self.handle_mouse_event(event, cx),
+            _ => return EventResult::Ignored(None),
+        };
+
+        let push_prompt = |prompt: Prompt| -> EventResult {
+            EventResult::Consumed(Some(Box::new(|compositor: &mut Compositor, _| {
+                compositor.push(Box::new(prompt))
+            })))
+        };
+
+        let config = cx.editor.config();
+        match key_event {
+            key!('k') | key!(Up) | shift!(Tab) | ctrl!('p') => self.move_up(1),
+            key!('j') | key!(Down) | key!(Tab) | ctrl!('n') => self.move_down(1),
+            key!(PageUp) | ctrl!('u') => self.move_up(self.page_size() / 2),
+            key!(PageDown) | ctrl!('d') => self.move_down(self.page_size() / 2),
+            key!('g') | key!(Home) => self.selected = 0,
+            key!('G') | key!(End) => self.selected = self.items.len().saturating_sub(1),
+            key!('h') | key!(Left) => self.collapse_or_select_parent(&config.file_picker),
+            key!('l') | key!(Right) => self.expand_selected(&config.file_picker),
+            key!(Enter) => self.open_selected(cx, Action::Replace),
+            ctrl!('s') => self.open_selected(cx, Action::HorizontalSplit),
+            ctrl!('v') => self.open_selected(cx, Action::VerticalSplit),
+            key!('a') => return push_prompt(self.create_prompt(cx.editor)),
+            key!('r') => {
+                if let Some(prompt) = self.rename_prompt(cx.editor) {
+                    return push_prompt(prompt);
+                }
+            }
+            key!('d') => {
+                if let Some(prompt) = self.delete_prompt() {
+                    return push_prompt(prompt);
+                }
+            }
+            key!('R') => self.refresh(&config.file_picker),
+            key!(Esc) | key!('q') | ctrl!('c') => self.unfocus(),
+            // command mode and space mode work as in the editor
+            key!(':') | key!(' ') => return EventResult::Ignored(None),
+            // swallow all other keys so they don't edit the document behind the explorer
+            _ => (),
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        self.area = area;
+        let theme = &cx.editor.theme;
+        let text_style = theme.get("ui.text");
+        let dir_style = theme.try_get("ui.explorer.dir").unwrap_or(text_style);
+        let selected_style = if self.focused {
+            theme.get("ui.menu.selected")
+        } else {
+            theme.get("ui.text.focus")
+        };
+
+        surface.clear_with(area, theme.get("ui.background"));
+        // leave the last column for the border
+        let inner = area.clip_right(1);
+
+        let title = self.root.file_name().map_or_else(
+            || self.root.to_string_lossy(),
+            |name| name.to_string_lossy(),
+        );
+        surface.set_stringn(
+            inner.x,
+            inner.y,
+            format!(" {}", title),
+            inner.width as usize,
+            text_style.add_modifier(Modifier::BOLD),
+        );
+
+        // keep the selected item in view
+        let height = self.page_size();
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if height > 0 && self.selected >= self.offset + height {
+            self.offset = self.selected + 1 - height;
+        }
+
+        for (row, (idx, item)) in self
+            .items
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(height)
+            .enumerate()
+        {
+            let y = inner.y + 1 + row as u16;
+            let icon = match item.is_dir {
+                true if self.expanded.contains(&item.path) => "▾ ",
+                true => "▸ ",
+                false => "  ",
+            };
+            let mut style = if item.is_dir { dir_style } else { text_style };
+            if idx == self.selected {
+                style = style.patch(selected_style);
+                surface.set_style(Rect::new(inner.x, y, inner.width, 1), selected_style);
+            }
+            let suffix = if item.is_dir { "/" } else { "" };
+            let line = format!(
+                "{}{}{}{}",
+                " ".repeat(item.depth * 2 + 1),
+                icon,
+                item.name(),
+                suffix
+            );
+            surface.set_stringn(inner.x, y, line, inner.width as usize, style);
+        }
+
+        let border_style = theme.get("ui.window");
+        for y in area.top()..area.bottom() {
+            surface[(area.right().saturating_sub(1), y)]
+                .set_symbol(tui::symbols::line::VERTICAL)
+                .set_style(border_style);
+        }
+    }
+}
+
+/// Lists the contents of `dir` as `(path, is_dir)` pairs, honoring the file picker's
+/// ignore settings. Directories are listed first, then files, each sorted by name.
+fn read_dir(dir: &Path, config: &FilePickerConfig) -> Vec<(PathBuf, bool)> {
+    use ignore::WalkBuilder;
+
+    let mut entries: Vec<_> = WalkBuilder::new(dir)
+        .hidden(config.hidden)
+        .parents(config.parents)
+        .ignore(config.ignore)
+        .follow_links(config.follow_symlinks)
+        .git_ignore(config.git_ignore)
+        .git_global(config.git_global)
+        .git_exclude(config.git_exclude)
+        .max_depth(Some(1))
+        // We always want to ignore the .git directory, otherwise if
+        // `ignore` is turned off above, we end up with a lot of noise.
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            // skip the directory itself
+            if entry.depth() == 0 {
+                return None;
+            }
+            let is_dir = entry.file_type().map_or(false, |ft| ft.is_dir());
+            Some((entry.into_path(), is_dir))
+        })
+        .collect();
+
+    entries.sort_by(|(a, a_is_dir), (b, b_is_dir)| b_is_dir.cmp(a_is_dir).then_with(|| a.cmp(b)));
+    entries
+}
+
+fn create_file(path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map(|_| ())
+}
+
+/// Refreshes the explorer once the prompt modifying the file system has closed,
+/// optionally selecting `path`.
+fn refresh_explorer(cx: &mut Context, path: Option<PathBuf>) {
+    cx.jobs.callback(async move {
+        let call = move |editor: &mut Editor, compositor: &mut Compositor| {
+            reveal_in_explorer(editor, compositor, path)
+        };
+        Ok(Callback::EditorCompositor(Box::new(call)))
+    });
+}
+
+/// Refreshes the open explorer, selecting `path` if it's given.
+fn reveal_in_explorer(editor: &mut Editor, compositor: &mut Compositor, path: Option<PathBuf>) {
+    let explorer = compositor
+        .find::<EditorView>()
+        .and_then(|editor_view| editor_view.explorer.as_mut());
+    if let Some(explorer) = explorer {
+        let config = &editor.config().file_picker;
+        match path.and_then(|path| get_canonicalized_path(&path).ok()) {
+            Some(path) => explorer.reveal(&path, config),
+            None => explorer.refresh(config),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The displayed items as `(path relative to the root, depth)` pairs.
+    fn items(explorer: &Explorer) -> Vec<(String, usize)> {
+        explorer
+            .items
+            .iter()
+            .map(|item| {
+                let path = item.path.strip_prefix(&explorer.root).unwrap();
+                (path.to_string_lossy().into_owned(), item.depth)
+            })
+            .collect()
+    }
+
+    fn selected(explorer: &Explorer) -> PathBuf {
+        let item = explorer.selected_item().unwrap();
+        item.path
+            .strip_prefix(&explorer.root)
+            .unwrap()
+            .to_path_buf()
+    }
+
+    #[test]
+    fn test_explorer_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        for file in ["a/b/file3", "a/file2", "file1"] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let config = FilePickerConfig::default();
+
+        let mut explorer = Explorer::new(root.clone(), &config);
+        assert_eq!(items(&explorer), [("a".into(), 0), ("file1".into(), 0)]);
+
+        // revealing a file expands the directories containing it
+        explorer.reveal(&root.join("a/b/file3"), &config);
+        assert_eq!(
+            items(&explorer),
+            [
+                ("a".into(), 0),
+                ("a/b".into(), 1),
+                ("a/b/file3".into(), 2),
+                ("a/file2".into(), 1),
+                ("file1".into(), 0),
+            ]
+        );
+        assert_eq!(selected(&explorer), Path::new("a/b/file3"));
+
+        // a file selects its parent, which is then collapsed
+        explorer.collapse_or_select_parent(&config);
+        assert_eq!(selected(&explorer), Path::new("a/b"));
+        explorer.collapse_or_select_parent(&config);
+        assert_eq!(selected(&explorer), Path::new("a/b"));
+        assert_eq!(items(&explorer).len(), 4);
+        explorer.collapse_or_select_parent(&config);
+        assert_eq!(selected(&explorer), Path::new("a"));
+
+        explorer.expand_selected(&config);
+        explorer.move_down(1);
+        explorer.toggle_expanded(&config);
+        assert_eq!(items(&explorer).len(), 5);
+
+        // removed paths disappear, the selection stays in place
+        fs::remove_dir_all(root.join("a/b")).unwrap();
+        explorer.refresh(&config);
+        assert_eq!(
+            items(&explorer),
+            [("a".into(), 0), ("a/file2".into(), 1), ("file1".into(), 0)]
+        );
+        assert_eq!(selected(&explorer), Path::new("a/file2"));
+        assert!(!explorer.expanded.contains(&root.join("a/b")));
+    }
+}
@@ -0,0 +1,509 @@
+use crate::{
+    alt,
+    commands::lsp::{jump_to_location, location_to_file_location},
+    compositor::{Component, Compositor, Context, Event, EventResult},
+    job::Callback,
+    ui::{menu::Item, overlay::Overlay, FilePicker},
+};
+use futures_util::{future::BoxFuture, FutureExt};
+use helix_core::{path, Position};
+use helix_lsp::{lsp, Client, OffsetEncoding};
+use helix_view::{
+    graphics::{CursorKind, Rect},
+    Editor,
+};
+use tui::{buffer::Buffer as Surface, text::Spans};
+
+use std::sync::Arc;
+
+/// The direction a call or type hierarchy is walked in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyDirection {
+    IncomingCalls,
+    OutgoingCalls,
+    Supertypes,
+    Subtypes,
+}
+
+impl HierarchyDirection {
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::IncomingCalls => "incoming calls",
+            Self::OutgoingCalls => "outgoing calls",
+            Self::Supertypes => "supertypes",
+            Self::Subtypes => "subtypes",
+        }
+    }
+}
+
+/// An item of a call or type hierarchy, as returned by the language server.
+#[derive(Debug, Clone)]
+pub enum HierarchyItem {
+    Call(lsp::CallHierarchyItem),
+    Type(lsp::TypeHierarchyItem),
+}
+
+impl HierarchyItem {
+    fn name(&self) -> &str {
+        match self {
+            Self::Call(item) => &item.name,
+            Self::Type(item) => &item.name,
+        }
+    }
+
+    fn detail(&self) -> Option<&str> {
+        match self {
+            Self::Call(item) => item.detail.as_deref(),
+            Self::Type(item) => item.detail.as_deref(),
+        }
+    }
+
+    /// The location of the name of the item.
+    fn location(&self) -> lsp::Location {
+        match self {
+            Self::Call(item) => lsp::Location::new(item.uri.clone(), item.selection_range),
+            Self::Type(item) => lsp::Location::new(item.uri.clone(), item.selection_range),
+        }
+    }
+}
+
+/// An entry of a hierarchy picker: an item along with the location to jump to for it.
+#[derive(Debug, Clone)]
+pub struct HierarchyEntry {
+    pub item: HierarchyItem,
+    /// The first call site for calls, otherwise the name of the item.
+    pub location: lsp::Location,
+}
+
+impl HierarchyEntry {
+    pub fn new(item: HierarchyItem) -> Self {
+        let location = item.location();
+        Self { item, location }
+    }
+
+    /// An entry for a caller, which jumps to where it calls the item.
+    fn incoming_call(call: lsp::CallHierarchyIncomingCall) -> Self {
+        let mut entry = Self::new(HierarchyItem::Call(call.from));
+        if let Some(range) = call.from_ranges.first() {
+            entry.location.range = *range;
+        }
+        entry
+    }
+
+    /// An entry for a callee of the item at `caller`, which jumps to where the item calls it.
+    fn outgoing_call(caller: &lsp::Url, call: lsp::CallHierarchyOutgoingCall) -> Self {
+        let mut entry = Self::new(HierarchyItem::Call(call.to));
+        // the ranges are relative to the caller
+        if let Some(range) = call.from_ranges.first() {
+            entry.location = lsp::Location::new(caller.clone(), *range);
+        }
+        entry
+    }
+}
+
+impl Item for HierarchyEntry {
+    type Data = ();
+
+    fn label(&self, _data: &Self::Data) -> Spans {
+        let mut label = self.item.name().to_string();
+        if let Some(detail) = self.item.detail() {
+            label.push(' ');
+            label.push_str(detail);
+        }
+        let uri = &self.location.uri;
+        let file = match uri.to_file_path() {
+            Ok(path) => path::get_relative_path(&path)
+                .to_string_lossy()
+                .into_owned(),
+            Err(_) => uri.to_string(),
+        };
+        format!(
+            "{} ({}:{})",
+            label,
+            file,
+            self.location.range.start.line + 1
+        )
+        .into()
+    }
+}
+
+/// Requests the entries one level along `direction` from the symbol at `position`.
+pub fn prepare_hierarchy_entries(
+    language_server: Arc<Client>,
+    direction: HierarchyDirection,
+    text_document: lsp::TextDocumentIdentifier,
+    position: lsp::Position,
+) -> Option<BoxFuture<'static, anyhow::Result<Vec<HierarchyEntry>>>> {
+    let prepare: BoxFuture<'static, anyhow::Result<Option<HierarchyItem>>> = match direction {
+        HierarchyDirection::IncomingCalls | HierarchyDirection::OutgoingCalls => {
+            let request = language_server.prepare_call_hierarchy(text_document, position, None)?;
+            async move {
+                let items: Option<Vec<lsp::CallHierarchyItem>> =
+                    serde_json::from_value(request.await?)?;
+                Ok(items.into_iter().flatten().next().map(HierarchyItem::Call))
+            }
+            .boxed()
+        }
+        HierarchyDirection::Supertypes | HierarchyDirection::Subtypes => {
+            let request = language_server.prepare_type_hierarchy(text_document, position, None)?;
+            async move {
+                let items: Option<Vec<lsp::TypeHierarchyItem>> =
+                    serde_json::from_value(request.await?)?;
+                Ok(items.into_iter().flatten().next().map(HierarchyItem::Type))
+            }
+            .boxed()
+        }
+    };
+
+    let future = async move {
+        let item = match prepare.await? {
+            Some(item) => item,
+            None => return Ok(Vec::new()),
+        };
+        match hierarchy_entries(&language_server, direction, item) {
+            Some(future) => future.await,
+            None => Ok(Vec::new()),
+        }
+    };
+    Some(future.boxed())
+}
+
+/// Requests the entries one level further along `direction` from `item`.
+pub fn hierarchy_entries(
+    language_server: &Client,
+    direction: HierarchyDirection,
+    item: HierarchyItem,
+) -> Option<BoxFuture<'static, anyhow::Result<Vec<HierarchyEntry>>>> {
+    let future = match (direction, item) {
+        (HierarchyDirection::IncomingCalls, HierarchyItem::Call(item)) => {
+            let request = language_server.call_hierarchy_incoming_calls(item)?;
+            async move {
+                let json = request.await?;
+                let calls: Option<Vec<lsp::CallHierarchyIncomingCall>> =
+                    serde_json::from_value(json)?;
+                Ok(calls
+                    .into_iter()
+                    .flatten()
+                    .map(HierarchyEntry::incoming_call)
+                    .collect())
+            }
+            .boxed()
+        }
+        (HierarchyDirection::OutgoingCalls, HierarchyItem::Call(item)) => {
+            let caller = item.uri.clone();
+            let request = language_server.call_hierarchy_outgoing_calls(item)?;
+            async move {
+                let json = request.await?;
+                let calls: Option<Vec<lsp::CallHierarchyOutgoingCall>> =
+                    serde_json::from_value(json)?;
+                Ok(calls
+                    .into_iter()
+                    .flatten()
+                    .map(|call| HierarchyEntry::outgoing_call(&caller, call))
+                    .collect())
+            }
+            .boxed()
+        }
+        (HierarchyDirection::Supertypes, HierarchyItem::Type(item)) => {
+            type_entries(language_server.type_hierarchy_supertypes(item)?)
+        }
+        (HierarchyDirection::Subtypes, HierarchyItem::Type(item)) => {
+            type_entries(language_server.type_hierarchy_subtypes(item)?)
+        }
+        _ => return None,
+    };
+    Some(future)
+}
+
+fn type_entries(
+    request: impl std::future::Future<Output = helix_lsp::Result<serde_json::Value>> + Send + 'static,
+) -> BoxFuture<'static, anyhow::Result<Vec<HierarchyEntry>>> {
+    async move {
+        let json = request.await?;
+        let items: Option<Vec<lsp::TypeHierarchyItem>> = serde_json::from_value(json)?;
+        Ok(items
+            .into_iter()
+            .flatten()
+            .map(|item| HierarchyEntry::new(HierarchyItem::Type(item)))
+            .collect())
+    }
+    .boxed()
+}
+
+/// Picker for the callers or callees of a function, or the supertypes or subtypes of a
+/// type. Entries can be expanded to list their own callers, callees, etc.
+pub struct HierarchyPicker {
+    picker: FilePicker<HierarchyEntry>,
+    direction: HierarchyDirection,
+    language_server_id: usize,
+    offset_encoding: OffsetEncoding,
+    /// The entries of the levels expanded before the current one, to go back to.
+    parents: Vec<Vec<HierarchyEntry>>,
+}
+
+impl HierarchyPicker {
+    pub fn new(
+        entries: Vec<HierarchyEntry>,
+        direction: HierarchyDirection,
+        language_server_id: usize,
+        offset_encoding: OffsetEncoding,
+    ) -> Self {
+        Self {
+            picker: Self::picker(entries, offset_encoding),
+            direction,
+            language_server_id,
+            offset_encoding,
+            parents: Vec::new(),
+        }
+    }
+
+    fn picker(
+        entries: Vec<HierarchyEntry>,
+        offset_encoding: OffsetEncoding,
+    ) -> FilePicker<HierarchyEntry> {
+        FilePicker::new(
+            entries,
+            (),
+            move |cx, entry: &HierarchyEntry, action| {
+                jump_to_location(cx.editor, &entry.location, offset_encoding, action)
+            },
+            |_editor, entry| Some(location_to_file_location(&entry.location)),
+        )
+    }
+
+    /// Lists the entries of the selected entry in place of the current ones.
+    fn expand(&mut self, cx: &mut Context) {
+        let item = match self.picker.selection() {
+            Some(entry) => entry.item.clone(),
+            None => return,
+        };
+        let language_server = match cx
+            .editor
+            .language_servers
+            .get_by_id(self.language_server_id)
+        {
+            Some(language_server) => language_server,
+            None => {
+                cx.editor.set_error("Language server is no longer running");
+                return;
+            }
+        };
+        let description = self.direction.description();
+        let future = match hierarchy_entries(language_server, self.direction, item) {
+            Some(future) => future,
+            None => {
+                cx.editor
+                    .set_error(format!("Language server does not support {}", description));
+                return;
+            }
+        };
+
+        cx.jobs.callback(async move {
+            let entries = future.await?;
+            let call = move |editor: &mut Editor, compositor: &mut Compositor| {
+                if entries.is_empty() {
+                    editor.set_status(format!("No {} found", description));
+                    return;
+                }
+                if let Some(overlay) = compositor.find::<Overlay<HierarchyPicker>>() {
+                    overlay.content.push_level(entries);
+                }
+            };
+            Ok(Callback::EditorCompositor(Box::new(call)))
+        });
+    }
+
+    /// Lists `entries` one level below the current entries.
+    fn push_level(&mut self, entries: Vec<HierarchyEntry>) {
+        let picker = Self::picker(entries, self.offset_encoding);
+        let parent = std::mem::replace(&mut self.picker, picker);
+        self.parents.push(parent.options().to_vec());
+    }
+
+    /// Lists the entries of the previous level again.
+    fn collapse(&mut self) {
+        if let Some(entries) = self.parents.pop() {
+            self.picker = Self::picker(entries, self.offset_encoding);
+        }
+    }
+}
+
+impl Component for HierarchyPicker {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let key_event = match event {
+            Event::Key(event) => *event,
+            _ => return self.picker.handle_event(event, cx),
+        };
+
+        match key_event {
+            alt!('l') => self.expand(cx),
+            alt!('h') => self.collapse(),
+            _ => return self.picker.handle_event(event, cx),
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        // the picker may have been replaced since the last resize
+        self.picker.required_size((area.width, area.height));
+        self.picker.render(area, surface, cx)
+    }
+
+    fn cursor(&self, area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
+        self.picker.cursor(area, editor)
+    }
+
+    fn required_size(&mut self, viewport: (u16, u16)) -> Option<(u16, u16)> {
+        self.picker.required_size(viewport)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::job::Jobs;
+    use arc_swap::{access::Map, ArcSwap};
+    use helix_core::syntax::{self, Configuration};
+    use helix_view::theme;
+
+    fn editor() -> Editor {
+        let config = Arc::new(ArcSwap::from_pointee(crate::config::Config::default()));
+        Editor::new(
+            Rect::new(0, 0, 80, 24),
+            Arc::new(theme::Loader::new("", "")),
+            Arc::new(syntax::Loader::new(Configuration {
+                language: Vec::new(),
+            })),
+            Arc::new(Map::new(config, |config: &crate::config::Config| {
+                &config.editor
+            })),
+        )
+    }
+
+    fn url(file: &str) -> lsp::Url {
+        lsp::Url::parse(&format!("file:///src/{}", file)).unwrap()
+    }
+
+    fn range(line: u32) -> lsp::Range {
+        lsp::Range::new(lsp::Position::new(line, 4), lsp::Position::new(line, 8))
+    }
+
+    fn item(name: &str, file: &str, line: u32) -> lsp::CallHierarchyItem {
+        lsp::CallHierarchyItem {
+            name: name.to_string(),
+            kind: lsp::SymbolKind::FUNCTION,
+            tags: None,
+            detail: None,
+            uri: url(file),
+            range: lsp::Range::new(lsp::Position::new(line, 0), lsp::Position::new(line + 5, 1)),
+            selection_range: range(line),
+            data: None,
+        }
+    }
+
+    fn entries(names: &[&str]) -> Vec<HierarchyEntry> {
+        names
+            .iter()
+            .map(|name| HierarchyEntry::new(HierarchyItem::Call(item(name, "lib.rs", 0))))
+            .collect()
+    }
+
+    fn names(hierarchy: &HierarchyPicker) -> Vec<&str> {
+        hierarchy
+            .picker
+            .options()
+            .iter()
+            .map(|entry| entry.item.name())
+            .collect()
+    }
+
+    #[test]
+    fn incoming_call_entry() {
+        let entry = HierarchyEntry::incoming_call(lsp::CallHierarchyIncomingCall {
+            from: item("caller", "caller.rs", 10),
+            from_ranges: vec![range(12), range(14)],
+        });
+        assert_eq!(entry.item.name(), "caller");
+        // the first call in the caller
+        assert_eq!(
+            entry.location,
+            lsp::Location::new(url("caller.rs"), range(12))
+        );
+
+        let entry = HierarchyEntry::incoming_call(lsp::CallHierarchyIncomingCall {
+            from: item("caller", "caller.rs", 10),
+            from_ranges: Vec::new(),
+        });
+        assert_eq!(
+            entry.location,
+            lsp::Location::new(url("caller.rs"), range(10))
+        );
+    }
+
+    #[test]
+    fn outgoing_call_entry() {
+        let caller = url("caller.rs");
+        let entry = HierarchyEntry::outgoing_call(
+            &caller,
+            lsp::CallHierarchyOutgoingCall {
+                to: item("callee", "callee.rs", 20),
+                from_ranges: vec![range(12), range(14)],
+            },
+        );
+        assert_eq!(entry.item.name(), "callee");
+        // the first call of the callee, in the caller
+        assert_eq!(
+            entry.location,
+            lsp::Location::new(caller.clone(), range(12))
+        );
+
+        let entry = HierarchyEntry::outgoing_call(
+            &caller,
+            lsp::CallHierarchyOutgoingCall {
+                to: item("callee", "callee.rs", 20),
+                from_ranges: Vec::new(),
+            },
+        );
+        assert_eq!(
+            entry.location,
+            lsp::Location::new(url("callee.rs"), range(20))
+        );
+    }
+
+    #[tokio::test]
+    async fn expand_and_collapse() {
+        let mut editor = editor();
+        let mut jobs = Jobs::new();
+        let mut cx = Context {
+            editor: &mut editor,
+            scroll: None,
+            jobs: &mut jobs,
+        };
+        let mut hierarchy = HierarchyPicker::new(
+            entries(&["a", "b"]),
+            HierarchyDirection::IncomingCalls,
+            0,
+            OffsetEncoding::Utf8,
+        );
+
+        // entries can't be expanded once the language server is gone
+        hierarchy.handle_event(&Event::Key(alt!('l')), &mut cx);
+        assert!(cx.editor.is_err());
+        assert_eq!(names(&hierarchy), ["a", "b"]);
+
+        // the entries of the selected entry, as alt-l lists them
+        hierarchy.push_level(entries(&["c"]));
+        assert_eq!(names(&hierarchy), ["c"]);
+        hierarchy.push_level(entries(&["d", "e"]));
+        assert_eq!(names(&hierarchy), ["d", "e"]);
+
+        hierarchy.handle_event(&Event::Key(alt!('h')), &mut cx);
+        assert_eq!(names(&hierarchy), ["c"]);
+        hierarchy.handle_event(&Event::Key(alt!('h')), &mut cx);
+        assert_eq!(names(&hierarchy), ["a", "b"]);
+        // the first level stays
+        hierarchy.handle_event(&Event::Key(alt!('h')), &mut cx);
+        assert_eq!(names(&hierarchy), ["a", "b"]);
+    }
+}
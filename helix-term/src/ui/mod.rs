@@ -1,6 +1,11 @@
+mod breakpoints;
 mod completion;
+mod debug_console;
+mod debug_panel;
 pub(crate) mod editor;
+mod explore;
 mod fuzzy_match;
+pub mod hierarchy;
 mod info;
 pub mod lsp;
 mod markdown;
@@ -9,19 +14,26 @@
 mod picker;
 pub mod popup;
 mod prompt;
+mod replace;
 mod spinner;
 mod statusline;
+pub mod terminal;
 mod text;
 
 use crate::compositor::{Component, Compositor};
 use crate::job::{self, Callback};
+pub use breakpoints::{BreakpointItem, BreakpointPicker};
 pub use completion::Completion;
-pub use editor::EditorView;
+pub use debug_console::DebugConsoleView;
+pub use debug_panel::DebugPanelView;
+pub use editor::{EditorView, Panel};
+pub use explore::Explorer;
 pub use markdown::Markdown;
 pub use menu::Menu;
 pub use picker::{FileLocation, FilePicker, Picker};
 pub use popup::Popup;
 pub use prompt::{Prompt, PromptEvent};
+pub use replace::{ReplaceMatch, ReplacePicker};
 pub use spinner::{ProgressSpinners, Spinner};
 pub use text::Text;
 
@@ -238,6 +250,7 @@ pub mod completers {
     use crate::ui::prompt::Completion;
     use fuzzy_matcher::skim::SkimMatcherV2 as Matcher;
     use fuzzy_matcher::FuzzyMatcher;
+    use helix_core::syntax::LanguageServerFeature;
     use helix_view::document::SCRATCH_BUFFER_NAME;
     use helix_view::theme;
     use helix_view::{editor::Config, Editor};
@@ -395,7 +408,10 @@ pub fn lsp_workspace_command(editor: &Editor, input: &str) -> Vec<Completion> {
 
         let (_, doc) = current_ref!(editor);
 
-        let language_server = match doc.language_server() {
+        let language_server = match doc
+            .language_servers_with_feature(LanguageServerFeature::WorkspaceCommand)
+            .next()
+        {
             Some(language_server) => language_server,
             None => {
                 return vec![];
@@ -15,7 +15,7 @@
 use std::{collections::HashMap, io::Read, path::PathBuf};
 
 use crate::ui::{Prompt, PromptEvent};
-use helix_core::{movement::Direction, Position};
+use helix_core::{fold::HiddenLines, movement::Direction, Position};
 use helix_view::{
     editor::Action,
     graphics::{CursorKind, Margin, Modifier, Rect},
@@ -132,6 +132,14 @@ pub fn truncate_start(mut self, truncate_start: bool) -> Self {
         self
     }
 
+    pub fn selection(&self) -> Option<&T> {
+        self.picker.selection()
+    }
+
+    pub fn options(&self) -> &[T] {
+        self.picker.options()
+    }
+
     fn current_file(&self, editor: &Editor) -> Option<FileLocation> {
         self.picker
             .selection()
@@ -174,7 +182,7 @@ fn get_preview<'picker, 'editor>(
                             }
                             _ => {
                                 // TODO: enable syntax highlighting; blocked by async rendering
-                                Document::open(path, None, None)
+                                Document::open(path, None, None, editor.config.clone())
                                     .map(|doc| CachedPreview::Document(Box::new(doc)))
                                     .unwrap_or(CachedPreview::NotFound)
                             }
@@ -280,8 +288,12 @@ fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
 
             let offset = Position::new(first_line, 0);
 
-            let mut highlights =
-                EditorView::doc_syntax_highlights(doc, offset, area.height, &cx.editor.theme);
+            let mut highlights = EditorView::doc_syntax_highlights(
+                doc,
+                offset,
+                area.height as usize,
+                &cx.editor.theme,
+            );
             for spans in EditorView::doc_diagnostics_highlights(doc, &cx.editor.theme) {
                 if spans.is_empty() {
                     continue;
@@ -296,6 +308,10 @@ fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
                 &cx.editor.theme,
                 highlights,
                 &cx.editor.config(),
+                None,
+                None,
+                None,
+                &HiddenLines::default(),
             );
 
             // highlight the line
@@ -536,6 +552,10 @@ pub fn to_end(&mut self) {
         self.cursor = self.matches.len().saturating_sub(1);
     }
 
+    pub fn options(&self) -> &[T] {
+        &self.options
+    }
+
     pub fn selection(&self) -> Option<&T> {
         self.matches
             .get(self.cursor)
@@ -0,0 +1,257 @@
+use crate::{
+    alt,
+    compositor::{Component, Context, Event, EventResult},
+    ctrl,
+    ui::{menu::Item, FilePicker},
+};
+use helix_core::{
+    line_ending::get_line_ending, path::get_relative_path, Change, Position, Rope, Selection,
+    Transaction,
+};
+use helix_view::{
+    align_view, apply_transaction,
+    editor::Action,
+    graphics::{CursorKind, Rect},
+    Align, Editor,
+};
+use tui::{buffer::Buffer as Surface, text::Spans};
+
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// A line matched by a workspace search and what it is replaced with.
+#[derive(Debug)]
+pub struct ReplaceMatch {
+    pub path: PathBuf,
+    /// 0 indexed line
+    pub line_num: usize,
+    /// The line without its line ending.
+    pub line: String,
+    pub replacement: String,
+    pub excluded: Cell<bool>,
+}
+
+impl Item for ReplaceMatch {
+    type Data = ();
+
+    fn label(&self, _data: &Self::Data) -> Spans {
+        let marker = if self.excluded.get() { "[ ]" } else { "[x]" };
+        format!(
+            "{} {}:{}: {}",
+            marker,
+            get_relative_path(&self.path).to_string_lossy(),
+            self.line_num + 1,
+            self.replacement.trim()
+        )
+        .into()
+    }
+}
+
+/// Picker for the results of a workspace search and replace. The matches are grouped
+/// per file and can be excluded before all remaining replacements are applied.
+pub struct ReplacePicker {
+    picker: FilePicker<ReplaceMatch>,
+}
+
+impl ReplacePicker {
+    pub const ID: &'static str = "replace-picker";
+
+    pub fn new(mut matches: Vec<ReplaceMatch>) -> Self {
+        matches.sort_by(|a, b| (&a.path, a.line_num).cmp(&(&b.path, b.line_num)));
+
+        let picker = FilePicker::new(
+            matches,
+            (),
+            |cx, item: &ReplaceMatch, action| {
+                if let Err(e) = cx.editor.open(&item.path, action) {
+                    cx.editor.set_error(format!(
+                        "Failed to open file '{}': {}",
+                        item.path.display(),
+                        e
+                    ));
+                    return;
+                }
+
+                let (view, doc) = current!(cx.editor);
+                let text = doc.text();
+                let line_num = item.line_num.min(text.len_lines().saturating_sub(1));
+                let start = text.line_to_char(line_num);
+                let end = text.line_to_char((line_num + 1).min(text.len_lines()));
+
+                doc.set_selection(view.id, Selection::single(start, end));
+                align_view(doc, view, Align::Center);
+            },
+            |_editor, item| {
+                Some((
+                    item.path.clone().into(),
+                    Some((item.line_num, item.line_num)),
+                ))
+            },
+        );
+
+        Self { picker }
+    }
+}
+
+/// Returns the changes replacing the lines of `matches` in `text`, skipping the lines
+/// that no longer have the content they had when they were searched.
+fn replacement_changes(text: &Rope, matches: &[&ReplaceMatch]) -> Vec<Change> {
+    matches
+        .iter()
+        .filter(|item| item.line_num < text.len_lines())
+        .filter_map(|item| {
+            let line = text.line(item.line_num);
+            let len = line.len_chars() - get_line_ending(&line).map_or(0, |le| le.len_chars());
+            if line.slice(..len) != item.line.as_str() {
+                return None;
+            }
+            let start = text.line_to_char(item.line_num);
+            Some((start, start + len, Some(item.replacement.as_str().into())))
+        })
+        .collect()
+}
+
+/// Replaces the lines of the matches that are not excluded, with one transaction per
+/// document. Lines that changed since the search are skipped.
+pub fn apply_replacements(editor: &mut Editor, matches: &[ReplaceMatch]) {
+    let mut files: BTreeMap<&Path, Vec<&ReplaceMatch>> = BTreeMap::new();
+    for item in matches.iter().filter(|item| !item.excluded.get()) {
+        files.entry(&item.path).or_default().push(item);
+    }
+
+    let (mut replaced, mut skipped, mut changed_files) = (0, 0, 0);
+    for (path, matches) in files {
+        let current_view_id = view!(editor).id;
+        let doc_id = match editor.open(path, Action::Load) {
+            Ok(doc_id) => doc_id,
+            Err(err) => {
+                log::error!("failed to open {}: {}", path.display(), err);
+                skipped += matches.len();
+                continue;
+            }
+        };
+
+        let doc = doc_mut!(editor, &doc_id);
+        let changes = replacement_changes(doc.text(), &matches);
+        skipped += matches.len() - changes.len();
+        if changes.is_empty() {
+            continue;
+        }
+        replaced += changes.len();
+        changed_files += 1;
+
+        // Need to determine a view for apply/append_changes_to_history
+        let selections = doc.selections();
+        let view_id = if selections.contains_key(&current_view_id) {
+            current_view_id
+        } else {
+            selections
+                .keys()
+                .next()
+                .copied()
+                .expect("No view_id available")
+        };
+
+        let transaction = Transaction::change(doc.text(), changes.into_iter());
+        let view = view_mut!(editor, view_id);
+        apply_transaction(&transaction, doc, view);
+        doc.append_changes_to_history(view);
+    }
+
+    let mut status = format!("Replaced {} lines in {} files", replaced, changed_files);
+    if skipped > 0 {
+        status.push_str(&format!(", skipped {} changed lines", skipped));
+    }
+    editor.set_status(status);
+}
+
+impl Component for ReplacePicker {
+    fn handle_event(&mut self, event: &Event, cx: &mut Context) -> EventResult {
+        let key_event = match event {
+            Event::Key(event) => *event,
+            _ => return self.picker.handle_event(event, cx),
+        };
+
+        match key_event {
+            ctrl!('x') => {
+                if let Some(item) = self.picker.selection() {
+                    item.excluded.set(!item.excluded.get());
+                }
+            }
+            alt!('x') => {
+                if let Some(selected) = self.picker.selection() {
+                    let excluded = !selected.excluded.get();
+                    for item in self.picker.options() {
+                        if item.path == selected.path {
+                            item.excluded.set(excluded);
+                        }
+                    }
+                }
+            }
+            alt!('r') => {
+                apply_replacements(cx.editor, self.picker.options());
+                return EventResult::Consumed(Some(Box::new(|compositor, _cx| {
+                    compositor.pop();
+                })));
+            }
+            _ => return self.picker.handle_event(event, cx),
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        self.picker.render(area, surface, cx)
+    }
+
+    fn cursor(&self, area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
+        self.picker.cursor(area, editor)
+    }
+
+    fn required_size(&mut self, viewport: (u16, u16)) -> Option<(u16, u16)> {
+        self.picker.required_size(viewport)
+    }
+
+    fn id(&self) -> Option<&'static str> {
+        Some(Self::ID)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn replace_match(line_num: usize, line: &str, replacement: &str) -> ReplaceMatch {
+        ReplaceMatch {
+            path: PathBuf::from("/file"),
+            line_num,
+            line: line.to_string(),
+            replacement: replacement.to_string(),
+            excluded: Cell::new(false),
+        }
+    }
+
+    #[test]
+    fn test_replacement_changes() {
+        let mut text = Rope::from("foo bar\r\nbaz foo\nunchanged\nfoo");
+        let matches = [
+            replace_match(0, "foo bar", "qux bar"),
+            replace_match(1, "baz foo", "baz qux"),
+            // the line changed since it was searched
+            replace_match(2, "foo unchanged", "qux unchanged"),
+            replace_match(3, "foo", "qux"),
+            // the line doesn't exist anymore
+            replace_match(4, "foo", "qux"),
+        ];
+        let matches: Vec<_> = matches.iter().collect();
+
+        let changes = replacement_changes(&text, &matches);
+        assert_eq!(changes.len(), 3);
+        Transaction::change(&text, changes.into_iter()).apply(&mut text);
+        // line endings are kept
+        assert_eq!(text, "qux bar\r\nbaz qux\nunchanged\nqux");
+    }
+}
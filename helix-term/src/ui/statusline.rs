@@ -199,8 +199,8 @@ fn render_lsp_spinner<F>(context: &mut RenderContext, write: F)
         context,
         context
             .doc
-            .language_server()
-            .and_then(|srv| {
+            .language_servers()
+            .find_map(|srv| {
                 context
                     .spinners
                     .get(srv.id())
@@ -260,7 +260,7 @@ fn render_workspace_diagnostics<F>(context: &mut RenderContext, write: F)
             .diagnostics
             .values()
             .flatten()
-            .fold((0, 0), |mut counts, diag| {
+            .fold((0, 0), |mut counts, (diag, _)| {
                 match diag.severity {
                     Some(DiagnosticSeverity::WARNING) => counts.0 += 1,
                     Some(DiagnosticSeverity::ERROR) | None => counts.1 += 1,
@@ -0,0 +1,237 @@
+//! The embedded terminals, which are shown in views in place of their document. All
+//! keys pressed in a terminal view except the one moving the focus to another view are
+//! sent to the process.
+
+use crate::{compositor::EventResult, ctrl};
+use helix_core::Position;
+use helix_view::{
+    graphics::{Color, CursorKind, Modifier, Rect, Style, UnderlineStyle},
+    input::{KeyEvent, MouseButton, MouseEvent, MouseEventKind},
+    keyboard::{KeyCode, KeyModifiers},
+    terminal::{key_to_bytes, vt100, Terminal, TerminalCommand, TerminalId},
+    Editor, Theme, View, ViewId,
+};
+use tui::buffer::Buffer as Surface;
+
+/// Starts a new terminal running `command` with the configured shell, or the user's
+/// shell if `command` is empty, in a split below the focused view.
+pub fn spawn(editor: &mut Editor, command: &str) -> anyhow::Result<()> {
+    let (args, title) = match command {
+        "" => {
+            let shell = std::env::var("SHELL").unwrap_or_else(|_| "shell".to_string());
+            let title = shell.rsplit('/').next().unwrap_or(&shell).to_string();
+            (Vec::new(), title)
+        }
+        command => {
+            let mut args = editor.config().shell.clone();
+            args.push(command.to_string());
+            (args, command.to_string())
+        }
+    };
+    let command = TerminalCommand {
+        args,
+        ..Default::default()
+    };
+    editor.open_terminal(command, title, true)?;
+    Ok(())
+}
+
+/// Moves the focus from a terminal view to the closest view before it that shows a
+/// document.
+pub fn leave(editor: &mut Editor) {
+    let views: Vec<_> = editor
+        .tree
+        .traverse()
+        .map(|(id, view)| (id, view.terminal.is_some()))
+        .collect();
+    let pos = views
+        .iter()
+        .position(|(id, _)| *id == editor.tree.focus)
+        .unwrap_or(0);
+    let target = (1..views.len())
+        .map(|offset| views[(pos + views.len() - offset) % views.len()])
+        .find(|(_, is_terminal)| !is_terminal);
+    if let Some((id, _)) = target {
+        editor.focus(id);
+    }
+}
+
+/// Handles a key pressed in the focused view, which shows the terminal `id`.
+pub fn handle_key_event(editor: &mut Editor, id: TerminalId, key_event: KeyEvent) {
+    // Ctrl-\ is reported as Ctrl-4 by terminals without extended key reporting
+    if matches!(key_event, ctrl!('\\') | ctrl!('4')) {
+        leave(editor);
+        return;
+    }
+
+    let terminal = match editor.terminals.get_mut(id) {
+        Some(terminal) => terminal,
+        None => return,
+    };
+    let page = terminal.screen().size().0 as usize / 2;
+    match key_event {
+        KeyEvent {
+            code: KeyCode::PageUp,
+            modifiers: KeyModifiers::SHIFT,
+        } => terminal.set_scrollback(terminal.scrollback() + page),
+        KeyEvent {
+            code: KeyCode::PageDown,
+            modifiers: KeyModifiers::SHIFT,
+        } => terminal.set_scrollback(terminal.scrollback().saturating_sub(page)),
+        // any key closes a terminal whose process exited
+        _ if terminal.is_exited() => {
+            let view_id = editor.tree.focus;
+            if editor.tree.views().count() > 1 {
+                editor.close(view_id);
+            } else {
+                // the last view is kept to show its document
+                view_mut!(editor, view_id).terminal = None;
+                editor.terminals.close(id);
+            }
+        }
+        key_event => {
+            if let Some(bytes) = key_to_bytes(key_event, terminal.screen().application_cursor()) {
+                terminal.set_scrollback(0);
+                terminal.write(&bytes);
+            }
+        }
+    }
+}
+
+/// Handles a mouse event over the view `view_id` showing the terminal `id`. Scrolling
+/// moves through the scrollback and clicking moves the focus to the view.
+pub fn handle_mouse_event(
+    editor: &mut Editor,
+    view_id: ViewId,
+    id: TerminalId,
+    event: &MouseEvent,
+) -> EventResult {
+    let scroll_lines = editor.config().scroll_lines.unsigned_abs();
+    let terminal = match editor.terminals.get_mut(id) {
+        Some(terminal) => terminal,
+        None => return EventResult::Ignored(None),
+    };
+    match event.kind {
+        MouseEventKind::ScrollUp => {
+            terminal.set_scrollback(terminal.scrollback() + scroll_lines);
+        }
+        MouseEventKind::ScrollDown => {
+            terminal.set_scrollback(terminal.scrollback().saturating_sub(scroll_lines));
+        }
+        MouseEventKind::Down(MouseButton::Left) => editor.focus(view_id),
+        _ => return EventResult::Ignored(None),
+    }
+    EventResult::Consumed(None)
+}
+
+/// The area of `view` the screen of its terminal is rendered to, above the status line.
+pub fn screen_area(view: &View) -> Rect {
+    view.area.clip_bottom(1)
+}
+
+/// Converts a color of the terminal screen, `None` for the default color.
+fn color(color: vt100::Color) -> Option<Color> {
+    match color {
+        vt100::Color::Default => None,
+        vt100::Color::Idx(idx) => Some(Color::Indexed(idx)),
+        vt100::Color::Rgb(r, g, b) => Some(Color::Rgb(r, g, b)),
+    }
+}
+
+fn cell_style(cell: &vt100::Cell, base: Style) -> Style {
+    let mut style = base;
+    if let Some(fg) = color(cell.fgcolor()) {
+        style = style.fg(fg);
+    }
+    if let Some(bg) = color(cell.bgcolor()) {
+        style = style.bg(bg);
+    }
+    if cell.bold() {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if cell.italic() {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if cell.underline() {
+        style = style.underline_style(UnderlineStyle::Line);
+    }
+    if cell.inverse() {
+        style = style.add_modifier(Modifier::REVERSED);
+    }
+    style
+}
+
+/// Renders `terminal` into the area of `view`, with a status line showing its title.
+pub fn render_view(
+    theme: &Theme,
+    terminal: &Terminal,
+    view: &View,
+    viewport: Rect,
+    surface: &mut Surface,
+    is_focused: bool,
+) {
+    let area = screen_area(view);
+    let base_style = theme.get("ui.background").patch(theme.get("ui.text"));
+    surface.clear_with(area, base_style);
+
+    let screen = terminal.screen();
+    let (rows, cols) = screen.size();
+    for row in 0..rows.min(area.height) {
+        for col in 0..cols.min(area.width) {
+            let cell = match screen.cell(row, col) {
+                Some(cell) if !cell.is_wide_continuation() => cell,
+                _ => continue,
+            };
+            let contents = cell.contents();
+            let symbol = match contents.as_str() {
+                "" => " ",
+                contents => contents,
+            };
+            surface[(area.x + col, area.y + row)]
+                .set_symbol(symbol)
+                .set_style(cell_style(cell, base_style));
+        }
+    }
+
+    // if we're not at the edge of the screen, draw a right border
+    if viewport.right() != view.area.right() {
+        let x = view.area.right();
+        let border_style = theme.get("ui.window");
+        for y in view.area.top()..view.area.bottom() {
+            surface[(x, y)]
+                .set_symbol(tui::symbols::line::VERTICAL)
+                .set_style(border_style);
+        }
+    }
+
+    let statusline_area = view.area.clip_top(view.area.height.saturating_sub(1));
+    let style = if is_focused {
+        theme.get("ui.statusline")
+    } else {
+        theme.get("ui.statusline.inactive")
+    };
+    let status = match &terminal.exit_status {
+        Some(status) => format!(" {} ({})", terminal.title, status),
+        None => format!(" {}", terminal.title),
+    };
+    surface.set_style(statusline_area, style);
+    surface.set_stringn(
+        statusline_area.x,
+        statusline_area.y,
+        &status,
+        statusline_area.width as usize,
+        style,
+    );
+}
+
+/// The cursor of `terminal` shown in `view`.
+pub fn cursor(terminal: &Terminal, view: &View) -> (Option<Position>, CursorKind) {
+    let screen = terminal.screen();
+    if terminal.is_exited() || screen.hide_cursor() || screen.scrollback() > 0 {
+        return (None, CursorKind::Hidden);
+    }
+    let area = screen_area(view);
+    let (row, col) = screen.cursor_position();
+    let position = Position::new((area.y + row) as usize, (area.x + col) as usize);
+    (Some(position), CursorKind::Block)
+}
@@ -311,3 +311,38 @@ async fn test_undo_redo() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_snippet_choices() -> anyhow::Result<()> {
+    use helix_core::snippets::{self, Snippet};
+
+    let mut app = helpers::AppBuilder::new().build()?;
+    let (view, doc) = helix_view::current!(app.editor);
+    let snippet = Snippet::parse("${1:x} = ${2|one,two,three|};");
+    let (transaction, active_snippet) = snippets::expand(
+        doc.text(),
+        doc.selection(view.id),
+        &snippet,
+        0,
+        0,
+        "\t",
+        None,
+    );
+    doc.apply(&transaction, view.id);
+    doc.active_snippet = active_snippet;
+
+    // moving to the choice tabstop offers its options, `<C-n>` twice selects the second
+    test_key_sequence(
+        &mut app,
+        Some("i<tab><C-n><C-n><ret><tab>"),
+        Some(&|app| {
+            let doc = helix_view::doc!(app.editor);
+            assert_eq!(doc.text(), "x = two;\n");
+            assert!(doc.active_snippet.is_none());
+        }),
+        false,
+    )
+    .await?;
+
+    Ok(())
+}
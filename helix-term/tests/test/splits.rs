@@ -188,3 +188,154 @@ async fn test_changes_in_splits_apply_to_all_views() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_session_save_load() -> anyhow::Result<()> {
+    let file1 = tempfile::NamedTempFile::new()?;
+    let file2 = tempfile::NamedTempFile::new()?;
+    let session = tempfile::NamedTempFile::new()?;
+
+    let mut app = helpers::AppBuilder::new()
+        .with_file(file1.path(), None)
+        .build()?;
+
+    test_key_sequences(
+        &mut app,
+        vec![
+            (
+                Some(&format!(
+                    "ihello<esc>%\"ay:vsplit {}<ret>:session<minus>save {}<ret>",
+                    file2.path().to_string_lossy(),
+                    session.path().to_string_lossy()
+                )),
+                Some(&|app| {
+                    helpers::assert_status_not_error(&app.editor);
+                    assert_eq!(2, app.editor.tree.views().count());
+                }),
+            ),
+            (
+                Some(&format!(
+                    "<C-w>oibye<esc>%\"ay:session<minus>load {}<ret>",
+                    session.path().to_string_lossy()
+                )),
+                Some(&|app| {
+                    helpers::assert_status_not_error(&app.editor);
+                    assert_eq!(2, app.editor.tree.views().count());
+                    let view = app.editor.tree.get(app.editor.tree.focus);
+                    let doc = app.editor.document(view.doc).unwrap();
+                    assert_eq!(file2.path(), doc.path().unwrap().as_path());
+                    assert_eq!(Some(&"hello".to_string()), app.editor.registers.first('a'));
+                }),
+            ),
+            (Some(":qa!<ret>"), None),
+        ],
+        true,
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_session_load_missing_file() -> anyhow::Result<()> {
+    use helix_view::session::{Session, SessionLayout, SessionView};
+
+    let dir = tempfile::tempdir()?;
+    let missing = dir.path().join("missing.txt");
+    let file = tempfile::NamedTempFile::new()?;
+    let session_file = tempfile::NamedTempFile::new()?;
+
+    // the missing file and the directory come first, and shouldn't stop the restore
+    Session {
+        cwd: std::env::current_dir()?,
+        buffers: vec![
+            missing.clone(),
+            dir.path().to_path_buf(),
+            file.path().to_path_buf(),
+        ],
+        layout: SessionLayout::View(SessionView {
+            path: Some(file.path().to_path_buf()),
+            selection: vec![(0, 0)],
+            primary: 0,
+            offset: (0, 0),
+            focused: true,
+        }),
+        registers: Default::default(),
+    }
+    .save(session_file.path())?;
+
+    test_key_sequences(
+        &mut helpers::AppBuilder::new().build()?,
+        vec![
+            (
+                Some(&format!(
+                    ":session<minus>load {}<ret>",
+                    session_file.path().to_string_lossy()
+                )),
+                Some(&|app| {
+                    helpers::assert_status_not_error(&app.editor);
+                    let view = app.editor.tree.get(app.editor.tree.focus);
+                    let doc = app.editor.document(view.doc).unwrap();
+                    assert_eq!(file.path(), doc.path().unwrap().as_path());
+                    assert!(app
+                        .editor
+                        .documents()
+                        .all(|doc| doc.path() != Some(&missing)));
+                }),
+            ),
+            (Some(":qa!<ret>"), None),
+        ],
+        true,
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_terminal_split() -> anyhow::Result<()> {
+    let terminal_view = |app: &helix_term::application::Application| {
+        app.editor
+            .tree
+            .views()
+            .find(|(view, _)| view.terminal.is_some())
+            .map(|(view, focused)| (view.id, focused))
+    };
+
+    test_key_sequences(
+        &mut helpers::AppBuilder::new().build()?,
+        vec![
+            (
+                Some(":terminal sleep 10<ret>"),
+                Some(&|app| {
+                    assert_eq!(2, app.editor.tree.views().count());
+                    let (view_id, focused) = terminal_view(app).unwrap();
+                    assert!(focused);
+                    let terminal = helix_view::view!(app.editor, view_id).terminal.unwrap();
+                    assert!(app.editor.terminals.get(terminal).is_some());
+                }),
+            ),
+            (
+                Some("<C-4>"),
+                Some(&|app| {
+                    let (_, focused) = terminal_view(app).unwrap();
+                    assert!(!focused);
+                }),
+            ),
+            (
+                Some("<space>t"),
+                Some(&|app| {
+                    assert_eq!(2, app.editor.tree.views().count());
+                    let (_, focused) = terminal_view(app).unwrap();
+                    assert!(focused);
+                }),
+            ),
+            // all keys but Ctrl-\ are sent to the process
+            (Some("<C-4>:qa!<ret>"), None),
+        ],
+        true,
+    )
+    .await?;
+
+    Ok(())
+}
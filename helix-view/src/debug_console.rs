@@ -0,0 +1,148 @@
+//! The output of the debugged program and of the debug adapter, together with the
+//! expressions evaluated in the debug console.
+
+use std::collections::VecDeque;
+
+/// The number of lines kept in the console, older lines are dropped.
+const MAX_LINES: usize = 10_000;
+/// The number of evaluated expressions kept in the history.
+const MAX_HISTORY: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputKind {
+    /// Messages of the debug adapter.
+    Console,
+    /// Messages of the debug adapter that should stand out.
+    Important,
+    Stdout,
+    Stderr,
+    /// An expression evaluated in the console.
+    Input,
+    /// The result of an evaluated expression.
+    Result,
+    /// A failed evaluation.
+    Error,
+}
+
+impl OutputKind {
+    /// The kind of an output event of the given category, which defaults to `console`.
+    pub fn from_category(category: Option<&str>) -> Self {
+        match category {
+            Some("important") => Self::Important,
+            Some("stdout") => Self::Stdout,
+            Some("stderr") => Self::Stderr,
+            _ => Self::Console,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputLine {
+    pub kind: OutputKind,
+    pub text: String,
+}
+
+#[derive(Debug, Default)]
+pub struct DebugConsole {
+    lines: VecDeque<OutputLine>,
+    /// Whether the last line is waiting for the rest of its text: output events don't
+    /// necessarily end at line breaks.
+    partial: bool,
+    history: Vec<String>,
+}
+
+impl DebugConsole {
+    pub fn lines(&self) -> &VecDeque<OutputLine> {
+        &self.lines
+    }
+
+    /// Appends output to the console, continuing the last line if it wasn't terminated
+    /// and is of the same kind.
+    pub fn push(&mut self, kind: OutputKind, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let (text, complete) = match text.strip_suffix('\n') {
+            Some(text) => (text, true),
+            None => (text, false),
+        };
+
+        let mut lines = text
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line));
+        // `split` yields at least one item
+        let first = lines.next().unwrap_or_default();
+        match self.lines.back_mut() {
+            Some(line) if self.partial && line.kind == kind => line.text.push_str(first),
+            _ => self.push_line(kind, first),
+        }
+        for line in lines {
+            self.push_line(kind, line);
+        }
+        self.partial = !complete;
+    }
+
+    fn push_line(&mut self, kind: OutputKind, text: &str) {
+        if self.lines.len() == MAX_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(OutputLine {
+            kind,
+            text: text.to_string(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.partial = false;
+    }
+
+    /// The evaluated expressions, from the oldest to the most recent.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn push_history(&mut self, expression: String) {
+        // don't repeat the last expression when it's evaluated again
+        if self.history.last() == Some(&expression) {
+            return;
+        }
+        if self.history.len() == MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.history.push(expression);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_push() {
+        let mut console = DebugConsole::default();
+        console.push(OutputKind::Stdout, "Enter a number: ");
+        console.push(OutputKind::Stdout, "42\nfirst\r\nsecond\n");
+        console.push(OutputKind::Stderr, "oops");
+        // output of another kind starts a new line
+        console.push(OutputKind::Stdout, "third\n");
+        console.push(OutputKind::Stdout, "fourth\n");
+
+        let lines: Vec<_> = console
+            .lines()
+            .iter()
+            .map(|line| (line.kind, line.text.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (OutputKind::Stdout, "Enter a number: 42"),
+                (OutputKind::Stdout, "first"),
+                (OutputKind::Stdout, "second"),
+                (OutputKind::Stderr, "oops"),
+                (OutputKind::Stdout, "third"),
+                (OutputKind::Stdout, "fourth"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Watch expressions and the variables of the active stack frame, refreshed whenever
+//! the debugged program stops.
+
+use helix_dap::{self as dap, requests, Client};
+use std::collections::HashSet;
+
+/// The first element of the path of a watch expression.
+const WATCH: &str = "watch";
+/// The first element of the path of a scope.
+const SCOPE: &str = "scope";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableNode {
+    pub name: String,
+    /// The value of the variable, or the error message if `error` is set.
+    pub value: String,
+    pub ty: Option<String>,
+    pub error: bool,
+    /// Reference of the children of the variable, `0` if it has none.
+    pub variables_reference: usize,
+    /// Reference of the scope or variable this variable was fetched from, `0` for
+    /// watch expressions and scopes. Used to set the value of the variable.
+    pub container_reference: usize,
+    pub evaluate_name: Option<String>,
+    /// The children of expanded variables.
+    pub children: Vec<VariableNode>,
+    /// The names from the root to this node, which identify it across stops since
+    /// variable references are only valid while the program is stopped.
+    pub path: Vec<String>,
+}
+
+impl VariableNode {
+    fn new(variable: dap::Variable, container_reference: usize, parent: &[String]) -> Self {
+        let mut path = parent.to_vec();
+        path.push(variable.name.clone());
+        Self {
+            name: variable.name,
+            value: variable.value,
+            ty: variable.ty,
+            error: false,
+            variables_reference: variable.variables_reference,
+            container_reference,
+            evaluate_name: variable.evaluate_name,
+            children: Vec::new(),
+            path,
+        }
+    }
+
+    fn scope(scope: dap::Scope) -> Self {
+        Self {
+            path: vec![SCOPE.to_string(), scope.name.clone()],
+            name: scope.name,
+            value: String::new(),
+            ty: None,
+            error: false,
+            variables_reference: scope.variables_reference,
+            container_reference: 0,
+            evaluate_name: None,
+            children: Vec::new(),
+        }
+    }
+
+    fn watch(expression: &str, response: dap::Result<requests::EvaluateResponse>) -> Self {
+        let (value, ty, error, variables_reference) = match response {
+            Ok(response) => (
+                response.result,
+                response.ty,
+                false,
+                response.variables_reference,
+            ),
+            Err(err) => (err.to_string(), None, true, 0),
+        };
+        Self {
+            name: expression.to_string(),
+            value,
+            ty,
+            error,
+            variables_reference,
+            container_reference: 0,
+            evaluate_name: Some(expression.to_string()),
+            children: Vec::new(),
+            path: DebugPanel::watch_path(expression),
+        }
+    }
+
+    pub fn is_scope(&self) -> bool {
+        self.path.len() == 2 && self.path[0] == SCOPE
+    }
+
+    pub fn has_children(&self) -> bool {
+        self.variables_reference > 0
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DebugPanel {
+    watches: Vec<String>,
+    watch_nodes: Vec<VariableNode>,
+    scopes: Vec<VariableNode>,
+    pub threads: Vec<dap::Thread>,
+    /// The paths of the expanded nodes.
+    expanded: HashSet<Vec<String>>,
+}
+
+impl DebugPanel {
+    /// The watched expressions, which are kept between debug sessions.
+    pub fn watches(&self) -> &[String] {
+        &self.watches
+    }
+
+    /// The evaluated watch expression, if it was evaluated in the current stop.
+    pub fn watch(&self, expression: &str) -> Option<&VariableNode> {
+        self.watch_nodes.iter().find(|node| node.name == expression)
+    }
+
+    /// The path of the node of a watch expression.
+    pub fn watch_path(expression: &str) -> Vec<String> {
+        vec![WATCH.to_string(), expression.to_string()]
+    }
+
+    /// The expression of the watch at `path`, if the path is the root of a watch.
+    pub fn watch_expression(path: &[String]) -> Option<&str> {
+        match path {
+            [root, expression] if root == WATCH => Some(expression),
+            _ => None,
+        }
+    }
+
+    pub fn add_watch(&mut self, expression: String) {
+        if !self.watches.contains(&expression) {
+            self.watches.push(expression);
+        }
+    }
+
+    pub fn remove_watch(&mut self, expression: &str) {
+        self.watches.retain(|watch| watch != expression);
+        self.watch_nodes.retain(|node| node.name != expression);
+    }
+
+    /// The scopes of the active stack frame.
+    pub fn scopes(&self) -> &[VariableNode] {
+        &self.scopes
+    }
+
+    pub fn is_expanded(&self, path: &[String]) -> bool {
+        self.expanded.contains(path)
+    }
+
+    pub fn node(&self, path: &[String]) -> Option<&VariableNode> {
+        let (root, rest) = path.split_first()?;
+        let mut nodes = match root.as_str() {
+            WATCH => &self.watch_nodes,
+            _ => &self.scopes,
+        };
+        let mut node = None;
+        for name in rest {
+            let next = nodes.iter().find(|node| &node.name == name)?;
+            nodes = &next.children;
+            node = Some(next);
+        }
+        node
+    }
+
+    fn node_mut(&mut self, path: &[String]) -> Option<&mut VariableNode> {
+        let (root, rest) = path.split_first()?;
+        let mut nodes = match root.as_str() {
+            WATCH => &mut self.watch_nodes,
+            _ => &mut self.scopes,
+        };
+        let (last, parents) = rest.split_last()?;
+        for name in parents {
+            nodes = &mut nodes.iter_mut().find(|node| &node.name == name)?.children;
+        }
+        nodes.iter_mut().find(|node| &node.name == last)
+    }
+
+    /// Drops the state of the previous debug session, keeping the watch expressions.
+    pub fn clear(&mut self) {
+        self.watch_nodes.clear();
+        self.scopes.clear();
+        self.threads.clear();
+    }
+
+    /// Re-evaluates the watch expressions and fetches the threads and the variables of
+    /// the active stack frame, including the children of the expanded variables.
+    pub async fn refresh(&mut self, debugger: &Client) {
+        if let Ok(response) = debugger.request::<requests::Threads>(()).await {
+            self.threads = response.threads;
+        }
+
+        let frame_id = debugger.current_stack_frame().map(|frame| frame.id);
+
+        let mut watch_nodes = Vec::with_capacity(self.watches.len());
+        for expression in &self.watches {
+            let call = debugger.eval(expression.clone(), frame_id, Some("watch".to_string()));
+            let response = match call.await {
+                Ok(json) => serde_json::from_value(json).map_err(dap::Error::from),
+                Err(err) => Err(err),
+            };
+            watch_nodes.push(VariableNode::watch(expression, response));
+        }
+        self.watch_nodes = watch_nodes;
+
+        self.scopes = match frame_id {
+            Some(frame_id) => debugger
+                .scopes(frame_id)
+                .await
+                .map(|scopes| scopes.into_iter().map(VariableNode::scope).collect())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+        // show the variables of the innermost scope unless another scope was expanded
+        if !self.expanded.iter().any(|path| path[0] == SCOPE) {
+            if let Some(scope) = self.scopes.first() {
+                self.expanded.insert(scope.path.clone());
+            }
+        }
+
+        let roots = self
+            .watch_nodes
+            .iter()
+            .chain(&self.scopes)
+            .map(|node| node.path.clone())
+            .collect();
+        self.fetch_children(debugger, roots).await;
+    }
+
+    /// Expands the node at `path` or collapses it if it's expanded.
+    pub async fn toggle_expanded(&mut self, debugger: &Client, path: &[String]) {
+        if self.expanded.remove(path) {
+            if let Some(node) = self.node_mut(path) {
+                node.children.clear();
+            }
+        } else {
+            self.expanded.insert(path.to_vec());
+            self.fetch_children(debugger, vec![path.to_vec()]).await;
+        }
+    }
+
+    /// Fetches the children of the expanded nodes at `paths` and of their expanded
+    /// descendants.
+    async fn fetch_children(&mut self, debugger: &Client, mut paths: Vec<Vec<String>>) {
+        while let Some(path) = paths.pop() {
+            if !self.expanded.contains(&path) {
+                continue;
+            }
+            let reference = match self.node(&path) {
+                Some(node) if node.has_children() => node.variables_reference,
+                _ => continue,
+            };
+            let children: Vec<_> = match debugger.variables(reference).await {
+                Ok(variables) => variables
+                    .into_iter()
+                    .map(|variable| VariableNode::new(variable, reference, &path))
+                    .collect(),
+                Err(_) => continue,
+            };
+            paths.extend(children.iter().map(|child| child.path.clone()));
+            if let Some(node) = self.node_mut(&path) {
+                node.children = children;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_watches() {
+        let mut panel = DebugPanel::default();
+        panel.add_watch("a + b".to_string());
+        panel.add_watch("c".to_string());
+        panel.add_watch("a + b".to_string());
+        assert_eq!(panel.watches(), ["a + b", "c"]);
+
+        let path = DebugPanel::watch_path("c");
+        assert_eq!(DebugPanel::watch_expression(&path), Some("c"));
+        let scope = vec![SCOPE.to_string(), "Locals".to_string()];
+        assert_eq!(DebugPanel::watch_expression(&scope), None);
+
+        panel.remove_watch("a + b");
+        assert_eq!(panel.watches(), ["c"]);
+    }
+}
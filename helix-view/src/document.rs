@@ -16,17 +16,21 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
+use arc_swap::access::DynAccess;
 use helix_core::{
     encoding,
+    fold::{self, Fold, Folds, HiddenLines},
     history::{History, State, UndoKind},
     indent::{auto_detect_indent_style, IndentStyle},
     line_ending::auto_detect_line_ending,
-    syntax::{self, LanguageConfiguration},
-    ChangeSet, Diagnostic, LineEnding, Rope, RopeBuilder, Selection, Syntax, Transaction,
-    DEFAULT_LINE_ENDING,
+    snippets::ActiveSnippet,
+    syntax::{self, LanguageConfiguration, LanguageServerFeature},
+    unicode::width::UnicodeWidthStr,
+    Assoc, ChangeSet, Diagnostic, LineEnding, Rope, RopeBuilder, RopeSlice, Selection, Syntax,
+    Transaction, DEFAULT_LINE_ENDING,
 };
 
-use crate::editor::RedrawHandle;
+use crate::editor::{Config, RedrawHandle};
 use crate::{apply_transaction, DocumentId, Editor, View, ViewId};
 
 /// 8kB of buffer space for encoding and decoding `Rope`s.
@@ -98,6 +102,149 @@ pub struct DocumentSavedEvent {
 pub type DocumentSavedEventResult = Result<DocumentSavedEvent, anyhow::Error>;
 pub type DocumentSavedEventFuture = BoxFuture<'static, DocumentSavedEventResult>;
 
+/// The kind of an [`InlayHint`], used to pick its theme scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InlayHintKind {
+    Type,
+    Parameter,
+    Other,
+}
+
+/// A label displayed as virtual text in front of the grapheme at `char_idx`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlayHint {
+    pub char_idx: usize,
+    /// The text displayed, including any padding requested by the language server.
+    pub label: String,
+    pub kind: InlayHintKind,
+}
+
+/// The inlay hints of a document for a single view.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentInlayHints {
+    /// The document lines the hints were requested for.
+    pub lines: std::ops::Range<usize>,
+    /// The hints, sorted by `char_idx`.
+    pub hints: Vec<InlayHint>,
+}
+
+impl DocumentInlayHints {
+    /// Returns the hints displayed on the given line.
+    pub fn line_hints(&self, text: RopeSlice, line: usize) -> &[InlayHint] {
+        let line_start = text.line_to_char(line);
+        let line_end = if line + 1 < text.len_lines() {
+            text.line_to_char(line + 1)
+        } else {
+            // hints at the end of the last line
+            text.len_chars() + 1
+        };
+        let start = self
+            .hints
+            .partition_point(|hint| hint.char_idx < line_start);
+        let end = self.hints.partition_point(|hint| hint.char_idx < line_end);
+        &self.hints[start..end]
+    }
+
+    /// Returns the virtual text displayed on the given line as pairs of line-relative
+    /// char indices and widths, see [`helix_core::wrap::VirtualText`].
+    pub fn line_virtual_text(&self, text: RopeSlice, line: usize) -> Vec<(usize, usize)> {
+        let line_start = text.line_to_char(line);
+        self.line_hints(text, line)
+            .iter()
+            .map(|hint| (hint.char_idx - line_start, hint.label.width()))
+            .collect()
+    }
+}
+
+/// The semantic tokens of a document, highlighted on top of the syntax highlights.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentSemanticTokens {
+    /// The language server the tokens were received from.
+    pub language_server_id: usize,
+    /// Identifies the result for requesting only the changes to it.
+    pub result_id: Option<String>,
+    /// The tokens as received from the language server, needed to apply deltas.
+    pub data: Vec<helix_lsp::lsp::SemanticToken>,
+    /// The theme scopes of the highlighted tokens.
+    pub scopes: Vec<String>,
+    /// Sorted, non-overlapping char ranges paired with an index into `scopes`.
+    pub spans: Vec<(usize, std::ops::Range<usize>)>,
+}
+
+/// A code lens of a language server, displayed at the end of the line of `char_idx`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeLens {
+    pub char_idx: usize,
+    /// The language server the lens was received from, which executes its command.
+    pub language_server_id: usize,
+    pub lens: helix_lsp::lsp::CodeLens,
+}
+
+impl CodeLens {
+    /// The title of the command of the lens, if it has been resolved.
+    pub fn title(&self) -> Option<&str> {
+        self.lens
+            .command
+            .as_ref()
+            .map(|command| command.title.as_str())
+    }
+}
+
+/// A link of a language server covering the char range `range`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentLink {
+    pub range: std::ops::Range<usize>,
+    /// The language server the link was received from, which resolves its target.
+    pub language_server_id: usize,
+    pub link: helix_lsp::lsp::DocumentLink,
+}
+
+/// State received from a language server, kept in place across edits until it's requested
+/// again.
+trait MapChanges {
+    /// Maps the state through `changes`, returning `false` if it was deleted.
+    fn map_changes(&mut self, changes: &ChangeSet) -> bool;
+}
+
+impl MapChanges for std::ops::Range<usize> {
+    fn map_changes(&mut self, changes: &ChangeSet) -> bool {
+        self.start = changes.map_pos(self.start, Assoc::After);
+        self.end = changes.map_pos(self.end, Assoc::Before);
+        self.start < self.end
+    }
+}
+
+impl MapChanges for (usize, std::ops::Range<usize>) {
+    fn map_changes(&mut self, changes: &ChangeSet) -> bool {
+        self.1.map_changes(changes)
+    }
+}
+
+impl MapChanges for InlayHint {
+    fn map_changes(&mut self, changes: &ChangeSet) -> bool {
+        self.char_idx = changes.map_pos(self.char_idx, Assoc::After);
+        true
+    }
+}
+
+impl MapChanges for CodeLens {
+    fn map_changes(&mut self, changes: &ChangeSet) -> bool {
+        self.char_idx = changes.map_pos(self.char_idx, Assoc::After);
+        true
+    }
+}
+
+impl MapChanges for DocumentLink {
+    fn map_changes(&mut self, changes: &ChangeSet) -> bool {
+        self.range.map_changes(changes)
+    }
+}
+
+/// Maps `items` through `changes`, dropping the deleted ones.
+fn map_changes<T: MapChanges>(items: &mut Vec<T>, changes: &ChangeSet) {
+    items.retain_mut(|item| item.map_changes(changes));
+}
+
 pub struct Document {
     pub(crate) id: DocumentId,
     text: Rope,
@@ -130,14 +277,31 @@ pub struct Document {
 
     pub savepoint: Option<Transaction>,
 
+    /// The snippet whose tabstops are being filled in, if any.
+    pub active_snippet: Option<ActiveSnippet>,
+    /// The text before the changes which weren't mapped over the breakpoints of the
+    /// document yet, and the changes. See [`Editor::shift_breakpoints`].
+    pub(crate) breakpoint_changes: Option<(Rope, ChangeSet)>,
+
     last_saved_revision: usize,
     version: i32, // should be usize?
     pub(crate) modified_since_accessed: bool,
 
     diagnostics: Vec<Diagnostic>,
-    language_server: Option<Arc<helix_lsp::Client>>,
+    pub(crate) language_servers: Vec<Arc<helix_lsp::Client>>,
+    inlay_hints: HashMap<ViewId, DocumentInlayHints>,
+    folds: HashMap<ViewId, Folds>,
+    semantic_tokens: Option<DocumentSemanticTokens>,
+    /// The code lenses sorted by `char_idx`, or `None` if they haven't been requested yet.
+    code_lenses: Option<Vec<CodeLens>>,
+    /// The links of the language server sorted by their range.
+    document_links: Vec<DocumentLink>,
+    /// The references to the symbol under the cursor of each view, sorted by their range.
+    document_highlights: HashMap<ViewId, Vec<std::ops::Range<usize>>>,
 
     diff_handle: Option<DiffHandle>,
+
+    config: Arc<dyn DynAccess<Config>>,
 }
 
 use std::{fmt, mem};
@@ -350,8 +514,19 @@ fn take_with<T, F>(mut_ref: &mut T, f: F)
 use helix_lsp::lsp;
 use url::Url;
 
+/// The file the undo history of the document at `path` is persisted to. It's named after
+/// a hash of the full path, which can be longer than a file name is allowed to be.
+fn undo_file(path: &Path) -> PathBuf {
+    let hash = sha1_smol::Sha1::from(path.to_string_lossy().as_bytes()).digest();
+    helix_loader::undo_dir().join(hash.to_string())
+}
+
 impl Document {
-    pub fn from(text: Rope, encoding: Option<&'static encoding::Encoding>) -> Self {
+    pub fn from(
+        text: Rope,
+        encoding: Option<&'static encoding::Encoding>,
+        config: Arc<dyn DynAccess<Config>>,
+    ) -> Self {
         let encoding = encoding.unwrap_or(encoding::UTF_8);
         let changes = ChangeSet::new(&text);
         let old_state = None;
@@ -373,13 +548,27 @@ pub fn from(text: Rope, encoding: Option<&'static encoding::Encoding>) -> Self {
             version: 0,
             history: Cell::new(History::default()),
             savepoint: None,
+            active_snippet: None,
+            breakpoint_changes: None,
             last_saved_revision: 0,
             modified_since_accessed: false,
-            language_server: None,
+            language_servers: Vec::new(),
+            inlay_hints: HashMap::new(),
+            folds: HashMap::new(),
+            semantic_tokens: None,
+            code_lenses: None,
+            document_links: Vec::new(),
+            document_highlights: HashMap::new(),
             diff_handle: None,
+            config,
         }
     }
 
+    pub fn default(config: Arc<dyn DynAccess<Config>>) -> Self {
+        let text = Rope::from(DEFAULT_LINE_ENDING.as_str());
+        Self::from(text, None, config)
+    }
+
     // TODO: async fn?
     /// Create a new document from `path`. Encoding is auto-detected, but it can be manually
     /// overwritten with the `encoding` parameter.
@@ -387,6 +576,7 @@ pub fn open(
         path: &Path,
         encoding: Option<&'static encoding::Encoding>,
         config_loader: Option<Arc<syntax::Loader>>,
+        config: Arc<dyn DynAccess<Config>>,
     ) -> Result<Self, Error> {
         // Open the file if it exists, otherwise assume it is a new file (and thus empty).
         let (rope, encoding) = if path.exists() {
@@ -398,7 +588,8 @@ pub fn open(
             (Rope::from(DEFAULT_LINE_ENDING.as_str()), encoding)
         };
 
-        let mut doc = Self::from(rope, Some(encoding));
+        let persistent_undo = config.load().persistent_undo;
+        let mut doc = Self::from(rope, Some(encoding), config);
 
         // set the path and try detecting the language
         doc.set_path(Some(path))?;
@@ -408,9 +599,33 @@ pub fn open(
 
         doc.detect_indent_and_line_ending();
 
+        if persistent_undo {
+            doc.load_history();
+        }
+
         Ok(doc)
     }
 
+    /// Restores the undo history persisted when the document was last written. Missing,
+    /// unreadable or out of date undo files leave the history empty.
+    fn load_history(&mut self) {
+        let undo_file = match self.path() {
+            Some(path) => undo_file(path),
+            None => return,
+        };
+
+        let history = std::fs::File::open(&undo_file)
+            .and_then(|file| History::deserialize(&mut std::io::BufReader::new(file), self.text()));
+        match history {
+            Ok(history) => {
+                self.last_saved_revision = history.current_revision();
+                self.history.set(history);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+            Err(err) => log::info!("discarding undo file {:?}: {}", undo_file, err),
+        }
+    }
+
     /// The same as [`format`], but only returns formatting changes if auto-formatting
     /// is configured.
     pub fn auto_format(&self) -> Option<BoxFuture<'static, Result<Transaction, FormatterError>>> {
@@ -482,7 +697,9 @@ pub fn format(&self) -> Option<BoxFuture<'static, Result<Transaction, FormatterE
             return Some(formatting_future.boxed());
         };
 
-        let language_server = self.language_server()?;
+        let language_server = self
+            .language_servers_with_feature(LanguageServerFeature::Format)
+            .next()?;
         let text = self.text.clone();
         let offset_encoding = language_server.offset_encoding();
 
@@ -555,7 +772,7 @@ impl Future<Output = Result<DocumentSavedEvent, anyhow::Error>> + 'static + Send
         };
 
         let identifier = self.path().map(|_| self.identifier());
-        let language_server = self.language_server.clone();
+        let language_servers = self.language_servers.clone();
 
         // mark changes up to now as saved
         let current_rev = self.get_current_revision();
@@ -563,6 +780,24 @@ impl Future<Output = Result<DocumentSavedEvent, anyhow::Error>> + 'static + Send
 
         let encoding = self.encoding;
 
+        // The history can only be persisted once pending changes are committed, otherwise
+        // the current revision doesn't match the saved text.
+        let undo = if self.config.load().persistent_undo && self.changes.is_empty() {
+            let history = self.history.take();
+            let mut undo = Vec::new();
+            let result = history.serialize(&mut undo, &text);
+            self.history.set(history);
+            match result {
+                Ok(()) => Some(undo),
+                Err(err) => {
+                    log::error!("failed to serialize undo history: {}", err);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         // We encode the file according to the `Document`'s encoding.
         let future = async move {
             use tokio::fs::File;
@@ -580,6 +815,19 @@ impl Future<Output = Result<DocumentSavedEvent, anyhow::Error>> + 'static + Send
             let mut file = File::create(&path).await?;
             to_writer(&mut file, encoding, &text).await?;
 
+            if let Some(undo) = undo {
+                let undo_file = undo_file(&path);
+                let result = async {
+                    if let Some(dir) = undo_file.parent() {
+                        tokio::fs::create_dir_all(dir).await?;
+                    }
+                    tokio::fs::write(&undo_file, undo).await
+                };
+                if let Err(err) = result.await {
+                    log::error!("failed to write undo file {:?}: {}", undo_file, err);
+                }
+            }
+
             let event = DocumentSavedEvent {
                 revision: current_rev,
                 doc_id,
@@ -587,14 +835,14 @@ impl Future<Output = Result<DocumentSavedEvent, anyhow::Error>> + 'static + Send
                 text: text.clone(),
             };
 
-            if let Some(language_server) = language_server {
-                if !language_server.is_initialized() {
-                    return Ok(event);
-                }
+            if let Some(identifier) = identifier {
+                for language_server in language_servers {
+                    if !language_server.is_initialized() {
+                        continue;
+                    }
 
-                if let Some(identifier) = identifier {
                     if let Some(notification) =
-                        language_server.text_document_did_save(identifier, &text)
+                        language_server.text_document_did_save(identifier.clone(), &text)
                     {
                         notification.await?;
                     }
@@ -664,6 +912,15 @@ pub fn reload(
         Ok(())
     }
 
+    /// Whether the contents of the file of the document differ from its text, for example
+    /// because another program changed the file.
+    pub fn is_changed_on_disk(&self) -> Result<bool, Error> {
+        let path = self.path().ok_or_else(|| anyhow!("document has no file"))?;
+        let mut file = std::fs::File::open(path)?;
+        let (rope, ..) = from_reader(&mut file, Some(self.encoding))?;
+        Ok(rope != self.text)
+    }
+
     /// Sets the [`Document`]'s encoding with the encoding correspondent to `label`.
     pub fn set_encoding(&mut self, label: &str) -> Result<(), Error> {
         self.encoding = encoding::Encoding::for_label(label.as_bytes())
@@ -730,9 +987,9 @@ pub fn set_language_by_language_id(
         Ok(())
     }
 
-    /// Set the LSP.
-    pub fn set_language_server(&mut self, language_server: Option<Arc<helix_lsp::Client>>) {
-        self.language_server = language_server;
+    /// Set the language servers.
+    pub fn set_language_servers(&mut self, language_servers: Vec<Arc<helix_lsp::Client>>) {
+        self.language_servers = language_servers;
     }
 
     /// Select text within the [`Document`].
@@ -771,20 +1028,24 @@ pub fn ensure_view_init(&mut self, view_id: ViewId) {
     /// Remove a view's selection from this document.
     pub fn remove_view(&mut self, view_id: ViewId) {
         self.selections.remove(&view_id);
+        self.inlay_hints.remove(&view_id);
+        self.document_highlights.remove(&view_id);
+        self.folds.remove(&view_id);
     }
 
     /// Apply a [`Transaction`] to the [`Document`] to change its text.
     fn apply_impl(&mut self, transaction: &Transaction, view_id: ViewId) -> bool {
         let old_doc = self.text().clone();
+        let changes = transaction.changes();
 
-        let success = transaction.changes().apply(&mut self.text);
+        let success = changes.apply(&mut self.text);
 
         if success {
             for selection in self.selections.values_mut() {
                 *selection = selection
                     .clone()
                     // Map through changes
-                    .map(transaction.changes())
+                    .map(changes)
                     // Ensure all selections across all views still adhere to invariants.
                     .ensure_invariants(self.text.slice(..));
             }
@@ -800,7 +1061,7 @@ fn apply_impl(&mut self, transaction: &Transaction, view_id: ViewId) -> bool {
             self.modified_since_accessed = true;
         }
 
-        if !transaction.changes().is_empty() {
+        if !changes.is_empty() {
             self.version += 1;
             // start computing the diff in parallel
             if let Some(diff_handle) = &self.diff_handle {
@@ -818,15 +1079,11 @@ fn apply_impl(&mut self, transaction: &Transaction, view_id: ViewId) -> bool {
             // update tree-sitter syntax tree
             if let Some(syntax) = &mut self.syntax {
                 // TODO: no unwrap
-                syntax
-                    .update(&old_doc, &self.text, transaction.changes())
-                    .unwrap();
+                syntax.update(&old_doc, &self.text, changes).unwrap();
             }
 
             // map state.diagnostics over changes::map_pos too
             for diagnostic in &mut self.diagnostics {
-                use helix_core::Assoc;
-                let changes = transaction.changes();
                 diagnostic.range.start = changes.map_pos(diagnostic.range.start, Assoc::After);
                 diagnostic.range.end = changes.map_pos(diagnostic.range.end, Assoc::After);
                 diagnostic.line = self.text.char_to_line(diagnostic.range.start);
@@ -834,13 +1091,44 @@ fn apply_impl(&mut self, transaction: &Transaction, view_id: ViewId) -> bool {
             self.diagnostics
                 .sort_unstable_by_key(|diagnostic| diagnostic.range);
 
+            // keep the state of the language servers in place until it's requested again,
+            // dropping what was deleted
+            for inlay_hints in self.inlay_hints.values_mut() {
+                map_changes(&mut inlay_hints.hints, changes);
+            }
+
+            for folds in self.folds.values_mut() {
+                folds.map(changes, self.text.slice(..));
+            }
+
+            if let Some(semantic_tokens) = &mut self.semantic_tokens {
+                map_changes(&mut semantic_tokens.spans, changes);
+            }
+            if let Some(code_lenses) = &mut self.code_lenses {
+                map_changes(code_lenses, changes);
+            }
+            map_changes(&mut self.document_links, changes);
+            for highlights in self.document_highlights.values_mut() {
+                map_changes(highlights, changes);
+            }
+
+            if let Some(active_snippet) = &mut self.active_snippet {
+                active_snippet.map(changes);
+            }
+
+            // the breakpoints are kept by the editor, which moves them later
+            self.breakpoint_changes = Some(match self.breakpoint_changes.take() {
+                Some((text, pending)) => (text, pending.compose(changes.clone())),
+                None => (old_doc.clone(), changes.clone()),
+            });
+
             // emit lsp notification
-            if let Some(language_server) = self.language_server() {
+            for language_server in self.language_servers() {
                 let notify = language_server.text_document_did_change(
                     self.versioned_identifier(),
                     &old_doc,
                     self.text(),
-                    transaction.changes(),
+                    changes,
                 );
 
                 if let Some(notify) = notify {
@@ -1035,15 +1323,17 @@ pub fn language_name(&self) -> Option<&str> {
             .map(|language| language.language_id.as_str())
     }
 
-    /// Language ID for the document. Either the `language-id` from the
-    /// `language-server` configuration, or the document language if no
+    /// Language ID of the document sent to the language server `server_name`. Either the
+    /// `language-id` from the configuration of the server, or the document language if no
     /// `language-id` has been specified.
-    pub fn language_id(&self) -> Option<&str> {
+    pub fn language_id(&self, server_name: &str) -> Option<&str> {
         let language_config = self.language.as_deref()?;
+        let server = language_config
+            .language_servers()
+            .iter()
+            .find(|config| config.name() == server_name)?;
 
-        language_config
-            .language_server
-            .as_ref()?
+        server
             .language_id
             .as_deref()
             .or(Some(language_config.language_id.as_str()))
@@ -1059,10 +1349,43 @@ pub fn version(&self) -> i32 {
         self.version
     }
 
-    /// Language server if it has been initialized.
-    pub fn language_server(&self) -> Option<&helix_lsp::Client> {
-        let server = self.language_server.as_deref()?;
-        server.is_initialized().then(|| server)
+    /// Language servers that have been initialized.
+    pub fn language_servers(&self) -> impl Iterator<Item = &helix_lsp::Client> {
+        self.language_servers
+            .iter()
+            .map(|server| server.as_ref())
+            .filter(|server| server.is_initialized())
+    }
+
+    /// Initialized language servers that support `feature`, in configuration order.
+    /// A server is skipped if its configuration excludes the feature or if it
+    /// doesn't advertise the matching capability.
+    pub fn language_servers_with_feature(
+        &self,
+        feature: LanguageServerFeature,
+    ) -> impl Iterator<Item = &helix_lsp::Client> {
+        let configs = self
+            .language_config()
+            .map(|config| config.language_servers())
+            .unwrap_or_default();
+
+        self.language_servers().filter(move |server| {
+            configs
+                .iter()
+                .filter(|config| config.name() == server.name())
+                .all(|config| config.supports_feature(feature))
+                && server.supports_feature(feature)
+        })
+    }
+
+    /// Initialized language server of this document with the given id.
+    pub fn language_server_by_id(&self, id: usize) -> Option<&helix_lsp::Client> {
+        self.language_servers().find(|server| server.id() == id)
+    }
+
+    /// Whether the language server with the given id is attached to this document.
+    pub fn supports_language_server(&self, id: usize) -> bool {
+        self.language_servers.iter().any(|server| server.id() == id)
     }
 
     pub fn diff_handle(&self) -> Option<&DiffHandle> {
@@ -1168,12 +1491,158 @@ pub fn diagnostics(&self) -> &[Diagnostic] {
         &self.diagnostics
     }
 
-    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
-        self.diagnostics = diagnostics;
+    /// Replaces the diagnostics published by the language server with the given id.
+    pub fn set_diagnostics(&mut self, language_server_id: usize, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics
+            .retain(|diagnostic| diagnostic.language_server_id != language_server_id);
+        self.diagnostics.extend(diagnostics);
         self.diagnostics
             .sort_unstable_by_key(|diagnostic| diagnostic.range);
     }
 
+    /// The inlay hints displayed in the given view, if any were received.
+    pub fn inlay_hints(&self, view_id: ViewId) -> Option<&DocumentInlayHints> {
+        self.inlay_hints.get(&view_id)
+    }
+
+    pub fn set_inlay_hints(&mut self, view_id: ViewId, inlay_hints: DocumentInlayHints) {
+        self.inlay_hints.insert(view_id, inlay_hints);
+    }
+
+    pub fn clear_inlay_hints(&mut self) {
+        self.inlay_hints.clear();
+    }
+
+    /// The closed folds of the document in the given view.
+    pub fn folds(&self, view_id: ViewId) -> Option<&Folds> {
+        self.folds.get(&view_id)
+    }
+
+    pub fn folds_mut(&mut self, view_id: ViewId) -> &mut Folds {
+        self.folds.entry(view_id).or_default()
+    }
+
+    /// Closes `fold` in the given view.
+    pub fn close_fold(&mut self, view_id: ViewId, fold: Fold) {
+        let text = self.text.slice(..);
+        self.folds.entry(view_id).or_default().close(text, fold);
+    }
+
+    /// Opens the closed folds containing `line` in the given view. Returns whether any
+    /// fold was opened.
+    pub fn open_folds_at_line(&mut self, view_id: ViewId, line: usize) -> bool {
+        let text = self.text.slice(..);
+        match self.folds.get_mut(&view_id) {
+            Some(folds) => folds.open_at_line(text, line),
+            None => false,
+        }
+    }
+
+    /// The lines hidden by closed folds in the given view.
+    pub fn hidden_lines(&self, view_id: ViewId) -> HiddenLines {
+        self.folds(view_id)
+            .map(|folds| folds.hidden_lines(self.text.slice(..)))
+            .unwrap_or_default()
+    }
+
+    /// The foldable ranges of the document computed from its tree-sitter fold query, or
+    /// `None` if the language has no fold query.
+    pub fn syntax_folds(&self) -> Option<Vec<Fold>> {
+        let query = self.language_config()?.fold_query()?;
+        let syntax = self.syntax()?;
+        Some(fold::syntax_folds(syntax, query, self.text.slice(..)))
+    }
+
+    /// The semantic tokens of the document, if any were received.
+    pub fn semantic_tokens(&self) -> Option<&DocumentSemanticTokens> {
+        self.semantic_tokens.as_ref()
+    }
+
+    pub fn set_semantic_tokens(&mut self, semantic_tokens: DocumentSemanticTokens) {
+        self.semantic_tokens = Some(semantic_tokens);
+    }
+
+    pub fn clear_semantic_tokens(&mut self) {
+        self.semantic_tokens = None;
+    }
+
+    /// The code lenses of the document, or `None` if they haven't been requested yet.
+    pub fn code_lenses(&self) -> Option<&[CodeLens]> {
+        self.code_lenses.as_deref()
+    }
+
+    pub fn set_code_lenses(&mut self, mut code_lenses: Vec<CodeLens>) {
+        code_lenses.sort_by_key(|code_lens| code_lens.char_idx);
+        self.code_lenses = Some(code_lenses);
+    }
+
+    pub fn clear_code_lenses(&mut self) {
+        self.code_lenses = None;
+    }
+
+    /// The links of the language server, sorted by their range.
+    pub fn document_links(&self) -> &[DocumentLink] {
+        &self.document_links
+    }
+
+    pub fn set_document_links(&mut self, mut document_links: Vec<DocumentLink>) {
+        document_links.sort_by_key(|link| (link.range.start, link.range.end));
+        self.document_links = document_links;
+    }
+
+    /// Returns the link of the language server under `pos`, if any.
+    pub fn document_link_at(&self, pos: usize) -> Option<&DocumentLink> {
+        self.document_links
+            .iter()
+            .find(|link| link.range.contains(&pos))
+    }
+
+    /// The references to the symbol under the cursor of the view, sorted by their range.
+    pub fn document_highlights(&self, view_id: ViewId) -> &[std::ops::Range<usize>] {
+        self.document_highlights
+            .get(&view_id)
+            .map_or(&[], |highlights| highlights.as_slice())
+    }
+
+    pub fn set_document_highlights(
+        &mut self,
+        view_id: ViewId,
+        mut highlights: Vec<std::ops::Range<usize>>,
+    ) {
+        highlights.sort_unstable_by_key(|range| (range.start, range.end));
+        self.document_highlights.insert(view_id, highlights);
+    }
+
+    pub fn clear_document_highlights(&mut self) {
+        self.document_highlights.clear();
+    }
+
+    /// Returns the code lenses displayed on the given line.
+    pub fn line_code_lenses(&self, line: usize) -> &[CodeLens] {
+        let code_lenses = match &self.code_lenses {
+            Some(code_lenses) => code_lenses,
+            None => return &[],
+        };
+        let text = self.text.slice(..);
+        let line_start = text.line_to_char(line);
+        let line_end = if line + 1 < text.len_lines() {
+            text.line_to_char(line + 1)
+        } else {
+            text.len_chars() + 1
+        };
+        let start = code_lenses.partition_point(|code_lens| code_lens.char_idx < line_start);
+        let end = code_lenses.partition_point(|code_lens| code_lens.char_idx < line_end);
+        &code_lenses[start..end]
+    }
+
+    /// Returns the virtual text displayed on the given line of the view, see
+    /// [`DocumentInlayHints::line_virtual_text`].
+    pub fn line_virtual_text(&self, view_id: ViewId, line: usize) -> Vec<(usize, usize)> {
+        self.inlay_hints(view_id)
+            .map(|inlay_hints| inlay_hints.line_virtual_text(self.text.slice(..), line))
+            .unwrap_or_default()
+    }
+
     /// Get the document's auto pairs. If the document has a recognized
     /// language config with auto pairs configured, returns that;
     /// otherwise, falls back to the global auto pairs config. If the global
@@ -1195,12 +1664,29 @@ pub fn auto_pairs<'a>(&'a self, editor: &'a Editor) -> Option<&'a AutoPairs> {
             None => global_config,
         }
     }
-}
 
-impl Default for Document {
-    fn default() -> Self {
-        let text = Rope::from(DEFAULT_LINE_ENDING.as_str());
-        Self::from(text, None)
+    /// Get the indicator drawn at the start of soft-wrapped rows, or `None` if
+    /// soft wrapping is disabled. Language settings override the editor config.
+    pub fn soft_wrap_indicator(&self) -> Option<String> {
+        let config = self.config.load();
+        let language_soft_wrap = self
+            .language
+            .as_ref()
+            .and_then(|lang| lang.soft_wrap.as_ref());
+
+        let enable = language_soft_wrap
+            .and_then(|soft_wrap| soft_wrap.enable)
+            .or(config.soft_wrap.enable)
+            .unwrap_or(false);
+        if !enable {
+            return None;
+        }
+
+        let indicator = language_soft_wrap
+            .and_then(|soft_wrap| soft_wrap.wrap_indicator.clone())
+            .or_else(|| config.soft_wrap.wrap_indicator.clone())
+            .unwrap_or_else(|| "↪ ".to_string());
+        Some(indicator)
     }
 }
 
@@ -1239,13 +1725,19 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 
 #[cfg(test)]
 mod test {
+    use arc_swap::ArcSwap;
+
     use super::*;
 
     #[test]
     fn changeset_to_changes_ignore_line_endings() {
         use helix_lsp::{lsp, Client, OffsetEncoding};
         let text = Rope::from("hello\r\nworld");
-        let mut doc = Document::from(text, None);
+        let mut doc = Document::from(
+            text,
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
         let view = ViewId::default();
         doc.set_selection(view, Selection::single(0, 0));
 
@@ -1275,11 +1767,43 @@ fn changeset_to_changes_ignore_line_endings() {
         );
     }
 
+    #[test]
+    fn document_highlights_follow_changes() {
+        let mut doc = Document::from(
+            Rope::from("let foo = foo + foo;"),
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
+        let view = ViewId::default();
+        doc.set_selection(view, Selection::single(4, 4));
+        doc.set_document_highlights(view, vec![16..19, 4..7, 10..13]);
+        assert_eq!(doc.document_highlights(view), &[4..7, 10..13, 16..19]);
+
+        // insert before the first reference and inside the second one
+        let transaction = Transaction::change(
+            doc.text(),
+            vec![(0, 0, Some("  ".into())), (11, 11, Some("x".into()))].into_iter(),
+        );
+        doc.apply(&transaction, view);
+        assert_eq!(doc.text(), "  let foo = fxoo + foo;");
+        assert_eq!(doc.document_highlights(view), &[6..9, 12..16, 19..22]);
+
+        // deleted references are dropped
+        let transaction = Transaction::change(doc.text(), vec![(12, 16, None)].into_iter());
+        doc.apply(&transaction, view);
+        assert_eq!(doc.text(), "  let foo =  + foo;");
+        assert_eq!(doc.document_highlights(view), &[6..9, 15..18]);
+    }
+
     #[test]
     fn changeset_to_changes() {
         use helix_lsp::{lsp, Client, OffsetEncoding};
         let text = Rope::from("hello");
-        let mut doc = Document::from(text, None);
+        let mut doc = Document::from(
+            text,
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
         let view = ViewId::default();
         doc.set_selection(view, Selection::single(5, 5));
 
@@ -1389,10 +1913,76 @@ fn changeset_to_changes() {
         );
     }
 
+    #[test]
+    fn code_lenses_follow_edits() {
+        use helix_lsp::lsp;
+        let text = Rope::from("fn a() {}\nfn b() {}\n");
+        let mut doc = Document::from(
+            text,
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
+        let view = ViewId::default();
+        doc.set_selection(view, Selection::single(0, 0));
+
+        let code_lens = |char_idx| CodeLens {
+            char_idx,
+            language_server_id: 0,
+            lens: lsp::CodeLens {
+                range: lsp::Range::default(),
+                command: None,
+                data: None,
+            },
+        };
+        doc.set_code_lenses(vec![code_lens(10), code_lens(0)]);
+        assert_eq!(doc.line_code_lenses(0), &[code_lens(0)]);
+        assert_eq!(doc.line_code_lenses(1), &[code_lens(10)]);
+
+        // inserting a line in front of an item moves its lens along with it
+        let transaction =
+            Transaction::change(doc.text(), [(10, 10, Some("\n".into()))].into_iter());
+        doc.apply(&transaction, view);
+        assert!(doc.line_code_lenses(1).is_empty());
+        assert_eq!(doc.line_code_lenses(2), &[code_lens(11)]);
+    }
+
+    #[test]
+    fn language_id_of_each_server() {
+        let language: helix_core::syntax::LanguageConfiguration = toml::from_str(
+            r#"
+            name = "test"
+            scope = "source.test"
+            file-types = ["test"]
+            roots = []
+            language-servers = [
+              { command = "tsserver", language-id = "typescriptreact" },
+              { command = "eslint" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let mut doc = Document::from(
+            Rope::new(),
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
+        assert_eq!(doc.language_id("tsserver"), None);
+
+        let loader = helix_core::syntax::Loader::new(helix_core::syntax::Configuration {
+            language: Vec::new(),
+        });
+        doc.set_language(Some(Arc::new(language)), Some(Arc::new(loader)));
+        assert_eq!(doc.language_id("tsserver"), Some("typescriptreact"));
+        assert_eq!(doc.language_id("eslint"), Some("test"));
+        assert_eq!(doc.language_id("unknown"), None);
+    }
+
     #[test]
     fn test_line_ending() {
         assert_eq!(
-            Document::default().text().to_string(),
+            Document::default(Arc::new(ArcSwap::new(Arc::new(Config::default()))))
+                .text()
+                .to_string(),
             DEFAULT_LINE_ENDING.as_str()
         );
     }
@@ -1,10 +1,15 @@
 use crate::{
     align_view,
     clipboard::{get_clipboard_provider, ClipboardProvider},
+    debug_console::DebugConsole,
+    debug_panel::DebugPanel,
     document::{DocumentSavedEventFuture, DocumentSavedEventResult, Mode},
+    file_watcher,
     graphics::{CursorKind, Rect},
     info::Info,
     input::KeyEvent,
+    plugin,
+    terminal::{TerminalCommand, TerminalEvent, TerminalId, Terminals},
     theme::{self, Theme},
     tree::{self, Tree},
     Align, Document, DocumentId, View, ViewId,
@@ -18,7 +23,7 @@
 
 use std::{
     borrow::Cow,
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     io::stdin,
     num::NonZeroUsize,
     path::{Path, PathBuf},
@@ -41,7 +46,8 @@
 use helix_core::Position;
 use helix_core::{
     auto_pairs::AutoPairs,
-    syntax::{self, AutoPairConfig},
+    snippets::UserSnippets,
+    syntax::{self, AutoPairConfig, SoftWrap},
     Change,
 };
 use helix_dap as dap;
@@ -126,6 +132,9 @@ pub struct Config {
     pub mouse: bool,
     /// Shell to use for shell commands. Defaults to ["cmd", "/C"] on Windows and ["sh", "-c"] otherwise.
     pub shell: Vec<String>,
+    /// Command used to open URLs, which is passed the URL as its last argument. Defaults to
+    /// ["open"] on macOS, ["cmd", "/C", "start", ""] on Windows and ["xdg-open"] otherwise.
+    pub url_opener: Vec<String>,
     /// Line number mode.
     pub line_number: LineNumber,
     /// Highlight the lines cursors are currently on. Defaults to false.
@@ -178,6 +187,56 @@ pub struct Config {
     pub indent_guides: IndentGuidesConfig,
     /// Whether to color modes with different colors. Defaults to `false`.
     pub color_modes: bool,
+    /// Soft wrapping of lines that exceed the width of the view.
+    pub soft_wrap: SoftWrap,
+    /// Persist the undo history of documents to disk on write so that it is kept
+    /// across editor sessions. Defaults to false.
+    pub persistent_undo: bool,
+    /// Save the session when quitting and restore it when started without files.
+    /// Defaults to false.
+    pub persistent_session: bool,
+    /// File explorer sidebar configuration.
+    pub explorer: ExplorerConfig,
+    /// Watching files for changes on disk.
+    pub file_watcher: FileWatcherConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct FileWatcherConfig {
+    /// Reload documents changed on disk and notify language servers of changed files.
+    /// Defaults to true.
+    pub enable: bool,
+    /// Time in milliseconds between checks of the watched files if they have to be polled
+    /// because the file system notifications of the platform aren't available. Defaults
+    /// to 1000ms.
+    #[serde(
+        serialize_with = "serialize_duration_millis",
+        deserialize_with = "deserialize_duration_millis"
+    )]
+    pub poll_interval: Duration,
+}
+
+impl Default for FileWatcherConfig {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            poll_interval: Duration::from_millis(1000),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct ExplorerConfig {
+    /// Width of the file explorer sidebar in columns. Defaults to 30.
+    pub column_width: u16,
+}
+
+impl Default for ExplorerConfig {
+    fn default() -> Self {
+        Self { column_width: 30 }
+    }
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -242,6 +301,12 @@ pub struct LspConfig {
     pub auto_signature_help: bool,
     /// Display docs under signature help popup
     pub display_signature_help_docs: bool,
+    /// Display inlay hints as virtual text
+    pub display_inlay_hints: bool,
+    /// Display code lenses at the end of the lines they belong to
+    pub display_code_lenses: bool,
+    /// Highlight the references of the symbol under the cursor when idle
+    pub auto_document_highlight: bool,
 }
 
 impl Default for LspConfig {
@@ -250,6 +315,9 @@ fn default() -> Self {
             display_messages: false,
             auto_signature_help: true,
             display_signature_help_docs: true,
+            display_inlay_hints: false,
+            display_code_lenses: true,
+            auto_document_highlight: false,
         }
     }
 }
@@ -460,6 +528,8 @@ pub enum GutterType {
     Spacer,
     /// Highlight local changes
     Diff,
+    /// Mark the lines closed folds are displayed on
+    Folds,
 }
 
 impl std::str::FromStr for GutterType {
@@ -471,7 +541,10 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
             "spacer" => Ok(Self::Spacer),
             "line-numbers" => Ok(Self::LineNumbers),
             "diff" => Ok(Self::Diff),
-            _ => anyhow::bail!("Gutter type can only be `diagnostics` or `line-numbers`."),
+            "folds" => Ok(Self::Folds),
+            _ => anyhow::bail!(
+                "Gutter type can only be `diagnostics`, `spacer`, `line-numbers`, `diff` or `folds`."
+            ),
         }
     }
 }
@@ -562,7 +635,7 @@ pub struct WhitespaceCharacters {
 impl Default for WhitespaceCharacters {
     fn default() -> Self {
         Self {
-            space: '·',    // U+00B7
+            space: '·',   // U+00B7
             nbsp: '⍽',    // U+237D
             tab: '→',     // U+2192
             newline: '⏎', // U+23CE
@@ -600,6 +673,13 @@ fn default() -> Self {
             } else {
                 vec!["sh".to_owned(), "-c".to_owned()]
             },
+            url_opener: if cfg!(windows) {
+                ["cmd", "/C", "start", ""].map(ToOwned::to_owned).to_vec()
+            } else if cfg!(target_os = "macos") {
+                vec!["open".to_owned()]
+            } else {
+                vec!["xdg-open".to_owned()]
+            },
             line_number: LineNumber::Absolute,
             cursorline: false,
             cursorcolumn: false,
@@ -630,6 +710,11 @@ fn default() -> Self {
             bufferline: BufferLine::default(),
             indent_guides: IndentGuidesConfig::default(),
             color_modes: false,
+            soft_wrap: SoftWrap::default(),
+            persistent_undo: false,
+            persistent_session: false,
+            explorer: ExplorerConfig::default(),
+            file_watcher: FileWatcherConfig::default(),
         }
     }
 }
@@ -655,10 +740,16 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+/// A source breakpoint. Only the location, conditions and whether it's enabled are
+/// persisted, the rest is reported by the debugger.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Breakpoint {
+    #[serde(skip)]
     pub id: Option<usize>,
+    #[serde(skip)]
     pub verified: bool,
+    #[serde(skip)]
     pub message: Option<String>,
 
     pub line: usize,
@@ -666,6 +757,51 @@ pub struct Breakpoint {
     pub condition: Option<String>,
     pub hit_condition: Option<String>,
     pub log_message: Option<String>,
+    /// Disabled breakpoints are kept but not sent to the debugger.
+    pub enabled: bool,
+}
+
+impl Default for Breakpoint {
+    fn default() -> Self {
+        Self {
+            id: None,
+            verified: false,
+            message: None,
+            line: 0,
+            column: None,
+            condition: None,
+            hit_condition: None,
+            log_message: None,
+            enabled: true,
+        }
+    }
+}
+
+/// A breakpoint on a function name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FunctionBreakpoint {
+    #[serde(skip)]
+    pub verified: bool,
+    #[serde(skip)]
+    pub message: Option<String>,
+
+    pub name: String,
+    pub condition: Option<String>,
+    pub hit_condition: Option<String>,
+    pub enabled: bool,
+}
+
+/// A breakpoint on a change of a variable. The data id is only valid during the debug
+/// session it was obtained in, so data breakpoints aren't persisted.
+#[derive(Debug, Clone)]
+pub struct DataBreakpoint {
+    pub verified: bool,
+    pub message: Option<String>,
+
+    pub data_id: String,
+    pub description: String,
+    pub enabled: bool,
 }
 
 use futures_util::stream::{Flatten, Once};
@@ -689,12 +825,26 @@ pub struct Editor {
     pub macro_recording: Option<(char, Vec<KeyEvent>)>,
     pub macro_replaying: Vec<char>,
     pub language_servers: helix_lsp::Registry,
-    pub diagnostics: BTreeMap<lsp::Url, Vec<lsp::Diagnostic>>,
+    pub plugins: plugin::Registry,
+    pub file_watcher: file_watcher::Watcher,
+    pub diagnostics: BTreeMap<lsp::Url, Vec<(lsp::Diagnostic, usize)>>,
+    /// The result ids of the diagnostics pulled from each language server, by document.
+    pub diagnostic_result_ids: HashMap<usize, HashMap<lsp::Url, String>>,
+    /// The language servers a workspace diagnostic request is pending for.
+    pub pending_workspace_diagnostics: HashSet<usize>,
     pub diff_providers: DiffProviderRegistry,
 
     pub debugger: Option<dap::Client>,
     pub debugger_events: SelectAll<UnboundedReceiverStream<dap::Payload>>,
     pub breakpoints: HashMap<PathBuf, Vec<Breakpoint>>,
+    pub function_breakpoints: Vec<FunctionBreakpoint>,
+    pub data_breakpoints: Vec<DataBreakpoint>,
+    /// The output of the debugged program and the expressions evaluated in the debug console.
+    pub debug_console: DebugConsole,
+    /// The watch expressions and the variables of the active stack frame.
+    pub debug_panel: DebugPanel,
+    /// The terminals embedded in the editor, running shells or debugged programs.
+    pub terminals: Terminals,
 
     pub clipboard_provider: Box<dyn ClipboardProvider>,
 
@@ -710,8 +860,10 @@ pub struct Editor {
     pub status_msg: Option<(Cow<'static, str>, Severity)>,
     pub autoinfo: Option<Info>,
 
-    pub config: Box<dyn DynAccess<Config>>,
+    pub config: Arc<dyn DynAccess<Config>>,
     pub auto_pairs: Option<AutoPairs>,
+    /// Snippets defined by the user, reloaded with the config.
+    pub snippets: UserSnippets,
 
     pub idle_timer: Pin<Box<Sleep>>,
     pub last_motion: Option<Motion>,
@@ -735,7 +887,10 @@ pub enum EditorEvent {
     DocumentSaved(DocumentSavedEventResult),
     ConfigEvent(ConfigEvent),
     LanguageServerMessage((usize, Call)),
+    PluginEvent((plugin::PluginEvent, serde_json::Value)),
+    FileEvents(Vec<file_watcher::FileEvent>),
     DebuggerEvent(dap::Payload),
+    TerminalEvent(TerminalEvent),
     IdleTimer,
 }
 
@@ -779,10 +934,12 @@ pub fn new(
         mut area: Rect,
         theme_loader: Arc<theme::Loader>,
         syn_loader: Arc<syntax::Loader>,
-        config: Box<dyn DynAccess<Config>>,
+        config: Arc<dyn DynAccess<Config>>,
     ) -> Self {
         let conf = config.load();
         let auto_pairs = (&conf.auto_pairs).into();
+        let mut language_servers = helix_lsp::Registry::new();
+        language_servers.set_watch_files(conf.file_watcher.enable);
 
         // HAXX: offset the render area height by 1 to account for prompt/commandline
         area.height -= 1;
@@ -800,12 +957,21 @@ pub fn new(
             macro_recording: None,
             macro_replaying: Vec::new(),
             theme: theme_loader.default(),
-            language_servers: helix_lsp::Registry::new(),
+            language_servers,
+            plugins: plugin::Registry::new(),
+            file_watcher: file_watcher::Watcher::new(&conf.file_watcher),
             diagnostics: BTreeMap::new(),
+            diagnostic_result_ids: HashMap::new(),
+            pending_workspace_diagnostics: HashSet::new(),
             diff_providers: DiffProviderRegistry::default(),
             debugger: None,
             debugger_events: SelectAll::new(),
             breakpoints: HashMap::new(),
+            function_breakpoints: Vec::new(),
+            data_breakpoints: Vec::new(),
+            debug_console: DebugConsole::default(),
+            debug_panel: DebugPanel::default(),
+            terminals: Terminals::new(),
             syn_loader,
             theme_loader,
             last_theme: None,
@@ -819,6 +985,7 @@ pub fn new(
             last_completion: None,
             config,
             auto_pairs,
+            snippets: UserSnippets::load(),
             exit_code: 0,
             config_events: unbounded_channel(),
             redraw_handle: Default::default(),
@@ -840,9 +1007,22 @@ pub fn config(&self) -> DynGuard<Config> {
     pub fn refresh_config(&mut self) {
         let config = self.config();
         self.auto_pairs = (&config.auto_pairs).into();
+        self.file_watcher.set_config(&config.file_watcher);
+        self.language_servers
+            .set_watch_files(config.file_watcher.enable);
         self.reset_idle_timer();
     }
 
+    /// Watches the files of the open documents for changes on disk, see
+    /// [`file_watcher::Watcher`].
+    pub fn watch_document_paths(&self) {
+        self.file_watcher.set_paths(
+            self.documents
+                .values()
+                .filter_map(|doc| doc.path().cloned()),
+        );
+    }
+
     pub fn clear_idle_timer(&mut self) {
         // equivalent to internal Instant::far_future() (30 years)
         self.idle_timer
@@ -935,44 +1115,55 @@ pub fn refresh_language_server(&mut self, doc_id: DocumentId) -> Option<()> {
         Self::launch_language_server(&mut self.language_servers, doc)
     }
 
-    /// Launch a language server for a given document
+    /// Launch the language servers for a given document
     fn launch_language_server(ls: &mut helix_lsp::Registry, doc: &mut Document) -> Option<()> {
         // if doc doesn't have a URL it's a scratch buffer, ignore it
         let doc_url = doc.url()?;
 
-        // try to find a language server based on the language name
-        let language_server = doc.language.as_ref().and_then(|language| {
-            ls.get(language, doc.path())
-                .map_err(|e| {
-                    log::error!(
-                        "Failed to initialize the LSP for `{}` {{ {} }}",
-                        language.scope(),
-                        e
-                    )
-                })
-                .ok()
-                .flatten()
-        });
-        if let Some(language_server) = language_server {
-            // only spawn a new lang server if the servers aren't the same
-            if Some(language_server.id()) != doc.language_server().map(|server| server.id()) {
-                if let Some(language_server) = doc.language_server() {
-                    tokio::spawn(language_server.text_document_did_close(doc.identifier()));
-                }
+        // try to find the language servers based on the language name
+        let language_servers: Vec<_> = doc
+            .language
+            .as_ref()
+            .map(|language| {
+                ls.get(language, doc.path())
+                    .into_iter()
+                    .filter_map(|client| {
+                        client
+                            .map_err(|e| {
+                                log::error!(
+                                    "Failed to initialize the LSP for `{}` {{ {} }}",
+                                    language.scope(),
+                                    e
+                                )
+                            })
+                            .ok()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
 
-                let language_id = doc.language_id().map(ToOwned::to_owned).unwrap_or_default();
+        // only notify servers that weren't attached to the document before
+        let is_same = |a: &Arc<helix_lsp::Client>, b: &Arc<helix_lsp::Client>| a.id() == b.id();
+        for old in &doc.language_servers {
+            if old.is_initialized() && !language_servers.iter().any(|new| is_same(old, new)) {
+                tokio::spawn(old.text_document_did_close(doc.identifier()));
+            }
+        }
 
+        for new in &language_servers {
+            if !doc.language_servers.iter().any(|old| is_same(old, new)) {
+                let language_id = doc.language_id(new.name()).unwrap_or_default();
                 // TODO: this now races with on_init code if the init happens too quickly
-                tokio::spawn(language_server.text_document_did_open(
-                    doc_url,
+                tokio::spawn(new.text_document_did_open(
+                    doc_url.clone(),
                     doc.version(),
                     doc.text(),
-                    language_id,
+                    language_id.to_owned(),
                 ));
-
-                doc.set_language_server(Some(language_server));
             }
         }
+
+        doc.set_language_servers(language_servers);
         Some(())
     }
 
@@ -1007,6 +1198,25 @@ pub fn switch(&mut self, id: DocumentId, action: Action) {
 
         self.enter_normal_mode();
 
+        // documents replace the one of a view without a terminal, or are opened in a split
+        let action = match action {
+            Action::Replace if view!(self).terminal.is_some() => {
+                let view_id = self
+                    .tree
+                    .views()
+                    .find(|(view, _)| view.terminal.is_none())
+                    .map(|(view, _)| view.id);
+                match view_id {
+                    Some(view_id) => {
+                        self.focus(view_id);
+                        Action::Replace
+                    }
+                    None => Action::HorizontalSplit,
+                }
+            }
+            action => action,
+        };
+
         match action {
             Action::Replace => {
                 let (view, doc) = current_ref!(self);
@@ -1067,12 +1277,14 @@ pub fn switch(&mut self, id: DocumentId, action: Action) {
             }
             Action::HorizontalSplit | Action::VerticalSplit => {
                 // copy the current view, unless there is no view yet
-                let view = self
+                let mut view = self
                     .tree
                     .try_get(self.tree.focus)
                     .filter(|v| id == v.doc) // Different Document
                     .cloned()
                     .unwrap_or_else(|| View::new(id, self.config().gutters.clone()));
+                // the split of a terminal shows its document
+                view.terminal = None;
                 let view_id = self.tree.split(
                     view,
                     match action {
@@ -1115,12 +1327,15 @@ fn new_file_from_document(&mut self, action: Action, doc: Document) -> DocumentI
     }
 
     pub fn new_file(&mut self, action: Action) -> DocumentId {
-        self.new_file_from_document(action, Document::default())
+        self.new_file_from_document(action, Document::default(self.config.clone()))
     }
 
     pub fn new_file_from_stdin(&mut self, action: Action) -> Result<DocumentId, Error> {
         let (rope, encoding) = crate::document::from_reader(&mut stdin(), None)?;
-        Ok(self.new_file_from_document(action, Document::from(rope, Some(encoding))))
+        Ok(self.new_file_from_document(
+            action,
+            Document::from(rope, Some(encoding), self.config.clone()),
+        ))
     }
 
     // ??? possible use for integration tests
@@ -1131,13 +1346,21 @@ pub fn open(&mut self, path: &Path, action: Action) -> Result<DocumentId, Error>
         let id = if let Some(id) = id {
             id
         } else {
-            let mut doc = Document::open(&path, None, Some(self.syn_loader.clone()))?;
+            let mut doc = Document::open(
+                &path,
+                None,
+                Some(self.syn_loader.clone()),
+                self.config.clone(),
+            )?;
 
             let _ = Self::launch_language_server(&mut self.language_servers, &mut doc);
             if let Some(diff_base) = self.diff_providers.get_diff_base(&path) {
                 doc.set_diff_base(diff_base, self.redraw_handle.clone());
             }
-            self.new_document(doc)
+            self.plugins.document_opened(&path);
+            let id = self.new_document(doc);
+            self.watch_document_paths();
+            id
         };
 
         self.switch(id, action);
@@ -1145,6 +1368,10 @@ pub fn open(&mut self, path: &Path, action: Action) -> Result<DocumentId, Error>
     }
 
     pub fn close(&mut self, id: ViewId) {
+        // the terminal is only shown in this view
+        if let Some(terminal) = self.tree.get(id).terminal {
+            self.terminals.close(terminal);
+        }
         // Remove selections for the closed view on all documents.
         for doc in self.documents_mut() {
             doc.remove_view(id);
@@ -1153,6 +1380,30 @@ pub fn close(&mut self, id: ViewId) {
         self._refresh();
     }
 
+    /// Starts `command` in a new terminal shown in a split below the focused view. The
+    /// focus moves to the terminal if `focus` is true.
+    pub fn open_terminal(
+        &mut self,
+        command: TerminalCommand,
+        title: String,
+        focus: bool,
+    ) -> anyhow::Result<TerminalId> {
+        // the terminal is resized to the split when it's rendered
+        let area = view!(self).area;
+        let id = self
+            .terminals
+            .spawn(command, title, (area.height / 2, area.width))?;
+
+        let prev_id = self.tree.focus;
+        let doc = view!(self).doc;
+        self.switch(doc, Action::HorizontalSplit);
+        view_mut!(self).terminal = Some(id);
+        if !focus {
+            self.focus(prev_id);
+        }
+        Ok(id)
+    }
+
     pub fn close_document(&mut self, doc_id: DocumentId, force: bool) -> Result<(), CloseError> {
         let doc = match self.documents.get_mut(&doc_id) {
             Some(doc) => doc,
@@ -1165,7 +1416,7 @@ pub fn close_document(&mut self, doc_id: DocumentId, force: bool) -> Result<(),
         // This will also disallow any follow-up writes
         self.saves.remove(&doc_id);
 
-        if let Some(language_server) = doc.language_server() {
+        for language_server in doc.language_servers() {
             // TODO: track error
             tokio::spawn(language_server.text_document_did_close(doc.identifier()));
         }
@@ -1207,6 +1458,7 @@ enum Action {
         }
 
         self.documents.remove(&doc_id);
+        self.watch_document_paths();
 
         // If the document we removed was visible in all views, we will have no more views. We don't
         // want to close the editor just for a simple buffer close, so we need to create a new view
@@ -1217,7 +1469,7 @@ enum Action {
                 .iter()
                 .map(|(&doc_id, _)| doc_id)
                 .next()
-                .unwrap_or_else(|| self.new_document(Document::default()));
+                .unwrap_or_else(|| self.new_document(Document::default(self.config.clone())));
             let view = View::new(doc_id, self.config().gutters.clone());
             let view_id = self.tree.insert(view);
             let doc = doc_mut!(self, &doc_id);
@@ -1255,6 +1507,60 @@ pub fn save<P: Into<PathBuf>>(
         Ok(())
     }
 
+    /// Moves the file or directory at `old_path` to `new_path`, updating the paths of the
+    /// affected documents and notifying the language servers about the rename. `is_dir`
+    /// tells whether `old_path` is a directory, as it was sent in `willRenameFiles`.
+    pub fn move_path(
+        &mut self,
+        old_path: &Path,
+        new_path: &Path,
+        is_dir: bool,
+    ) -> anyhow::Result<()> {
+        if let Some(parent) = new_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(old_path, new_path)?;
+
+        let moved: Vec<_> = self
+            .documents()
+            .filter_map(|doc| {
+                let relative = doc.path()?.strip_prefix(old_path).ok()?;
+                let path = if relative.as_os_str().is_empty() {
+                    new_path.to_path_buf()
+                } else {
+                    new_path.join(relative)
+                };
+                Some((doc.id(), path))
+            })
+            .collect();
+
+        let loader = self.syn_loader.clone();
+        for (doc_id, path) in moved {
+            let doc = doc_mut!(self, &doc_id);
+
+            // the servers only know the document by its old URI: close it there and let
+            // `refresh_language_server` open it again under the new one
+            for server in doc.language_servers() {
+                tokio::spawn(server.text_document_did_close(doc.identifier()));
+            }
+            doc.set_language_servers(Vec::new());
+
+            doc.set_path(Some(&path))?;
+            doc.detect_language(loader.clone());
+            self.refresh_language_server(doc_id);
+        }
+
+        for client in self.language_servers.iter_clients() {
+            if let Some(notification) = client.did_rename(old_path, new_path, is_dir) {
+                tokio::spawn(notification);
+            }
+        }
+
+        self.watch_document_paths();
+
+        Ok(())
+    }
+
     pub fn resize(&mut self, area: Rect) {
         if self.tree.resize(area) {
             self._refresh();
@@ -1394,6 +1700,15 @@ pub async fn wait_event(&mut self) -> EditorEvent {
                 Some(event) = self.debugger_events.next() => {
                     return EditorEvent::DebuggerEvent(event)
                 }
+                Some(event) = self.plugins.events.next() => {
+                    return EditorEvent::PluginEvent(event)
+                }
+                Some(events) = self.file_watcher.events.next() => {
+                    return EditorEvent::FileEvents(events)
+                }
+                Some(event) = self.terminals.events.next() => {
+                    return EditorEvent::TerminalEvent(event)
+                }
 
                 _ = self.redraw_handle.0.notified() => {
                     if  !self.needs_redraw{
@@ -1445,6 +1760,7 @@ pub fn enter_normal_mode(&mut self) {
         let (view, doc) = current!(self);
 
         try_restore_indent(doc, view);
+        doc.active_snippet = None;
 
         // if leaving append mode, move cursor back by 1
         if doc.restore_cursor {
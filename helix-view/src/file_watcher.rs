@@ -0,0 +1,642 @@
+//! Watching files for changes on disk.
+//!
+//! The files of open documents and the files matching the `workspace/didChangeWatchedFiles`
+//! registrations of language servers are watched with the file system notifications of the
+//! platform, or polled if those aren't available. A background task sends the changes as
+//! batches of [`FileEvent`]s.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use globset::{GlobBuilder, GlobMatcher};
+use helix_lsp::lsp;
+use notify::RecursiveMode;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use crate::editor::FileWatcherConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+    Created,
+    Changed,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEvent {
+    pub path: PathBuf,
+    pub change: FileChange,
+}
+
+impl FileEvent {
+    fn to_lsp(&self) -> Option<lsp::FileEvent> {
+        let typ = match self.change {
+            FileChange::Created => lsp::FileChangeType::CREATED,
+            FileChange::Changed => lsp::FileChangeType::CHANGED,
+            FileChange::Deleted => lsp::FileChangeType::DELETED,
+        };
+        let uri = lsp::Url::from_file_path(&self.path).ok()?;
+        Some(lsp::FileEvent { uri, typ })
+    }
+}
+
+/// A glob of a `workspace/didChangeWatchedFiles` registration.
+#[derive(Debug, Clone)]
+struct Glob {
+    /// The directory watched for files matching the glob.
+    root: PathBuf,
+    /// The directory the glob is relative to, absolute paths are matched otherwise.
+    base: Option<PathBuf>,
+    matcher: GlobMatcher,
+    kind: lsp::WatchKind,
+}
+
+impl Glob {
+    fn is_match(&self, path: &Path) -> bool {
+        match &self.base {
+            Some(base) => match path.strip_prefix(base) {
+                Ok(path) => self.matcher.is_match(path),
+                Err(_) => false,
+            },
+            None => self.matcher.is_match(path),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Registration {
+    language_server_id: usize,
+    id: String,
+    globs: Vec<Glob>,
+}
+
+/// The native watcher of the platform, or a watcher polling the files if the native one
+/// isn't available.
+struct Backend {
+    watcher: Box<dyn notify::Watcher + Send>,
+    polling: bool,
+    /// The watched directories, and whether their subdirectories are watched as well.
+    watches: HashMap<PathBuf, RecursiveMode>,
+}
+
+impl Backend {
+    fn new(
+        config: &FileWatcherConfig,
+        tx: &UnboundedSender<notify::Result<notify::Event>>,
+    ) -> Self {
+        let handler = {
+            let tx = tx.clone();
+            move |event| {
+                let _ = tx.send(event);
+            }
+        };
+        match notify::recommended_watcher(handler) {
+            Ok(watcher) => Self {
+                watcher: Box::new(watcher),
+                polling: false,
+                watches: HashMap::new(),
+            },
+            Err(err) => {
+                log::warn!("failed to watch files, polling them instead: {}", err);
+                Self::polling(config, tx)
+            }
+        }
+    }
+
+    fn polling(
+        config: &FileWatcherConfig,
+        tx: &UnboundedSender<notify::Result<notify::Event>>,
+    ) -> Self {
+        let tx = tx.clone();
+        let handler = move |event| {
+            let _ = tx.send(event);
+        };
+        let config = notify::Config::default().with_poll_interval(config.poll_interval);
+        let watcher = notify::PollWatcher::new(handler, config)
+            .expect("creating a polling watcher doesn't fail");
+        Self {
+            watcher: Box::new(watcher),
+            polling: true,
+            watches: HashMap::new(),
+        }
+    }
+
+    /// Watches exactly the directories of `watches`. Switches to polling if the native
+    /// watcher fails to watch a directory, for example because of a limit of watches.
+    fn watch(
+        &mut self,
+        watches: &HashMap<PathBuf, RecursiveMode>,
+        config: &FileWatcherConfig,
+        tx: &UnboundedSender<notify::Result<notify::Event>>,
+    ) {
+        let stale: Vec<PathBuf> = self
+            .watches
+            .iter()
+            .filter(|(dir, mode)| watches.get(*dir) != Some(mode))
+            .map(|(dir, _)| dir.clone())
+            .collect();
+        for dir in stale {
+            self.watches.remove(&dir);
+            if let Err(err) = self.watcher.unwatch(&dir) {
+                log::debug!("failed to stop watching {:?}: {}", dir, err);
+            }
+        }
+
+        for (dir, mode) in watches {
+            if self.watches.contains_key(dir) {
+                continue;
+            }
+            match self.watcher.watch(dir, *mode) {
+                Ok(()) => {
+                    self.watches.insert(dir.clone(), *mode);
+                }
+                // the directory was removed in the meantime
+                Err(notify::Error {
+                    kind: notify::ErrorKind::PathNotFound,
+                    ..
+                }) => (),
+                Err(err) if !self.polling => {
+                    log::warn!("failed to watch {:?}, polling files instead: {}", dir, err);
+                    *self = Self::polling(config, tx);
+                    return self.watch(watches, config, tx);
+                }
+                Err(err) => log::error!("failed to watch {:?}: {}", dir, err),
+            }
+        }
+    }
+}
+
+struct Watched {
+    config: FileWatcherConfig,
+    paths: HashSet<PathBuf>,
+    registrations: Vec<Registration>,
+    /// Started once there are files to watch.
+    backend: Option<Backend>,
+    tx: UnboundedSender<notify::Result<notify::Event>>,
+}
+
+impl Watched {
+    /// Updates the directories watched by the backend after the watched files changed.
+    fn update(&mut self) {
+        if !self.config.enable {
+            self.backend = None;
+            return;
+        }
+
+        // the files of documents are watched through their directory since they may be
+        // replaced by a new file when they are written
+        let mut watches: HashMap<PathBuf, RecursiveMode> = self
+            .paths
+            .iter()
+            .filter_map(|path| Some((path.parent()?.to_path_buf(), RecursiveMode::NonRecursive)))
+            .collect();
+        for glob in self
+            .registrations
+            .iter()
+            .flat_map(|registration| &registration.globs)
+        {
+            // the files below a directory that doesn't exist yet are created in its parent
+            if let Some(root) = glob.root.ancestors().find(|dir| dir.is_dir()) {
+                watches.insert(root.to_path_buf(), RecursiveMode::Recursive);
+            }
+        }
+        let recursive: Vec<PathBuf> = watches
+            .iter()
+            .filter(|(_, mode)| **mode == RecursiveMode::Recursive)
+            .map(|(dir, _)| dir.clone())
+            .collect();
+        watches.retain(|dir, _| {
+            !recursive
+                .iter()
+                .any(|root| root != dir && dir.starts_with(root))
+        });
+
+        if watches.is_empty() && self.backend.is_none() {
+            return;
+        }
+        let (config, tx) = (&self.config, &self.tx);
+        self.backend
+            .get_or_insert_with(|| Backend::new(config, tx))
+            .watch(&watches, config, tx);
+    }
+
+    /// Whether changes of `path` are reported.
+    fn is_watched(&self, path: &Path) -> bool {
+        self.paths.contains(path)
+            || self
+                .registrations
+                .iter()
+                .flat_map(|registration| &registration.globs)
+                .any(|glob| glob.is_match(path))
+    }
+}
+
+pub struct Watcher {
+    watched: Arc<Mutex<Watched>>,
+    pub events: UnboundedReceiverStream<Vec<FileEvent>>,
+}
+
+impl Watcher {
+    /// Creates the watcher, which requires a tokio runtime to collect the changes.
+    pub fn new(config: &FileWatcherConfig) -> Self {
+        let (backend_tx, backend_rx) = unbounded_channel();
+        let watched = Arc::new(Mutex::new(Watched {
+            config: config.clone(),
+            paths: HashSet::new(),
+            registrations: Vec::new(),
+            backend: None,
+            tx: backend_tx,
+        }));
+        let (tx, rx) = unbounded_channel();
+        tokio::spawn(collect(watched.clone(), backend_rx, tx));
+
+        Self {
+            watched,
+            events: UnboundedReceiverStream::new(rx),
+        }
+    }
+
+    fn watched(&self) -> MutexGuard<'_, Watched> {
+        self.watched.lock().unwrap()
+    }
+
+    pub fn set_config(&self, config: &FileWatcherConfig) {
+        let mut watched = self.watched();
+        if watched.config != *config {
+            watched.config = config.clone();
+            // restarts the backend with the new poll interval
+            watched.backend = None;
+            watched.update();
+        }
+    }
+
+    /// Sets the files of the open documents.
+    pub fn set_paths(&self, paths: impl IntoIterator<Item = PathBuf>) {
+        let paths: HashSet<_> = paths.into_iter().collect();
+        let mut watched = self.watched();
+        if watched.paths != paths {
+            watched.paths = paths;
+            watched.update();
+        }
+    }
+
+    /// Adds a `workspace/didChangeWatchedFiles` registration of a language server. Glob
+    /// patterns that aren't relative to a base directory are watched in `root`, only below
+    /// the directories the patterns name literally.
+    pub fn register(
+        &self,
+        language_server_id: usize,
+        id: String,
+        root: &Path,
+        watchers: &[lsp::FileSystemWatcher],
+    ) {
+        let globs = watchers
+            .iter()
+            .filter_map(|watcher| {
+                let (pattern, base) = match &watcher.glob_pattern {
+                    lsp::GlobPattern::String(pattern) => (pattern, None),
+                    lsp::GlobPattern::Relative(relative) => {
+                        let uri = match &relative.base_uri {
+                            lsp::OneOf::Left(folder) => &folder.uri,
+                            lsp::OneOf::Right(uri) => uri,
+                        };
+                        (&relative.pattern, Some(uri.to_file_path().ok()?))
+                    }
+                };
+                let matcher = match GlobBuilder::new(pattern).literal_separator(true).build() {
+                    Ok(glob) => glob.compile_matcher(),
+                    Err(err) => {
+                        log::warn!("invalid glob pattern {:?} to watch: {}", pattern, err);
+                        return None;
+                    }
+                };
+                // only the directory below the literal part of the pattern is watched
+                let root = base
+                    .as_deref()
+                    .unwrap_or(root)
+                    .join(literal_prefix(pattern));
+                Some(Glob {
+                    root,
+                    base,
+                    matcher,
+                    kind: watcher.kind.unwrap_or_else(lsp::WatchKind::all),
+                })
+            })
+            .collect();
+
+        let mut watched = self.watched();
+        watched.registrations.push(Registration {
+            language_server_id,
+            id,
+            globs,
+        });
+        watched.update();
+    }
+
+    pub fn unregister(&self, language_server_id: usize, id: &str) {
+        let mut watched = self.watched();
+        watched.registrations.retain(|registration| {
+            registration.language_server_id != language_server_id || registration.id != id
+        });
+        watched.update();
+    }
+
+    /// Removes the registrations of a language server that exited.
+    pub fn unregister_language_server(&self, language_server_id: usize) {
+        let mut watched = self.watched();
+        watched
+            .registrations
+            .retain(|registration| registration.language_server_id != language_server_id);
+        watched.update();
+    }
+
+    /// Returns the events each language server registered for, by language server id.
+    pub fn language_server_events(
+        &self,
+        events: &[FileEvent],
+    ) -> Vec<(usize, Vec<lsp::FileEvent>)> {
+        let watched = self.watched();
+        let mut server_events: Vec<(usize, Vec<lsp::FileEvent>)> = Vec::new();
+        for registration in &watched.registrations {
+            let matching = events.iter().filter(|event| {
+                let kind = match event.change {
+                    FileChange::Created => lsp::WatchKind::Create,
+                    FileChange::Changed => lsp::WatchKind::Change,
+                    FileChange::Deleted => lsp::WatchKind::Delete,
+                };
+                registration
+                    .globs
+                    .iter()
+                    .any(|glob| glob.kind.contains(kind) && glob.is_match(&event.path))
+            });
+
+            let index = match server_events
+                .iter()
+                .position(|(id, _)| *id == registration.language_server_id)
+            {
+                Some(index) => index,
+                None => {
+                    server_events.push((registration.language_server_id, Vec::new()));
+                    server_events.len() - 1
+                }
+            };
+            let lsp_events = &mut server_events[index].1;
+            for event in matching.filter_map(FileEvent::to_lsp) {
+                if !lsp_events.contains(&event) {
+                    lsp_events.push(event);
+                }
+            }
+        }
+        server_events.retain(|(_, events)| !events.is_empty());
+        server_events
+    }
+}
+
+/// How long to wait for more changes after one is noticed, so that the changes of a
+/// file being written are sent together.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Collects the changes noticed by the backend into batches of the watched files.
+async fn collect(
+    watched: Arc<Mutex<Watched>>,
+    mut rx: UnboundedReceiver<notify::Result<notify::Event>>,
+    tx: UnboundedSender<Vec<FileEvent>>,
+) {
+    while let Some(event) = rx.recv().await {
+        let mut changes = HashMap::new();
+        add_changes(&mut changes, event);
+
+        let deadline = tokio::time::sleep(DEBOUNCE);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                _ = &mut deadline => break,
+                event = rx.recv() => match event {
+                    Some(event) => add_changes(&mut changes, event),
+                    None => break,
+                },
+            }
+        }
+
+        let mut events: Vec<_> = {
+            let watched = watched.lock().unwrap();
+            changes
+                .into_iter()
+                .filter(|(path, _)| watched.is_watched(path))
+                .map(|(path, change)| FileEvent { path, change })
+                .collect()
+        };
+        events.sort_by(|a, b| a.path.cmp(&b.path));
+        if !events.is_empty() && tx.send(events).is_err() {
+            return;
+        }
+    }
+}
+
+/// Adds the changes of a backend event to the ones of the paths noticed before.
+fn add_changes(changes: &mut HashMap<PathBuf, FileChange>, event: notify::Result<notify::Event>) {
+    use notify::event::{EventKind, MetadataKind, ModifyKind, RenameMode};
+
+    let event = match event {
+        Ok(event) => event,
+        Err(err) => {
+            log::error!("failed to watch files: {}", err);
+            return;
+        }
+    };
+    let change = |path: &Path| match event.kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            Some(FileChange::Created)
+        }
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            Some(FileChange::Deleted)
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if path == event.paths[0] => {
+            Some(FileChange::Deleted)
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => Some(FileChange::Created),
+        EventKind::Modify(ModifyKind::Name(_)) if path.exists() => Some(FileChange::Created),
+        EventKind::Modify(ModifyKind::Name(_)) => Some(FileChange::Deleted),
+        EventKind::Access(_)
+        | EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime)) => None,
+        EventKind::Modify(_) | EventKind::Any | EventKind::Other => Some(FileChange::Changed),
+    };
+
+    for path in &event.paths {
+        let change = match change(path) {
+            Some(change) => change,
+            None => continue,
+        };
+        match (changes.get(path).copied(), change) {
+            (Some(FileChange::Created), FileChange::Changed) => (),
+            (Some(FileChange::Created), FileChange::Deleted) => {
+                changes.remove(path);
+            }
+            (Some(FileChange::Deleted), FileChange::Created) => {
+                changes.insert(path.clone(), FileChange::Changed);
+            }
+            _ => {
+                changes.insert(path.clone(), change);
+            }
+        }
+    }
+}
+
+/// Returns the leading directories of a glob pattern which don't contain any glob syntax.
+fn literal_prefix(pattern: &str) -> PathBuf {
+    let mut components: Vec<&str> = pattern
+        .split('/')
+        .take_while(|component| !component.contains(&['*', '?', '[', '{'][..]))
+        .collect();
+    // the last component names a file if the whole pattern is literal
+    if components.len() == pattern.split('/').count() {
+        components.pop();
+    }
+    match components.as_slice() {
+        [""] => PathBuf::from("/"),
+        components => components.join("/").into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, EventKind, ModifyKind, RemoveKind, RenameMode};
+
+    fn event(kind: EventKind, paths: &[&str]) -> notify::Result<notify::Event> {
+        let event = paths.iter().fold(notify::Event::new(kind), |event, path| {
+            event.add_path(path.into())
+        });
+        Ok(event)
+    }
+
+    #[test]
+    fn test_add_changes() {
+        let mut changes = HashMap::new();
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        add_changes(&mut changes, event(modify, &["/a", "/b"]));
+        add_changes(
+            &mut changes,
+            event(EventKind::Create(CreateKind::File), &["/c"]),
+        );
+        add_changes(&mut changes, event(modify, &["/c"]));
+        add_changes(
+            &mut changes,
+            event(EventKind::Create(CreateKind::File), &["/d"]),
+        );
+        add_changes(
+            &mut changes,
+            event(EventKind::Remove(RemoveKind::File), &["/d"]),
+        );
+        // files written by replacing them
+        add_changes(
+            &mut changes,
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                &["/e.tmp", "/e"],
+            ),
+        );
+        add_changes(
+            &mut changes,
+            event(EventKind::Remove(RemoveKind::File), &["/f"]),
+        );
+        add_changes(
+            &mut changes,
+            event(EventKind::Modify(ModifyKind::Name(RenameMode::To)), &["/f"]),
+        );
+
+        let mut changes: Vec<_> = changes.into_iter().collect();
+        changes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let expected = [
+            ("/a", FileChange::Changed),
+            ("/b", FileChange::Changed),
+            ("/c", FileChange::Created),
+            ("/e", FileChange::Created),
+            ("/e.tmp", FileChange::Deleted),
+            ("/f", FileChange::Changed),
+        ]
+        .map(|(path, change)| (PathBuf::from(path), change));
+        assert_eq!(changes, expected);
+    }
+
+    #[tokio::test]
+    async fn test_watch_files() {
+        use tokio_stream::StreamExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("open.txt"), "").unwrap();
+
+        let mut watcher = Watcher::new(&FileWatcherConfig::default());
+        watcher.set_paths([root.join("open.txt")]);
+        let base_uri = lsp::Url::from_directory_path(&root).unwrap();
+        let glob: lsp::FileSystemWatcher = serde_json::from_value(serde_json::json!({
+            "globPattern": { "baseUri": base_uri, "pattern": "src/*.rs" },
+        }))
+        .unwrap();
+        watcher.register(0, "watch".to_string(), &root, &[glob]);
+
+        // files in subdirectories or not matching the glob aren't reported
+        std::fs::write(root.join("open.txt"), "changed").unwrap();
+        std::fs::write(root.join("other.txt"), "").unwrap();
+        std::fs::create_dir(root.join("src/nested")).unwrap();
+        std::fs::write(root.join("src/nested/lib.rs"), "").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "").unwrap();
+
+        // the first change of each file, until no more changes arrive
+        let mut changes = HashMap::new();
+        while let Ok(Some(events)) =
+            tokio::time::timeout(Duration::from_millis(500), watcher.events.next()).await
+        {
+            for event in events {
+                changes.entry(event.path).or_insert(event.change);
+            }
+        }
+        let mut changes: Vec<_> = changes.into_iter().collect();
+        changes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        assert_eq!(
+            changes,
+            [
+                (root.join("open.txt"), FileChange::Changed),
+                (root.join("src/lib.rs"), FileChange::Created),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_literal_prefix() {
+        assert_eq!(literal_prefix("**/*.rs"), PathBuf::new());
+        assert_eq!(literal_prefix("src/**/*.rs"), PathBuf::from("src"));
+        assert_eq!(
+            literal_prefix("crates/a/{b,c}/*.rs"),
+            PathBuf::from("crates/a")
+        );
+        assert_eq!(literal_prefix("Cargo.toml"), PathBuf::new());
+        assert_eq!(literal_prefix("/project/*.toml"), PathBuf::from("/project"));
+        assert_eq!(literal_prefix("/*.toml"), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        let glob = |pattern: &str, base: Option<&str>| Glob {
+            root: PathBuf::from("/project"),
+            base: base.map(PathBuf::from),
+            matcher: GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .unwrap()
+                .compile_matcher(),
+            kind: lsp::WatchKind::all(),
+        };
+
+        assert!(glob("**/Cargo.toml", None).is_match(Path::new("/project/Cargo.toml")));
+        assert!(glob("**/*.{rs,toml}", None).is_match(Path::new("/project/src/lib.rs")));
+        assert!(!glob("*.rs", Some("/project")).is_match(Path::new("/project/src/lib.rs")));
+        assert!(glob("src/*.rs", Some("/project")).is_match(Path::new("/project/src/lib.rs")));
+        assert!(!glob("src/*.rs", Some("/project")).is_match(Path::new("/other/src/lib.rs")));
+    }
+}
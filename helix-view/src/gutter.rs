@@ -32,6 +32,7 @@ pub fn style<'doc>(
             GutterType::LineNumbers => line_numbers(editor, doc, view, theme, is_focused),
             GutterType::Spacer => padding(editor, doc, view, theme, is_focused),
             GutterType::Diff => diff(editor, doc, view, theme, is_focused),
+            GutterType::Folds => folds(editor, doc, view, theme, is_focused),
         }
     }
 
@@ -41,6 +42,7 @@ pub fn width(self, _view: &View, doc: &Document) -> usize {
             GutterType::LineNumbers => line_numbers_width(_view, doc),
             GutterType::Spacer => 1,
             GutterType::Diff => 1,
+            GutterType::Folds => 1,
         }
     }
 }
@@ -132,6 +134,28 @@ pub fn diff<'doc>(
     }
 }
 
+pub fn folds<'doc>(
+    _editor: &'doc Editor,
+    doc: &'doc Document,
+    view: &View,
+    theme: &Theme,
+    _is_focused: bool,
+) -> GutterFn<'doc> {
+    let hidden_lines = doc.hidden_lines(view.id);
+    if hidden_lines.is_empty() {
+        return Box::new(move |_, _, _| None);
+    }
+    let style = theme
+        .try_get("ui.virtual.fold")
+        .unwrap_or_else(|| theme.get("ui.linenr"));
+
+    Box::new(move |line: usize, _selected: bool, out: &mut String| {
+        hidden_lines.folded_after(line)?;
+        write!(out, "▸").unwrap();
+        Some(style)
+    })
+}
+
 pub fn line_numbers<'doc>(
     editor: &'doc Editor,
     doc: &'doc Document,
@@ -150,9 +174,12 @@ pub fn line_numbers<'doc>(
     let linenr = theme.get("ui.linenr");
     let linenr_select = theme.get("ui.linenr.selected");
 
-    let current_line = doc
-        .text()
-        .char_to_line(doc.selection(view.id).primary().cursor(text));
+    let hidden_lines = doc.hidden_lines(view.id);
+    // a cursor in a closed fold is displayed on the first line of the fold
+    let current_line = hidden_lines.visible_line(
+        doc.text()
+            .char_to_line(doc.selection(view.id).primary().cursor(text)),
+    );
 
     let line_number = editor.config().line_number;
     let mode = editor.mode;
@@ -170,7 +197,8 @@ pub fn line_numbers<'doc>(
                 && current_line != line;
 
             let display_num = if relative {
-                abs_diff(current_line, line)
+                // lines hidden by closed folds aren't counted
+                hidden_lines.count_visible(current_line.min(line)..current_line.max(line))
             } else {
                 line + 1
             };
@@ -207,15 +235,6 @@ pub fn padding<'doc>(
     Box::new(|_line: usize, _selected: bool, _out: &mut String| None)
 }
 
-#[inline(always)]
-const fn abs_diff(a: usize, b: usize) -> usize {
-    if a > b {
-        a - b
-    } else {
-        b - a
-    }
-}
-
 pub fn breakpoints<'doc>(
     editor: &'doc Editor,
     doc: &'doc Document,
@@ -239,9 +258,10 @@ pub fn breakpoints<'doc>(
             .iter()
             .find(|breakpoint| breakpoint.line == line)?;
 
-        let mut style = if breakpoint.condition.is_some() && breakpoint.log_message.is_some() {
+        let conditional = breakpoint.condition.is_some() || breakpoint.hit_condition.is_some();
+        let mut style = if conditional && breakpoint.log_message.is_some() {
             error.underline_style(UnderlineStyle::Line)
-        } else if breakpoint.condition.is_some() {
+        } else if conditional {
             error
         } else if breakpoint.log_message.is_some() {
             info
@@ -262,7 +282,13 @@ pub fn breakpoints<'doc>(
             }
         };
 
-        let sym = if breakpoint.verified { "▲" } else { "⊚" };
+        let sym = if !breakpoint.enabled {
+            "○"
+        } else if breakpoint.verified {
+            "▲"
+        } else {
+            "⊚"
+        };
         write!(out, "{}", sym).unwrap();
         Some(style)
     })
@@ -1,11 +1,17 @@
-use crate::editor::{Action, Breakpoint};
+use crate::debug_console::OutputKind;
+use crate::editor::{Action, Breakpoint, DataBreakpoint, FunctionBreakpoint};
+use crate::session::path_hash;
+use crate::terminal::TerminalCommand;
 use crate::{align_view, Align, Editor};
-use helix_core::Selection;
+use anyhow::Context;
+use helix_core::{Assoc, ChangeSet, Rope, Selection};
 use helix_dap::{self as dap, Client, Payload, Request, ThreadId};
 use helix_lsp::block_on;
 use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[macro_export]
 macro_rules! debugger {
@@ -111,16 +117,31 @@ pub fn breakpoints_changed(
     // }
     let source_breakpoints = breakpoints
         .iter()
+        .filter(|breakpoint| breakpoint.enabled)
         .map(|breakpoint| helix_dap::SourceBreakpoint {
             line: breakpoint.line + 1, // convert from 0-indexing to 1-indexing (TODO: could set debugger to 0-indexing on init)
-            ..Default::default()
+            column: breakpoint.column,
+            condition: breakpoint.condition.clone(),
+            hit_condition: breakpoint.hit_condition.clone(),
+            log_message: breakpoint.log_message.clone(),
         })
         .collect::<Vec<_>>();
 
+    for breakpoint in breakpoints
+        .iter_mut()
+        .filter(|breakpoint| !breakpoint.enabled)
+    {
+        breakpoint.id = None;
+        breakpoint.verified = false;
+    }
+
     let request = debugger.set_breakpoints(path, source_breakpoints);
     match block_on(request) {
         Ok(Some(dap_breakpoints)) => {
-            for (breakpoint, dap_breakpoint) in breakpoints.iter_mut().zip(dap_breakpoints) {
+            let enabled = breakpoints
+                .iter_mut()
+                .filter(|breakpoint| breakpoint.enabled);
+            for (breakpoint, dap_breakpoint) in enabled.zip(dap_breakpoints) {
                 breakpoint.id = dap_breakpoint.id;
                 breakpoint.verified = dap_breakpoint.verified;
                 breakpoint.message = dap_breakpoint.message;
@@ -138,7 +159,171 @@ pub fn breakpoints_changed(
     Ok(())
 }
 
+pub fn function_breakpoints_changed(
+    debugger: &mut dap::Client,
+    breakpoints: &mut [FunctionBreakpoint],
+) -> Result<(), anyhow::Error> {
+    let function_breakpoints = breakpoints
+        .iter()
+        .filter(|breakpoint| breakpoint.enabled)
+        .map(|breakpoint| dap::FunctionBreakpoint {
+            name: breakpoint.name.clone(),
+            condition: breakpoint.condition.clone(),
+            hit_condition: breakpoint.hit_condition.clone(),
+        })
+        .collect();
+
+    let dap_breakpoints = block_on(debugger.set_function_breakpoints(function_breakpoints))
+        .map_err(|e| anyhow::anyhow!("Failed to set function breakpoints: {}", e))?;
+    for breakpoint in breakpoints.iter_mut() {
+        breakpoint.verified = false;
+        breakpoint.message = None;
+    }
+    let enabled = breakpoints
+        .iter_mut()
+        .filter(|breakpoint| breakpoint.enabled);
+    for (breakpoint, dap_breakpoint) in enabled.zip(dap_breakpoints) {
+        breakpoint.verified = dap_breakpoint.verified;
+        breakpoint.message = dap_breakpoint.message;
+    }
+    Ok(())
+}
+
+pub fn data_breakpoints_changed(
+    debugger: &mut dap::Client,
+    breakpoints: &mut [DataBreakpoint],
+) -> Result<(), anyhow::Error> {
+    let data_breakpoints = breakpoints
+        .iter()
+        .filter(|breakpoint| breakpoint.enabled)
+        .map(|breakpoint| dap::DataBreakpoint {
+            data_id: breakpoint.data_id.clone(),
+            ..Default::default()
+        })
+        .collect();
+
+    let dap_breakpoints = block_on(debugger.set_data_breakpoints(data_breakpoints))
+        .map_err(|e| anyhow::anyhow!("Failed to set data breakpoints: {}", e))?;
+    for breakpoint in breakpoints.iter_mut() {
+        breakpoint.verified = false;
+        breakpoint.message = None;
+    }
+    let enabled = breakpoints
+        .iter_mut()
+        .filter(|breakpoint| breakpoint.enabled);
+    for (breakpoint, dap_breakpoint) in enabled.zip(dap_breakpoints) {
+        breakpoint.verified = dap_breakpoint.verified;
+        breakpoint.message = dap_breakpoint.message;
+    }
+    Ok(())
+}
+
+/// The breakpoints of a workspace as they are saved between sessions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+struct SavedBreakpoints {
+    source: BTreeMap<PathBuf, Vec<Breakpoint>>,
+    function: Vec<FunctionBreakpoint>,
+}
+
+/// The file the breakpoints of the workspace `root` are saved in.
+pub fn breakpoints_file(root: &Path) -> PathBuf {
+    helix_loader::breakpoints_dir().join(path_hash(root))
+}
+
+/// Moves `breakpoints` from the lines of `old_text` to the ones of `text` they are on after
+/// `changes`. Returns whether any breakpoint moved.
+fn shift_lines(
+    breakpoints: &mut Vec<Breakpoint>,
+    old_text: &Rope,
+    changes: &ChangeSet,
+    text: &Rope,
+) -> bool {
+    let mut shifted = false;
+    let last_line = old_text.len_lines() - 1;
+    for breakpoint in breakpoints.iter_mut() {
+        let pos = old_text.line_to_char(breakpoint.line.min(last_line));
+        let line = text.char_to_line(changes.map_pos(pos, Assoc::After));
+        if line != breakpoint.line {
+            breakpoint.line = line;
+            shifted = true;
+        }
+    }
+    let mut lines = HashSet::new();
+    breakpoints.retain(|breakpoint| lines.insert(breakpoint.line));
+    shifted
+}
+
 impl Editor {
+    /// Saves the source and function breakpoints, which are restored with
+    /// [`Editor::load_breakpoints`] when the editor is started in the same workspace.
+    pub fn save_breakpoints(&self) -> Result<(), anyhow::Error> {
+        let path = breakpoints_file(&helix_core::find_root(None, &[]));
+        let saved = SavedBreakpoints {
+            source: self
+                .breakpoints
+                .iter()
+                .filter(|(_, breakpoints)| !breakpoints.is_empty())
+                .map(|(path, breakpoints)| (path.clone(), breakpoints.clone()))
+                .collect(),
+            function: self.function_breakpoints.clone(),
+        };
+
+        if saved.source.is_empty() && saved.function.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("remove breakpoints {}", path.display()))?;
+            }
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string(&saved)?;
+        std::fs::write(&path, content)
+            .with_context(|| format!("write breakpoints {}", path.display()))
+    }
+
+    /// Moves the source breakpoints with the lines they are on through the edits of their
+    /// documents, and saves the breakpoints if any moved. The breakpoints of deleted lines
+    /// are moved to the line of the deletion, merging the ones ending up on the same line.
+    pub fn shift_breakpoints(&mut self) {
+        let mut shifted = false;
+        for doc in self.documents.values_mut() {
+            let (old_text, changes) = match doc.breakpoint_changes.take() {
+                Some(changes) => changes,
+                None => continue,
+            };
+            let breakpoints = match doc.path().and_then(|path| self.breakpoints.get_mut(path)) {
+                Some(breakpoints) => breakpoints,
+                None => continue,
+            };
+
+            shifted |= shift_lines(breakpoints, &old_text, &changes, doc.text());
+        }
+
+        if shifted {
+            if let Err(err) = self.save_breakpoints() {
+                log::error!("failed to save breakpoints: {}", err);
+            }
+        }
+    }
+
+    /// Restores the breakpoints saved in the current workspace.
+    pub fn load_breakpoints(&mut self) -> Result<(), anyhow::Error> {
+        let path = breakpoints_file(&helix_core::find_root(None, &[]));
+        if !path.exists() {
+            return Ok(());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("read breakpoints {}", path.display()))?;
+        let saved: SavedBreakpoints = serde_json::from_str(&content)
+            .with_context(|| format!("parse breakpoints {}", path.display()))?;
+        self.breakpoints = saved.source.into_iter().collect();
+        self.function_breakpoints = saved.function;
+        Ok(())
+    }
+
     pub async fn handle_debugger_message(&mut self, payload: helix_dap::Payload) -> bool {
         use dap::requests::RunInTerminal;
         use helix_dap::{events, Event};
@@ -190,6 +375,10 @@ pub async fn handle_debugger_message(&mut self, payload: helix_dap::Payload) ->
                     }
 
                     self.set_status(status);
+
+                    if let Some(debugger) = self.debugger.as_ref() {
+                        self.debug_panel.refresh(debugger).await;
+                    }
                 }
                 Event::Continued(events::Continued { thread_id, .. }) => {
                     debugger
@@ -249,9 +438,9 @@ pub async fn handle_debugger_message(&mut self, payload: helix_dap::Payload) ->
                 Event::Output(events::Output {
                     category, output, ..
                 }) => {
-                    let prefix = match category {
+                    let prefix = match &category {
                         Some(category) => {
-                            if &category == "telemetry" {
+                            if category == "telemetry" {
                                 return false;
                             }
                             format!("Debug ({}):", category)
@@ -260,7 +449,9 @@ pub async fn handle_debugger_message(&mut self, payload: helix_dap::Payload) ->
                     };
 
                     log::info!("{}", output);
-                    self.set_status(format!("{} {}", prefix, output));
+                    self.set_status(format!("{} {}", prefix, output.trim_end()));
+                    self.debug_console
+                        .push(OutputKind::from_category(category.as_deref()), &output);
                 }
                 Event::Initialized(_) => {
                     // send existing breakpoints
@@ -268,6 +459,12 @@ pub async fn handle_debugger_message(&mut self, payload: helix_dap::Payload) ->
                         // TODO: call futures in parallel, await all
                         let _ = breakpoints_changed(debugger, path.clone(), breakpoints);
                     }
+                    if !self.function_breakpoints.is_empty()
+                        && debugger.capabilities().supports_function_breakpoints == Some(true)
+                    {
+                        let _ =
+                            function_breakpoints_changed(debugger, &mut self.function_breakpoints);
+                    }
                     // TODO: fetch breakpoints (in case we're attaching)
 
                     if debugger.configuration_done().await.is_ok() {
@@ -286,11 +483,51 @@ pub async fn handle_debugger_message(&mut self, payload: helix_dap::Payload) ->
                         serde_json::from_value(request.arguments.unwrap_or_default()).unwrap();
                     // TODO: no unwrap
 
-                    let config = match self.config().terminal.clone() {
-                        Some(config) => config,
+                    // use an external terminal only if it's requested and configured, so
+                    // that debugging also works without a graphical environment
+                    let external = match self.config().terminal.clone() {
+                        Some(config) if arguments.kind.as_deref() == Some("external") => {
+                            Some(config)
+                        }
+                        _ => None,
+                    };
+
+                    let process_id = match external {
+                        Some(config) => {
+                            match std::process::Command::new(config.command)
+                                .args(config.args)
+                                .arg(arguments.args.join(" "))
+                                .spawn()
+                            {
+                                Ok(process) => Some(process.id()),
+                                Err(err) => {
+                                    // TODO replace the pretty print {:?} with a regular format {}
+                                    // when the MSRV is raised to 1.60.0
+                                    self.set_error(format!(
+                                        "Error starting external terminal: {:?}",
+                                        err
+                                    ));
+                                    return true;
+                                }
+                            }
+                        }
                         None => {
-                            self.set_error("No external terminal defined");
-                            return true;
+                            let title = arguments.title.unwrap_or_else(|| arguments.args.join(" "));
+                            let command = TerminalCommand {
+                                args: arguments.args,
+                                cwd: match arguments.cwd.as_str() {
+                                    "" => None,
+                                    cwd => Some(cwd.into()),
+                                },
+                                env: arguments.env.unwrap_or_default(),
+                            };
+                            match self.open_terminal(command, title, false) {
+                                Ok(id) => self.terminals.get(id).and_then(|t| t.process_id()),
+                                Err(err) => {
+                                    self.set_error(format!("Error starting terminal: {}", err));
+                                    return true;
+                                }
+                            }
                         }
                     };
 
@@ -300,26 +537,12 @@ pub async fn handle_debugger_message(&mut self, payload: helix_dap::Payload) ->
                         None => return false,
                     };
 
-                    let process = match std::process::Command::new(config.command)
-                        .args(config.args)
-                        .arg(arguments.args.join(" "))
-                        .spawn()
-                    {
-                        Ok(process) => process,
-                        Err(err) => {
-                            // TODO replace the pretty print {:?} with a regular format {}
-                            // when the MSRV is raised to 1.60.0
-                            self.set_error(format!("Error starting external terminal: {:?}", err));
-                            return true;
-                        }
-                    };
-
                     let _ = debugger
                         .reply(
                             request.seq,
                             dap::requests::RunInTerminal::COMMAND,
                             serde_json::to_value(dap::requests::RunInTerminalResponse {
-                                process_id: Some(process.id()),
+                                process_id,
                                 shell_process_id: None,
                             })
                             .map_err(|e| e.into()),
@@ -332,3 +555,122 @@ pub async fn handle_debugger_message(&mut self, payload: helix_dap::Payload) ->
         true
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use helix_core::Transaction;
+
+    #[test]
+    fn test_saved_breakpoints_round_trip() {
+        let mut saved = SavedBreakpoints::default();
+        saved.source.insert(
+            PathBuf::from("/src/main.rs"),
+            vec![
+                Breakpoint {
+                    id: Some(1),
+                    verified: true,
+                    line: 4,
+                    condition: Some("x > 1".to_string()),
+                    hit_condition: Some("3".to_string()),
+                    ..Default::default()
+                },
+                Breakpoint {
+                    line: 9,
+                    log_message: Some("x is {x}".to_string()),
+                    enabled: false,
+                    ..Default::default()
+                },
+            ],
+        );
+        saved.function.push(FunctionBreakpoint {
+            verified: true,
+            message: Some("resolved".to_string()),
+            name: "main".to_string(),
+            condition: Some("argc == 2".to_string()),
+            hit_condition: Some("> 1".to_string()),
+            enabled: false,
+        });
+
+        let content = serde_json::to_string(&saved).unwrap();
+        let loaded: SavedBreakpoints = serde_json::from_str(&content).unwrap();
+
+        let breakpoints = &loaded.source[Path::new("/src/main.rs")];
+        assert_eq!(breakpoints.len(), 2);
+        // the state reported by the debugger isn't saved
+        assert_eq!(breakpoints[0].id, None);
+        assert!(!breakpoints[0].verified);
+        assert_eq!(breakpoints[0].line, 4);
+        assert_eq!(breakpoints[0].condition.as_deref(), Some("x > 1"));
+        assert_eq!(breakpoints[0].hit_condition.as_deref(), Some("3"));
+        assert_eq!(breakpoints[0].log_message, None);
+        assert!(breakpoints[0].enabled);
+        assert_eq!(breakpoints[1].line, 9);
+        assert_eq!(breakpoints[1].log_message.as_deref(), Some("x is {x}"));
+        assert!(!breakpoints[1].enabled);
+
+        assert_eq!(loaded.function.len(), 1);
+        let function = &loaded.function[0];
+        assert!(!function.verified);
+        assert_eq!(function.message, None);
+        assert_eq!(function.name, "main");
+        assert_eq!(function.condition.as_deref(), Some("argc == 2"));
+        assert_eq!(function.hit_condition.as_deref(), Some("> 1"));
+        assert!(!function.enabled);
+    }
+
+    #[test]
+    fn test_shift_lines() {
+        let old_text = Rope::from("a\nb\nc\nd\n");
+        let mut breakpoints: Vec<_> = [1, 2, 3]
+            .into_iter()
+            .map(|line| Breakpoint {
+                line,
+                ..Default::default()
+            })
+            .collect();
+        let lines = |breakpoints: &[Breakpoint]| -> Vec<_> {
+            breakpoints
+                .iter()
+                .map(|breakpoint| breakpoint.line)
+                .collect()
+        };
+
+        // a line inserted above the breakpoints moves them down
+        let mut text = old_text.clone();
+        let transaction = Transaction::change(&text, [(0, 0, Some("new\n".into()))].into_iter());
+        transaction.apply(&mut text);
+        assert!(shift_lines(
+            &mut breakpoints,
+            &old_text,
+            transaction.changes(),
+            &text
+        ));
+        assert_eq!(lines(&breakpoints), [2, 3, 4]);
+
+        // deleting the lines of two breakpoints merges them on the line of the deletion
+        let old_text = text.clone();
+        let transaction = Transaction::change(&text, [(4, 8, None)].into_iter());
+        transaction.apply(&mut text);
+        assert_eq!(text, "new\nc\nd\n");
+        assert!(shift_lines(
+            &mut breakpoints,
+            &old_text,
+            transaction.changes(),
+            &text
+        ));
+        assert_eq!(lines(&breakpoints), [1, 2]);
+
+        // editing within a line keeps them
+        let old_text = text.clone();
+        let transaction = Transaction::change(&text, [(5, 5, Some("xy".into()))].into_iter());
+        transaction.apply(&mut text);
+        assert!(!shift_lines(
+            &mut breakpoints,
+            &old_text,
+            transaction.changes(),
+            &text
+        ));
+        assert_eq!(lines(&breakpoints), [1, 2]);
+    }
+}
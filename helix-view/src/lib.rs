@@ -2,9 +2,12 @@
 pub mod macros;
 
 pub mod clipboard;
+pub mod debug_console;
+pub mod debug_panel;
 pub mod document;
 pub mod editor;
 pub mod env;
+pub mod file_watcher;
 pub mod graphics;
 pub mod gutter;
 pub mod handlers {
@@ -15,6 +18,9 @@ pub mod handlers {
 pub mod info;
 pub mod input;
 pub mod keyboard;
+pub mod plugin;
+pub mod session;
+pub mod terminal;
 pub mod theme;
 pub mod tree;
 pub mod view;
@@ -63,7 +69,10 @@ pub fn align_view(doc: &Document, view: &mut View, align: Align) {
         Align::Bottom => last_line_height,
     };
 
-    view.offset.row = line.saturating_sub(relative);
+    view.offset.row = match view.wrap_format(doc) {
+        Some(format) => view.wrapped_top_line(doc, &format, pos, relative),
+        None => doc.hidden_lines(view.id).line_above(line, relative),
+    };
 }
 
 /// Applies a [`helix_core::Transaction`] to the given [`Document`]
@@ -0,0 +1,380 @@
+//! Plugins.
+//!
+//! A plugin is a dynamic library in `plugins/<name>/` of the config directory, described
+//! by a `plugin.toml` manifest. It is loaded into the editor at startup and talks to it
+//! through a C ABI with JSON messages: the editor calls the `helix_plugin_notify` function
+//! of the plugin for the commands and events the plugin registered, and the plugin calls
+//! the `request` function of the [`Host`] it was initialized with to read and edit
+//! documents.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Error};
+use libloading::Library;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use crate::document::Mode;
+
+/// Version of the plugin API, passed to plugins in the [`Host`]. It is only bumped for
+/// changes that break existing plugins.
+pub const API_VERSION: u32 = 2;
+
+/// The functions of the editor a plugin can call. Strings are NUL-terminated UTF-8.
+#[repr(C)]
+pub struct Host {
+    pub api_version: u32,
+    /// Makes a request to the editor with the JSON `params`. Returns a JSON object with
+    /// either the `result` or an `error`, which must be released with `free`. Requests
+    /// can only be made while the plugin handles a notification, on the same thread.
+    pub request: unsafe extern "C" fn(method: *const c_char, params: *const c_char) -> *mut c_char,
+    pub free: unsafe extern "C" fn(string: *mut c_char),
+}
+
+/// `helix_plugin_init`, called once after the library is loaded. Returns 0 on success.
+type InitFn = unsafe extern "C" fn(host: *const Host) -> c_int;
+/// `helix_plugin_notify`, called with the method and the JSON params of a notification.
+type NotifyFn = unsafe extern "C" fn(method: *const c_char, params: *const c_char);
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Manifest {
+    /// Name of the directory the plugin was loaded from.
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip)]
+    pub dir: PathBuf,
+    /// Path of the dynamic library, relative to the plugin directory.
+    pub library: PathBuf,
+    /// Events the plugin is notified about.
+    #[serde(default)]
+    pub events: Vec<PluginEvent>,
+    /// Typable commands the plugin provides.
+    #[serde(default)]
+    pub commands: Vec<PluginCommand>,
+    /// Keybindings in the same format as the `keys` section of `config.toml`.
+    #[serde(default)]
+    pub keys: Option<toml::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PluginCommand {
+    pub name: String,
+    #[serde(default)]
+    pub doc: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PluginEvent {
+    DocumentOpen,
+    DocumentSave,
+    ModeChange,
+}
+
+impl PluginEvent {
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::DocumentOpen => "event/document-open",
+            Self::DocumentSave => "event/document-save",
+            Self::ModeChange => "event/mode-change",
+        }
+    }
+}
+
+static MANIFESTS: OnceCell<Vec<Manifest>> = OnceCell::new();
+
+/// The manifests of all plugins in the config directory. They are read once; adding or
+/// removing plugins requires a restart.
+pub fn manifests() -> &'static [Manifest] {
+    MANIFESTS.get_or_init(|| load_manifests(&helix_loader::plugin_dir()))
+}
+
+/// Finds the plugin providing the typable command `name`.
+pub fn command(name: &str) -> Option<(&'static Manifest, &'static PluginCommand)> {
+    manifests().iter().find_map(|manifest| {
+        manifest
+            .commands
+            .iter()
+            .find(|command| command.name == name)
+            .map(|command| (manifest, command))
+    })
+}
+
+pub fn load_manifests(dir: &Path) -> Vec<Manifest> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut manifests: Vec<_> = entries
+        .filter_map(|entry| {
+            let dir = entry.ok()?.path();
+            let path = dir.join("plugin.toml");
+            let content = std::fs::read_to_string(&path).ok()?;
+            match toml::from_str::<Manifest>(&content) {
+                Ok(mut manifest) => {
+                    manifest.name = dir.file_name()?.to_string_lossy().into_owned();
+                    manifest.dir = dir;
+                    Some(manifest)
+                }
+                Err(err) => {
+                    log::error!("failed to parse plugin manifest {:?}: {}", path, err);
+                    None
+                }
+            }
+        })
+        .collect();
+    manifests.sort_by(|a, b| a.name.cmp(&b.name));
+    manifests
+}
+
+// Parameters of the requests plugins can send.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectionRange {
+    pub anchor: usize,
+    pub head: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DocumentParams {
+    /// Document to operate on, the current document if unset.
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Change {
+    /// Char offsets of the replaced range.
+    pub from: usize,
+    pub to: usize,
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApplyParams {
+    pub path: Option<PathBuf>,
+    /// The edit is rejected if the document changed since this version.
+    pub version: Option<i32>,
+    #[serde(default)]
+    pub changes: Vec<Change>,
+    pub selection: Option<Vec<SelectionRange>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct StatusParams {
+    pub message: String,
+    #[serde(default)]
+    pub error: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct OpenParams {
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CommandParams {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// Calls the `helix_plugin_notify` function of a plugin. It can be used as long as the
+/// [`Registry`] the plugin was loaded by exists.
+#[derive(Debug, Clone, Copy)]
+pub struct Notifier(NotifyFn);
+
+impl Notifier {
+    pub fn notify(&self, method: &str, params: &Value) {
+        let (method, params) = match (CString::new(method), CString::new(params.to_string())) {
+            (Ok(method), Ok(params)) => (method, params),
+            _ => return log::error!("plugin notification {} contains a NUL byte", method),
+        };
+        // Safety: the library is kept loaded by the registry, and the strings outlive the
+        // call.
+        unsafe { (self.0)(method.as_ptr(), params.as_ptr()) }
+    }
+}
+
+#[derive(Debug)]
+pub struct Plugin {
+    pub manifest: &'static Manifest,
+    notify: NotifyFn,
+    _library: Library,
+}
+
+impl Plugin {
+    /// Loads the library of a plugin and initializes it.
+    pub fn load(manifest: &'static Manifest, host: &'static Host) -> Result<Self, Error> {
+        let path = manifest.dir.join(&manifest.library);
+        // Safety: plugins are trusted like the grammars in the runtime directory, loading
+        // them runs their code.
+        let library = unsafe { Library::new(&path) }
+            .map_err(|err| anyhow!("failed to load plugin {}: {}", manifest.name, err))?;
+        let (init, notify) = unsafe {
+            let init = *library.get::<InitFn>(b"helix_plugin_init\0")?;
+            let notify = *library.get::<NotifyFn>(b"helix_plugin_notify\0")?;
+            (init, notify)
+        };
+
+        let status = unsafe { init(host) };
+        if status != 0 {
+            bail!(
+                "plugin {} failed to initialize with status {}",
+                manifest.name,
+                status
+            );
+        }
+
+        Ok(Self {
+            manifest,
+            notify,
+            _library: library,
+        })
+    }
+
+    pub fn notifier(&self) -> Notifier {
+        Notifier(self.notify)
+    }
+}
+
+#[derive(Debug)]
+pub struct Registry {
+    plugins: Vec<Plugin>,
+    /// Events queued for the plugins, which are notified when the editor handles them.
+    events_tx: UnboundedSender<(PluginEvent, Value)>,
+    pub events: UnboundedReceiverStream<(PluginEvent, Value)>,
+    /// Mode the plugins were last notified about.
+    mode: Mode,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        let (events_tx, events) = unbounded_channel();
+        Self {
+            plugins: Vec::new(),
+            events_tx,
+            events: UnboundedReceiverStream::new(events),
+            mode: Mode::Normal,
+        }
+    }
+
+    /// Loads all plugins in the config directory.
+    pub fn start(&mut self, host: &'static Host) {
+        for manifest in manifests() {
+            match Plugin::load(manifest, host) {
+                Ok(plugin) => self.plugins.push(plugin),
+                Err(err) => log::error!("{}", err),
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Plugin> {
+        self.plugins.iter()
+    }
+
+    /// The notifier of the plugin providing `command`.
+    pub fn command_notifier(&self, command: &str) -> Result<Notifier, Error> {
+        let (manifest, _) = self::command(command).ok_or_else(|| anyhow!("no such command"))?;
+        self.plugins
+            .iter()
+            .find(|plugin| std::ptr::eq(plugin.manifest, manifest))
+            .map(Plugin::notifier)
+            .ok_or_else(|| anyhow!("plugin {} is not loaded", manifest.name))
+    }
+
+    /// The notifiers of the plugins subscribed to `event`.
+    pub fn event_notifiers(&self, event: PluginEvent) -> Vec<Notifier> {
+        self.plugins
+            .iter()
+            .filter(|plugin| plugin.manifest.events.contains(&event))
+            .map(Plugin::notifier)
+            .collect()
+    }
+
+    fn queue_event(&self, event: PluginEvent, params: Value) {
+        if self
+            .plugins
+            .iter()
+            .any(|plugin| plugin.manifest.events.contains(&event))
+        {
+            let _ = self.events_tx.send((event, params));
+        }
+    }
+
+    pub fn document_opened(&self, path: &Path) {
+        self.queue_event(PluginEvent::DocumentOpen, json!({ "path": path }));
+    }
+
+    pub fn document_saved(&self, path: &Path) {
+        self.queue_event(PluginEvent::DocumentSave, json!({ "path": path }));
+    }
+
+    /// Notifies the plugins if the mode differs from the last one they were sent.
+    pub fn set_mode(&mut self, mode: Mode) {
+        if self.mode != mode {
+            self.mode = mode;
+            self.queue_event(PluginEvent::ModeChange, json!({ "mode": mode.to_string() }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_manifest() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+            library = "target/release/libsort_words.so"
+            events = ["document-save", "mode-change"]
+
+            [[commands]]
+            name = "sort-words"
+            doc = "Sort the words of the selection"
+
+            [keys.normal.space]
+            o = ":sort-words"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.library,
+            PathBuf::from("target/release/libsort_words.so")
+        );
+        assert_eq!(
+            manifest.events,
+            vec![PluginEvent::DocumentSave, PluginEvent::ModeChange]
+        );
+        assert_eq!(
+            manifest.commands,
+            vec![PluginCommand {
+                name: "sort-words".into(),
+                doc: "Sort the words of the selection".into(),
+            }]
+        );
+        assert!(manifest.keys.is_some());
+    }
+}
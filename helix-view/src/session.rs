@@ -0,0 +1,231 @@
+//! Saving and restoring the open buffers, splits, selections and registers of the
+//! editor.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use helix_core::{Position, Range, Selection};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    editor::Action,
+    tree::{Layout, TreeLayout},
+    Editor, ViewId,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Session {
+    pub cwd: PathBuf,
+    /// Paths of all open documents, including ones that aren't visible.
+    pub buffers: Vec<PathBuf>,
+    pub layout: SessionLayout,
+    pub registers: BTreeMap<char, Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SessionLayout {
+    View(SessionView),
+    Container {
+        layout: Layout,
+        children: Vec<SessionLayout>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SessionView {
+    /// Path of the document shown in the view, `None` for a scratch buffer.
+    pub path: Option<PathBuf>,
+    /// `(anchor, head)` char positions of the selection ranges.
+    pub selection: Vec<(usize, usize)>,
+    pub primary: usize,
+    /// `(row, col)` of the scroll offset.
+    pub offset: (usize, usize),
+    pub focused: bool,
+}
+
+/// The file a session of the working directory `cwd` is stored in by default.
+pub fn session_file(cwd: &Path) -> PathBuf {
+    helix_loader::session_dir().join(path_hash(cwd))
+}
+
+/// Turns `path` into a file name. It's a hash of the full path, which can be longer than a
+/// file name is allowed to be.
+pub(crate) fn path_hash(path: &Path) -> String {
+    sha1_smol::Sha1::from(path.to_string_lossy().as_bytes())
+        .digest()
+        .to_string()
+}
+
+impl Session {
+    /// Captures the current state of the editor.
+    pub fn capture(editor: &Editor) -> Result<Self, Error> {
+        let buffers = editor
+            .documents()
+            .filter_map(|doc| doc.path().cloned())
+            .collect();
+        let registers = editor
+            .registers
+            .inner()
+            .iter()
+            .map(|(name, register)| (*name, register.read().to_vec()))
+            .collect();
+
+        Ok(Self {
+            cwd: std::env::current_dir().context("get current dir")?,
+            buffers,
+            layout: Self::capture_layout(editor, editor.tree.layout()),
+            registers,
+        })
+    }
+
+    fn capture_layout(editor: &Editor, layout: TreeLayout) -> SessionLayout {
+        match layout {
+            TreeLayout::View(view_id) => {
+                let view = editor.tree.get(view_id);
+                let doc = &editor.documents[&view.doc];
+                let selection = doc.selection(view_id);
+                SessionLayout::View(SessionView {
+                    path: doc.path().cloned(),
+                    selection: selection
+                        .iter()
+                        .map(|range| (range.anchor, range.head))
+                        .collect(),
+                    primary: selection.primary_index(),
+                    offset: (view.offset.row, view.offset.col),
+                    focused: editor.tree.focus == view_id,
+                })
+            }
+            TreeLayout::Container(layout, children) => SessionLayout::Container {
+                layout,
+                children: children
+                    .into_iter()
+                    .map(|child| Self::capture_layout(editor, child))
+                    .collect(),
+            },
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("read session {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("parse session {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string(self)?;
+        std::fs::write(path, content).with_context(|| format!("write session {}", path.display()))
+    }
+
+    /// Restores the session, replacing the splits of the editor. Documents that are
+    /// already open stay open.
+    pub fn restore(self, editor: &mut Editor) -> Result<(), Error> {
+        if let Err(err) = std::env::set_current_dir(&self.cwd) {
+            log::warn!(
+                "failed to restore working directory {:?}: {}",
+                self.cwd,
+                err
+            );
+        }
+
+        // files that were deleted or can't be read are left out
+        for path in &self.buffers {
+            if !path.exists() {
+                continue;
+            }
+            if let Err(err) = editor.open(path, Action::Load) {
+                log::error!("failed to restore buffer {:?}: {}", path, err);
+            }
+        }
+
+        // the layout is rebuilt by splitting the focused view
+        let slot = editor.tree.focus;
+        let views: Vec<_> = editor
+            .tree
+            .views()
+            .map(|(view, _)| view.id)
+            .filter(|&view_id| view_id != slot)
+            .collect();
+        for view_id in views {
+            editor.close(view_id);
+        }
+
+        let mut focus = slot;
+        Self::restore_layout(editor, self.layout, slot, &mut focus);
+        editor.focus(focus);
+
+        for (name, values) in self.registers {
+            editor.registers.write(name, values);
+        }
+
+        Ok(())
+    }
+
+    fn restore_layout(
+        editor: &mut Editor,
+        layout: SessionLayout,
+        slot: ViewId,
+        focus: &mut ViewId,
+    ) {
+        editor.tree.focus = slot;
+        match layout {
+            SessionLayout::View(session_view) => {
+                let opened = match &session_view.path {
+                    Some(path) => match editor.open(path, Action::Replace) {
+                        Ok(_) => true,
+                        Err(err) => {
+                            log::error!("failed to restore view of {:?}: {}", path, err);
+                            false
+                        }
+                    },
+                    None => false,
+                };
+                if !opened {
+                    editor.new_file(Action::Replace);
+                }
+
+                let (view, doc) = current!(editor);
+                let text = doc.text().slice(..);
+                let len = text.len_chars();
+                let last_line = text.len_lines().saturating_sub(1);
+                let ranges = session_view
+                    .selection
+                    .iter()
+                    .map(|&(anchor, head)| Range::new(anchor.min(len), head.min(len)))
+                    .collect();
+                if !session_view.selection.is_empty() {
+                    let primary = session_view.primary.min(session_view.selection.len() - 1);
+                    let selection = Selection::new(ranges, primary).ensure_invariants(text);
+                    doc.set_selection(view.id, selection);
+                }
+                let (row, col) = session_view.offset;
+                view.offset = Position::new(row.min(last_line), col);
+
+                if session_view.focused {
+                    *focus = slot;
+                }
+            }
+            SessionLayout::Container { layout, children } => {
+                let action = match layout {
+                    Layout::Horizontal => Action::HorizontalSplit,
+                    Layout::Vertical => Action::VerticalSplit,
+                };
+                let mut slots = vec![slot];
+                for _ in 1..children.len() {
+                    let doc = view!(editor).doc;
+                    editor.switch(doc, action);
+                    slots.push(editor.tree.focus);
+                }
+                for (child, slot) in children.into_iter().zip(slots) {
+                    Self::restore_layout(editor, child, slot, focus);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Terminals embedded in the editor.
+//!
+//! Each terminal runs a process in a pseudo terminal. The output of the process is read
+//! on a background thread and sent as [`TerminalEvent`]s, which are fed into a virtual
+//! screen that is rendered in place of the document of the view showing the terminal.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use anyhow::Context;
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use crate::input::KeyEvent;
+use crate::keyboard::{KeyCode, KeyModifiers};
+
+pub use vt100;
+
+/// Number of lines kept above the screen to scroll back to.
+const SCROLLBACK: usize = 1000;
+
+pub type TerminalId = usize;
+
+#[derive(Debug)]
+pub enum TerminalEvent {
+    Output(TerminalId, Vec<u8>),
+    /// The process exited, with a description of its exit status.
+    Exited(TerminalId, String),
+}
+
+/// The process run in a new terminal.
+#[derive(Debug, Default, Clone)]
+pub struct TerminalCommand {
+    /// The program and its arguments, the user's shell is started if it's empty.
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    /// Environment variables to set, or to remove if their value is `None`.
+    pub env: HashMap<String, Option<String>>,
+}
+
+pub struct Terminal {
+    pub id: TerminalId,
+    pub title: String,
+    parser: vt100::Parser,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    killer: Box<dyn ChildKiller + Send + Sync>,
+    process_id: Option<u32>,
+    /// Set once the process exited, describes the exit status.
+    pub exit_status: Option<String>,
+}
+
+impl Terminal {
+    pub fn screen(&self) -> &vt100::Screen {
+        self.parser.screen()
+    }
+
+    pub fn process_id(&self) -> Option<u32> {
+        self.process_id
+    }
+
+    pub fn is_exited(&self) -> bool {
+        self.exit_status.is_some()
+    }
+
+    /// Sends input to the process.
+    pub fn write(&mut self, bytes: &[u8]) {
+        if self.is_exited() {
+            return;
+        }
+        if let Err(err) = self
+            .writer
+            .write_all(bytes)
+            .and_then(|_| self.writer.flush())
+        {
+            log::error!("failed to write to terminal {}: {}", self.id, err);
+        }
+    }
+
+    /// Sends pasted text to the process, wrapped in bracketed paste sequences if the
+    /// process enabled them.
+    pub fn paste(&mut self, text: &str) {
+        if self.screen().bracketed_paste() {
+            let text = format!("\x1b[200~{}\x1b[201~", text);
+            self.write(text.as_bytes());
+        } else {
+            self.write(text.as_bytes());
+        }
+    }
+
+    /// Resizes the screen and notifies the process if the size changed.
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        if self.screen().size() == (rows, cols) || rows == 0 || cols == 0 {
+            return;
+        }
+        self.parser.set_size(rows, cols);
+        let size = PtySize {
+            rows,
+            cols,
+            ..Default::default()
+        };
+        if let Err(err) = self.master.resize(size) {
+            log::error!("failed to resize terminal {}: {}", self.id, err);
+        }
+    }
+
+    /// Number of lines scrolled up into the scrollback.
+    pub fn scrollback(&self) -> usize {
+        self.screen().scrollback()
+    }
+
+    pub fn set_scrollback(&mut self, lines: usize) {
+        self.parser.set_scrollback(lines);
+    }
+
+    fn kill(&mut self) {
+        if !self.is_exited() {
+            let _ = self.killer.kill();
+        }
+    }
+}
+
+pub struct Terminals {
+    terminals: Vec<Terminal>,
+    next_id: TerminalId,
+    sender: UnboundedSender<TerminalEvent>,
+    pub events: UnboundedReceiverStream<TerminalEvent>,
+}
+
+impl Default for Terminals {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Terminals {
+    pub fn new() -> Self {
+        let (sender, receiver) = unbounded_channel();
+        Self {
+            terminals: Vec::new(),
+            next_id: 0,
+            sender,
+            events: UnboundedReceiverStream::new(receiver),
+        }
+    }
+
+    /// Starts `command` in a new terminal.
+    pub fn spawn(
+        &mut self,
+        command: TerminalCommand,
+        title: String,
+        (rows, cols): (u16, u16),
+    ) -> anyhow::Result<TerminalId> {
+        let size = PtySize {
+            rows: rows.max(1),
+            cols: cols.max(1),
+            ..Default::default()
+        };
+        let pair = native_pty_system().openpty(size).context("open pty")?;
+
+        let mut builder = match command.args.split_first() {
+            Some((program, args)) => {
+                let mut builder = CommandBuilder::new(program);
+                builder.args(args);
+                builder
+            }
+            None => CommandBuilder::new_default_prog(),
+        };
+        let cwd = match command.cwd {
+            Some(cwd) => cwd,
+            None => std::env::current_dir().context("get current directory")?,
+        };
+        builder.cwd(cwd);
+        builder.env("TERM", "xterm-256color");
+        for (key, value) in command.env {
+            match value {
+                Some(value) => builder.env(key, value),
+                None => builder.env_remove(key),
+            }
+        }
+
+        let mut child = pair.slave.spawn_command(builder).context("spawn process")?;
+        // the slave is only needed by the child, keeping it open would prevent noticing
+        // that the child exited
+        drop(pair.slave);
+        let mut reader = pair.master.try_clone_reader().context("read pty")?;
+        let writer = pair.master.take_writer().context("write pty")?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let killer = child.clone_killer();
+        let process_id = child.process_id();
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0; 4096];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if sender
+                            .send(TerminalEvent::Output(id, buf[..n].to_vec()))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            }
+            // the pty is closed when the process exits, wait for it to get its exit status
+            let status = match child.wait() {
+                Ok(status) if status.success() => "exited".to_string(),
+                Ok(status) => format!("exited with code {}", status.exit_code()),
+                Err(err) => format!("exited: {}", err),
+            };
+            let _ = sender.send(TerminalEvent::Exited(id, status));
+        });
+
+        self.terminals.push(Terminal {
+            id,
+            title,
+            parser: vt100::Parser::new(size.rows, size.cols, SCROLLBACK),
+            master: pair.master,
+            writer,
+            killer,
+            process_id,
+            exit_status: None,
+        });
+        Ok(id)
+    }
+
+    pub fn handle_event(&mut self, event: TerminalEvent) {
+        match event {
+            TerminalEvent::Output(id, bytes) => {
+                if let Some(terminal) = self.get_mut(id) {
+                    terminal.parser.process(&bytes);
+                }
+            }
+            TerminalEvent::Exited(id, status) => {
+                if let Some(terminal) = self.get_mut(id) {
+                    terminal.exit_status = Some(status);
+                }
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Terminal> {
+        self.terminals.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terminals.is_empty()
+    }
+
+    pub fn get(&self, id: TerminalId) -> Option<&Terminal> {
+        self.terminals.iter().find(|terminal| terminal.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: TerminalId) -> Option<&mut Terminal> {
+        self.terminals.iter_mut().find(|terminal| terminal.id == id)
+    }
+
+    /// Kills the process of the terminal and removes it.
+    pub fn close(&mut self, id: TerminalId) {
+        if let Some(pos) = self.terminals.iter().position(|terminal| terminal.id == id) {
+            self.terminals.remove(pos).kill();
+        }
+    }
+}
+
+impl Drop for Terminals {
+    fn drop(&mut self) {
+        for terminal in &mut self.terminals {
+            terminal.kill();
+        }
+    }
+}
+
+/// Encodes a key as the bytes a terminal sends for it. `application_cursor` is the
+/// cursor key mode requested by the process.
+pub fn key_to_bytes(key: KeyEvent, application_cursor: bool) -> Option<Vec<u8>> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+
+    let cursor = |c: char| {
+        if application_cursor {
+            format!("\x1bO{}", c)
+        } else {
+            format!("\x1b[{}", c)
+        }
+    };
+    let mut bytes = match key.code {
+        KeyCode::Char(c) if ctrl => match c.to_ascii_lowercase() {
+            c @ 'a'..='z' => vec![c as u8 - b'a' + 1],
+            '@' | ' ' => vec![0],
+            '[' => vec![0x1b],
+            '\\' | '4' => vec![0x1c],
+            ']' | '5' => vec![0x1d],
+            '^' | '6' => vec![0x1e],
+            '_' | '7' => vec![0x1f],
+            _ => return None,
+        },
+        KeyCode::Char(c) => c.to_string().into_bytes(),
+        KeyCode::Enter => vec![b'\r'],
+        KeyCode::Backspace => vec![0x7f],
+        KeyCode::Tab if shift => b"\x1b[Z".to_vec(),
+        KeyCode::Tab => vec![b'\t'],
+        KeyCode::Esc => vec![0x1b],
+        KeyCode::Up => cursor('A').into_bytes(),
+        KeyCode::Down => cursor('B').into_bytes(),
+        KeyCode::Right => cursor('C').into_bytes(),
+        KeyCode::Left => cursor('D').into_bytes(),
+        KeyCode::Home => cursor('H').into_bytes(),
+        KeyCode::End => cursor('F').into_bytes(),
+        KeyCode::Insert => b"\x1b[2~".to_vec(),
+        KeyCode::Delete => b"\x1b[3~".to_vec(),
+        KeyCode::PageUp => b"\x1b[5~".to_vec(),
+        KeyCode::PageDown => b"\x1b[6~".to_vec(),
+        KeyCode::F(n @ 1..=4) => format!("\x1bO{}", (b'P' + n - 1) as char).into_bytes(),
+        KeyCode::F(n @ 5..=12) => {
+            let code = [15, 17, 18, 19, 20, 21, 23, 24][n as usize - 5];
+            format!("\x1b[{}~", code).into_bytes()
+        }
+        _ => return None,
+    };
+    if alt {
+        bytes.insert(0, 0x1b);
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent { code, modifiers }
+    }
+
+    #[test]
+    fn test_key_to_bytes() {
+        let none = KeyModifiers::NONE;
+        assert_eq!(
+            key_to_bytes(key(KeyCode::Char('a'), none), false),
+            Some(b"a".to_vec())
+        );
+        assert_eq!(
+            key_to_bytes(key(KeyCode::Char('c'), KeyModifiers::CONTROL), false),
+            Some(vec![3])
+        );
+        assert_eq!(
+            key_to_bytes(key(KeyCode::Char('b'), KeyModifiers::ALT), false),
+            Some(b"\x1bb".to_vec())
+        );
+        assert_eq!(
+            key_to_bytes(key(KeyCode::Up, none), false),
+            Some(b"\x1b[A".to_vec())
+        );
+        assert_eq!(
+            key_to_bytes(key(KeyCode::Up, none), true),
+            Some(b"\x1bOA".to_vec())
+        );
+        assert_eq!(
+            key_to_bytes(key(KeyCode::F(5), none), false),
+            Some(b"\x1b[15~".to_vec())
+        );
+        assert_eq!(key_to_bytes(key(KeyCode::CapsLock, none), false), None);
+    }
+
+    #[test]
+    fn test_spawn() {
+        let mut terminals = Terminals::new();
+        let command = TerminalCommand {
+            args: vec![
+                "sh".into(),
+                "-c".into(),
+                "printf \"$GREETING\"; exit 3".into(),
+            ],
+            env: HashMap::from([("GREETING".to_string(), Some("hello".to_string()))]),
+            ..Default::default()
+        };
+        let id = terminals.spawn(command, "test".into(), (4, 20)).unwrap();
+
+        // the output is read until the process exited
+        while terminals.get(id).unwrap().exit_status.is_none() {
+            let event = terminals.events.as_mut().blocking_recv().unwrap();
+            terminals.handle_event(event);
+        }
+        let terminal = terminals.get(id).unwrap();
+        assert_eq!(terminal.screen().contents(), "hello");
+        assert_eq!(terminal.exit_status.as_deref(), Some("exited with code 3"));
+
+        terminals.close(id);
+        assert!(terminals.is_empty());
+    }
+}
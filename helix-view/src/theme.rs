@@ -302,6 +302,13 @@ pub fn find_scope_index(&self, scope: &str) -> Option<usize> {
         self.scopes().iter().position(|s| s == scope)
     }
 
+    /// Like [`Theme::find_scope_index`], but falls back to dot separated broader
+    /// scopes like [`Theme::try_get`].
+    pub fn find_highlight(&self, scope: &str) -> Option<usize> {
+        std::iter::successors(Some(scope), |s| Some(s.rsplit_once('.')?.0))
+            .find_map(|s| self.find_scope_index(s))
+    }
+
     pub fn is_16_color(&self) -> bool {
         self.styles.iter().all(|(_, style)| {
             [style.fg, style.bg]
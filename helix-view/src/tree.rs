@@ -1,4 +1,5 @@
 use crate::{graphics::Rect, View, ViewId};
+use serde::{Deserialize, Serialize};
 use slotmap::HopSlotMap;
 
 // the dimensions are recomputed on window resize/tree change.
@@ -45,7 +46,8 @@ pub fn view(view: View) -> Self {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Layout {
     Horizontal,
     Vertical,
@@ -60,6 +62,13 @@ pub enum Direction {
     Right,
 }
 
+/// The nesting of the containers and views of a [`Tree`], used to recreate its splits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeLayout {
+    View(ViewId),
+    Container(Layout, Vec<TreeLayout>),
+}
+
 #[derive(Debug)]
 pub struct Container {
     layout: Layout,
@@ -645,6 +654,24 @@ pub fn swap_split_in_direction(&mut self, direction: Direction) -> Option<()> {
     pub fn area(&self) -> Rect {
         self.area
     }
+
+    pub fn layout(&self) -> TreeLayout {
+        self.node_layout(self.root)
+    }
+
+    fn node_layout(&self, id: ViewId) -> TreeLayout {
+        match &self.nodes[id].content {
+            Content::View(_) => TreeLayout::View(id),
+            Content::Container(container) => TreeLayout::Container(
+                container.layout,
+                container
+                    .children
+                    .iter()
+                    .map(|&child| self.node_layout(child))
+                    .collect(),
+            ),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -771,6 +798,41 @@ fn find_split_in_direction() {
         assert_eq!(None, tree.find_split_in_direction(r0, Direction::Up));
     }
 
+    #[test]
+    fn layout() {
+        let mut tree = Tree::new(Rect::new(0, 0, 180, 80));
+        let gutters = || vec![GutterType::Diagnostics, GutterType::LineNumbers];
+        tree.insert(View::new(DocumentId::default(), gutters()));
+        let l0 = tree.focus;
+        tree.split(
+            View::new(DocumentId::default(), gutters()),
+            Layout::Vertical,
+        );
+        let r0 = tree.focus;
+        tree.focus = l0;
+        tree.split(
+            View::new(DocumentId::default(), gutters()),
+            Layout::Horizontal,
+        );
+        let l1 = tree.focus;
+
+        // | L0 |    |
+        // | L1 | R0 |
+        assert_eq!(
+            tree.layout(),
+            TreeLayout::Container(
+                Layout::Vertical,
+                vec![
+                    TreeLayout::Container(
+                        Layout::Horizontal,
+                        vec![TreeLayout::View(l0), TreeLayout::View(l1)]
+                    ),
+                    TreeLayout::View(r0),
+                ]
+            )
+        );
+    }
+
     #[test]
     fn swap_split_in_direction() {
         let mut tree = Tree::new(Rect {
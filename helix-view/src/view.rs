@@ -1,11 +1,23 @@
-use crate::{align_view, editor::GutterType, graphics::Rect, Align, Document, DocumentId, ViewId};
+use crate::{
+    align_view, editor::GutterType, graphics::Rect, terminal::TerminalId, Align, Document,
+    DocumentId, ViewId,
+};
 use helix_core::{
-    pos_at_visual_coords, visual_coords_at_pos, Position, RopeSlice, Selection, Transaction,
+    fold::HiddenLines,
+    pos_at_visual_coords,
+    unicode::width::UnicodeWidthStr,
+    visual_coords_at_pos,
+    wrap::{
+        pos_at_visual_coords_in_wrapped_line, soft_wrap_breaks, visual_coords_in_wrapped_line,
+        WrapFormat,
+    },
+    Position, RopeSlice, Selection, Transaction,
 };
 
 use std::{
     collections::{HashMap, VecDeque},
     fmt,
+    ops::Range,
 };
 
 const JUMP_LIST_CAPACITY: usize = 30;
@@ -110,6 +122,8 @@ pub struct View {
     /// mapping keeps track of the last applied history revision so that only new changes
     /// are applied.
     doc_revisions: HashMap<DocumentId, usize>,
+    /// The embedded terminal shown in place of the document.
+    pub terminal: Option<TerminalId>,
 }
 
 impl fmt::Debug for View {
@@ -135,6 +149,7 @@ pub fn new(doc: DocumentId, gutter_types: Vec<crate::editor::GutterType>) -> Sel
             object_selections: Vec::new(),
             gutters: gutter_types,
             doc_revisions: HashMap::new(),
+            terminal: None,
         }
     }
 
@@ -179,16 +194,23 @@ pub fn offset_coords_to_in_view_center(
         scrolloff: usize,
         centering: bool,
     ) -> Option<(usize, usize)> {
-        let cursor = doc
-            .selection(self.id)
-            .primary()
-            .cursor(doc.text().slice(..));
-
-        let Position { col, row: line } =
-            visual_coords_at_pos(doc.text().slice(..), cursor, doc.tab_width());
+        let text = doc.text().slice(..);
+        let cursor = doc.selection(self.id).primary().cursor(text);
+        let wrap_format = self.wrap_format(doc);
+        let hidden = doc.hidden_lines(self.id);
+
+        // a cursor hidden by a closed fold is displayed on the line of the fold
+        let cursor_line = text.char_to_line(cursor);
+        let line = hidden.visible_line(cursor_line);
+        let cursor = if line != cursor_line {
+            text.line_to_char(line)
+        } else {
+            cursor
+        };
+        let col = self.unwrapped_col(doc, cursor);
 
         let inner_area = self.inner_area(doc);
-        let last_line = (self.offset.row + inner_area.height as usize).saturating_sub(1);
+        let last_row = (inner_area.height as usize).saturating_sub(1);
         let last_col = self.offset.col + inner_area.width.saturating_sub(1) as usize;
 
         let new_offset = |scrolloff: usize| {
@@ -197,12 +219,24 @@ pub fn offset_coords_to_in_view_center(
             // as we type
             let scrolloff = scrolloff.min(inner_area.height.saturating_sub(1) as usize / 2);
 
-            let row = if line > last_line.saturating_sub(scrolloff) {
+            if let Some(format) = &wrap_format {
+                // soft wrapped lines never scroll horizontally
+                return (self.wrapped_offset_row(doc, format, cursor, scrolloff), 0);
+            }
+
+            // rows are counted in visible lines, a closed fold takes up a single row
+            let rows_above = if line >= self.offset.row {
+                hidden.count_visible(self.offset.row..line)
+            } else {
+                0
+            };
+            let row = if line >= self.offset.row && rows_above > last_row.saturating_sub(scrolloff)
+            {
                 // scroll down
-                self.offset.row + line - (last_line.saturating_sub(scrolloff))
-            } else if line < self.offset.row + scrolloff {
+                hidden.line_above(line, last_row.saturating_sub(scrolloff))
+            } else if line < self.offset.row || rows_above < scrolloff {
                 // scroll up
-                line.saturating_sub(scrolloff)
+                hidden.line_above(line, scrolloff)
             } else {
                 self.offset.row
             };
@@ -256,9 +290,223 @@ pub fn is_cursor_in_view(&mut self, doc: &Document, scrolloff: usize) -> bool {
         self.offset_coords_to_in_view(doc, scrolloff).is_none()
     }
 
+    /// Returns the layout of the soft wrapped rows if soft wrapping is enabled for `doc`.
+    pub fn wrap_format(&self, doc: &Document) -> Option<WrapFormat> {
+        let indicator = doc.soft_wrap_indicator()?;
+        Some(WrapFormat {
+            width: self.inner_area(doc).width as usize,
+            tab_width: doc.tab_width(),
+            indicator_width: indicator.width(),
+        })
+    }
+
+    /// Computes where the visual rows of `line` begin, see [`soft_wrap_breaks`].
+    fn line_breaks(&self, doc: &Document, format: &WrapFormat, line: usize) -> Vec<usize> {
+        let text = doc.text().slice(..);
+        soft_wrap_breaks(
+            text.line(line),
+            format,
+            &doc.line_virtual_text(self.id, line),
+        )
+    }
+
+    /// Returns the visual row and column of `pos` within its soft wrapped line.
+    fn wrapped_coords(&self, doc: &Document, format: &WrapFormat, pos: usize) -> Position {
+        let text = doc.text().slice(..);
+        let line = text.char_to_line(pos);
+        let virtual_text = doc.line_virtual_text(self.id, line);
+        let breaks = soft_wrap_breaks(text.line(line), format, &virtual_text);
+        visual_coords_in_wrapped_line(
+            text.line(line),
+            &breaks,
+            pos - text.line_to_char(line),
+            format,
+            &virtual_text,
+        )
+    }
+
+    /// The layout of a line that isn't soft wrapped, for use with the soft wrap helpers
+    /// when a line contains virtual text.
+    fn unwrapped_format(tab_width: usize) -> WrapFormat {
+        WrapFormat {
+            width: usize::MAX,
+            tab_width,
+            indicator_width: 0,
+        }
+    }
+
+    /// Returns the visual column of `pos` within its line when the line isn't soft wrapped.
+    fn unwrapped_col(&self, doc: &Document, pos: usize) -> usize {
+        let text = doc.text().slice(..);
+        let line = text.char_to_line(pos);
+        let virtual_text = doc.line_virtual_text(self.id, line);
+        if virtual_text.is_empty() {
+            // TODO: visual_coords_at_pos also does char_to_line which we ignore, can we reuse the call?
+            return visual_coords_at_pos(text, pos, doc.tab_width()).col;
+        }
+
+        // lay the line out as a single row to account for the virtual text
+        visual_coords_in_wrapped_line(
+            text.line(line),
+            &[],
+            pos - text.line_to_char(line),
+            &Self::unwrapped_format(doc.tab_width()),
+            &virtual_text,
+        )
+        .col
+    }
+
+    /// Counts the visual rows of the lines in `lines`, stopping once `limit` is reached.
+    fn wrapped_rows(
+        &self,
+        doc: &Document,
+        format: &WrapFormat,
+        lines: Range<usize>,
+        limit: usize,
+    ) -> usize {
+        let hidden = doc.hidden_lines(self.id);
+        let mut rows = 0;
+        let mut line = lines.start;
+        while line < lines.end && rows < limit {
+            if !hidden.is_hidden(line) {
+                rows += self.line_breaks(doc, format, line).len() + 1;
+            }
+            line = hidden.next_visible_line(line);
+        }
+        rows
+    }
+
+    /// Returns the first line to display so that at most `rows` visual rows are shown
+    /// above the row containing `pos`.
+    pub(crate) fn wrapped_top_line(
+        &self,
+        doc: &Document,
+        format: &WrapFormat,
+        pos: usize,
+        rows: usize,
+    ) -> usize {
+        let text = doc.text().slice(..);
+        let hidden = doc.hidden_lines(self.id);
+        let mut line = text.char_to_line(pos);
+        let mut rows_above = if hidden.is_hidden(line) {
+            line = hidden.visible_line(line);
+            0
+        } else {
+            self.wrapped_coords(doc, format, pos).row
+        };
+
+        while let Some(prev_line) = hidden.prev_visible_line(line) {
+            let line_rows = self.line_breaks(doc, format, prev_line).len() + 1;
+            if rows_above + line_rows > rows {
+                break;
+            }
+            rows_above += line_rows;
+            line = prev_line;
+        }
+        line
+    }
+
+    /// Computes the first displayed line that keeps the soft wrapped row of `cursor`
+    /// `scrolloff` rows away from the edges of the view.
+    fn wrapped_offset_row(
+        &self,
+        doc: &Document,
+        format: &WrapFormat,
+        cursor: usize,
+        scrolloff: usize,
+    ) -> usize {
+        let text = doc.text().slice(..);
+        let height = self.inner_height();
+        let line = text.char_to_line(cursor);
+        if line < self.offset.row {
+            return self.wrapped_top_line(doc, format, cursor, scrolloff);
+        }
+
+        let row_in_line = self.wrapped_coords(doc, format, cursor).row;
+        let row = self.wrapped_rows(doc, format, self.offset.row..line, height) + row_in_line;
+
+        if row < scrolloff {
+            self.wrapped_top_line(doc, format, cursor, scrolloff)
+        } else if row + scrolloff >= height {
+            self.wrapped_top_line(doc, format, cursor, height.saturating_sub(scrolloff + 1))
+        } else {
+            self.offset.row
+        }
+    }
+
+    /// Returns the document lines from the top of the view on, skipping the lines hidden
+    /// by closed folds.
+    fn lines_from_offset<'a>(
+        &self,
+        doc: &Document,
+        hidden: &'a HiddenLines,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let len_lines = doc.text().len_lines();
+        let first_line = if hidden.is_hidden(self.offset.row) {
+            hidden.next_visible_line(self.offset.row)
+        } else {
+            self.offset.row
+        };
+        std::iter::successors(Some(first_line), move |&line| {
+            Some(hidden.next_visible_line(line))
+        })
+        .take_while(move |&line| line < len_lines)
+    }
+
+    /// Returns the visible document lines along with the screen rows (relative to the
+    /// top of the view) each of them is displayed on.
+    pub fn visible_line_rows(&self, doc: &Document) -> Vec<(usize, Range<usize>)> {
+        let height = self.inner_height();
+        let wrap_format = self.wrap_format(doc);
+        let hidden = doc.hidden_lines(self.id);
+
+        let mut row = 0;
+        let mut lines = Vec::new();
+        for line in self.lines_from_offset(doc, &hidden) {
+            let rows = match &wrap_format {
+                Some(format) => self.line_breaks(doc, format, line).len() + 1,
+                None => 1,
+            };
+            lines.push((line, row..(row + rows).min(height)));
+            row += rows;
+            if row >= height {
+                break;
+            }
+        }
+        lines
+    }
+
+    /// Returns the document line displayed on the given screen row, relative to the top
+    /// of the view.
+    pub fn line_at_row(&self, doc: &Document, row: usize) -> Option<usize> {
+        self.visible_line_rows(doc)
+            .into_iter()
+            .find(|(_, rows)| rows.contains(&row))
+            .map(|(line, _)| line)
+    }
+
     /// Calculates the last visible line on screen
     #[inline]
     pub fn last_line(&self, doc: &Document) -> usize {
+        let wrap_format = self.wrap_format(doc);
+        let hidden = doc.hidden_lines(self.id);
+        if wrap_format.is_some() || !hidden.is_empty() {
+            let height = self.inner_height();
+            let mut rows = 0;
+            let mut last_line = doc.text().len_lines().saturating_sub(1);
+            for line in self.lines_from_offset(doc, &hidden) {
+                last_line = line;
+                rows += match &wrap_format {
+                    Some(format) => self.line_breaks(doc, format, line).len() + 1,
+                    None => 1,
+                };
+                if rows >= height {
+                    break;
+                }
+            }
+            return last_line;
+        }
+
         std::cmp::min(
             // Saturating subs to make it inclusive zero indexing.
             (self.offset.row + self.inner_height()).saturating_sub(1),
@@ -282,12 +530,23 @@ pub fn screen_coords_at_pos(
             return None;
         }
 
-        let tab_width = doc.tab_width();
-        // TODO: visual_coords_at_pos also does char_to_line which we ignore, can we reuse the call?
-        let Position { col, .. } = visual_coords_at_pos(text, pos, tab_width);
+        let hidden = doc.hidden_lines(self.id);
+        if hidden.is_hidden(line) {
+            // Line is hidden by a closed fold
+            return None;
+        }
+
+        if let Some(format) = self.wrap_format(doc) {
+            let Position { row, col } = self.wrapped_coords(doc, &format, pos);
+            let row =
+                row + self.wrapped_rows(doc, &format, self.offset.row..line, self.inner_height());
+            return (row < self.inner_height()).then(|| Position::new(row, col));
+        }
+
+        let col = self.unwrapped_col(doc, pos);
 
         // It is possible for underflow to occur if the buffer length is larger than the terminal width.
-        let row = line.saturating_sub(self.offset.row);
+        let row = hidden.count_visible(self.offset.row..line);
         let col = col.saturating_sub(self.offset.col);
 
         Some(Position::new(row, col))
@@ -311,13 +570,50 @@ pub fn text_pos_at_screen_coords(
             return None;
         }
 
-        let text_row = (row - inner.y) as usize + self.offset.row;
-        if text_row > text.len_lines() - 1 {
+        let hidden = doc.hidden_lines(self.id);
+        if let Some(format) = self.wrap_format(doc) {
+            let mut row = (row - inner.y) as usize;
+            let col = (column - inner.x) as usize;
+            for line in self.lines_from_offset(doc, &hidden) {
+                let virtual_text = doc.line_virtual_text(self.id, line);
+                let breaks = soft_wrap_breaks(text.line(line), &format, &virtual_text);
+                if row <= breaks.len() {
+                    let pos = pos_at_visual_coords_in_wrapped_line(
+                        text.line(line),
+                        &breaks,
+                        Position::new(row, col),
+                        &format,
+                        &virtual_text,
+                    );
+                    return Some(text.line_to_char(line) + pos);
+                }
+                row -= breaks.len() + 1;
+            }
             return Some(text.len_chars());
         }
 
+        let text_row = match self
+            .lines_from_offset(doc, &hidden)
+            .nth((row - inner.y) as usize)
+        {
+            Some(line) => line,
+            None => return Some(text.len_chars()),
+        };
+
         let text_col = (column - inner.x) as usize + self.offset.col;
 
+        let virtual_text = doc.line_virtual_text(self.id, text_row);
+        if !virtual_text.is_empty() {
+            let pos = pos_at_visual_coords_in_wrapped_line(
+                text.line(text_row),
+                &[],
+                Position::new(0, text_col),
+                &Self::unwrapped_format(tab_width),
+                &virtual_text,
+            );
+            return Some(text.line_to_char(text_row) + pos);
+        }
+
         Some(pos_at_visual_coords(
             text,
             Position {
@@ -420,7 +716,11 @@ mod tests {
     const OFFSET_WITHOUT_LINE_NUMBERS: u16 = 1; // 1 diagnostic
                                                 // const OFFSET: u16 = GUTTERS.iter().map(|(_, width)| *width as u16).sum();
     use crate::document::Document;
-    use crate::editor::GutterType;
+    use crate::editor::{Config, GutterType};
+    use arc_swap::ArcSwap;
+    use helix_core::fold::Fold;
+    use helix_core::syntax::SoftWrap;
+    use std::sync::Arc;
 
     #[test]
     fn test_text_pos_at_screen_coords() {
@@ -430,7 +730,11 @@ fn test_text_pos_at_screen_coords() {
         );
         view.area = Rect::new(40, 40, 40, 40);
         let rope = Rope::from_str("abc\n\tdef");
-        let doc = Document::from(rope, None);
+        let doc = Document::from(
+            rope,
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
 
         assert_eq!(view.text_pos_at_screen_coords(&doc, 40, 2, 4), None);
 
@@ -476,7 +780,11 @@ fn test_text_pos_at_screen_coords_without_line_numbers_gutter() {
         let mut view = View::new(DocumentId::default(), vec![GutterType::Diagnostics]);
         view.area = Rect::new(40, 40, 40, 40);
         let rope = Rope::from_str("abc\n\tdef");
-        let doc = Document::from(rope, None);
+        let doc = Document::from(
+            rope,
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
         assert_eq!(
             view.text_pos_at_screen_coords(&doc, 41, 40 + OFFSET_WITHOUT_LINE_NUMBERS + 1, 4),
             Some(4)
@@ -488,7 +796,11 @@ fn test_text_pos_at_screen_coords_without_any_gutters() {
         let mut view = View::new(DocumentId::default(), vec![]);
         view.area = Rect::new(40, 40, 40, 40);
         let rope = Rope::from_str("abc\n\tdef");
-        let doc = Document::from(rope, None);
+        let doc = Document::from(
+            rope,
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
         assert_eq!(view.text_pos_at_screen_coords(&doc, 41, 40 + 1, 4), Some(4));
     }
 
@@ -500,7 +812,11 @@ fn test_text_pos_at_screen_coords_cjk() {
         );
         view.area = Rect::new(40, 40, 40, 40);
         let rope = Rope::from_str("Hi! こんにちは皆さん");
-        let doc = Document::from(rope, None);
+        let doc = Document::from(
+            rope,
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
 
         assert_eq!(
             view.text_pos_at_screen_coords(&doc, 40, 40 + OFFSET, 4),
@@ -540,7 +856,11 @@ fn test_text_pos_at_screen_coords_graphemes() {
         );
         view.area = Rect::new(40, 40, 40, 40);
         let rope = Rope::from_str("Hèl̀l̀ò world!");
-        let doc = Document::from(rope, None);
+        let doc = Document::from(
+            rope,
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
 
         assert_eq!(
             view.text_pos_at_screen_coords(&doc, 40, 40 + OFFSET, 4),
@@ -567,4 +887,84 @@ fn test_text_pos_at_screen_coords_graphemes() {
             Some(7)
         );
     }
+
+    #[test]
+    fn test_soft_wrapped_screen_coords() {
+        let mut view = View::new(
+            DocumentId::default(),
+            vec![GutterType::Diagnostics, GutterType::LineNumbers],
+        );
+        // 10 columns of text
+        view.area = Rect::new(40, 40, 10 + OFFSET, 40);
+        let rope = Rope::from_str("hello world foo\nabc");
+        let config = Config {
+            soft_wrap: SoftWrap {
+                enable: Some(true),
+                wrap_indicator: Some("↪ ".into()),
+            },
+            ..Config::default()
+        };
+        let doc = Document::from(rope, None, Arc::new(ArcSwap::new(Arc::new(config))));
+        let text = doc.text().slice(..);
+
+        assert_eq!(view.last_line(&doc), 1);
+        assert_eq!(
+            view.screen_coords_at_pos(&doc, text, 13),
+            Some(Position::new(2, 3))
+        );
+        assert_eq!(
+            view.screen_coords_at_pos(&doc, text, 17),
+            Some(Position::new(3, 1))
+        );
+
+        assert_eq!(
+            view.text_pos_at_screen_coords(&doc, 40, 40 + OFFSET, 4),
+            Some(0)
+        );
+        assert_eq!(
+            view.text_pos_at_screen_coords(&doc, 41, 40 + OFFSET + 2, 4),
+            Some(6)
+        );
+        assert_eq!(
+            view.text_pos_at_screen_coords(&doc, 42, 40 + OFFSET + 4, 4),
+            Some(14)
+        );
+        assert_eq!(
+            view.text_pos_at_screen_coords(&doc, 43, 40 + OFFSET, 4),
+            Some(16)
+        );
+
+        assert_eq!(view.line_at_row(&doc, 2), Some(0));
+        assert_eq!(view.line_at_row(&doc, 3), Some(1));
+        assert_eq!(view.line_at_row(&doc, 4), None);
+    }
+
+    #[test]
+    fn test_folded_screen_coords() {
+        let mut view = View::new(
+            DocumentId::default(),
+            vec![GutterType::Diagnostics, GutterType::LineNumbers],
+        );
+        view.area = Rect::new(40, 40, 40, 40);
+        let rope = Rope::from_str("a {\n  b\n  c\n}\nd");
+        let mut doc = Document::from(
+            rope,
+            None,
+            Arc::new(ArcSwap::new(Arc::new(Config::default()))),
+        );
+        doc.close_fold(view.id, Fold { start: 0, end: 12 });
+        let text = doc.text().slice(..);
+
+        assert_eq!(view.last_line(&doc), 4);
+        assert_eq!(view.screen_coords_at_pos(&doc, text, 5), None);
+        assert_eq!(
+            view.screen_coords_at_pos(&doc, text, 14),
+            Some(Position::new(1, 0))
+        );
+        assert_eq!(
+            view.text_pos_at_screen_coords(&doc, 41, 40 + OFFSET, 4),
+            Some(14)
+        );
+        assert_eq!(view.line_at_row(&doc, 1), Some(4));
+    }
 }
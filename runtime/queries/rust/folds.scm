@@ -0,0 +1,14 @@
+[
+ (function_item)
+ (impl_item)
+ (trait_item)
+ (struct_item)
+ (enum_item)
+ (union_item)
+ (mod_item)
+ (macro_definition)
+ (block)
+ (match_block)
+ (use_declaration)
+ (block_comment)
+] @fold